@@ -49,7 +49,9 @@ function ChangePreview({ changes }: { changes: RenameChange[] }) {
             {change.type === "update_reference" && (
               <>
                 <FileEdit className="h-3 w-3 text-amber-400 flex-shrink-0" />
-                <span className="text-white/50">{change.file}</span>
+                <span className="text-white/50">
+                  {change.layer ? `${change.layer}: ${change.file}` : change.file}
+                </span>
                 <span className="text-white/40">({change.field})</span>
               </>
             )}
@@ -1551,6 +1551,8 @@ export interface RenameChange {
   type: "rename_file" | "update_reference" | "update_workspace";
   from?: string;
   to?: string;
+  /** Overlay layer holding `file`; absent for the primary library */
+  layer?: string;
   file?: string;
   field?: string;
   old_value?: string;
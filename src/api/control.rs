@@ -25,16 +25,16 @@ use uuid::Uuid;
 
 use crate::agents::{AgentContext, AgentRef, TerminalReason};
 use crate::config::Config;
-use crate::mcp::McpRegistry;
-use crate::secrets::SecretsStore;
+use crate::mcp::{McpPolicies, McpRegistry, McpToolPolicy};
+use crate::secrets::{SecretRedactor, SecretsStore};
 use crate::workspace;
 
 use super::auth::AuthUser;
 use super::desktop;
 use super::library::SharedLibrary;
 use super::mission_store::{
-    self, create_mission_store, now_string, Mission, MissionHistoryEntry, MissionStore,
-    MissionStoreType, StoredEvent,
+    self, create_mission_store, now_string, redact_history, Mission, MissionHistoryEntry,
+    MissionStore, MissionStoreType, StoredEvent,
 };
 use super::routes::AppState;
 
@@ -524,6 +524,16 @@ pub enum AgentEvent {
         #[serde(skip_serializing_if = "Option::is_none")]
         mission_id: Option<Uuid>,
     },
+    /// Library items were written, pulled or merged
+    LibraryChanged {
+        /// Items that were added, modified or deleted
+        items: Vec<crate::library::LibraryItemRef>,
+        /// Workspaces the change was applied to (skills or mission harness configs)
+        workspaces: Vec<Uuid>,
+        /// Running missions whose harness config was re-rendered
+        #[serde(default)]
+        missions: Vec<Uuid>,
+    },
 }
 
 /// A node in the agent tree (for visualization)
@@ -604,6 +614,7 @@ impl AgentEvent {
             AgentEvent::Progress { .. } => "progress",
             AgentEvent::SessionIdUpdate { .. } => "session_id_update",
             AgentEvent::MissionActivity { .. } => "mission_activity",
+            AgentEvent::LibraryChanged { .. } => "library_changed",
         }
     }
 
@@ -623,8 +634,44 @@ impl AgentEvent {
             AgentEvent::Progress { mission_id, .. } => *mission_id,
             AgentEvent::SessionIdUpdate { mission_id, .. } => Some(*mission_id),
             AgentEvent::MissionActivity { mission_id, .. } => *mission_id,
+            AgentEvent::LibraryChanged { .. } => None,
         }
     }
+
+    /// This event with secrets masked in its text, before it is stored or
+    /// streamed to clients.
+    pub fn redacted(mut self, redactor: &SecretRedactor) -> Self {
+        match &mut self {
+            AgentEvent::UserMessage { content, .. }
+            | AgentEvent::AssistantMessage { content, .. }
+            | AgentEvent::Thinking {
+                content,
+                done: true,
+                ..
+            } => redactor.redact_string(content),
+            // Streamed text is resent whole with every event
+            AgentEvent::Thinking { content, .. } | AgentEvent::TextDelta { content, .. } => {
+                *content = redactor.redact_streaming(content)
+            }
+            AgentEvent::ToolCall { args: value, .. }
+            | AgentEvent::ToolResult { result: value, .. } => redactor.redact_json(value),
+            AgentEvent::Error { message, .. } => redactor.redact_string(message),
+            AgentEvent::MissionStatusChanged {
+                summary: Some(text),
+                ..
+            }
+            | AgentEvent::AgentPhase {
+                detail: Some(text), ..
+            }
+            | AgentEvent::Progress {
+                current_subtask: Some(text),
+                ..
+            }
+            | AgentEvent::MissionActivity { label: text, .. } => redactor.redact_string(text),
+            _ => {}
+        }
+        self
+    }
 }
 
 /// Internal control commands (queued and processed by the actor).
@@ -864,6 +911,8 @@ pub struct ControlState {
     pub max_parallel: usize,
     /// Mission persistence (SQLite-backed)
     pub mission_store: Arc<dyn MissionStore>,
+    /// Masks secrets in events before they are stored or streamed
+    pub redactor: Arc<SecretRedactor>,
 }
 
 /// Control session manager for per-user sessions.
@@ -876,6 +925,7 @@ pub struct ControlHub {
     workspaces: workspace::SharedWorkspaceStore,
     library: SharedLibrary,
     secrets: Option<Arc<SecretsStore>>,
+    redactor: Arc<SecretRedactor>,
 }
 
 impl ControlHub {
@@ -886,6 +936,7 @@ impl ControlHub {
         workspaces: workspace::SharedWorkspaceStore,
         library: SharedLibrary,
         secrets: Option<Arc<SecretsStore>>,
+        redactor: Arc<SecretRedactor>,
     ) -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
@@ -895,6 +946,7 @@ impl ControlHub {
             workspaces,
             library,
             secrets,
+            redactor,
         }
     }
 
@@ -930,15 +982,7 @@ impl ControlHub {
                 }
             };
 
-        let state = spawn_control_session(
-            self.config.clone(),
-            Arc::clone(&self.root_agent),
-            Arc::clone(&self.mcp),
-            Arc::clone(&self.workspaces),
-            Arc::clone(&self.library),
-            mission_store,
-            self.secrets.clone(),
-        );
+        let state = spawn_control_session(self, mission_store);
         sessions.insert(user.id.clone(), state.clone());
         state
     }
@@ -947,6 +991,43 @@ impl ControlHub {
         self.sessions.read().await.values().cloned().collect()
     }
 
+    /// Send an event that is not tied to a mission to every user session.
+    pub async fn broadcast(&self, event: AgentEvent) {
+        for session in self.sessions.read().await.values() {
+            let _ = session.events_tx.send(event.clone());
+        }
+    }
+
+    /// Find the user session that owns a mission, with the mission.
+    pub async fn mission_owner(&self, mission_id: Uuid) -> Option<(ControlState, Mission)> {
+        let sessions = self.all_sessions().await;
+        for session in sessions {
+            if let Ok(Some(mission)) = session.mission_store.get_mission(mission_id).await {
+                return Some((session, mission));
+            }
+        }
+        None
+    }
+
+    /// Missions marked active in any user session.
+    pub async fn active_missions(&self) -> Vec<Mission> {
+        let mut missions: Vec<Mission> = Vec::new();
+        for session in self.all_sessions().await {
+            match session.mission_store.get_all_active_missions().await {
+                Ok(active) => {
+                    for mission in active {
+                        // Sessions may share a store
+                        if !missions.iter().any(|m| m.id == mission.id) {
+                            missions.push(mission);
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "Failed to list active missions"),
+            }
+        }
+        missions
+    }
+
     /// Get a mission store for desktop management.
     /// Uses the default user's store if available, or creates a temporary one.
     pub async fn get_mission_store(&self) -> Arc<dyn MissionStore> {
@@ -1281,6 +1362,8 @@ pub struct CreateMissionRequest {
     pub config_profile: Option<String>,
     /// Backend to use for this mission ("opencode" or "claudecode")
     pub backend: Option<String>,
+    /// MCP tool policy for this mission (on top of the agent's)
+    pub mcp_policy: Option<McpToolPolicy>,
 }
 
 pub async fn create_mission(
@@ -1290,6 +1373,8 @@ pub async fn create_mission(
 ) -> Result<Json<Mission>, (StatusCode, String)> {
     let (tx, rx) = oneshot::channel();
 
+    let mcp_policy = body.as_ref().and_then(|b| b.mcp_policy.clone());
+    check_mcp_policy_enforceable(&state, mcp_policy.as_ref())?;
     let (title, workspace_id, agent, model_override, config_profile, mut backend) = body
         .map(|b| {
             (
@@ -1383,15 +1468,75 @@ pub async fn create_mission(
             )
         })?;
 
-    rx.await
+    let mut mission = rx
+        .await
         .map_err(|_| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Failed to receive response".to_string(),
             )
         })?
-        .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    if let Some(policy) = mcp_policy.filter(|p| !p.is_empty()) {
+        control
+            .mission_store
+            .update_mission_mcp_policy(mission.id, Some(&policy))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        mission.mcp_policy = Some(policy);
+    }
+    Ok(Json(mission))
+}
+
+/// Reject mission policies with argument rules while the MCP gateway, the
+/// only place they are enforced, is disabled.
+fn check_mcp_policy_enforceable(
+    state: &AppState,
+    policy: Option<&McpToolPolicy>,
+) -> Result<(), (StatusCode, String)> {
+    if policy.is_some_and(|p| !p.arguments.is_empty()) && state.mcp.gateway().is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "MCP argument rules require the MCP gateway (SANDBOXED_SH_MCP_GATEWAY)".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Set or clear the MCP tool policy of a mission.
+///
+/// Takes effect from the mission's next turn, when its harness config is
+/// rendered again.
+pub async fn set_mission_mcp_policy(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<Uuid>,
+    Json(policy): Json<Option<McpToolPolicy>>,
+) -> Result<Json<Mission>, (StatusCode, String)> {
+    let control = control_for_user(&state, &user).await;
+    let policy = policy.filter(|p| !p.is_empty());
+    check_mcp_policy_enforceable(&state, policy.as_ref())?;
+    control
+        .mission_store
+        .update_mission_mcp_policy(id, policy.as_ref())
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    match control
+        .mission_store
+        .get_mission(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?
+    {
+        Some(mission) => Ok(Json(mission)),
+        None => Err((StatusCode::NOT_FOUND, format!("Mission {} not found", id))),
+    }
 }
 
 /// Load/switch to a mission.
@@ -1918,6 +2063,29 @@ pub async fn cleanup_empty_missions(
     })))
 }
 
+/// Mask secrets in the stored events and history of all missions.
+/// Returns the count of changed events and history entries.
+/// Note: Both are redacted when stored; this is for content stored before.
+pub async fn scrub_mission_events(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let control = control_for_user(&state, &user).await;
+    // Pick up secrets added since the last periodic refresh
+    control.redactor.refresh().await;
+
+    let count = control
+        .mission_store
+        .scrub_events(Arc::clone(&control.redactor))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "scrubbed_count": count
+    })))
+}
+
 /// Stream control session events via SSE.
 pub async fn stream(
     State(state): State<Arc<AppState>>,
@@ -1925,6 +2093,7 @@ pub async fn stream(
 ) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
     let control = control_for_user(&state, &user).await;
     let mut rx = control.events_tx.subscribe();
+    let redactor = Arc::clone(&control.redactor);
     let stream_id = Uuid::new_v4();
     tracing::info!(
         stream_id = %stream_id,
@@ -1995,6 +2164,7 @@ pub async fn stream(
                                     );
                                 }
                             }
+                            let ev = ev.redacted(&redactor);
                             let sse = Event::default().event(ev.event_name()).json_data(&ev).unwrap();
                             yield Ok(sse);
                         }
@@ -2029,15 +2199,7 @@ pub async fn stream(
 }
 
 /// Spawn the global control session actor.
-fn spawn_control_session(
-    config: Config,
-    root_agent: AgentRef,
-    mcp: Arc<McpRegistry>,
-    workspaces: workspace::SharedWorkspaceStore,
-    library: SharedLibrary,
-    mission_store: Arc<dyn MissionStore>,
-    secrets: Option<Arc<SecretsStore>>,
-) -> ControlState {
+fn spawn_control_session(hub: &ControlHub, mission_store: Arc<dyn MissionStore>) -> ControlState {
     let (cmd_tx, cmd_rx) = mpsc::channel::<ControlCommand>(256);
     let (events_tx, events_rx) = broadcast::channel::<AgentEvent>(1024);
     let tool_hub = Arc::new(FrontendToolHub::new());
@@ -2055,7 +2217,7 @@ fn spawn_control_session(
     let current_tree = Arc::new(RwLock::new(None));
     let progress = Arc::new(RwLock::new(ExecutionProgress::default()));
     let running_missions = Arc::new(RwLock::new(Vec::new()));
-    let max_parallel = config.max_parallel_missions;
+    let max_parallel = hub.config.max_parallel_missions;
 
     let state = ControlState {
         cmd_tx,
@@ -2068,15 +2230,31 @@ fn spawn_control_session(
         running_missions: Arc::clone(&running_missions),
         max_parallel,
         mission_store: Arc::clone(&mission_store),
+        redactor: Arc::clone(&hub.redactor),
     };
 
+    // Gateway tokens of a mission stop working once it is no longer active
+    {
+        let mcp = Arc::clone(&hub.mcp);
+        let mut event_rx = events_tx.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match event_rx.recv().await {
+                    Ok(AgentEvent::MissionStatusChanged {
+                        mission_id, status, ..
+                    }) if status != MissionStatus::Active => {
+                        mcp.gateway_tokens().revoke(mission_id);
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
     // Spawn the main control actor
     tokio::spawn(control_actor_loop(
-        config.clone(),
-        root_agent,
-        mcp,
-        workspaces,
-        library,
+        hub.clone(),
         cmd_rx,
         mission_cmd_rx,
         mission_cmd_tx,
@@ -2088,7 +2266,6 @@ fn spawn_control_session(
         current_tree,
         progress,
         mission_store,
-        secrets,
     ));
 
     // Recover orphaned missions from previous run.
@@ -2146,10 +2323,10 @@ fn spawn_control_session(
     }
 
     // Spawn background stale mission cleanup task (if enabled)
-    if config.stale_mission_hours > 0 && state.mission_store.is_persistent() {
+    if hub.config.stale_mission_hours > 0 && state.mission_store.is_persistent() {
         tokio::spawn(stale_mission_cleanup_loop(
             Arc::clone(&state.mission_store),
-            config.stale_mission_hours,
+            hub.config.stale_mission_hours,
             Arc::clone(&running_missions),
             events_tx.clone(),
         ));
@@ -2158,6 +2335,7 @@ fn spawn_control_session(
     // Spawn event logger task (logs all events to SQLite for debugging/replay)
     if state.mission_store.is_persistent() {
         let store = Arc::clone(&state.mission_store);
+        let redactor = Arc::clone(&state.redactor);
         let mut event_rx = events_tx.subscribe();
         tokio::spawn(async move {
             loop {
@@ -2165,6 +2343,7 @@ fn spawn_control_session(
                     Ok(event) => {
                         // Extract mission_id from event
                         if let Some(mid) = event.mission_id() {
+                            let event = event.redacted(&redactor);
                             if let Err(e) = store.log_event(mid, &event).await {
                                 tracing::warn!("Failed to log event: {}", e);
                             }
@@ -2287,11 +2466,7 @@ async fn stale_mission_cleanup_loop(
 }
 
 async fn control_actor_loop(
-    config: Config,
-    root_agent: AgentRef,
-    mcp: Arc<McpRegistry>,
-    workspaces: workspace::SharedWorkspaceStore,
-    library: SharedLibrary,
+    hub: ControlHub,
     mut cmd_rx: mpsc::Receiver<ControlCommand>,
     mut mission_cmd_rx: mpsc::Receiver<crate::tools::mission::MissionControlCommand>,
     mission_cmd_tx: mpsc::Sender<crate::tools::mission::MissionControlCommand>,
@@ -2303,8 +2478,17 @@ async fn control_actor_loop(
     current_tree: Arc<RwLock<Option<AgentTreeNode>>>,
     progress: Arc<RwLock<ExecutionProgress>>,
     mission_store: Arc<dyn MissionStore>,
-    secrets: Option<Arc<SecretsStore>>,
 ) {
+    let ControlHub {
+        config,
+        root_agent,
+        mcp,
+        workspaces,
+        library,
+        secrets,
+        redactor,
+        ..
+    } = hub;
     // Queue stores (id, content, agent) for the current/primary mission
     let mut queue: VecDeque<(Uuid, String, Option<String>)> = VecDeque::new();
     let mut history: Vec<(String, String)> = Vec::new(); // (role, content) pairs (user/assistant)
@@ -2351,16 +2535,18 @@ async fn control_actor_loop(
         mission_store: &Arc<dyn MissionStore>,
         current_mission: &Arc<RwLock<Option<Uuid>>>,
         history: &[(String, String)],
+        redactor: &SecretRedactor,
     ) {
         let mission_id = current_mission.read().await.clone();
         if let Some(mid) = mission_id {
-            let entries: Vec<MissionHistoryEntry> = history
+            let mut entries: Vec<MissionHistoryEntry> = history
                 .iter()
                 .map(|(role, content)| MissionHistoryEntry {
                     role: role.clone(),
                     content: content.clone(),
                 })
                 .collect();
+            redact_history(&mut entries, redactor);
             if let Err(e) = mission_store.update_mission_history(mid, &entries).await {
                 tracing::warn!("Failed to persist mission history: {}", e);
             }
@@ -2727,6 +2913,7 @@ async fn control_actor_loop(
                                                 mission.session_id.clone(),
                                                 mission.config_profile.clone(),
                                             );
+                                            runner.mcp_policy = mission.mcp_policy.clone();
                                             // Load existing history
                                             for entry in &mission.history {
                                                 runner.history.push((entry.role.clone(), entry.content.clone()));
@@ -2813,7 +3000,7 @@ async fn control_actor_loop(
                                 if !main_is_running {
                                     if mission_id != Some(tid) {
                                         // Switch main session to target mission
-                                        persist_mission_history(&mission_store, &current_mission, &history).await;
+                                        persist_mission_history(&mission_store, &current_mission, &history, &redactor).await;
                                         if let Ok(mission) = load_mission_record(&mission_store, tid).await {
                                             history.clear();
                                             for entry in &mission.history {
@@ -2907,7 +3094,7 @@ async fn control_actor_loop(
 
                                 // Immediately persist user message so it's visible when loading mission
                                 history.push(("user".to_string(), msg.clone()));
-                                persist_mission_history(&mission_store, &current_mission, &history)
+                                persist_mission_history(&mission_store, &current_mission, &history, &redactor)
                                     .await;
 
                                 let cfg = config.clone();
@@ -2928,7 +3115,7 @@ async fn control_actor_loop(
                                 let progress_ref = Arc::clone(&progress);
                                 // Capture which mission this task is working on
                                 let mission_id = current_mission.read().await.clone();
-                                let (workspace_id, model_override, mission_agent, backend_id, session_id, mission_config_profile) = if let Some(mid) = mission_id {
+                                let (workspace_id, model_override, mission_agent, backend_id, session_id, mission_config_profile, mission_mcp_policy) = if let Some(mid) = mission_id {
                                     match mission_store.get_mission(mid).await {
                                         Ok(Some(mission)) => {
                                             // Activate mission: if pending, interrupted, or blocked, update status to active
@@ -2952,6 +3139,7 @@ async fn control_actor_loop(
                                                 Some(mission.backend.clone()),
                                                 mission.session_id.clone(),
                                                 mission.config_profile.clone(),
+                                                mission.mcp_policy.clone(),
                                             )
                                         }
                                         Ok(None) => {
@@ -2959,7 +3147,7 @@ async fn control_actor_loop(
                                                 "Mission {} not found while resolving workspace",
                                                 mid
                                             );
-                                            (None, None, None, None, None, None)
+                                            (None, None, None, None, None, None, None)
                                         }
                                         Err(e) => {
                                             tracing::warn!(
@@ -2967,14 +3155,14 @@ async fn control_actor_loop(
                                                 mid,
                                                 e
                                             );
-                                            (None, None, None, None, None, None)
+                                            (None, None, None, None, None, None, None)
                                         }
                                     }
                                 } else {
-                                    (None, None, None, None, None, None)
+                                    (None, None, None, None, None, None, None)
                                 };
                                 // Per-message agent overrides mission agent
-                                let agent_override = per_msg_agent.or(mission_agent);
+                                let agent_override = per_msg_agent.or(mission_agent.clone());
                                 running_cancel = Some(cancel.clone());
                                 running_mission_id = mission_id;
                                 // Reset activity tracking when new task starts
@@ -2982,6 +3170,14 @@ async fn control_actor_loop(
                                 main_runner_activity = None;
                                 main_runner_subtasks.clear();
                                 running = Some(tokio::spawn(async move {
+                                    // Tool policies follow the mission's agent, as in the MCP gateway
+                                    let cfg = with_mission_tool_policies(
+                                        cfg,
+                                        &library_ref,
+                                        mission_agent.as_deref(),
+                                        mission_mcp_policy.as_ref(),
+                                    )
+                                    .await;
                                     let result = run_single_control_turn(
                                         cfg,
                                         agent,
@@ -3037,6 +3233,7 @@ async fn control_actor_loop(
                             &mission_store,
                             &current_mission,
                             &history,
+                            &redactor,
                         )
                         .await;
 
@@ -3083,6 +3280,7 @@ async fn control_actor_loop(
                             &mission_store,
                             &current_mission,
                             &history,
+                            &redactor,
                         )
                         .await;
 
@@ -3189,6 +3387,7 @@ async fn control_actor_loop(
                                 mission.session_id.clone(),
                                 mission.config_profile.clone(),
                             );
+                            runner.mcp_policy = mission.mcp_policy.clone();
 
                             // Load existing history into runner to preserve conversation context
                             for entry in &mission.history {
@@ -3310,6 +3509,7 @@ async fn control_actor_loop(
                                     &mission_store,
                                     &current_mission,
                                     &history,
+                                    &redactor,
                                 )
                                 .await;
 
@@ -3375,12 +3575,20 @@ async fn control_actor_loop(
                                         let agent_override = mission.agent.clone();
                                         let session_id = mission.session_id.clone();
                                         let mission_config_profile = mission.config_profile.clone();
+                                        let mission_mcp_policy = mission.mcp_policy.clone();
                                         running_cancel = Some(cancel.clone());
                                         // Capture which mission this task is working on (the resumed mission)
                                         running_mission_id = Some(mission_id);
                                         main_runner_activity = None;
                                         main_runner_subtasks.clear();
                                         running = Some(tokio::spawn(async move {
+                                            let cfg = with_mission_tool_policies(
+                                                cfg,
+                                                &library_ref,
+                                                agent_override.as_deref(),
+                                                mission_mcp_policy.as_ref(),
+                                            )
+                                            .await;
                                             let result = run_single_control_turn(
                                                 cfg,
                                                 agent,
@@ -3444,6 +3652,7 @@ async fn control_actor_loop(
                                         &mission_store,
                                         &current_mission,
                                         &history,
+                                        &redactor,
                                     )
                                     .await;
                                 }
@@ -3469,7 +3678,7 @@ async fn control_actor_loop(
                         // Handle parallel missions
                         for (mission_id, runner) in parallel_runners.iter_mut() {
                             // Persist history for parallel mission
-                            let entries: Vec<MissionHistoryEntry> = runner
+                            let mut entries: Vec<MissionHistoryEntry> = runner
                                 .history
                                 .iter()
                                 .map(|(role, content)| MissionHistoryEntry {
@@ -3477,6 +3686,7 @@ async fn control_actor_loop(
                                     content: content.clone(),
                                 })
                                 .collect();
+                            redact_history(&mut entries, &redactor);
                             if let Err(e) = mission_store
                                 .update_mission_history(*mission_id, &entries)
                                 .await
@@ -3660,6 +3870,7 @@ async fn control_actor_loop(
                                             role: "assistant".to_string(),
                                             content: agent_result.output.clone(),
                                         });
+                                        redact_history(&mut entries, &redactor);
                                         if let Err(e) =
                                             mission_store.update_mission_history(mid, &entries).await
                                         {
@@ -3843,7 +4054,7 @@ async fn control_actor_loop(
 
                     // Immediately persist user message so it's visible when loading mission
                     history.push(("user".to_string(), msg.clone()));
-                    persist_mission_history(&mission_store, &current_mission, &history)
+                    persist_mission_history(&mission_store, &current_mission, &history, &redactor)
                         .await;
 
                     let cfg = config.clone();
@@ -3865,7 +4076,7 @@ async fn control_actor_loop(
                     running_cancel = Some(cancel.clone());
                     // Capture which mission this task is working on
                     let mission_id = current_mission.read().await.clone();
-                    let (workspace_id, model_override, mission_agent, backend_id, session_id, mission_config_profile) = if let Some(mid) = mission_id {
+                    let (workspace_id, model_override, mission_agent, backend_id, session_id, mission_config_profile, mission_mcp_policy) = if let Some(mid) = mission_id {
                         match mission_store.get_mission(mid).await {
                             Ok(Some(mission)) => (
                                 Some(mission.workspace_id),
@@ -3874,13 +4085,14 @@ async fn control_actor_loop(
                                 Some(mission.backend.clone()),
                                 mission.session_id.clone(),
                                 mission.config_profile.clone(),
+                                mission.mcp_policy.clone(),
                             ),
                             Ok(None) => {
                                 tracing::warn!(
                                     "Mission {} not found while resolving workspace",
                                     mid
                                 );
-                                (None, None, None, None, None, None)
+                                (None, None, None, None, None, None, None)
                             }
                             Err(e) => {
                                 tracing::warn!(
@@ -3888,20 +4100,28 @@ async fn control_actor_loop(
                                     mid,
                                     e
                                 );
-                                (None, None, None, None, None, None)
+                                (None, None, None, None, None, None, None)
                             }
                         }
                     } else {
-                        (None, None, None, None, None, None)
+                        (None, None, None, None, None, None, None)
                     };
                     // Per-message agent overrides mission agent
-                    let agent_override = per_msg_agent.or(mission_agent);
+                    let agent_override = per_msg_agent.or(mission_agent.clone());
                     running_mission_id = mission_id;
                     // Reset activity tracking when new task starts
                     main_runner_last_activity = std::time::Instant::now();
                     main_runner_activity = None;
                     main_runner_subtasks.clear();
                     running = Some(tokio::spawn(async move {
+                        // Tool policies follow the mission's agent, as in the MCP gateway
+                        let cfg = with_mission_tool_policies(
+                            cfg,
+                            &library_ref,
+                            mission_agent.as_deref(),
+                            mission_mcp_policy.as_ref(),
+                        )
+                        .await;
                         let result = run_single_control_turn(
                             cfg,
                             agent,
@@ -3960,7 +4180,7 @@ async fn control_actor_loop(
                             });
 
                             // Persist history for this mission
-                            let entries: Vec<MissionHistoryEntry> = runner
+                            let mut entries: Vec<MissionHistoryEntry> = runner
                                 .history
                                 .iter()
                                 .map(|(role, content)| MissionHistoryEntry {
@@ -3968,6 +4188,7 @@ async fn control_actor_loop(
                                     content: content.clone(),
                                 })
                                 .collect();
+                            redact_history(&mut entries, &redactor);
                             if let Err(e) = mission_store
                                 .update_mission_history(*mission_id, &entries)
                                 .await
@@ -4333,6 +4554,20 @@ async fn control_actor_loop(
     }
 }
 
+/// `config` for a turn of a mission with the given agent and tool policy,
+/// carrying the resolved [`McpPolicies`] of the mission.
+pub(crate) async fn with_mission_tool_policies(
+    mut config: Config,
+    library: &SharedLibrary,
+    mission_agent: Option<&str>,
+    mission_policy: Option<&McpToolPolicy>,
+) -> Config {
+    let library = library.read().await;
+    config.mcp_policies =
+        McpPolicies::resolve(library.as_deref(), &config, mission_agent, mission_policy).await;
+    config
+}
+
 async fn run_single_control_turn(
     mut config: Config,
     root_agent: AgentRef,
@@ -4380,6 +4615,18 @@ async fn run_single_control_turn(
     // Ensure a workspace directory for this mission (if applicable).
     let (working_dir_path, runtime_workspace) = if let Some(mid) = mission_id {
         let ws = workspace::resolve_workspace(&workspaces, &config, workspace_id).await;
+        // Resolve the secret references in the workspace env once for the turn;
+        // the harness config and every command below use the resolved values.
+        let ws = match workspace::with_resolved_env(&ws, mcp.secrets()).await {
+            Ok(ws) => ws,
+            Err(e) => {
+                return crate::agents::AgentResult::failure(
+                    format!("Failed to resolve workspace env: {}", e),
+                    0,
+                )
+                .with_terminal_reason(TerminalReason::LlmError);
+            }
+        };
         // Get library for skill syncing
         let lib_guard = library.read().await;
         let lib_ref = lib_guard.as_ref().map(|l| l.as_ref());
@@ -4388,6 +4635,7 @@ async fn run_single_control_turn(
             &mcp,
             lib_ref,
             mid,
+            &config.mcp_policies,
             backend_id.as_deref().unwrap_or("opencode"),
             None, // custom_providers: TODO integrate with provider store
             effective_config_profile.as_deref(),
@@ -0,0 +1,228 @@
+//! API endpoints for disk usage accounting, quotas and garbage collection.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::disk_usage::{DiskPolicy, DiskUsageReport, GcReport};
+
+use super::mission_store::{Mission, MissionStore};
+use super::routes::AppState;
+
+/// How often usage is sampled (and GC runs, when enabled).
+const SCAN_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Page size used when loading missions for a scan.
+const MISSION_PAGE_SIZE: usize = 500;
+
+/// Create the disk API routes.
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/usage", get(get_usage))
+        .route("/policy", get(get_policy).put(update_policy))
+        .route("/gc", post(run_gc))
+        .route("/pins", get(list_pins))
+        .route("/pins/:mission_id", put(pin_mission).delete(unpin_mission))
+}
+
+/// Request to run garbage collection.
+#[derive(Debug, Default, Deserialize)]
+pub struct GcRequest {
+    /// Only report what would be collected (default: true)
+    #[serde(default = "default_dry_run")]
+    pub dry_run: bool,
+}
+
+fn default_dry_run() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize)]
+pub struct PinsResponse {
+    pub pinned: Vec<Uuid>,
+}
+
+/// Load every mission from the store.
+pub(super) async fn load_all_missions(
+    store: &Arc<dyn MissionStore>,
+) -> Result<Vec<Mission>, String> {
+    let mut missions = Vec::new();
+    loop {
+        let page = store
+            .list_missions(MISSION_PAGE_SIZE, missions.len())
+            .await?;
+        let done = page.len() < MISSION_PAGE_SIZE;
+        missions.extend(page);
+        if done {
+            return Ok(missions);
+        }
+    }
+}
+
+async fn scan_inputs(
+    state: &AppState,
+) -> Result<(Vec<crate::workspace::Workspace>, Vec<Mission>), (StatusCode, String)> {
+    let workspaces = state.workspaces.list().await;
+    let store = state.control.get_mission_store().await;
+    let missions = load_all_missions(&store)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok((workspaces, missions))
+}
+
+/// GET /api/disk/usage
+/// Measure per-workspace and per-mission disk usage (trend samples are only
+/// recorded by the scheduled scan).
+async fn get_usage(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<DiskUsageReport>, (StatusCode, String)> {
+    let (workspaces, missions) = scan_inputs(&state).await?;
+    Ok(Json(state.disk.scan(&workspaces, &missions).await))
+}
+
+/// GET /api/disk/policy
+async fn get_policy(State(state): State<Arc<AppState>>) -> Json<DiskPolicy> {
+    Json(state.disk.policy().await)
+}
+
+/// PUT /api/disk/policy
+/// Replace quotas and GC policy.
+async fn update_policy(
+    State(state): State<Arc<AppState>>,
+    Json(policy): Json<DiskPolicy>,
+) -> Result<Json<DiskPolicy>, (StatusCode, String)> {
+    if policy.gc.statuses.iter().any(|s| {
+        matches!(
+            s,
+            super::control::MissionStatus::Active | super::control::MissionStatus::Pending
+        )
+    }) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "GC statuses cannot include active or pending missions".to_string(),
+        ));
+    }
+    state
+        .disk
+        .set_policy(policy.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(policy))
+}
+
+/// POST /api/disk/gc
+/// Run garbage collection (dry run unless `dry_run` is false).
+async fn run_gc(
+    State(state): State<Arc<AppState>>,
+    body: Option<Json<GcRequest>>,
+) -> Result<Json<GcReport>, (StatusCode, String)> {
+    let req = body.map(|Json(req)| req).unwrap_or(GcRequest {
+        dry_run: default_dry_run(),
+    });
+    let (workspaces, missions) = scan_inputs(&state).await?;
+    let store = state.control.get_mission_store().await;
+    Ok(Json(
+        state
+            .disk
+            .gc(&workspaces, &missions, store.as_ref(), req.dry_run)
+            .await,
+    ))
+}
+
+/// GET /api/disk/pins
+/// List missions excluded from GC.
+async fn list_pins(State(state): State<Arc<AppState>>) -> Json<PinsResponse> {
+    Json(PinsResponse {
+        pinned: state.disk.pinned().await,
+    })
+}
+
+/// PUT /api/disk/pins/:mission_id
+/// Exclude a mission's work directory from GC.
+async fn pin_mission(
+    State(state): State<Arc<AppState>>,
+    Path(mission_id): Path<Uuid>,
+) -> Result<Json<PinsResponse>, (StatusCode, String)> {
+    set_pinned(&state, mission_id, true).await
+}
+
+/// DELETE /api/disk/pins/:mission_id
+async fn unpin_mission(
+    State(state): State<Arc<AppState>>,
+    Path(mission_id): Path<Uuid>,
+) -> Result<Json<PinsResponse>, (StatusCode, String)> {
+    set_pinned(&state, mission_id, false).await
+}
+
+async fn set_pinned(
+    state: &AppState,
+    mission_id: Uuid,
+    pinned: bool,
+) -> Result<Json<PinsResponse>, (StatusCode, String)> {
+    state
+        .disk
+        .set_pinned(mission_id, pinned)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(PinsResponse {
+        pinned: state.disk.pinned().await,
+    }))
+}
+
+/// Background task that samples disk usage and runs GC when enabled, on
+/// startup and then hourly.
+pub async fn disk_maintenance_loop(state: Arc<AppState>) {
+    // The first tick completes immediately
+    let mut interval = tokio::time::interval(SCAN_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+
+        let (workspaces, missions) = match scan_inputs(&state).await {
+            Ok(inputs) => inputs,
+            Err((_, e)) => {
+                tracing::warn!("Disk maintenance: failed to load missions: {}", e);
+                continue;
+            }
+        };
+
+        let report = state.disk.scan(&workspaces, &missions).await;
+        state.disk.record_sample(&report).await;
+        for ws in report.workspaces.iter().filter(|w| w.over_quota) {
+            tracing::warn!(
+                workspace = %ws.name,
+                total_bytes = ws.total_bytes,
+                quota_bytes = ?ws.quota_bytes,
+                "Workspace is over its disk quota"
+            );
+        }
+
+        if state.disk.policy().await.gc.enabled {
+            let store = state.control.get_mission_store().await;
+            let report = state
+                .disk
+                .gc_with_report(&report, &missions, store.as_ref(), false)
+                .await;
+            if !report.candidates.is_empty() {
+                tracing::info!(
+                    "Disk GC collected {} mission directories ({} bytes)",
+                    report
+                        .candidates
+                        .iter()
+                        .filter(|c| c.error.is_none())
+                        .count(),
+                    report.freed_bytes
+                );
+            }
+        }
+    }
+}
@@ -13,29 +13,40 @@
 
 use axum::{
     extract::{Multipart, Path, Query, State},
-    http::{HeaderMap, StatusCode},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
     routing::{delete, get, post, put},
     Json, Router,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use super::control::AgentEvent;
+use crate::harness_export::{self, ExportFormat, ExportSelection};
 use crate::library::{
+    changes::mcp_server_changes,
+    layers::layers_root,
     rename::{ItemType, RenameResult},
     AmpCodeConfig, ClaudeCodeConfig, Command, CommandSummary, ConfigProfile, ConfigProfileSummary,
-    GitAuthor, InitScript, InitScriptSummary, LibraryAgent, LibraryAgentSummary, LibraryStatus,
-    LibraryStore, McpServer, MigrationReport, Plugin, SandboxedConfig, Skill, SkillSummary,
-    WorkspaceTemplate, WorkspaceTemplateSummary,
+    GitAuthor, HarnessImportOptions, HarnessImportReport, HarnessImportSource, InitScript,
+    InitScriptSummary, LayeredItem, LayeredItemKind, LibraryAgent, LibraryAgentSummary,
+    LibraryCommit, LibraryError, LibraryItemRef, LibraryStatus, LibraryStore, LockInstallReport,
+    McpServer, MergeStatus, MigrationReport, Plugin, SandboxedConfig, SecretScanReport, Skill,
+    SkillSummary, SkillUpdate, SkillsLock, ValidationReport, WorkspaceTemplate,
+    WorkspaceTemplateSummary,
 };
+use crate::mcp::McpPolicies;
 use crate::nspawn::NspawnDistro;
+use crate::skills_registry::RegistrySource;
 use crate::workspace::{self, WorkspaceType, DEFAULT_WORKSPACE_ID};
 
 /// Shared library state.
 pub type SharedLibrary = Arc<RwLock<Option<Arc<LibraryStore>>>>;
 
 const LIBRARY_REMOTE_HEADER: &str = "x-sandboxed-library-remote";
+const LIBRARY_LAYER_HEADER: &str = "x-sandboxed-library-layer";
 const GIT_AUTHOR_NAME_HEADER: &str = "x-sandboxed-git-author-name";
 const GIT_AUTHOR_EMAIL_HEADER: &str = "x-sandboxed-git-author-email";
 
@@ -48,6 +59,43 @@ fn extract_library_remote(headers: &HeaderMap) -> Option<String> {
         .map(|value| value.to_string())
 }
 
+fn extract_library_layer(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(LIBRARY_LAYER_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+}
+
+/// Resolve the library layer a write should go to.
+///
+/// Uses the layer named in the `x-sandboxed-library-layer` header, or else the
+/// layer currently providing the item.
+fn write_layer<'a>(
+    library: &'a LibraryStore,
+    headers: &HeaderMap,
+    kind: LayeredItemKind,
+    name: &str,
+) -> Result<&'a LibraryStore, (StatusCode, String)> {
+    library
+        .write_layer(extract_library_layer(headers).as_deref(), kind, name)
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))
+}
+
+/// Look up a library layer by name.
+fn find_layer<'a>(
+    library: &'a LibraryStore,
+    name: &str,
+) -> Result<&'a LibraryStore, (StatusCode, String)> {
+    library.layer(name).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Library layer not found: {}", name),
+        )
+    })
+}
+
 fn extract_git_author(headers: &HeaderMap) -> Option<GitAuthor> {
     let name = headers
         .get(GIT_AUTHOR_NAME_HEADER)
@@ -87,25 +135,187 @@ async fn sync_all_workspaces(state: &super::routes::AppState, library: &LibraryS
     }
 }
 
-async fn sync_skill_to_workspaces(
+/// Whether the workspace's skills include `name`.
+fn workspace_uses_skill(workspace: &workspace::Workspace, name: &str) -> bool {
+    // The default host workspace gets every library skill
+    is_default_host_workspace(workspace) || workspace.skills.iter().any(|s| s == name)
+}
+
+/// Whether `item` is rendered into the harness config of a mission running
+/// in `workspace` with the config profile `profile`.
+///
+/// Workspace templates and init scripts only take effect when the container
+/// is rebuilt, so they are never applied to running missions.
+fn mission_references_item(
+    workspace: &workspace::Workspace,
+    profile: Option<&str>,
+    item: &LibraryItemRef,
+) -> bool {
+    match item.kind {
+        LayeredItemKind::Skill => workspace_uses_skill(workspace, &item.name),
+        // Without an allowlist, all default-enabled MCPs apply
+        LayeredItemKind::Mcp => workspace.mcps.is_empty() || workspace.mcps.contains(&item.name),
+        LayeredItemKind::ConfigProfile => profile.unwrap_or("default") == item.name,
+        // Every harness config gets all commands and agents
+        LayeredItemKind::Command | LayeredItemKind::Agent => true,
+        LayeredItemKind::WorkspaceTemplate | LayeredItemKind::InitScript => false,
+    }
+}
+
+/// Re-render the harness config of a running mission from the current library.
+///
+/// Concurrent re-renders and launches of the same mission are serialized by
+/// [`workspace::prepare_mission_workspace_with_skills_backend`], and managed
+/// directories are swapped in whole, so the harness never sees a partial write.
+async fn rerender_mission_harness_config(
     state: &super::routes::AppState,
     library: &LibraryStore,
-    skill_name: &str,
+    workspace: &workspace::Workspace,
+    mission: &super::mission_store::Mission,
+    profile: Option<&str>,
+) -> anyhow::Result<()> {
+    let tool_policy = McpPolicies::resolve(
+        Some(library),
+        &state.config,
+        mission.agent.as_deref(),
+        mission.mcp_policy.as_ref(),
+    )
+    .await;
+    workspace::prepare_mission_workspace_with_skills_backend(
+        workspace,
+        &state.mcp,
+        Some(library),
+        mission.id,
+        &tool_policy,
+        &mission.backend,
+        None,
+        profile,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Resync the workspaces referencing changed library items in the background,
+/// then announce the change with a `LibraryChanged` control event.
+fn library_changed(
+    state: &Arc<super::routes::AppState>,
+    library: &Arc<LibraryStore>,
+    items: Vec<LibraryItemRef>,
 ) {
-    let workspaces = state.workspaces.list().await;
-    for workspace in workspaces {
-        if is_default_host_workspace(&workspace) || workspace.skills.iter().any(|s| s == skill_name)
-        {
-            if let Err(e) = workspace::sync_workspace_skills(&workspace, library).await {
-                tracing::warn!(
-                    workspace = %workspace.name,
-                    skill = %skill_name,
-                    error = %e,
-                    "Failed to sync skill to workspace"
-                );
+    if items.is_empty() {
+        return;
+    }
+    resync_workspaces(state, library, Some(items));
+}
+
+/// Apply library changes to workspaces and running missions in the background.
+///
+/// With `items`, only the workspace skills and mission harness configs that
+/// reference one of them are updated; without (the changes could not be
+/// determined), everything is. The `LibraryChanged` event lists only the
+/// workspaces and missions the change was applied to.
+fn resync_workspaces(
+    state: &Arc<super::routes::AppState>,
+    library: &Arc<LibraryStore>,
+    items: Option<Vec<LibraryItemRef>>,
+) {
+    let state = Arc::clone(state);
+    let library = Arc::clone(library);
+    tokio::spawn(async move {
+        let missions = state.control.active_missions().await;
+        let mut resynced = Vec::new();
+        let mut rerendered = Vec::new();
+        for workspace in state.workspaces.list().await {
+            let mut applied = false;
+
+            let sync_skills = match &items {
+                Some(items) => items.iter().any(|item| {
+                    item.kind == LayeredItemKind::Skill
+                        && workspace_uses_skill(&workspace, &item.name)
+                }),
+                None => is_default_host_workspace(&workspace) || !workspace.skills.is_empty(),
+            };
+            if sync_skills {
+                match workspace::sync_workspace_skills(&workspace, &library).await {
+                    Ok(()) => applied = true,
+                    Err(e) => {
+                        tracing::warn!(
+                            workspace = %workspace.name,
+                            error = %e,
+                            "Failed to resync workspace skills after library change"
+                        );
+                    }
+                }
+            }
+
+            for mission in missions.iter().filter(|m| m.workspace_id == workspace.id) {
+                let profile = mission
+                    .config_profile
+                    .clone()
+                    .or_else(|| workspace.config_profile.clone());
+                let affected = items.as_ref().is_none_or(|items| {
+                    items
+                        .iter()
+                        .any(|item| mission_references_item(&workspace, profile.as_deref(), item))
+                });
+                if !affected {
+                    continue;
+                }
+                match rerender_mission_harness_config(
+                    &state,
+                    &library,
+                    &workspace,
+                    mission,
+                    profile.as_deref(),
+                )
+                .await
+                {
+                    Ok(()) => {
+                        applied = true;
+                        rerendered.push(mission.id);
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            workspace = %workspace.name,
+                            mission = %mission.id,
+                            error = %e,
+                            "Failed to re-render mission harness config after library change"
+                        );
+                    }
+                }
+            }
+
+            if applied {
+                resynced.push(workspace.id);
             }
         }
-    }
+
+        let items = items.unwrap_or_default();
+        tracing::info!(
+            items = items.len(),
+            workspaces = resynced.len(),
+            missions = rerendered.len(),
+            "Resynced workspaces after library change"
+        );
+        state
+            .control
+            .broadcast(AgentEvent::LibraryChanged {
+                items,
+                workspaces: resynced,
+                missions: rerendered,
+            })
+            .await;
+    });
+}
+
+/// Shorthand for a single changed item.
+fn item_changed(
+    state: &Arc<super::routes::AppState>,
+    library: &Arc<LibraryStore>,
+    kind: LayeredItemKind,
+    name: &str,
+) {
+    library_changed(state, library, vec![LibraryItemRef::new(kind, name)]);
 }
 
 async fn ensure_library(
@@ -124,10 +334,24 @@ async fn ensure_library(
         )
     })?;
 
+    let layers = state.settings.get_library_layers().await;
+    let secret_scan = state.settings.secret_scan_mode().await;
+    let skills_registry = state.settings.skills_registry().await;
+    // Layers that failed to clone still count as configured, so a broken
+    // layer does not rebuild (and resync) the library on every request; the
+    // clone is retried once the layer's backoff has passed.
+    let is_current = |library: &LibraryStore| {
+        library.remote() == remote
+            && library.configured_layers() == layers.as_slice()
+            && library.secret_scan_mode() == secret_scan
+            && *library.skills_registry() == skills_registry
+            && !library.layer_retry_due()
+    };
+
     {
         let library_guard = state.library.read().await;
         if let Some(library) = library_guard.as_ref() {
-            if library.remote() == remote {
+            if is_current(library) {
                 return Ok(Arc::clone(library));
             }
         }
@@ -135,12 +359,22 @@ async fn ensure_library(
 
     let mut library_guard = state.library.write().await;
     if let Some(library) = library_guard.as_ref() {
-        if library.remote() == remote {
+        if is_current(library) {
             return Ok(Arc::clone(library));
         }
     }
 
-    match LibraryStore::new(state.config.library_path.clone(), &remote).await {
+    let store = match LibraryStore::new(state.config.library_path.clone(), &remote).await {
+        Ok(store) => {
+            store
+                .with_secret_scan(secret_scan)
+                .with_skills_registry(skills_registry.clone())
+                .with_layers(&layers)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match store {
         Ok(store) => {
             let store = Arc::new(store);
             *library_guard = Some(Arc::clone(&store));
@@ -165,6 +399,20 @@ pub fn routes() -> Router<Arc<super::routes::AppState>> {
         .route("/force-push", post(force_push_library))
         .route("/commit", post(commit_library))
         .route("/push", post(push_library))
+        .route("/validate", get(validate_library))
+        .route("/scan", get(scan_library))
+        // Three-way merge of diverged history
+        .route("/merge", get(get_merge_status).post(merge_library))
+        .route("/merge/files/*path", put(resolve_merge_conflict))
+        .route("/merge/complete", post(complete_merge))
+        .route("/merge/abort", post(abort_merge))
+        // Overlay layers
+        .route("/layers", get(list_layers))
+        .route("/layers/items", get(list_layered_items))
+        .route("/layers/:layer/status", get(get_layer_status))
+        .route("/layers/:layer/sync", post(sync_layer))
+        .route("/layers/:layer/commit", post(commit_layer))
+        .route("/layers/:layer/push", post(push_layer))
         // MCP servers
         .route("/mcps", get(get_mcps))
         .route("/mcps", put(save_mcps))
@@ -212,6 +460,10 @@ pub fn routes() -> Router<Arc<super::routes::AppState>> {
         // Workspace Templates
         .route("/workspace-template", get(list_workspace_templates))
         .route("/workspace-template/:name", get(get_workspace_template))
+        .route(
+            "/workspace-template/:name/resolved",
+            get(resolve_workspace_template),
+        )
         .route("/workspace-template/:name", put(save_workspace_template))
         .route(
             "/workspace-template/:name",
@@ -224,8 +476,18 @@ pub fn routes() -> Router<Arc<super::routes::AppState>> {
         .route("/init-script/:name", delete(delete_init_script))
         // Migration
         .route("/migrate", post(migrate_library))
+        // Import of existing .claude / .opencode project directories
+        .route("/import/harness", post(import_harness))
+        .route("/import/harness/archive", post(import_harness_archive))
+        // Export to other harness layouts (downloadable archive)
+        .route("/export", get(export_library))
         // Rename (works for all item types)
         .route("/rename/:item_type/:name", post(rename_item))
+        // Item history
+        .route("/history/:item_type/:name", get(get_item_history))
+        .route("/history/:item_type/:name/diff", get(get_item_diff))
+        .route("/history/:item_type/:name/show", get(get_item_at_revision))
+        .route("/history/:item_type/:name/revert", post(revert_item))
         // OpenCode Settings (oh-my-opencode.json)
         .route("/opencode/settings", get(get_opencode_settings))
         .route("/opencode/settings", put(save_opencode_settings))
@@ -302,6 +564,14 @@ pub fn routes() -> Router<Arc<super::routes::AppState>> {
         .route("/skill/registry/search", get(search_registry))
         .route("/skill/registry/list/:identifier", get(list_repo_skills))
         .route("/skill/registry/install", post(install_from_registry))
+        .route("/skill/registry/mirror", post(build_registry_mirror))
+        .route("/skill/registry/lock", get(get_skills_lock))
+        .route("/skill/registry/lock/install", post(install_locked_skills))
+        .route("/skill/registry/updates", get(check_skill_updates))
+        .route(
+            "/skill/registry/upgrade/:name",
+            post(upgrade_registry_skill),
+        )
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -324,25 +594,77 @@ pub struct ImportSkillRequest {
     name: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ImportHarnessRequest {
+    /// Local path or git repository to import from
+    source: HarnessImportSource,
+    #[serde(flatten)]
+    options: HarnessImportOptions,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportLibraryQuery {
+    format: ExportFormat,
+    /// Comma-separated names; omitted exports all, empty exports none
+    skills: Option<String>,
+    commands: Option<String>,
+    agents: Option<String>,
+    /// Config profile providing the rules (`.claudecode/CLAUDE.md`)
+    profile: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RegistrySearchQuery {
     /// Search query
     q: String,
+    /// Download the registry index even if the cached copy is fresh
+    #[serde(default)]
+    refresh: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BuildMirrorRequest {
+    /// Repository identifiers to mirror (refreshes the whole mirror if empty)
+    #[serde(default)]
+    identifiers: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct InstallFromRegistryRequest {
     /// Repository identifier (e.g., "vercel-labs/agent-skills")
     identifier: String,
-    /// Specific skill names to install (optional, installs all if empty)
+    /// Skill to install in multi-skill repositories (at most one entry)
     #[serde(default)]
     skills: Vec<String>,
     /// Target name for the skill in the library (defaults to skill name)
     name: Option<String>,
+    /// Branch, tag or commit to pin (defaults to the repository HEAD)
+    #[serde(default)]
+    revision: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct InstallLockedSkillsRequest {
+    /// Overwrite skills with local modifications
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct UpgradeSkillRequest {
+    /// Branch, tag or commit to upgrade to (defaults to the repository HEAD)
+    #[serde(default)]
+    revision: Option<String>,
+    /// Overwrite local modifications
+    #[serde(default)]
+    force: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct SaveWorkspaceTemplateRequest {
+    /// Parent template to inherit from
+    #[serde(default)]
+    pub extends: Option<String>,
     pub description: Option<String>,
     pub distro: Option<String>,
     pub skills: Option<Vec<String>>,
@@ -364,6 +686,53 @@ pub struct SaveWorkspaceTemplateRequest {
     /// Config profile to use for workspaces created from this template.
     #[serde(default)]
     pub config_profile: Option<String>,
+    /// Background services started when the workspace is ready.
+    #[serde(default)]
+    pub services: Option<Vec<crate::workspace_services::ServiceDefinition>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveConflictRequest {
+    /// Resolved content; `null` deletes the file.
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CompleteMergeRequest {
+    /// Merge commit message (defaults to git's merge message).
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    /// Maximum number of commits to return.
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    /// Base revision.
+    pub from: String,
+    /// Target revision (defaults to the working tree).
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShowQuery {
+    pub revision: String,
+    /// File within a directory item (skill, init script, config profile).
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevertRequest {
+    /// Revision to restore the item to.
+    pub revision: String,
+    /// Commit message (defaults to "Revert <type> <name> to <revision>").
+    pub message: Option<String>,
+    /// Discard uncommitted changes to the item instead of refusing to revert.
+    #[serde(default)]
+    pub force: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -407,7 +776,8 @@ fn sanitize_skill_list(skills: Vec<String>) -> Vec<String> {
 /// This includes plugins, OpenCode settings, Sandboxed config, and workspaces.
 async fn sync_library_configs(
     state: &Arc<super::routes::AppState>,
-    library: &LibraryStore,
+    library: &Arc<LibraryStore>,
+    changes: Option<Vec<LibraryItemRef>>,
 ) -> Result<(), (StatusCode, String)> {
     // Sync plugins to global OpenCode config
     let plugins = library
@@ -428,12 +798,35 @@ async fn sync_library_configs(
         tracing::warn!(error = %e, "Failed to sync sandboxed config during library sync");
     }
 
-    // Sync skills and tools to workspaces
-    sync_all_workspaces(state, library).await;
+    // Resync the workspaces referencing changed items, or all of them when
+    // the changes are unknown
+    match changes {
+        Some(items) => library_changed(state, library, items),
+        None => resync_workspaces(state, library, None),
+    }
 
     Ok(())
 }
 
+/// Items changed in `layer` since `before`, its `HEAD` prior to a pull or
+/// merge; `None` when they cannot be determined.
+async fn changes_since(
+    layer: &LibraryStore,
+    before: Option<String>,
+) -> Option<Vec<LibraryItemRef>> {
+    layer
+        .changed_items_since(before.as_deref())
+        .await
+        .map_err(|e| {
+            tracing::warn!(
+                layer = %layer.layer_name(),
+                error = %e,
+                "Failed to determine changed library items, resyncing everything"
+            );
+        })
+        .ok()
+}
+
 /// GET /api/library/status - Get git status of the library.
 async fn get_status(
     State(state): State<Arc<super::routes::AppState>>,
@@ -450,28 +843,21 @@ async fn get_status(
 /// POST /api/library/sync - Pull latest changes from remote.
 ///
 /// Returns 409 Conflict if history has diverged (e.g., after force push).
-/// In that case, use /force-sync to reset to remote or /force-push to overwrite remote.
+/// In that case, use /merge to combine both histories, /force-sync to reset to
+/// remote or /force-push to overwrite remote.
 async fn sync_library(
     State(state): State<Arc<super::routes::AppState>>,
     headers: HeaderMap,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
+    let before = library.head_revision().await;
 
-    // Try to sync - check for diverged history error
-    if let Err(e) = library.sync().await {
-        let error_msg = e.to_string();
-        if error_msg.starts_with("DIVERGED_HISTORY:") {
-            // Return 409 Conflict with a structured error message
-            let msg = error_msg
-                .strip_prefix("DIVERGED_HISTORY: ")
-                .unwrap_or(&error_msg);
-            return Err((StatusCode::CONFLICT, format!("DIVERGED_HISTORY: {}", msg)));
-        }
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, error_msg));
-    }
+    // Diverged history and a merge in progress are 409 Conflict
+    library.sync().await.map_err(library_error)?;
 
     // Sync all library configurations
-    sync_library_configs(&state, library.as_ref()).await?;
+    let changes = changes_since(&library, before).await;
+    sync_library_configs(&state, &library, changes).await?;
 
     Ok((StatusCode::OK, "Synced successfully".to_string()))
 }
@@ -485,13 +871,15 @@ async fn force_sync_library(
     headers: HeaderMap,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
+    let before = library.head_revision().await;
     library
         .force_sync()
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // Sync all library configurations
-    sync_library_configs(&state, library.as_ref()).await?;
+    let changes = changes_since(&library, before).await;
+    sync_library_configs(&state, &library, changes).await?;
 
     Ok((
         StatusCode::OK,
@@ -517,37 +905,340 @@ async fn force_push_library(
                 "Force pushed successfully - remote updated with local changes".to_string(),
             )
         })
+        .map_err(library_error)
+}
+
+/// GET /api/library/validate - Check items for schema errors and broken references.
+async fn validate_library(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ValidationReport>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    library
+        .validate()
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// GET /api/library/scan - Scan all layers for plaintext secrets.
+async fn scan_library(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<SecretScanReport>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    library
+        .scan_secrets()
+        .await
+        .map(Json)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
 
+/// HTTP status for a library error: typed [`LibraryError`]s map to 404, 400,
+/// 409 and 422 (plaintext secrets), anything else is a 500.
+fn library_error_status(e: &anyhow::Error) -> StatusCode {
+    match e.downcast_ref::<LibraryError>() {
+        Some(LibraryError::NotFound(_)) => StatusCode::NOT_FOUND,
+        Some(LibraryError::Invalid(_)) => StatusCode::BAD_REQUEST,
+        Some(LibraryError::Conflict(_)) => StatusCode::CONFLICT,
+        Some(LibraryError::SecretsDetected(_)) => StatusCode::UNPROCESSABLE_ENTITY,
+        None => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn library_error(e: anyhow::Error) -> (StatusCode, String) {
+    (library_error_status(&e), e.to_string())
+}
+
+/// Refuse to commit or push an invalid library when the gate is enabled.
+async fn check_validation_gate(
+    state: &super::routes::AppState,
+    library: &LibraryStore,
+) -> Result<(), (StatusCode, String)> {
+    if !state.settings.validate_library_on_commit().await {
+        return Ok(());
+    }
+    let report = library
+        .validate()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if report.is_ok() {
+        Ok(())
+    } else {
+        Err((StatusCode::UNPROCESSABLE_ENTITY, report.summary()))
+    }
+}
+
 /// POST /api/library/commit - Commit all changes.
+///
+/// Returns 422 if validation on commit is enabled and the library has errors,
+/// or if plaintext secrets are found.
 async fn commit_library(
     State(state): State<Arc<super::routes::AppState>>,
     headers: HeaderMap,
     Json(req): Json<CommitRequest>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
+    check_validation_gate(&state, &library).await?;
     let author = extract_git_author(&headers);
     library
         .commit(&req.message, author.as_ref())
         .await
         .map(|_| (StatusCode::OK, "Committed successfully".to_string()))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(library_error)
 }
 
 /// POST /api/library/push - Push changes to remote.
+///
+/// Returns 422 if validation on commit is enabled and the library has errors,
+/// or if plaintext secrets are found.
 async fn push_library(
     State(state): State<Arc<super::routes::AppState>>,
     headers: HeaderMap,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
+    check_validation_gate(&state, &library).await?;
     library
         .push()
         .await
         .map(|_| (StatusCode::OK, "Pushed successfully".to_string()))
+        .map_err(library_error)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Merge
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// GET /api/library/merge - Current merge state and conflicted files.
+async fn get_merge_status(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<MergeStatus>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    library
+        .merge_status()
+        .await
+        .map(Json)
+        .map_err(library_error)
+}
+
+/// POST /api/library/merge - Merge remote changes into local history.
+///
+/// Use this instead of force-sync/force-push when history has diverged. If the
+/// merge stops on conflicts, `in_progress` is true and `conflicts` lists the
+/// files with their base/ours/theirs content.
+async fn merge_library(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<MergeStatus>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    let author = extract_git_author(&headers);
+    let before = library.head_revision().await;
+    let status = library
+        .merge(author.as_ref())
+        .await
+        .map_err(library_error)?;
+
+    if !status.in_progress {
+        let changes = changes_since(&library, before).await;
+        sync_library_configs(&state, &library, changes).await?;
+    }
+    Ok(Json(status))
+}
+
+/// PUT /api/library/merge/files/*path - Resolve a conflicted file.
+async fn resolve_merge_conflict(
+    State(state): State<Arc<super::routes::AppState>>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<ResolveConflictRequest>,
+) -> Result<Json<MergeStatus>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    library
+        .resolve_conflict(&path, req.content.as_deref())
+        .await
+        .map(Json)
+        .map_err(library_error)
+}
+
+/// POST /api/library/merge/complete - Commit the merge once all conflicts are resolved.
+///
+/// Subject to the same validation gate and secret scan as `/commit`.
+async fn complete_merge(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+    body: Option<Json<CompleteMergeRequest>>,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    check_validation_gate(&state, &library).await?;
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    let author = extract_git_author(&headers);
+    let before = library.head_revision().await;
+    library
+        .complete_merge(req.message.as_deref(), author.as_ref())
+        .await
+        .map_err(library_error)?;
+
+    let changes = changes_since(&library, before).await;
+    sync_library_configs(&state, &library, changes).await?;
+
+    Ok((StatusCode::OK, "Merge completed successfully".to_string()))
+}
+
+/// POST /api/library/merge/abort - Abort the merge and restore the previous state.
+async fn abort_merge(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    library
+        .abort_merge()
+        .await
+        .map(|_| (StatusCode::OK, "Merge aborted".to_string()))
+        .map_err(library_error)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Overlay Layers
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A library layer and its git status.
+#[derive(Debug, Serialize)]
+struct LayerInfo {
+    name: String,
+    remote: String,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<LibraryStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// GET /api/library/layers - List library layers, lowest precedence first.
+///
+/// Layers that failed to clone come last, with the clone error.
+async fn list_layers(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<LayerInfo>>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    let mut layers = Vec::new();
+    for layer in library.layer_stack() {
+        let (status, error) = match layer.status().await {
+            Ok(status) => (Some(status), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        layers.push(LayerInfo {
+            name: layer.layer_name().to_string(),
+            remote: layer.remote().to_string(),
+            path: layer.path().to_string_lossy().to_string(),
+            status,
+            error,
+        });
+    }
+    for failed in library.failed_layers() {
+        layers.push(LayerInfo {
+            name: failed.name.clone(),
+            remote: failed.remote.clone(),
+            path: layers_root(library.path())
+                .join(&failed.name)
+                .to_string_lossy()
+                .to_string(),
+            status: None,
+            error: Some(failed.error.clone()),
+        });
+    }
+    Ok(Json(layers))
+}
+
+/// GET /api/library/layers/items - Show which layer provides each item.
+async fn list_layered_items(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<LayeredItem>>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    library
+        .layered_items()
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// GET /api/library/layers/:layer/status - Get git status of one layer.
+async fn get_layer_status(
+    State(state): State<Arc<super::routes::AppState>>,
+    Path(layer): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<LibraryStatus>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    find_layer(&library, &layer)?
+        .status()
+        .await
+        .map(Json)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
 
+/// POST /api/library/layers/:layer/sync - Pull latest changes of one layer.
+///
+/// Returns 409 Conflict if the layer's history has diverged.
+async fn sync_layer(
+    State(state): State<Arc<super::routes::AppState>>,
+    Path(layer): Path<String>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    let layer_store = find_layer(&library, &layer)?;
+    let before = layer_store.head_revision().await;
+    layer_store.sync().await.map_err(library_error)?;
+
+    // The effective library changed, so resync configurations
+    let changes = changes_since(layer_store, before).await;
+    sync_library_configs(&state, &library, changes).await?;
+
+    Ok((
+        StatusCode::OK,
+        format!("Layer '{}' synced successfully", layer),
+    ))
+}
+
+/// POST /api/library/layers/:layer/commit - Commit all changes in one layer.
+///
+/// Subject to the same validation gate and secret scan as `/commit`.
+async fn commit_layer(
+    State(state): State<Arc<super::routes::AppState>>,
+    Path(layer): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<CommitRequest>,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    find_layer(&library, &layer)?;
+    check_validation_gate(&state, &library).await?;
+    let author = extract_git_author(&headers);
+    find_layer(&library, &layer)?
+        .commit(&req.message, author.as_ref())
+        .await
+        .map(|_| (StatusCode::OK, "Committed successfully".to_string()))
+        .map_err(library_error)
+}
+
+/// POST /api/library/layers/:layer/push - Push one layer to its remote.
+///
+/// Subject to the same validation gate and secret scan as `/push`.
+async fn push_layer(
+    State(state): State<Arc<super::routes::AppState>>,
+    Path(layer): Path<String>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    find_layer(&library, &layer)?;
+    check_validation_gate(&state, &library).await?;
+    find_layer(&library, &layer)?
+        .push()
+        .await
+        .map(|_| (StatusCode::OK, "Pushed successfully".to_string()))
+        .map_err(library_error)
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // MCP Servers
 // ─────────────────────────────────────────────────────────────────────────────
@@ -572,11 +1263,15 @@ async fn save_mcps(
     Json(servers): Json<HashMap<String, McpServer>>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
+    let layer = extract_library_layer(&headers).unwrap_or_else(|| library.layer_name().to_string());
+    let before = library.get_mcp_servers().await.unwrap_or_default();
     library
-        .save_mcp_servers(&servers)
+        .save_layer_mcp_servers(&layer, &servers)
         .await
-        .map(|_| (StatusCode::OK, "MCPs saved successfully".to_string()))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let after = library.get_mcp_servers().await.unwrap_or_default();
+    library_changed(&state, &library, mcp_server_changes(&before, &after));
+    Ok((StatusCode::OK, "MCPs saved successfully".to_string()))
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -620,11 +1315,11 @@ async fn save_skill(
     Json(req): Json<SaveContentRequest>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::Skill, &name)?
         .save_skill(&name, &req.content)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    sync_skill_to_workspaces(&state, library.as_ref(), &name).await;
+    item_changed(&state, &library, LayeredItemKind::Skill, &name);
     Ok((StatusCode::OK, "Skill saved successfully".to_string()))
 }
 
@@ -635,11 +1330,11 @@ async fn delete_skill(
     headers: HeaderMap,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::Skill, &name)?
         .delete_skill(&name)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    sync_skill_to_workspaces(&state, library.as_ref(), &name).await;
+    item_changed(&state, &library, LayeredItemKind::Skill, &name);
     Ok((StatusCode::OK, "Skill deleted successfully".to_string()))
 }
 
@@ -671,11 +1366,11 @@ async fn save_skill_reference(
     Json(req): Json<SaveContentRequest>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::Skill, &name)?
         .save_skill_reference(&name, &path, &req.content)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    sync_skill_to_workspaces(&state, library.as_ref(), &name).await;
+    item_changed(&state, &library, LayeredItemKind::Skill, &name);
     Ok((StatusCode::OK, "Reference saved successfully".to_string()))
 }
 
@@ -686,7 +1381,7 @@ async fn delete_skill_reference(
     headers: HeaderMap,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::Skill, &name)?
         .delete_skill_reference(&name, &path)
         .await
         .map_err(|e| {
@@ -698,7 +1393,7 @@ async fn delete_skill_reference(
                 (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
             }
         })?;
-    sync_skill_to_workspaces(&state, library.as_ref(), &name).await;
+    item_changed(&state, &library, LayeredItemKind::Skill, &name);
     Ok((StatusCode::OK, "Reference deleted successfully".to_string()))
 }
 
@@ -827,7 +1522,7 @@ async fn import_skill(
         )
     })?;
 
-    sync_skill_to_workspaces(&state, library.as_ref(), &skill_name).await;
+    item_changed(&state, &library, LayeredItemKind::Skill, &skill_name);
     Ok(Json(skill))
 }
 
@@ -907,6 +1602,125 @@ fn find_zip_prefix(archive: &mut zip::ZipArchive<std::io::Cursor<&[u8]>>) -> Opt
     first_dir
 }
 
+/// Run a harness import and resync the workspaces using the imported items.
+async fn run_harness_import(
+    state: &Arc<super::routes::AppState>,
+    library: &Arc<LibraryStore>,
+    headers: &HeaderMap,
+    source: HarnessImportSource,
+    options: &HarnessImportOptions,
+) -> Result<Json<HarnessImportReport>, (StatusCode, String)> {
+    let layer = extract_library_layer(headers);
+    let report = library
+        .import_harness(source, options, layer.as_deref())
+        .await
+        .map_err(|e| (library_error_status(&e), format!("{:#}", e)))?;
+    library_changed(state, library, report.changed_items());
+    Ok(Json(report))
+}
+
+/// POST /api/library/import/harness - Import `.claude` / `.opencode` artifacts
+/// from a local path or git repository.
+async fn import_harness(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<ImportHarnessRequest>,
+) -> Result<Json<HarnessImportReport>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    run_harness_import(&state, &library, &headers, req.source, &req.options).await
+}
+
+/// POST /api/library/import/harness/archive - Import `.claude` / `.opencode`
+/// artifacts from an uploaded ZIP archive (multipart field `file`).
+async fn import_harness_archive(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+    Query(options): Query<HarnessImportOptions>,
+    mut multipart: Multipart,
+) -> Result<Json<HarnessImportReport>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+
+    let mut data = None;
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Failed to read upload: {}", e),
+        )
+    })? {
+        if field.name() == Some("file") {
+            let bytes = field.bytes().await.map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("Failed to read file: {}", e),
+                )
+            })?;
+            data = Some(bytes.to_vec());
+            break;
+        }
+    }
+    let data = data.ok_or_else(|| (StatusCode::BAD_REQUEST, "No file uploaded".to_string()))?;
+
+    run_harness_import(
+        &state,
+        &library,
+        &headers,
+        HarnessImportSource::Archive(data),
+        &options,
+    )
+    .await
+}
+
+/// Split a comma-separated name list.
+fn split_names(names: Option<String>) -> Option<Vec<String>> {
+    names.map(|names| {
+        names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect()
+    })
+}
+
+/// GET /api/library/export - Download library items in another harness layout.
+async fn export_library(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<ExportLibraryQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    let selection = ExportSelection {
+        skills: split_names(query.skills),
+        commands: split_names(query.commands),
+        agents: split_names(query.agents),
+        profile: query.profile,
+    };
+    let items = harness_export::collect_export_items(&library, &selection)
+        .await
+        .map_err(|e| {
+            if e.to_string().contains("not found") {
+                (StatusCode::NOT_FOUND, e.to_string())
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            }
+        })?;
+    let archive = harness_export::render_export(query.format, &items)
+        .to_zip()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let filename = format!("library-{}.zip", query.format.as_str());
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        archive,
+    ))
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // Commands
 // ─────────────────────────────────────────────────────────────────────────────
@@ -948,11 +1762,12 @@ async fn save_command(
     Json(req): Json<SaveContentRequest>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::Command, &name)?
         .save_command(&name, &req.content)
         .await
-        .map(|_| (StatusCode::OK, "Command saved successfully".to_string()))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::Command, &name);
+    Ok((StatusCode::OK, "Command saved successfully".to_string()))
 }
 
 /// DELETE /api/library/commands/:name - Delete a command.
@@ -962,11 +1777,12 @@ async fn delete_command(
     headers: HeaderMap,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::Command, &name)?
         .delete_command(&name)
         .await
-        .map(|_| (StatusCode::OK, "Command deleted successfully".to_string()))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::Command, &name);
+    Ok((StatusCode::OK, "Command deleted successfully".to_string()))
 }
 
 /// Response for builtin commands endpoint.
@@ -1196,11 +2012,12 @@ async fn save_library_agent(
     Json(agent): Json<LibraryAgent>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::Agent, &name)?
         .save_library_agent(&name, &agent)
         .await
-        .map(|_| (StatusCode::OK, "Agent saved successfully".to_string()))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::Agent, &name);
+    Ok((StatusCode::OK, "Agent saved successfully".to_string()))
 }
 
 /// DELETE /api/library/agent/:name - Delete a library agent.
@@ -1210,11 +2027,12 @@ async fn delete_library_agent(
     headers: HeaderMap,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::Agent, &name)?
         .delete_library_agent(&name)
         .await
-        .map(|_| (StatusCode::OK, "Agent deleted successfully".to_string()))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::Agent, &name);
+    Ok((StatusCode::OK, "Agent deleted successfully".to_string()))
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -1254,6 +2072,25 @@ async fn get_workspace_template(
         })
 }
 
+/// GET /api/library/workspace-template/:name/resolved - Get a workspace template
+/// with its `extends` chain applied, as used to build workspaces.
+async fn resolve_workspace_template(
+    State(state): State<Arc<super::routes::AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<WorkspaceTemplate>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    library
+        .resolve_workspace_template(&name)
+        .await
+        .map(Json)
+        .map_err(|e| match e.downcast_ref::<LibraryError>() {
+            // A broken `extends` chain
+            Some(LibraryError::Invalid(_)) => (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()),
+            _ => library_error(e),
+        })
+}
+
 /// PUT /api/library/workspace-template/:name - Save workspace template.
 async fn save_workspace_template(
     State(state): State<Arc<super::routes::AppState>>,
@@ -1274,9 +2111,34 @@ async fn save_workspace_template(
         }
     }
 
+    let services = req.services.unwrap_or_default();
+    crate::workspace_services::validate_services(&services)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
     let library = ensure_library(&state, &headers).await?;
+    let extends = req
+        .extends
+        .map(|parent| parent.trim().to_string())
+        .filter(|parent| !parent.is_empty());
+    if let Some(parent) = extends.as_deref() {
+        let chain = library
+            .workspace_template_chain(parent)
+            .await
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        if chain.contains(&name) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Template '{}' cannot extend '{}': inheritance cycle",
+                    name, parent
+                ),
+            ));
+        }
+    }
+
     let template = WorkspaceTemplate {
         name: name.clone(),
+        extends,
         description: req.description.clone(),
         path: format!("workspace-template/{}.json", name),
         distro: req.distro.clone(),
@@ -1289,18 +2151,23 @@ async fn save_workspace_template(
         tailscale_mode: req.tailscale_mode,
         mcps: req.mcps.unwrap_or_default(),
         config_profile: req.config_profile.clone(),
+        services,
     };
 
-    library
-        .save_workspace_template(&name, &template)
-        .await
-        .map(|_| {
-            (
-                StatusCode::OK,
-                "Workspace template saved successfully".to_string(),
-            )
-        })
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+    write_layer(
+        &library,
+        &headers,
+        LayeredItemKind::WorkspaceTemplate,
+        &name,
+    )?
+    .save_workspace_template(&name, &template)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::WorkspaceTemplate, &name);
+    Ok((
+        StatusCode::OK,
+        "Workspace template saved successfully".to_string(),
+    ))
 }
 
 /// DELETE /api/library/workspace-template/:name - Delete workspace template.
@@ -1310,16 +2177,20 @@ async fn delete_workspace_template(
     headers: HeaderMap,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
-        .delete_workspace_template(&name)
-        .await
-        .map(|_| {
-            (
-                StatusCode::OK,
-                "Workspace template deleted successfully".to_string(),
-            )
-        })
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+    write_layer(
+        &library,
+        &headers,
+        LayeredItemKind::WorkspaceTemplate,
+        &name,
+    )?
+    .delete_workspace_template(&name)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::WorkspaceTemplate, &name);
+    Ok((
+        StatusCode::OK,
+        "Workspace template deleted successfully".to_string(),
+    ))
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -1363,11 +2234,12 @@ async fn save_init_script(
     Json(req): Json<SaveContentRequest>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::InitScript, &name)?
         .save_init_script(&name, &req.content)
         .await
-        .map(|_| (StatusCode::OK, "Init script saved successfully".to_string()))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::InitScript, &name);
+    Ok((StatusCode::OK, "Init script saved successfully".to_string()))
 }
 
 /// DELETE /api/library/init-script/:name - Delete an init script fragment.
@@ -1377,16 +2249,15 @@ async fn delete_init_script(
     headers: HeaderMap,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::InitScript, &name)?
         .delete_init_script(&name)
         .await
-        .map(|_| {
-            (
-                StatusCode::OK,
-                "Init script deleted successfully".to_string(),
-            )
-        })
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::InitScript, &name);
+    Ok((
+        StatusCode::OK,
+        "Init script deleted successfully".to_string(),
+    ))
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -1743,11 +2614,26 @@ async fn rename_item(
                 // Update workspace skill lists
                 update_workspace_skill_references(&state, &name, &req.new_name).await;
                 // Sync skills to workspaces
-                sync_skill_to_workspaces(&state, library.as_ref(), &req.new_name).await;
+                library_changed(
+                    &state,
+                    &library,
+                    vec![
+                        LibraryItemRef::new(LayeredItemKind::Skill, &name),
+                        LibraryItemRef::new(LayeredItemKind::Skill, &req.new_name),
+                    ],
+                );
             }
             ItemType::WorkspaceTemplate => {
                 // Update workspace template references
                 update_workspace_template_references(&state, &name, &req.new_name).await;
+                library_changed(
+                    &state,
+                    &library,
+                    vec![
+                        LibraryItemRef::new(LayeredItemKind::WorkspaceTemplate, &name),
+                        LibraryItemRef::new(LayeredItemKind::WorkspaceTemplate, &req.new_name),
+                    ],
+                );
             }
             _ => {}
         }
@@ -1766,6 +2652,118 @@ async fn rename_item(
     Ok(Json(result))
 }
 
+// ─────────────────────────────────────────────────────────────────────────────
+// Item History
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn parse_history_item_type(item_type: &str) -> Result<LayeredItemKind, (StatusCode, String)> {
+    match item_type {
+        "skill" => Ok(LayeredItemKind::Skill),
+        "command" => Ok(LayeredItemKind::Command),
+        "agent" => Ok(LayeredItemKind::Agent),
+        "workspace-template" => Ok(LayeredItemKind::WorkspaceTemplate),
+        "init-script" => Ok(LayeredItemKind::InitScript),
+        "config-profile" => Ok(LayeredItemKind::ConfigProfile),
+        _ => Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Invalid item type '{}'. Valid types: skill, command, agent, workspace-template, init-script, config-profile",
+                item_type
+            ),
+        )),
+    }
+}
+
+/// GET /api/library/history/:item_type/:name - Commits that touched an item.
+async fn get_item_history(
+    State(state): State<Arc<super::routes::AppState>>,
+    Path((item_type, name)): Path<(String, String)>,
+    Query(query): Query<HistoryQuery>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<LibraryCommit>>, (StatusCode, String)> {
+    let kind = parse_history_item_type(&item_type)?;
+    let library = ensure_library(&state, &headers).await?;
+    let limit = query
+        .limit
+        .unwrap_or(crate::library::history::DEFAULT_HISTORY_LIMIT);
+    library
+        .item_history(kind, &name, limit)
+        .await
+        .map(Json)
+        .map_err(library_error)
+}
+
+/// GET /api/library/history/:item_type/:name/diff?from=&to= - Diff an item between revisions.
+async fn get_item_diff(
+    State(state): State<Arc<super::routes::AppState>>,
+    Path((item_type, name)): Path<(String, String)>,
+    Query(query): Query<DiffQuery>,
+    headers: HeaderMap,
+) -> Result<String, (StatusCode, String)> {
+    let kind = parse_history_item_type(&item_type)?;
+    let library = ensure_library(&state, &headers).await?;
+    library
+        .item_diff(kind, &name, &query.from, query.to.as_deref())
+        .await
+        .map_err(library_error)
+}
+
+/// GET /api/library/history/:item_type/:name/show?revision=&file= - Item content at a revision.
+async fn get_item_at_revision(
+    State(state): State<Arc<super::routes::AppState>>,
+    Path((item_type, name)): Path<(String, String)>,
+    Query(query): Query<ShowQuery>,
+    headers: HeaderMap,
+) -> Result<String, (StatusCode, String)> {
+    let kind = parse_history_item_type(&item_type)?;
+    let library = ensure_library(&state, &headers).await?;
+    library
+        .item_at_revision(kind, &name, &query.revision, query.file.as_deref())
+        .await
+        .map_err(library_error)
+}
+
+/// POST /api/library/history/:item_type/:name/revert - Restore an item to a revision.
+///
+/// The revert is committed with the acting user (git author headers) as author.
+/// Returns 422 if validation on commit is enabled and the restored library has
+/// errors, or if plaintext secrets are found; the restored item is then left
+/// staged.
+async fn revert_item(
+    State(state): State<Arc<super::routes::AppState>>,
+    Path((item_type, name)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(req): Json<RevertRequest>,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    let kind = parse_history_item_type(&item_type)?;
+    let library = ensure_library(&state, &headers).await?;
+    let author = extract_git_author(&headers);
+    let revision = library
+        .restore_item(kind, &name, &req.revision, req.force)
+        .await
+        .map_err(library_error)?;
+    check_validation_gate(&state, &library).await?;
+    library
+        .commit_revert(
+            kind,
+            &name,
+            &revision,
+            req.message.as_deref(),
+            author.as_ref(),
+        )
+        .await
+        .map_err(library_error)?;
+
+    sync_library_configs(
+        &state,
+        &library,
+        Some(vec![LibraryItemRef::new(kind, &name)]),
+    )
+    .await?;
+
+    Ok((StatusCode::OK, "Reverted successfully".to_string()))
+}
+
 /// Update workspace skill references when a skill is renamed.
 async fn update_workspace_skill_references(
     state: &super::routes::AppState,
@@ -1882,17 +2880,18 @@ async fn create_config_profile(
     Json(req): Json<CreateConfigProfileRequest>,
 ) -> Result<Json<ConfigProfile>, (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    let profile = library
         .create_config_profile(&req.name, req.base_profile.as_deref())
         .await
-        .map(Json)
         .map_err(|e| {
             if e.to_string().contains("already exists") {
                 (StatusCode::CONFLICT, e.to_string())
             } else {
                 (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
             }
-        })
+        })?;
+    item_changed(&state, &library, LayeredItemKind::ConfigProfile, &req.name);
+    Ok(Json(profile))
 }
 
 /// GET /api/library/config-profile/:name - Get a config profile by name.
@@ -1923,16 +2922,15 @@ async fn save_config_profile(
     Json(profile): Json<ConfigProfile>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::ConfigProfile, &name)?
         .save_config_profile(&name, &profile)
         .await
-        .map(|_| {
-            (
-                StatusCode::OK,
-                "Config profile saved successfully".to_string(),
-            )
-        })
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::ConfigProfile, &name);
+    Ok((
+        StatusCode::OK,
+        "Config profile saved successfully".to_string(),
+    ))
 }
 
 /// DELETE /api/library/config-profile/:name - Delete a config profile.
@@ -1942,22 +2940,21 @@ async fn delete_config_profile(
     headers: HeaderMap,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::ConfigProfile, &name)?
         .delete_config_profile(&name)
         .await
-        .map(|_| {
-            (
-                StatusCode::OK,
-                "Config profile deleted successfully".to_string(),
-            )
-        })
         .map_err(|e| {
             if e.to_string().contains("Cannot delete") {
                 (StatusCode::BAD_REQUEST, e.to_string())
             } else {
                 (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
             }
-        })
+        })?;
+    item_changed(&state, &library, LayeredItemKind::ConfigProfile, &name);
+    Ok((
+        StatusCode::OK,
+        "Config profile deleted successfully".to_string(),
+    ))
 }
 
 /// GET /api/library/config-profile/:name/opencode/settings - Get OpenCode settings for a profile.
@@ -1990,16 +2987,15 @@ async fn save_opencode_settings_for_profile(
         ));
     }
 
-    library
+    write_layer(&library, &headers, LayeredItemKind::ConfigProfile, &name)?
         .save_opencode_settings_for_profile(&name, &settings)
         .await
-        .map(|_| {
-            (
-                StatusCode::OK,
-                "OpenCode settings saved successfully".to_string(),
-            )
-        })
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::ConfigProfile, &name);
+    Ok((
+        StatusCode::OK,
+        "OpenCode settings saved successfully".to_string(),
+    ))
 }
 
 /// GET /api/library/config-profile/:name/sandboxed-sh/config - Get Sandboxed config for a profile.
@@ -2024,16 +3020,15 @@ async fn save_sandboxed_config_for_profile(
     Json(config): Json<SandboxedConfig>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::ConfigProfile, &name)?
         .save_sandboxed_config_for_profile(&name, &config)
         .await
-        .map(|_| {
-            (
-                StatusCode::OK,
-                "Sandboxed config saved successfully".to_string(),
-            )
-        })
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::ConfigProfile, &name);
+    Ok((
+        StatusCode::OK,
+        "Sandboxed config saved successfully".to_string(),
+    ))
 }
 
 /// GET /api/library/config-profile/:name/claudecode/config - Get Claude Code config for a profile.
@@ -2058,16 +3053,15 @@ async fn save_claudecode_config_for_profile(
     Json(config): Json<ClaudeCodeConfig>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::ConfigProfile, &name)?
         .save_claudecode_config_for_profile(&name, &config)
         .await
-        .map(|_| {
-            (
-                StatusCode::OK,
-                "Claude Code config saved successfully".to_string(),
-            )
-        })
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::ConfigProfile, &name);
+    Ok((
+        StatusCode::OK,
+        "Claude Code config saved successfully".to_string(),
+    ))
 }
 
 /// GET /api/library/config-profile/:name/ampcode/config - Get Amp Code config for a profile.
@@ -2092,16 +3086,15 @@ async fn save_ampcode_config_for_profile(
     Json(config): Json<AmpCodeConfig>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::ConfigProfile, &name)?
         .save_ampcode_config_for_profile(&name, &config)
         .await
-        .map(|_| {
-            (
-                StatusCode::OK,
-                "Amp Code config saved successfully".to_string(),
-            )
-        })
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::ConfigProfile, &name);
+    Ok((
+        StatusCode::OK,
+        "Amp Code config saved successfully".to_string(),
+    ))
 }
 
 /// GET /api/library/config-profile/:name/files - List all files in a config profile.
@@ -2145,11 +3138,12 @@ async fn save_config_profile_file(
     body: String,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::ConfigProfile, &name)?
         .save_config_profile_file(&name, &file_path, &body)
         .await
-        .map(|_| (StatusCode::OK, "File saved successfully".to_string()))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    item_changed(&state, &library, LayeredItemKind::ConfigProfile, &name);
+    Ok((StatusCode::OK, "File saved successfully".to_string()))
 }
 
 /// DELETE /api/library/config-profile/:name/file/*file_path - Delete a specific file from a config profile.
@@ -2159,17 +3153,18 @@ async fn delete_config_profile_file(
     headers: HeaderMap,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let library = ensure_library(&state, &headers).await?;
-    library
+    write_layer(&library, &headers, LayeredItemKind::ConfigProfile, &name)?
         .delete_config_profile_file(&name, &file_path)
         .await
-        .map(|_| (StatusCode::OK, "File deleted successfully".to_string()))
         .map_err(|e| {
             if e.to_string().contains("not found") {
                 (StatusCode::NOT_FOUND, e.to_string())
             } else {
                 (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
             }
-        })
+        })?;
+    item_changed(&state, &library, LayeredItemKind::ConfigProfile, &name);
+    Ok((StatusCode::OK, "File deleted successfully".to_string()))
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -2218,14 +3213,33 @@ async fn get_harness_default_file(
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
-// Skills Registry (skills.sh) Handlers
+// Skills Registry Handlers
 // ─────────────────────────────────────────────────────────────────────────────
 
-/// GET /api/library/skill/registry/search?q=<query> - Search skills.sh registry.
+/// Directory the downloaded registry index is cached in.
+fn registry_cache_dir(state: &super::routes::AppState) -> std::path::PathBuf {
+    state
+        .config
+        .working_dir
+        .join(".sandboxed-sh")
+        .join("skills-registry")
+}
+
+/// GET /api/library/skill/registry/search?q=<query> - Search the configured skills registry.
 async fn search_registry(
+    State(state): State<Arc<super::routes::AppState>>,
     axum::extract::Query(query): axum::extract::Query<RegistrySearchQuery>,
 ) -> Result<Json<Vec<crate::skills_registry::RegistrySkillListing>>, (StatusCode, String)> {
-    let results = crate::skills_registry::search_skills(&query.q)
+    let source = state.settings.skills_registry().await;
+    let cache_dir = registry_cache_dir(&state);
+    if query.refresh {
+        if let RegistrySource::Index { url } = &source {
+            crate::skills_registry::cached_index(url, &cache_dir, true)
+                .await
+                .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+        }
+    }
+    let results = crate::skills_registry::search(&source, &cache_dir, &query.q)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
@@ -2234,161 +3248,149 @@ async fn search_registry(
 
 /// GET /api/library/skill/registry/list/:identifier - List skills in a repository.
 async fn list_repo_skills(
+    State(state): State<Arc<super::routes::AppState>>,
     Path(identifier): Path<String>,
 ) -> Result<Json<Vec<String>>, (StatusCode, String)> {
-    let skills = crate::skills_registry::list_repo_skills(&identifier)
+    let source = state.settings.skills_registry().await;
+    let skills = crate::skills_registry::list_repo_skills(&source, &identifier)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(skills))
 }
 
+/// POST /api/library/skill/registry/mirror - Build or refresh the local skill mirror.
+///
+/// Requires a `mirror` skills registry source in settings.
+async fn build_registry_mirror(
+    State(state): State<Arc<super::routes::AppState>>,
+    Json(request): Json<BuildMirrorRequest>,
+) -> Result<Json<crate::library::skills_mirror::MirrorReport>, (StatusCode, String)> {
+    let RegistrySource::Mirror { path } = state.settings.skills_registry().await else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Skills registry is not a mirror. Set a mirror directory in Settings.".to_string(),
+        ));
+    };
+    let report = crate::library::skills_mirror::build_mirror(&path, &request.identifiers)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(report))
+}
+
 /// POST /api/library/skill/registry/install - Install a skill from skills.sh.
+///
+/// The skill is fetched with git and pinned in `skills.lock`.
 async fn install_from_registry(
     State(state): State<Arc<super::routes::AppState>>,
     headers: HeaderMap,
     Json(request): Json<InstallFromRegistryRequest>,
 ) -> Result<Json<Skill>, (StatusCode, String)> {
-    use crate::library::SkillSource;
-
-    let library = ensure_library(&state, &headers).await?;
-
-    // Create a temporary directory for the skills CLI to work in
-    let temp_dir = library.path().join(".tmp-registry-install");
-    if temp_dir.exists() {
-        tokio::fs::remove_dir_all(&temp_dir)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    }
-    tokio::fs::create_dir_all(&temp_dir)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    // Initialize a minimal structure for the skills CLI
-    // The skills CLI expects certain directories to exist
-    let claude_skills_dir = temp_dir.join(".claude").join("skills");
-    tokio::fs::create_dir_all(&claude_skills_dir)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    // Run the install command
-    let skill_refs: Vec<&str> = request.skills.iter().map(|s| s.as_str()).collect();
-    let skill_names = if skill_refs.is_empty() {
-        None
-    } else {
-        Some(skill_refs.as_slice())
-    };
-
-    let result = crate::skills_registry::install_skill(&request.identifier, skill_names, &temp_dir)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    if !result.errors.is_empty() {
-        // Clean up temp dir
-        let _ = tokio::fs::remove_dir_all(&temp_dir).await;
+    if request.skills.len() > 1 {
         return Err((
             StatusCode::BAD_REQUEST,
-            format!("Installation errors: {}", result.errors.join(", ")),
+            "Install one skill per request".to_string(),
         ));
     }
-
-    // Find the installed skill in .claude/skills/
-    let mut installed_skill_dir = None;
-    let mut entries = tokio::fs::read_dir(&claude_skills_dir)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    while let Some(entry) = entries
-        .next_entry()
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    {
-        let path = entry.path();
-        if path.is_dir() && path.join("SKILL.md").exists() {
-            installed_skill_dir = Some(path);
-            break;
-        }
-    }
-
-    let source_dir = installed_skill_dir.ok_or_else(|| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "No skill found after installation".to_string(),
+    let library = ensure_library(&state, &headers).await?;
+    let skill = library
+        .install_registry_skill(
+            &request.identifier,
+            request.skills.first().map(String::as_str),
+            request.name.as_deref(),
+            request.revision.as_deref(),
         )
-    })?;
+        .await
+        .map_err(registry_error)?;
 
-    // Determine target name
-    let skill_name = request.name.unwrap_or_else(|| {
-        source_dir
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_else(|| "imported-skill".to_string())
-    });
+    item_changed(&state, &library, LayeredItemKind::Skill, &skill.name);
 
-    // Copy to library
-    let target_dir = library.path().join("skill").join(&skill_name);
-    if target_dir.exists() {
-        let _ = tokio::fs::remove_dir_all(&temp_dir).await;
-        return Err((
-            StatusCode::CONFLICT,
-            format!("Skill '{}' already exists", skill_name),
-        ));
-    }
+    Ok(Json(skill))
+}
 
-    copy_dir_recursive(&source_dir, &target_dir)
+/// GET /api/library/skill/registry/lock - Get the pinned registry skills.
+async fn get_skills_lock(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<SkillsLock>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    library
+        .skills_lock()
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
 
-    // Write the source metadata file
-    let source = SkillSource::SkillsRegistry {
-        identifier: request.identifier.clone(),
-        skill_name: request.skills.first().cloned(),
-        version: None,
-        installed_at: Some(chrono::Utc::now().to_rfc3339()),
-        updated_at: None,
-    };
-    let source_json = serde_json::to_string_pretty(&source)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    tokio::fs::write(target_dir.join(".skill-source.json"), source_json)
+/// POST /api/library/skill/registry/lock/install - Install skills at their locked commits.
+async fn install_locked_skills(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+    body: Option<Json<InstallLockedSkillsRequest>>,
+) -> Result<Json<LockInstallReport>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    let force = body.map(|Json(req)| req.force).unwrap_or(false);
+    let report = library
+        .install_locked_skills(force)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    // Clean up temp directory
-    let _ = tokio::fs::remove_dir_all(&temp_dir).await;
+    library_changed(
+        &state,
+        &library,
+        report
+            .installed
+            .iter()
+            .map(|name| LibraryItemRef::new(LayeredItemKind::Skill, name))
+            .collect(),
+    );
+
+    Ok(Json(report))
+}
+
+/// GET /api/library/skill/registry/updates - Locked skills changed upstream, with diffs.
+async fn check_skill_updates(
+    State(state): State<Arc<super::routes::AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<SkillUpdate>>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    library
+        .check_skill_updates()
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))
+}
 
-    // Get and return the skill
+/// POST /api/library/skill/registry/upgrade/:name - Upgrade one locked skill.
+async fn upgrade_registry_skill(
+    State(state): State<Arc<super::routes::AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: Option<Json<UpgradeSkillRequest>>,
+) -> Result<Json<Skill>, (StatusCode, String)> {
+    let library = ensure_library(&state, &headers).await?;
+    let req = body.map(|Json(req)| req).unwrap_or_default();
     let skill = library
-        .get_skill(&skill_name)
+        .upgrade_skill(&name, req.revision.as_deref(), req.force)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(registry_error)?;
 
-    // Sync to workspaces
-    sync_skill_to_workspaces(&state, &library, &skill_name).await;
+    item_changed(&state, &library, LayeredItemKind::Skill, &name);
 
     Ok(Json(skill))
 }
 
-/// Recursively copy a directory.
-#[async_recursion::async_recursion]
-async fn copy_dir_recursive(
-    src: &std::path::Path,
-    dst: &std::path::Path,
-) -> Result<(), std::io::Error> {
-    tokio::fs::create_dir_all(dst).await?;
-
-    let mut entries = tokio::fs::read_dir(src).await?;
-    while let Some(entry) = entries.next_entry().await? {
-        let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
-
-        if src_path.is_dir() {
-            copy_dir_recursive(&src_path, &dst_path).await?;
-        } else {
-            tokio::fs::copy(&src_path, &dst_path).await?;
-        }
+fn registry_error(e: anyhow::Error) -> (StatusCode, String) {
+    let msg = e.to_string();
+    if msg.contains("already exists") || msg.contains("local modifications") {
+        (StatusCode::CONFLICT, msg)
+    } else if msg.contains("not in skills.lock") || msg.contains("not found") {
+        (StatusCode::NOT_FOUND, msg)
+    } else if msg.contains("several skills") || msg.starts_with("Invalid") {
+        (StatusCode::BAD_REQUEST, msg)
+    } else {
+        (StatusCode::INTERNAL_SERVER_ERROR, msg)
     }
-
-    Ok(())
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -2520,4 +3522,74 @@ mod tests {
         assert!(!skill_dir.join(".gitignore").exists());
         assert!(!skill_dir.join("refs/.hidden").exists());
     }
+
+    #[test]
+    fn test_mission_references_item() {
+        let item = |kind, name: &str| LibraryItemRef::new(kind, name);
+        let host = workspace::Workspace::default_host(std::path::PathBuf::from("/tmp"));
+        let mut container =
+            workspace::Workspace::new_container("dev".to_string(), "/tmp/dev".into());
+        container.skills = vec!["lint".to_string()];
+        container.mcps = vec!["github".to_string()];
+
+        // The default host workspace receives every skill and MCP
+        assert!(mission_references_item(
+            &host,
+            None,
+            &item(LayeredItemKind::Skill, "any")
+        ));
+        assert!(mission_references_item(
+            &host,
+            None,
+            &item(LayeredItemKind::Mcp, "any")
+        ));
+        assert!(mission_references_item(
+            &host,
+            None,
+            &item(LayeredItemKind::ConfigProfile, "default")
+        ));
+
+        assert!(mission_references_item(
+            &container,
+            Some("strict"),
+            &item(LayeredItemKind::Skill, "lint")
+        ));
+        assert!(!mission_references_item(
+            &container,
+            Some("strict"),
+            &item(LayeredItemKind::Skill, "fmt")
+        ));
+        assert!(!mission_references_item(
+            &container,
+            Some("strict"),
+            &item(LayeredItemKind::Mcp, "fs")
+        ));
+        assert!(!mission_references_item(
+            &container,
+            Some("strict"),
+            &item(LayeredItemKind::ConfigProfile, "default")
+        ));
+        assert!(mission_references_item(
+            &container,
+            Some("strict"),
+            &item(LayeredItemKind::ConfigProfile, "strict")
+        ));
+        // Commands and agents are rendered into every harness config
+        assert!(mission_references_item(
+            &container,
+            Some("strict"),
+            &item(LayeredItemKind::Command, "deploy")
+        ));
+        assert!(mission_references_item(
+            &container,
+            Some("strict"),
+            &item(LayeredItemKind::Agent, "reviewer")
+        ));
+        // Templates only apply when the container is rebuilt
+        assert!(!mission_references_item(
+            &container,
+            Some("strict"),
+            &item(LayeredItemKind::WorkspaceTemplate, "base")
+        ));
+    }
 }
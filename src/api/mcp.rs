@@ -4,17 +4,24 @@ use std::collections::HashSet;
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::mcp::{AddMcpRequest, McpServerState, UpdateMcpRequest};
+use crate::mcp::gateway::{GatewayEvent, McpGateway};
+use crate::mcp::{
+    AddMcpRequest, McpLimits, McpPolicies, McpPromptDescriptor, McpPromptResult,
+    McpReadResourceResponse, McpRequestError, McpResourceDescriptor, McpServerState, McpStatsQuery,
+    McpToolStats, UpdateMcpRequest,
+};
 use crate::tools::ToolRegistry;
 use crate::workspace;
 
+use super::control::{AgentEvent, MissionStatus};
 use super::routes::AppState;
 
 /// List all MCP servers.
@@ -132,6 +139,116 @@ pub async fn refresh_mcp(
     Ok(Json(current_state))
 }
 
+/// Get the recent stderr output of a stdio MCP server.
+pub async fn get_mcp_logs(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    if state.mcp.get(id).await.is_none() {
+        return Err((StatusCode::NOT_FOUND, format!("MCP {} not found", id)));
+    }
+    Ok(Json(
+        serde_json::json!({ "id": id, "stderr": state.mcp.stderr_lines(id) }),
+    ))
+}
+
+/// Set the rate limits and concurrency caps of an MCP server's tool calls.
+/// Harness calls are only limited when the MCP gateway is enabled.
+pub async fn set_mcp_limits(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(limits): Json<McpLimits>,
+) -> Result<Json<McpServerState>, (StatusCode, String)> {
+    state
+        .mcp
+        .set_limits(id, limits)
+        .await
+        .map(Json)
+        .map_err(|e| match e.downcast_ref::<McpRequestError>() {
+            Some(McpRequestError::NotFound) => (StatusCode::NOT_FOUND, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct McpOAuthAuthorizeRequest {
+    /// Redirect URI registered with the authorization server
+    #[serde(default)]
+    pub redirect_uri: Option<String>,
+    /// Pre-registered client ID; a client is registered dynamically without it
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct McpOAuthAuthorizeResponse {
+    pub url: String,
+    pub instructions: String,
+}
+
+/// Start the OAuth authorization of a remote MCP server.
+pub async fn begin_mcp_oauth(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    body: Option<Json<McpOAuthAuthorizeRequest>>,
+) -> Result<Json<McpOAuthAuthorizeResponse>, (StatusCode, String)> {
+    let Json(req) = body.unwrap_or_default();
+    let url = state
+        .mcp
+        .begin_oauth(id, req.redirect_uri, req.client_id)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(Json(McpOAuthAuthorizeResponse {
+        url,
+        instructions:
+            "Open the URL, authorize access, then paste the URL you are redirected to (or the code)"
+                .to_string(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct McpOAuthCallbackRequest {
+    /// Authorization code, or the full redirect URL
+    pub code: String,
+}
+
+/// Complete the OAuth authorization of a remote MCP server and reconnect it.
+pub async fn complete_mcp_oauth(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<McpOAuthCallbackRequest>,
+) -> Result<Json<McpServerState>, (StatusCode, String)> {
+    state
+        .mcp
+        .complete_oauth(id, &req.code)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+/// Remove the OAuth authorization (and stored tokens) of an MCP server.
+pub async fn revoke_mcp_oauth(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<McpServerState>, (StatusCode, String)> {
+    state
+        .mcp
+        .revoke_oauth(id)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))
+}
+
+/// Per-tool call statistics (counts, error rate, latency, last error),
+/// optionally filtered by MCP or mission and grouped by mission. Harness
+/// calls are only counted when the MCP gateway is enabled.
+pub async fn get_mcp_stats(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<McpStatsQuery>,
+) -> Json<Vec<McpToolStats>> {
+    Json(state.mcp.tool_stats(&query))
+}
+
 /// Refresh all MCP servers.
 /// This spawns refreshes in the background and returns immediately.
 pub async fn refresh_all_mcps(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
@@ -144,6 +261,104 @@ pub async fn refresh_all_mcps(State(state): State<Arc<AppState>>) -> Json<serde_
     Json(serde_json::json!({ "success": true, "message": "Refresh started in background" }))
 }
 
+// ==================== Resources and Prompts ====================
+
+/// Map a registry error from a resource or prompt request to a status code.
+fn mcp_request_error(e: anyhow::Error) -> (StatusCode, String) {
+    let status = match e.downcast_ref::<McpRequestError>() {
+        Some(McpRequestError::NotFound) => StatusCode::NOT_FOUND,
+        Some(
+            McpRequestError::Disabled(_)
+            | McpRequestError::NotConnected(_)
+            | McpRequestError::WorkspaceScoped(_)
+            | McpRequestError::UpdatesUnsupported(_),
+        ) => StatusCode::CONFLICT,
+        None => StatusCode::BAD_GATEWAY,
+    };
+    (status, e.to_string())
+}
+
+/// List the resources discovered on an MCP server.
+pub async fn list_resources(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<McpResourceDescriptor>>, (StatusCode, String)> {
+    state
+        .mcp
+        .get(id)
+        .await
+        .map(|s| Json(s.config.resources))
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("MCP {} not found", id)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResourceQuery {
+    pub uri: String,
+}
+
+/// Read a resource from an MCP server.
+pub async fn read_resource(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ResourceQuery>,
+) -> Result<Json<McpReadResourceResponse>, (StatusCode, String)> {
+    state
+        .mcp
+        .read_resource(id, &query.uri)
+        .await
+        .map(Json)
+        .map_err(mcp_request_error)
+}
+
+/// Subscribe to updates of a resource. Updates are sent as
+/// `notifications/resources/updated` MCP notifications; stdio MCPs cannot
+/// send them and get `409 Conflict`.
+pub async fn subscribe_resource(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<ResourceQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    state
+        .mcp
+        .subscribe_resource(id, &req.uri)
+        .await
+        .map_err(mcp_request_error)?;
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+/// List the prompts discovered on an MCP server.
+pub async fn list_prompts(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<McpPromptDescriptor>>, (StatusCode, String)> {
+    state
+        .mcp
+        .get(id)
+        .await
+        .map(|s| Json(s.config.prompts))
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("MCP {} not found", id)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct GetPromptRequest {
+    #[serde(default)]
+    pub arguments: std::collections::HashMap<String, String>,
+}
+
+/// Get a prompt from an MCP server, filled in with the given arguments.
+pub async fn get_prompt(
+    State(state): State<Arc<AppState>>,
+    Path((id, name)): Path<(Uuid, String)>,
+    Json(req): Json<GetPromptRequest>,
+) -> Result<Json<McpPromptResult>, (StatusCode, String)> {
+    state
+        .mcp
+        .get_prompt(id, &name, req.arguments)
+        .await
+        .map(Json)
+        .map_err(mcp_request_error)
+}
+
 // ==================== Tools Management ====================
 
 /// Response for listing all tools.
@@ -231,3 +446,139 @@ pub async fn toggle_tool(
     }
     Json(serde_json::json!({ "success": true, "name": name, "enabled": req.enabled }))
 }
+
+// ==================== Gateway ====================
+
+/// MCP gateway of a mission (Streamable HTTP with JSON responses).
+///
+/// The caller is the harness inside the mission's workspace, so requests are
+/// authorized with the mission's gateway token instead of a dashboard JWT,
+/// and only while the mission is active.
+/// Every tool call is reported as `tool_call`/`tool_result` mission events.
+pub async fn mcp_gateway(
+    State(state): State<Arc<AppState>>,
+    Path(mission_id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(body): Json<serde_json::Value>,
+) -> Result<Response, (StatusCode, String)> {
+    if state.mcp.gateway().is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "MCP gateway is not enabled".to_string(),
+        ));
+    }
+    if state.config.auth.auth_required(state.config.dev_mode) {
+        let secret = state.config.auth.jwt_secret.as_deref().ok_or_else(|| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "JWT_SECRET not configured".to_string(),
+            )
+        })?;
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| {
+                h.strip_prefix("Bearer ")
+                    .or_else(|| h.strip_prefix("bearer "))
+            });
+        if !token.is_some_and(|t| state.mcp.gateway_tokens().verify(t, secret, mission_id)) {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                "Invalid gateway token".to_string(),
+            ));
+        }
+    }
+
+    let (session, mission) = state
+        .control
+        .mission_owner(mission_id)
+        .await
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("Mission {} not found", mission_id),
+            )
+        })?;
+    if mission.status != MissionStatus::Active {
+        state.mcp.gateway_tokens().revoke(mission_id);
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Mission {} is not running", mission_id),
+        ));
+    }
+    let workspace = state
+        .workspaces
+        .get(mission.workspace_id)
+        .await
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("Workspace {} not found", mission.workspace_id),
+            )
+        })?;
+
+    // Workspace-scoped stdio MCPs are called in their instance inside the
+    // workspace; the gateway leaves out MCPs it does not proxy
+    let allowed = workspace::filter_mcp_configs_for_workspace(
+        state.mcp.list_configs().await,
+        &workspace.mcps,
+    );
+    let policies = {
+        let library = state.library.read().await;
+        McpPolicies::resolve(
+            library.as_deref(),
+            &state.config,
+            mission.agent.as_deref(),
+            mission.mcp_policy.as_ref(),
+        )
+        .await
+    };
+    let gateway = McpGateway::new(&state.mcp, mission_id, &workspace, &allowed, policies);
+
+    let events_tx = session.events_tx;
+    let on_event = move |event: GatewayEvent| {
+        let event = match event {
+            GatewayEvent::Call {
+                call_id,
+                name,
+                arguments,
+            } => AgentEvent::ToolCall {
+                tool_call_id: call_id,
+                name,
+                args: arguments,
+                mission_id: Some(mission_id),
+            },
+            GatewayEvent::Result {
+                call_id,
+                name,
+                output,
+            } => AgentEvent::ToolResult {
+                tool_call_id: call_id,
+                name,
+                result: match output {
+                    Ok(text) => serde_json::json!(text),
+                    Err(e) => serde_json::json!({ "error": e }),
+                },
+                mission_id: Some(mission_id),
+            },
+        };
+        let _ = events_tx.send(event);
+    };
+
+    let response = match body {
+        serde_json::Value::Array(messages) => {
+            let mut responses = Vec::new();
+            for message in messages {
+                responses.extend(gateway.handle(message, &on_event).await);
+            }
+            (!responses.is_empty()).then_some(serde_json::Value::Array(responses))
+        }
+        message => gateway.handle(message, &on_event).await,
+    };
+
+    Ok(match response {
+        Some(response) => Json(response).into_response(),
+        // Only notifications or responses were sent
+        None => StatusCode::ACCEPTED.into_response(),
+    })
+}
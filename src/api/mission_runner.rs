@@ -21,7 +21,7 @@ use uuid::Uuid;
 use crate::agents::{AgentRef, AgentResult, TerminalReason};
 use crate::backend::claudecode::client::{ClaudeEvent, ContentBlock, StreamEvent};
 use crate::config::Config;
-use crate::mcp::McpRegistry;
+use crate::mcp::{McpPolicies, McpPromptDescriptor, McpRegistry, McpToolPolicy};
 use crate::opencode::{extract_reasoning, extract_text};
 use crate::secrets::SecretsStore;
 use crate::task::{extract_deliverables, DeliverableSet};
@@ -558,6 +558,9 @@ pub struct MissionRunner {
     /// Config profile from the mission (overrides workspace config_profile)
     pub config_profile: Option<String>,
 
+    /// MCP tool policy from the mission (on top of the agent's)
+    pub mcp_policy: Option<McpToolPolicy>,
+
     /// Current state
     pub state: MissionRunState,
 
@@ -614,6 +617,7 @@ impl MissionRunner {
             backend_id: backend_id.unwrap_or_else(|| "opencode".to_string()),
             session_id,
             config_profile,
+            mcp_policy: None,
             state: MissionRunState::Queued,
             agent_override,
             queue: VecDeque::new(),
@@ -744,6 +748,7 @@ impl MissionRunner {
         let backend_id = self.backend_id.clone();
         let session_id = self.session_id.clone();
         let config_profile = self.config_profile.clone();
+        let mcp_policy = self.mcp_policy.clone();
         let user_message = msg.content.clone();
         let msg_id = msg.id;
         tracing::info!(
@@ -770,6 +775,13 @@ impl MissionRunner {
         });
 
         let handle = tokio::spawn(async move {
+            let config = super::control::with_mission_tool_policies(
+                config,
+                &library,
+                agent_override.as_deref(),
+                mcp_policy.as_ref(),
+            )
+            .await;
             let result = run_mission_turn(
                 config,
                 root_agent,
@@ -877,8 +889,17 @@ fn build_history_context(history: &[(String, String)], max_chars: usize) -> Stri
 
 /// Try to resolve a library command from a user message starting with `/`.
 /// If the message starts with `/command-name` and a matching command exists in the library,
-/// returns the command's body content (frontmatter stripped). Otherwise returns the original message.
-async fn resolve_library_command(library: &SharedLibrary, message: &str) -> String {
+/// returns the command's body content (frontmatter stripped). Commands of the form
+/// `/mcp__<server>__<prompt>` are resolved to the text of that MCP prompt instead,
+/// looked up among the MCPs the mission can use in `workspace`.
+/// Otherwise returns the original message.
+async fn resolve_library_command(
+    library: &SharedLibrary,
+    mcp: &McpRegistry,
+    workspace: &Workspace,
+    tool_policy: &McpPolicies,
+    message: &str,
+) -> String {
     let trimmed = message.trim();
 
     // Must start with / and have at least one non-slash character
@@ -893,6 +914,30 @@ async fn resolve_library_command(library: &SharedLibrary, message: &str) -> Stri
         None => (without_slash, ""),
     };
 
+    if let Some((mcp_id, prompt)) = mcp
+        .find_prompt_command(command_name, workspace, tool_policy)
+        .await
+    {
+        let arguments = prompt_arguments(&prompt, args);
+        return match mcp
+            .get_prompt_in_workspace(mcp_id, workspace, &prompt.name, arguments)
+            .await
+        {
+            Ok(result) => {
+                tracing::info!(
+                    command_name = command_name,
+                    has_args = !args.is_empty(),
+                    "Resolved MCP prompt command"
+                );
+                result.text()
+            }
+            Err(e) => {
+                tracing::warn!(command_name = command_name, error = %e, "Failed to get MCP prompt");
+                message.to_string()
+            }
+        };
+    }
+
     // Try to fetch from library
     let lib_guard = library.read().await;
     let Some(lib) = lib_guard.as_ref() else {
@@ -924,6 +969,27 @@ async fn resolve_library_command(library: &SharedLibrary, message: &str) -> Stri
     }
 }
 
+/// Map the whitespace-separated arguments of a prompt command onto the
+/// prompt's declared arguments in order; the last one takes the remainder.
+fn prompt_arguments(prompt: &McpPromptDescriptor, args: &str) -> HashMap<String, String> {
+    let mut arguments = HashMap::new();
+    let mut rest = args.trim();
+    for (i, argument) in prompt.arguments.iter().enumerate() {
+        if rest.is_empty() {
+            break;
+        }
+        let value = if i + 1 == prompt.arguments.len() {
+            std::mem::take(&mut rest)
+        } else {
+            let (value, remainder) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            rest = remainder.trim_start();
+            value
+        };
+        arguments.insert(argument.name.clone(), value.to_string());
+    }
+    arguments
+}
+
 /// Execute a single turn for a mission.
 async fn run_mission_turn(
     config: Config,
@@ -982,8 +1048,22 @@ async fn run_mission_turn(
         "Mission turn started"
     );
 
+    // Ensure mission workspace exists and is configured for OpenCode.
+    let workspace = workspace::resolve_workspace(&workspaces, &config, workspace_id).await;
+    // Resolve the secret references in the workspace env once for the turn;
+    // the harness config and every command below use the resolved values.
+    let workspace = match workspace::with_resolved_env(&workspace, secrets.as_deref()).await {
+        Ok(workspace) => workspace,
+        Err(e) => {
+            return AgentResult::failure(format!("Failed to resolve workspace env: {}", e), 0)
+                .with_terminal_reason(TerminalReason::LlmError);
+        }
+    };
+    let tool_policy = config.mcp_policies.clone();
+
     // Resolve library commands (e.g., /bugbot-review → expanded command content)
-    let user_message = resolve_library_command(&library, &user_message).await;
+    let user_message =
+        resolve_library_command(&library, &mcp, &workspace, &tool_policy, &user_message).await;
 
     // Build context with history
     let max_history_chars = config.context.max_history_total_chars;
@@ -1038,8 +1118,6 @@ async fn run_mission_turn(
     convo.push_str(multi_step_instructions);
     convo.push_str("\n");
 
-    // Ensure mission workspace exists and is configured for OpenCode.
-    let workspace = workspace::resolve_workspace(&workspaces, &config, workspace_id).await;
     let workspace_root = workspace.path.clone();
     let mission_work_dir = match {
         let lib_guard = library.read().await;
@@ -1049,6 +1127,7 @@ async fn run_mission_turn(
             &mcp,
             lib_ref,
             mission_id,
+            &tool_policy,
             &backend_id,
             None, // custom_providers: TODO integrate with provider store
             effective_config_profile.as_deref(),
@@ -5909,9 +5988,32 @@ impl From<&MissionRunner> for RunningMissionInfo {
 
 #[cfg(test)]
 mod tests {
-    use super::sync_opencode_agent_config;
+    use super::{prompt_arguments, sync_opencode_agent_config};
+    use crate::mcp::{McpPromptArgument, McpPromptDescriptor};
     use std::fs;
 
+    #[test]
+    fn prompt_arguments_fill_in_declaration_order() {
+        let argument = |name: &str| McpPromptArgument {
+            name: name.to_string(),
+            description: None,
+            required: false,
+        };
+        let prompt = McpPromptDescriptor {
+            name: "review".to_string(),
+            description: None,
+            arguments: vec![argument("file"), argument("focus")],
+        };
+
+        let arguments = prompt_arguments(&prompt, "src/main.rs  error handling only");
+        assert_eq!(arguments["file"], "src/main.rs");
+        assert_eq!(arguments["focus"], "error handling only");
+
+        let arguments = prompt_arguments(&prompt, "src/main.rs");
+        assert_eq!(arguments.len(), 1);
+        assert!(prompt_arguments(&prompt, "").is_empty());
+    }
+
     #[test]
     fn sync_opencode_agent_config_removes_overrides_when_plugin_enabled() {
         let temp_dir = tempfile::tempdir().expect("temp dir");
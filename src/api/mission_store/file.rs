@@ -1,9 +1,12 @@
 //! JSON file-based mission store (legacy).
 
 use super::{
-    now_string, sanitize_filename, Mission, MissionHistoryEntry, MissionStatus, MissionStore,
+    now_string, redact_history, sanitize_filename, Mission, MissionHistoryEntry, MissionStatus,
+    MissionStore,
 };
 use crate::api::control::{AgentTreeNode, DesktopSessionInfo};
+use crate::mcp::McpToolPolicy;
+use crate::secrets::SecretRedactor;
 use async_trait::async_trait;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
@@ -124,6 +127,7 @@ impl MissionStore for FileMissionStore {
             desktop_sessions: Vec::new(),
             session_id: Some(Uuid::new_v4().to_string()),
             terminal_reason: None,
+            mcp_policy: None,
         };
         self.missions
             .write()
@@ -219,6 +223,21 @@ impl MissionStore for FileMissionStore {
         self.persist().await
     }
 
+    async fn update_mission_mcp_policy(
+        &self,
+        id: Uuid,
+        policy: Option<&McpToolPolicy>,
+    ) -> Result<(), String> {
+        let mut missions = self.missions.write().await;
+        let mission = missions
+            .get_mut(&id)
+            .ok_or_else(|| format!("Mission {} not found", id))?;
+        mission.mcp_policy = policy.cloned();
+        mission.updated_at = now_string();
+        drop(missions);
+        self.persist().await
+    }
+
     async fn update_mission_tree(&self, id: Uuid, tree: &AgentTreeNode) -> Result<(), String> {
         self.trees.write().await.insert(id, tree.clone());
         self.persist().await
@@ -313,4 +332,18 @@ impl MissionStore for FileMissionStore {
     ) -> Result<(), String> {
         Ok(())
     }
+
+    async fn scrub_events(&self, redactor: Arc<SecretRedactor>) -> Result<usize, String> {
+        let scrubbed: usize = self
+            .missions
+            .write()
+            .await
+            .values_mut()
+            .map(|m| redact_history(&mut m.history, &redactor))
+            .sum();
+        if scrubbed > 0 {
+            self.persist().await?;
+        }
+        Ok(scrubbed)
+    }
 }
@@ -1,7 +1,11 @@
 //! In-memory mission store (non-persistent).
 
-use super::{now_string, Mission, MissionHistoryEntry, MissionStatus, MissionStore};
+use super::{
+    now_string, redact_history, Mission, MissionHistoryEntry, MissionStatus, MissionStore,
+};
 use crate::api::control::{AgentTreeNode, DesktopSessionInfo};
+use crate::mcp::McpToolPolicy;
+use crate::secrets::SecretRedactor;
 use async_trait::async_trait;
 use chrono::Utc;
 use std::collections::HashMap;
@@ -75,6 +79,7 @@ impl MissionStore for InMemoryMissionStore {
             desktop_sessions: Vec::new(),
             session_id: Some(Uuid::new_v4().to_string()),
             terminal_reason: None,
+            mcp_policy: None,
         };
         self.missions
             .write()
@@ -164,6 +169,20 @@ impl MissionStore for InMemoryMissionStore {
         Ok(())
     }
 
+    async fn update_mission_mcp_policy(
+        &self,
+        id: Uuid,
+        policy: Option<&McpToolPolicy>,
+    ) -> Result<(), String> {
+        let mut missions = self.missions.write().await;
+        let mission = missions
+            .get_mut(&id)
+            .ok_or_else(|| format!("Mission {} not found", id))?;
+        mission.mcp_policy = policy.cloned();
+        mission.updated_at = now_string();
+        Ok(())
+    }
+
     async fn update_mission_tree(&self, id: Uuid, tree: &AgentTreeNode) -> Result<(), String> {
         self.trees.write().await.insert(id, tree.clone());
         Ok(())
@@ -255,4 +274,14 @@ impl MissionStore for InMemoryMissionStore {
     ) -> Result<(), String> {
         Ok(())
     }
+
+    async fn scrub_events(&self, redactor: Arc<SecretRedactor>) -> Result<usize, String> {
+        Ok(self
+            .missions
+            .write()
+            .await
+            .values_mut()
+            .map(|m| redact_history(&mut m.history, &redactor))
+            .sum())
+    }
 }
@@ -5,6 +5,8 @@ use super::{
     StoredEvent,
 };
 use crate::api::control::{AgentEvent, AgentTreeNode, DesktopSessionInfo};
+use crate::mcp::McpToolPolicy;
+use crate::secrets::SecretRedactor;
 use async_trait::async_trait;
 use chrono::Utc;
 use rusqlite::{params, Connection, OptionalExtension};
@@ -32,7 +34,8 @@ CREATE TABLE IF NOT EXISTS missions (
     interrupted_at TEXT,
     resumable INTEGER NOT NULL DEFAULT 0,
     desktop_sessions TEXT,
-    terminal_reason TEXT
+    terminal_reason TEXT,
+    mcp_policy TEXT
 );
 
 CREATE INDEX IF NOT EXISTS idx_missions_updated_at ON missions(updated_at DESC);
@@ -82,6 +85,9 @@ CREATE INDEX IF NOT EXISTS idx_summaries_mission ON mission_summaries(mission_id
 /// Content size threshold for inline storage (64KB).
 const CONTENT_SIZE_THRESHOLD: usize = 64 * 1024;
 
+/// Events scrubbed per lock of the connection.
+const SCRUB_BATCH_SIZE: i64 = 500;
+
 pub struct SqliteMissionStore {
     conn: Arc<Mutex<Connection>>,
     content_dir: PathBuf,
@@ -229,6 +235,19 @@ impl SqliteMissionStore {
                 .map_err(|e| format!("Failed to add config_profile column: {}", e))?;
         }
 
+        // Check if 'mcp_policy' column exists in missions table
+        let has_mcp_policy_column: bool = conn
+            .prepare("SELECT 1 FROM pragma_table_info('missions') WHERE name = 'mcp_policy'")
+            .map_err(|e| format!("Failed to check for mcp_policy column: {}", e))?
+            .exists([])
+            .map_err(|e| format!("Failed to query table info: {}", e))?;
+
+        if !has_mcp_policy_column {
+            tracing::info!("Running migration: adding 'mcp_policy' column to missions table");
+            conn.execute("ALTER TABLE missions ADD COLUMN mcp_policy TEXT", [])
+                .map_err(|e| format!("Failed to add mcp_policy column: {}", e))?;
+        }
+
         Ok(())
     }
 }
@@ -273,7 +292,7 @@ impl MissionStore for SqliteMissionStore {
                     "SELECT id, status, title, workspace_id, workspace_name, agent, model_override,
                             created_at, updated_at, interrupted_at, resumable, desktop_sessions,
                             COALESCE(backend, 'opencode') as backend, session_id, terminal_reason,
-                            config_profile
+                            config_profile, mcp_policy
                      FROM missions
                      ORDER BY updated_at DESC
                      LIMIT ?1 OFFSET ?2",
@@ -290,6 +309,7 @@ impl MissionStore for SqliteMissionStore {
                     let session_id: Option<String> = row.get(13)?;
                     let terminal_reason: Option<String> = row.get(14)?;
                     let config_profile: Option<String> = row.get(15)?;
+                    let mcp_policy_json: Option<String> = row.get(16)?;
 
                     Ok(Mission {
                         id: Uuid::parse_str(&id_str).unwrap_or_default(),
@@ -312,6 +332,7 @@ impl MissionStore for SqliteMissionStore {
                             .unwrap_or_default(),
                         session_id,
                         terminal_reason,
+                        mcp_policy: mcp_policy_json.and_then(|s| serde_json::from_str(&s).ok()),
                     })
                 })
                 .map_err(|e| e.to_string())?
@@ -337,7 +358,7 @@ impl MissionStore for SqliteMissionStore {
                     "SELECT id, status, title, workspace_id, workspace_name, agent, model_override,
                             created_at, updated_at, interrupted_at, resumable, desktop_sessions,
                             COALESCE(backend, 'opencode') as backend, session_id, terminal_reason,
-                            config_profile
+                            config_profile, mcp_policy
                      FROM missions WHERE id = ?1",
                 )
                 .map_err(|e| e.to_string())?;
@@ -352,6 +373,7 @@ impl MissionStore for SqliteMissionStore {
                     let session_id: Option<String> = row.get(13)?;
                     let terminal_reason: Option<String> = row.get(14)?;
                     let config_profile: Option<String> = row.get(15)?;
+                    let mcp_policy_json: Option<String> = row.get(16)?;
 
                     Ok(Mission {
                         id: Uuid::parse_str(&id_str).unwrap_or_default(),
@@ -374,6 +396,7 @@ impl MissionStore for SqliteMissionStore {
                             .unwrap_or_default(),
                         session_id,
                         terminal_reason,
+                        mcp_policy: mcp_policy_json.and_then(|s| serde_json::from_str(&s).ok()),
                     })
                 })
                 .optional()
@@ -459,6 +482,7 @@ impl MissionStore for SqliteMissionStore {
             desktop_sessions: Vec::new(),
             session_id: Some(session_id.clone()),
             terminal_reason: None,
+            mcp_policy: None,
         };
 
         let m = mission.clone();
@@ -622,6 +646,31 @@ impl MissionStore for SqliteMissionStore {
         .map_err(|e| e.to_string())?
     }
 
+    async fn update_mission_mcp_policy(
+        &self,
+        id: Uuid,
+        policy: Option<&McpToolPolicy>,
+    ) -> Result<(), String> {
+        let conn = self.conn.clone();
+        let now = now_string();
+        let policy_json = policy
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.blocking_lock();
+            conn.execute(
+                "UPDATE missions SET mcp_policy = ?1, updated_at = ?2 WHERE id = ?3",
+                params![policy_json, now, id.to_string()],
+            )
+            .map_err(|e| e.to_string())?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+
     async fn update_mission_tree(&self, id: Uuid, tree: &AgentTreeNode) -> Result<(), String> {
         let conn = self.conn.clone();
         let now = now_string();
@@ -774,6 +823,7 @@ impl MissionStore for SqliteMissionStore {
                             .unwrap_or_default(),
                         session_id: None, // Not needed for stale mission checks
                         terminal_reason: None,
+                        mcp_policy: None,
                     })
                 })
                 .map_err(|e| e.to_string())?
@@ -830,6 +880,7 @@ impl MissionStore for SqliteMissionStore {
                             .unwrap_or_default(),
                         session_id: None,
                         terminal_reason: None,
+                        mcp_policy: None,
                     })
                 })
                 .map_err(|e| e.to_string())?
@@ -981,7 +1032,8 @@ impl MissionStore for SqliteMissionStore {
             | AgentEvent::Progress { .. }
             | AgentEvent::SessionIdUpdate { .. }
             | AgentEvent::TextDelta { .. }
-            | AgentEvent::MissionActivity { .. } => return Ok(()),
+            | AgentEvent::MissionActivity { .. }
+            | AgentEvent::LibraryChanged { .. } => return Ok(()),
         };
 
         let event_type = event_type.to_string();
@@ -1056,6 +1108,73 @@ impl MissionStore for SqliteMissionStore {
         .map_err(|e| e.to_string())?
     }
 
+    async fn scrub_events(&self, redactor: Arc<SecretRedactor>) -> Result<usize, String> {
+        let conn = self.conn.clone();
+
+        // History is read from the events, so scrubbing them covers it too
+        tokio::task::spawn_blocking(move || {
+            let mut scrubbed = 0;
+            let mut after = 0i64;
+            loop {
+                // The connection is only locked per batch, so missions keep
+                // logging events while the table is scrubbed
+                let rows: Vec<(i64, Option<String>, Option<String>)> = {
+                    let conn = conn.blocking_lock();
+                    let mut stmt = conn
+                        .prepare(
+                            "SELECT id, content, content_file FROM mission_events
+                             WHERE id > ?1 ORDER BY id LIMIT ?2",
+                        )
+                        .map_err(|e| e.to_string())?;
+                    let rows = stmt
+                        .query_map(params![after, SCRUB_BATCH_SIZE], |row| {
+                            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                        })
+                        .map_err(|e| e.to_string())?;
+                    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+                };
+                let Some((last, _, _)) = rows.last() else {
+                    break;
+                };
+                after = *last;
+
+                let mut updates = Vec::new();
+                for (id, content, content_file) in rows {
+                    let full_content = SqliteMissionStore::load_content(
+                        content.as_deref(),
+                        content_file.as_deref(),
+                    );
+                    let std::borrow::Cow::Owned(masked) = redactor.redact(&full_content) else {
+                        continue;
+                    };
+                    match (content, content_file) {
+                        (None, Some(path)) => {
+                            std::fs::write(&path, masked)
+                                .map_err(|e| format!("Failed to rewrite {}: {}", path, e))?;
+                        }
+                        _ => updates.push((id, masked)),
+                    }
+                    scrubbed += 1;
+                }
+
+                if !updates.is_empty() {
+                    let conn = conn.blocking_lock();
+                    for (id, masked) in updates {
+                        conn.execute(
+                            "UPDATE mission_events SET content = ?1 WHERE id = ?2",
+                            params![masked, id],
+                        )
+                        .map_err(|e| e.to_string())?;
+                    }
+                }
+            }
+
+            Ok(scrubbed)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+
     async fn get_events(
         &self,
         mission_id: Uuid,
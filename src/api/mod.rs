@@ -22,6 +22,7 @@ mod console;
 pub mod control;
 pub mod desktop;
 mod desktop_stream;
+mod disk;
 mod fs;
 pub mod library;
 pub mod mcp;
@@ -30,6 +31,7 @@ pub mod mission_store;
 mod monitoring;
 pub mod opencode;
 mod providers;
+mod proxy;
 mod routes;
 pub mod secrets;
 pub mod settings;
@@ -2,7 +2,8 @@
 //!
 //! Provides CPU, memory, and network usage metrics streamed
 //! to connected clients via WebSocket. Maintains a history buffer
-//! so new clients receive recent data immediately.
+//! so new clients receive recent data immediately. Also exposes the
+//! disk usage trend recorded by the disk manager.
 
 use std::collections::VecDeque;
 use std::sync::Arc;
@@ -224,6 +225,25 @@ pub fn init_monitoring() {
     tracing::info!("Monitoring background collector started");
 }
 
+/// Disk usage trend response
+#[derive(Debug, Serialize)]
+pub struct DiskUsageTrend {
+    /// Most recent sample, if any scan has run
+    pub latest: Option<crate::disk_usage::UsageSample>,
+    /// Samples (oldest first), taken on startup and hourly by the scheduled
+    /// scan; on-demand usage scans are not recorded
+    pub history: Vec<crate::disk_usage::UsageSample>,
+}
+
+/// GET /api/monitoring/disk - Disk usage trend per workspace
+pub async fn disk_usage_trend(State(state): State<Arc<AppState>>) -> axum::Json<DiskUsageTrend> {
+    let history = state.disk.history().await;
+    axum::Json(DiskUsageTrend {
+        latest: history.last().cloned(),
+        history,
+    })
+}
+
 /// Extract JWT from WebSocket subprotocol header
 fn extract_jwt_from_protocols(headers: &HeaderMap) -> Option<String> {
     let raw = headers
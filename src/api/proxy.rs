@@ -0,0 +1,1167 @@
+//! Reverse proxy for dev servers running inside workspaces.
+//!
+//! Agents routinely start `npm run dev` or a Flask app inside a workspace.
+//! `/api/workspaces/:id/proxy/:port/*path` forwards HTTP requests and
+//! WebSocket upgrades (HMR clients rely on them) to that port so reviewers can
+//! open the result without an SSH tunnel.
+//!
+//! The upstream address depends on the workspace network mode:
+//! - Host workspaces and containers sharing the host network use `127.0.0.1`
+//! - Isolated containers (`shared_network = false`) are reached through the
+//!   address of `host0`, looked up inside the container's network namespace
+//!
+//! The proxy routes are mounted outside the auth middleware because browsers
+//! cannot attach an `Authorization` header when navigating. Each request is
+//! authorized here instead, accepting in order:
+//! - A dashboard JWT (`Authorization: Bearer` or `jwt.<token>` subprotocol)
+//! - A signed preview token in `?preview_token=` (shareable, with expiry)
+//! - The path-scoped cookie set after a preview token was accepted
+//!
+//! Previews share the origin of the dashboard and API, so every proxied
+//! response carries `Content-Security-Policy: sandbox` without
+//! `allow-same-origin`: the previewed app runs in an opaque origin and cannot
+//! read dashboard storage or call the API with its credentials. Dashboard
+//! credentials (`Authorization`, the `jwt.` subprotocol) and preview cookies
+//! are never forwarded upstream, and cookies set by the app are confined to
+//! the proxy path.
+//!
+//! Only ports that `list_ports` reports can be proxied: the API port is never
+//! reachable through the proxy, and neither is a port nothing listens on.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, Path as AxumPath, State,
+    },
+    http::{header, HeaderMap, HeaderName, HeaderValue, Request, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::Utc;
+use futures::{SinkExt, StreamExt};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tokio_tungstenite::tungstenite;
+use uuid::Uuid;
+
+use super::auth::{self, AuthUser};
+use super::routes::AppState;
+use crate::workspace::{use_nspawn_for_workspace, Workspace, WorkspaceStatus, WorkspaceType};
+use crate::workspace_exec::WorkspaceExec;
+
+/// Query parameter carrying a signed preview token.
+const PREVIEW_TOKEN_PARAM: &str = "preview_token";
+
+/// Cookie name prefix used to remember an accepted preview token.
+const PREVIEW_COOKIE_PREFIX: &str = "sandboxed_preview_";
+
+/// Default lifetime of a preview link (24 hours).
+const DEFAULT_PREVIEW_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Maximum lifetime of a preview link (30 days).
+const MAX_PREVIEW_TTL_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// How long the ports listed for a workspace are reused to vet proxied
+/// requests, so loading a page's subresources does not read /proc/net for
+/// each of them.
+const PORT_CACHE_TTL: Duration = Duration::from_secs(2);
+
+/// Sandbox applied to proxied responses; without `allow-same-origin` the
+/// previewed app gets an opaque origin, isolated from the dashboard.
+const PREVIEW_CSP: &str =
+    "sandbox allow-scripts allow-forms allow-popups allow-modals allow-downloads";
+
+/// Headers that only apply to a single connection and must not be forwarded.
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Request/Response Types
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize)]
+pub struct ListeningPort {
+    /// TCP port number
+    pub port: u16,
+    /// Bind address as reported by the kernel (e.g. `0.0.0.0`, `::1`)
+    pub address: String,
+    /// Whether the listener is only reachable from loopback.
+    /// Loopback listeners in isolated containers cannot be proxied.
+    pub loopback_only: bool,
+    /// Proxy path for this port
+    pub proxy_path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListPortsResponse {
+    pub workspace_id: Uuid,
+    /// Whether the workspace shares the host network namespace.
+    /// When true, the list includes every listener on the host.
+    pub shared_network: bool,
+    pub ports: Vec<ListeningPort>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePreviewLinkRequest {
+    /// Port inside the workspace to expose
+    pub port: u16,
+    /// Link lifetime in seconds (default: 24h, max: 30 days)
+    pub ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewLinkResponse {
+    /// Relative URL to open (includes the preview token when auth is enabled)
+    pub url: String,
+    /// Signed preview token (None when auth is disabled)
+    pub token: Option<String>,
+    /// Expiration time
+    pub expires_at: chrono::DateTime<Utc>,
+}
+
+/// Claims of a signed preview token.
+#[derive(Debug, Serialize, Deserialize)]
+struct PreviewClaims {
+    /// Workspace the token grants access to
+    ws: Uuid,
+    /// Port inside the workspace
+    port: u16,
+    /// Username that created the link (for auditing)
+    #[serde(default)]
+    usr: String,
+    /// Issued-at unix seconds
+    iat: i64,
+    /// Expiration unix seconds
+    exp: i64,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Preview Tokens
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Preview tokens are signed with a key derived from the JWT secret so that a
+/// preview token can never be replayed as a dashboard session token.
+fn preview_signing_key(secret: &str) -> Vec<u8> {
+    format!("{}:workspace-preview", secret).into_bytes()
+}
+
+fn issue_preview_token(
+    secret: &str,
+    workspace_id: Uuid,
+    port: u16,
+    ttl_secs: u64,
+    username: &str,
+) -> anyhow::Result<(String, i64)> {
+    let now = Utc::now().timestamp();
+    let claims = PreviewClaims {
+        ws: workspace_id,
+        port,
+        usr: username.to_string(),
+        iat: now,
+        exp: now + ttl_secs as i64,
+    };
+    let token = jsonwebtoken::encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(&preview_signing_key(secret)),
+    )?;
+    Ok((token, claims.exp))
+}
+
+fn verify_preview_token(token: &str, secret: &str, workspace_id: Uuid, port: u16) -> Option<i64> {
+    let data = jsonwebtoken::decode::<PreviewClaims>(
+        token,
+        &DecodingKey::from_secret(&preview_signing_key(secret)),
+        &Validation::default(),
+    )
+    .ok()?;
+    if data.claims.ws == workspace_id && data.claims.port == port {
+        Some(data.claims.exp)
+    } else {
+        None
+    }
+}
+
+/// Extract JWT from WebSocket subprotocol header
+fn extract_jwt_from_protocols(headers: &HeaderMap) -> Option<String> {
+    let raw = headers
+        .get("sec-websocket-protocol")
+        .and_then(|v| v.to_str().ok())?;
+    for part in raw.split(',').map(|s| s.trim()) {
+        if let Some(rest) = part.strip_prefix("jwt.") {
+            if !rest.is_empty() {
+                return Some(rest.to_string());
+            }
+        }
+    }
+    None
+}
+
+fn preview_cookie_name(port: u16) -> String {
+    format!("{}{}", PREVIEW_COOKIE_PREFIX, port)
+}
+
+fn cookie_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v.to_string())
+}
+
+/// Split a raw query string into the preview token (if any) and the remaining query.
+fn split_preview_token(query: Option<&str>) -> (Option<String>, Option<String>) {
+    let Some(query) = query else {
+        return (None, None);
+    };
+    let mut token = None;
+    let mut rest = Vec::new();
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        match pair.split_once('=') {
+            Some((k, v)) if k == PREVIEW_TOKEN_PARAM => token = Some(v.to_string()),
+            _ => rest.push(pair),
+        }
+    }
+    let rest = if rest.is_empty() {
+        None
+    } else {
+        Some(rest.join("&"))
+    };
+    (token, rest)
+}
+
+/// Outcome of authorizing a proxy request.
+enum Access {
+    /// Request is allowed as-is
+    Allowed,
+    /// Request carried a valid preview token in the query string; the caller
+    /// should remember it in a cookie.
+    PreviewAccepted { token: String, expires_at: i64 },
+}
+
+fn authorize(
+    state: &AppState,
+    headers: &HeaderMap,
+    query_token: Option<&str>,
+    workspace_id: Uuid,
+    port: u16,
+) -> Result<Access, (StatusCode, &'static str)> {
+    if !state.config.auth.auth_required(state.config.dev_mode) {
+        return Ok(Access::Allowed);
+    }
+
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| {
+            h.strip_prefix("Bearer ")
+                .or_else(|| h.strip_prefix("bearer "))
+        })
+        .map(|t| t.to_string());
+    for token in bearer
+        .into_iter()
+        .chain(extract_jwt_from_protocols(headers))
+    {
+        if auth::verify_token_for_config(&token, &state.config) {
+            return Ok(Access::Allowed);
+        }
+    }
+
+    let Some(secret) = state.config.auth.jwt_secret.as_deref() else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "JWT_SECRET not configured",
+        ));
+    };
+
+    if let Some(token) = query_token {
+        if let Some(expires_at) = verify_preview_token(token, secret, workspace_id, port) {
+            return Ok(Access::PreviewAccepted {
+                token: token.to_string(),
+                expires_at,
+            });
+        }
+        return Err((StatusCode::UNAUTHORIZED, "Invalid or expired preview link"));
+    }
+
+    if let Some(token) = cookie_value(headers, &preview_cookie_name(port)) {
+        if verify_preview_token(&token, secret, workspace_id, port).is_some() {
+            return Ok(Access::Allowed);
+        }
+    }
+
+    Err((StatusCode::UNAUTHORIZED, "Missing or invalid credentials"))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Upstream Resolution
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Whether the workspace runs in its own network namespace.
+fn has_isolated_network(workspace: &Workspace) -> bool {
+    workspace.workspace_type == WorkspaceType::Container
+        && use_nspawn_for_workspace(workspace)
+        && !workspace.shared_network.unwrap_or(true)
+}
+
+/// PID of the running container's leader process (isolated networks only).
+async fn isolated_network_leader(workspace: &Workspace) -> Result<String, (StatusCode, String)> {
+    WorkspaceExec::new(workspace.clone())
+        .running_container_leader()
+        .await
+        .ok_or_else(|| {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!(
+                    "Workspace {} has an isolated network but its container is not running",
+                    workspace.name
+                ),
+            )
+        })
+}
+
+/// Look up the IPv4 address of `host0` inside the container network namespace.
+async fn container_ipv4(leader: &str) -> Result<String, (StatusCode, String)> {
+    let nsenter = if Path::new("/usr/bin/nsenter").exists() {
+        "/usr/bin/nsenter"
+    } else {
+        "nsenter"
+    };
+    let output = Command::new(nsenter)
+        .args([
+            "--target", leader, "--net", "ip", "-4", "-o", "addr", "show", "dev", "host0",
+        ])
+        .output()
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to query container address: {}", e),
+            )
+        })?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_ip_addr_output(&stdout).ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Container network is not up (no IPv4 address on host0)".to_string(),
+        )
+    })
+}
+
+/// Extract the first IPv4 address from `ip -4 -o addr show` output.
+fn parse_ip_addr_output(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        parts.find(|p| *p == "inet")?;
+        let cidr = parts.next()?;
+        let addr = cidr.split('/').next()?;
+        addr.parse::<Ipv4Addr>().ok().map(|a| a.to_string())
+    })
+}
+
+/// Resolve the host to connect to for a workspace port.
+async fn resolve_upstream_host(workspace: &Workspace) -> Result<String, (StatusCode, String)> {
+    if workspace.workspace_type == WorkspaceType::Container
+        && workspace.status != WorkspaceStatus::Ready
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Workspace {} is not ready (status: {:?})",
+                workspace.id, workspace.status
+            ),
+        ));
+    }
+    if !has_isolated_network(workspace) {
+        return Ok("127.0.0.1".to_string());
+    }
+    let leader = isolated_network_leader(workspace).await?;
+    container_ipv4(&leader).await
+}
+
+fn proxy_prefix(workspace_id: Uuid, port: u16) -> String {
+    format!("/api/workspaces/{}/proxy/{}", workspace_id, port)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Port Discovery
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Parse `/proc/net/tcp` or `/proc/net/tcp6` and return listening sockets.
+fn parse_proc_net_tcp(contents: &str) -> Vec<(IpAddrString, u16)> {
+    contents
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // sl local_address rem_address st ...
+            if fields.len() < 4 || fields[3] != "0A" {
+                return None;
+            }
+            let (addr_hex, port_hex) = fields[1].split_once(':')?;
+            let port = u16::from_str_radix(port_hex, 16).ok()?;
+            let addr = decode_proc_addr(addr_hex)?;
+            Some((addr, port))
+        })
+        .collect()
+}
+
+/// Bind address decoded from `/proc/net/tcp*`, with its loopback flag.
+type IpAddrString = (String, bool);
+
+/// Decode a kernel-formatted address (host byte order words) into text.
+fn decode_proc_addr(hex: &str) -> Option<IpAddrString> {
+    let words: Option<Vec<[u8; 4]>> = (0..hex.len() / 8)
+        .map(|i| {
+            u32::from_str_radix(hex.get(i * 8..i * 8 + 8)?, 16)
+                .ok()
+                .map(|w| w.to_ne_bytes())
+        })
+        .collect();
+    let words = words?;
+    match words.len() {
+        1 => {
+            let addr = Ipv4Addr::from(words[0]);
+            Some((addr.to_string(), addr.is_loopback()))
+        }
+        4 => {
+            let mut bytes = [0u8; 16];
+            for (i, word) in words.iter().enumerate() {
+                bytes[i * 4..i * 4 + 4].copy_from_slice(word);
+            }
+            let addr = Ipv6Addr::from(bytes);
+            let loopback = addr.is_loopback()
+                || addr
+                    .to_ipv4_mapped()
+                    .map(|v4| v4.is_loopback())
+                    .unwrap_or(false);
+            Some((addr.to_string(), loopback))
+        }
+        _ => None,
+    }
+}
+
+/// GET /api/workspaces/:id/ports - List TCP ports listening in the workspace.
+pub async fn list_ports(
+    State(state): State<Arc<AppState>>,
+    AxumPath(id): AxumPath<Uuid>,
+) -> Result<Json<ListPortsResponse>, (StatusCode, String)> {
+    let workspace = state
+        .workspaces
+        .get(id)
+        .await
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Workspace {} not found", id)))?;
+
+    let ports = listening_ports(&state, &workspace).await?;
+    Ok(Json(ListPortsResponse {
+        workspace_id: id,
+        shared_network: !has_isolated_network(&workspace),
+        ports: ports.into_values().collect(),
+    }))
+}
+
+/// TCP ports listening in the workspace network namespace, keyed by port.
+async fn listening_ports(
+    state: &AppState,
+    workspace: &Workspace,
+) -> Result<BTreeMap<u16, ListeningPort>, (StatusCode, String)> {
+    let id = workspace.id;
+    let isolated = has_isolated_network(workspace);
+    let proc_net = if isolated {
+        let leader = isolated_network_leader(workspace).await?;
+        format!("/proc/{}/net", leader)
+    } else {
+        "/proc/net".to_string()
+    };
+
+    let mut ports: BTreeMap<u16, ListeningPort> = BTreeMap::new();
+    for file in ["tcp", "tcp6"] {
+        let contents = match tokio::fs::read_to_string(format!("{}/{}", proc_net, file)).await {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to read {}/{}: {}", proc_net, file, e),
+                ))
+            }
+        };
+        for ((address, loopback_only), port) in parse_proc_net_tcp(&contents) {
+            // Hide the sandboxed.sh API itself when sharing the host network.
+            if !isolated && port == state.config.port {
+                continue;
+            }
+            let entry = ports.entry(port).or_insert_with(|| ListeningPort {
+                port,
+                address: address.clone(),
+                loopback_only,
+                proxy_path: format!("{}/", proxy_prefix(id, port)),
+            });
+            // A port bound on any non-loopback address is reachable.
+            if entry.loopback_only && !loopback_only {
+                entry.address = address;
+                entry.loopback_only = false;
+            }
+        }
+    }
+
+    Ok(ports)
+}
+
+/// When a workspace's ports were listed, and the listening ports.
+type PortListing = (Instant, BTreeSet<u16>);
+
+/// Recently listed ports per workspace, see [`PORT_CACHE_TTL`].
+static PORT_CACHE: OnceLock<Mutex<HashMap<Uuid, PortListing>>> = OnceLock::new();
+
+/// Ports listening in the workspace, reusing a listing younger than
+/// [`PORT_CACHE_TTL`].
+async fn proxyable_ports(
+    state: &AppState,
+    workspace: &Workspace,
+) -> Result<BTreeSet<u16>, (StatusCode, String)> {
+    let cache = PORT_CACHE.get_or_init(Default::default);
+    if let Some((listed_at, ports)) = cache.lock().unwrap().get(&workspace.id) {
+        if listed_at.elapsed() < PORT_CACHE_TTL {
+            return Ok(ports.clone());
+        }
+    }
+    let ports: BTreeSet<u16> = listening_ports(state, workspace)
+        .await?
+        .into_keys()
+        .collect();
+    let mut cache = cache.lock().unwrap();
+    cache.retain(|_, (listed_at, _)| listed_at.elapsed() < PORT_CACHE_TTL);
+    cache.insert(workspace.id, (Instant::now(), ports.clone()));
+    Ok(ports)
+}
+
+/// Refuse ports that `list_ports` would not report, so neither the API itself
+/// nor other loopback services can be reached through a preview.
+async fn ensure_proxyable_port(
+    state: &AppState,
+    workspace: &Workspace,
+    port: u16,
+) -> Result<(), (StatusCode, String)> {
+    if !has_isolated_network(workspace) && port == state.config.port {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "The sandboxed.sh API port cannot be proxied".to_string(),
+        ));
+    }
+    if !proxyable_ports(state, workspace).await?.contains(&port) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!(
+                "Nothing is listening on port {} in workspace {}",
+                port, workspace.id
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// POST /api/workspaces/:id/preview-links - Create a signed, expiring preview link.
+pub async fn create_preview_link(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<AuthUser>,
+    AxumPath(id): AxumPath<Uuid>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(req): Json<CreatePreviewLinkRequest>,
+) -> Result<Json<PreviewLinkResponse>, (StatusCode, String)> {
+    let workspace = state
+        .workspaces
+        .get(id)
+        .await
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Workspace {} not found", id)))?;
+    if req.port == 0 {
+        return Err((StatusCode::BAD_REQUEST, "Port must be non-zero".to_string()));
+    }
+    ensure_proxyable_port(&state, &workspace, req.port).await?;
+
+    let ttl_secs = req
+        .ttl_secs
+        .unwrap_or(DEFAULT_PREVIEW_TTL_SECS)
+        .clamp(60, MAX_PREVIEW_TTL_SECS);
+    let base_url = format!("{}/", proxy_prefix(id, req.port));
+
+    if !state.config.auth.auth_required(state.config.dev_mode) {
+        return Ok(Json(PreviewLinkResponse {
+            url: base_url,
+            token: None,
+            expires_at: Utc::now() + chrono::Duration::seconds(ttl_secs as i64),
+        }));
+    }
+
+    let peer = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    if !is_secure_request(&headers, peer, &state.config.trusted_proxies) {
+        return Err((StatusCode::BAD_REQUEST, PREVIEW_REQUIRES_HTTPS.to_string()));
+    }
+    let secret = state.config.auth.jwt_secret.as_deref().ok_or_else(|| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "JWT_SECRET not configured".to_string(),
+        )
+    })?;
+    let (token, exp) = issue_preview_token(secret, id, req.port, ttl_secs, &user.username)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::info!(
+        workspace_id = %id,
+        port = req.port,
+        user = %user.username,
+        ttl_secs = ttl_secs,
+        "Created workspace preview link"
+    );
+
+    Ok(Json(PreviewLinkResponse {
+        url: format!("{}?{}={}", base_url, PREVIEW_TOKEN_PARAM, token),
+        token: Some(token),
+        expires_at: chrono::DateTime::from_timestamp(exp, 0).unwrap_or_else(Utc::now),
+    }))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Proxy Handlers
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// ANY /api/workspaces/:id/proxy/:port - Proxy to the root path.
+pub async fn proxy_root(
+    State(state): State<Arc<AppState>>,
+    AxumPath((id, port)): AxumPath<(Uuid, u16)>,
+    ws: Option<WebSocketUpgrade>,
+    req: Request<Body>,
+) -> Response {
+    proxy_request(state, id, port, String::new(), ws, req).await
+}
+
+/// ANY /api/workspaces/:id/proxy/:port/*path - Proxy to a sub-path.
+pub async fn proxy_path(
+    State(state): State<Arc<AppState>>,
+    AxumPath((id, port, path)): AxumPath<(Uuid, u16, String)>,
+    ws: Option<WebSocketUpgrade>,
+    req: Request<Body>,
+) -> Response {
+    proxy_request(state, id, port, path, ws, req).await
+}
+
+async fn proxy_request(
+    state: Arc<AppState>,
+    workspace_id: Uuid,
+    port: u16,
+    path: String,
+    ws: Option<WebSocketUpgrade>,
+    req: Request<Body>,
+) -> Response {
+    let (query_token, query) = split_preview_token(req.uri().query());
+
+    let access = match authorize(
+        &state,
+        req.headers(),
+        query_token.as_deref(),
+        workspace_id,
+        port,
+    ) {
+        Ok(access) => access,
+        Err(e) => return e.into_response(),
+    };
+
+    let Some(workspace) = state.workspaces.get(workspace_id).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Workspace {} not found", workspace_id),
+        )
+            .into_response();
+    };
+    if let Err(e) = ensure_proxyable_port(&state, &workspace, port).await {
+        return e.into_response();
+    }
+
+    let prefix = proxy_prefix(workspace_id, port);
+
+    // Trade a preview token in the URL for a cookie, then reload without it so
+    // the token does not leak into upstream logs or Referer headers.
+    if let Access::PreviewAccepted { token, expires_at } = &access {
+        if ws.is_none() {
+            let peer = req
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip());
+            if !is_secure_request(req.headers(), peer, &state.config.trusted_proxies) {
+                return (StatusCode::BAD_REQUEST, PREVIEW_REQUIRES_HTTPS).into_response();
+            }
+            let max_age = (expires_at - Utc::now().timestamp()).max(0);
+            let location = match &query {
+                Some(q) => format!("{}/{}?{}", prefix, path, q),
+                None => format!("{}/{}", prefix, path),
+            };
+            let cookie = format!(
+                "{}={}; Path={}/; Max-Age={}; HttpOnly; SameSite=None; Secure",
+                preview_cookie_name(port),
+                token,
+                prefix,
+                max_age
+            );
+            let mut resp = StatusCode::FOUND.into_response();
+            if let (Ok(location), Ok(cookie)) = (
+                HeaderValue::from_str(&location),
+                HeaderValue::from_str(&cookie),
+            ) {
+                resp.headers_mut().insert(header::LOCATION, location);
+                resp.headers_mut().insert(header::SET_COOKIE, cookie);
+            }
+            return resp;
+        }
+    }
+
+    let host = match resolve_upstream_host(&workspace).await {
+        Ok(host) => host,
+        Err(e) => return e.into_response(),
+    };
+    let upstream_path = match &query {
+        Some(q) => format!("/{}?{}", path, q),
+        None => format!("/{}", path),
+    };
+
+    match ws {
+        Some(ws) => proxy_websocket(ws, req.headers(), &host, port, &upstream_path).await,
+        None => proxy_http(req, &host, port, &upstream_path, &prefix).await,
+    }
+}
+
+/// Shared HTTP client for proxied requests (redirects are passed through).
+static PROXY_CLIENT: std::sync::OnceLock<reqwest::Client> = std::sync::OnceLock::new();
+
+fn proxy_client() -> &'static reqwest::Client {
+    PROXY_CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .connect_timeout(std::time::Duration::from_secs(10))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+fn is_hop_by_hop(name: &HeaderName) -> bool {
+    HOP_BY_HOP_HEADERS.contains(&name.as_str())
+}
+
+/// Why preview links are refused over plain HTTP.
+const PREVIEW_REQUIRES_HTTPS: &str =
+    "Preview links require HTTPS (or localhost): the sandboxed preview only sends its cookie on secure connections";
+
+/// Whether the request reached us over HTTPS (or from a browser on this
+/// host, which treats `localhost` as secure).
+///
+/// Requests made by the sandboxed (opaque origin) preview count as cross-site,
+/// so subresources only carry the preview cookie with `SameSite=None`, which
+/// browsers accept on secure connections only. `X-Forwarded-Proto` is only
+/// believed when `peer` is one of `trusted_proxies`. The localhost exemption
+/// goes by `peer`, not the client-controlled `Host`, and not for requests a
+/// proxy forwarded on behalf of another client.
+fn is_secure_request(
+    headers: &HeaderMap,
+    peer: Option<IpAddr>,
+    trusted_proxies: &[IpAddr],
+) -> bool {
+    let from_trusted_proxy = peer.is_some_and(|ip| trusted_proxies.contains(&ip.to_canonical()));
+    let forwarded_https = from_trusted_proxy
+        && headers
+            .get("x-forwarded-proto")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|proto| proto.eq_ignore_ascii_case("https"));
+    let forwarded = ["forwarded", "x-forwarded-for", "x-forwarded-proto"]
+        .iter()
+        .any(|name| headers.contains_key(*name));
+    let local_browser = !forwarded && peer.is_some_and(|ip| ip.to_canonical().is_loopback());
+    forwarded_https || local_browser
+}
+
+/// Set-Cookie value of the previewed app, confined to the proxy path so it
+/// cannot shadow cookies of the dashboard or other previews.
+fn scope_set_cookie(value: &str, prefix: &str) -> String {
+    let mut parts = value.split(';').map(|p| p.trim()).filter(|p| !p.is_empty());
+    let mut scoped = vec![parts.next().unwrap_or_default().to_string()];
+    scoped.extend(
+        parts
+            .filter(|attr| {
+                let name = attr.split('=').next().unwrap_or_default().trim();
+                !name.eq_ignore_ascii_case("path") && !name.eq_ignore_ascii_case("domain")
+            })
+            .map(|attr| attr.to_string()),
+    );
+    scoped.push(format!("Path={}/", prefix));
+    scoped.join("; ")
+}
+
+/// Cookie header with our preview cookies removed, or None if nothing is left.
+fn strip_preview_cookies(value: &str) -> Option<String> {
+    let kept: Vec<&str> = value
+        .split(';')
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty() && !c.starts_with(PREVIEW_COOKIE_PREFIX))
+        .collect();
+    if kept.is_empty() {
+        None
+    } else {
+        Some(kept.join("; "))
+    }
+}
+
+async fn proxy_http(
+    req: Request<Body>,
+    host: &str,
+    port: u16,
+    upstream_path: &str,
+    prefix: &str,
+) -> Response {
+    let (parts, body) = req.into_parts();
+    let url = format!("http://{}:{}{}", host, port, upstream_path);
+
+    let mut builder = proxy_client().request(parts.method.clone(), &url);
+    for (name, value) in parts.headers.iter() {
+        if is_hop_by_hop(name) || name == header::HOST || name == header::AUTHORIZATION {
+            continue;
+        }
+        if name == header::COOKIE {
+            if let Some(cookies) = value.to_str().ok().and_then(strip_preview_cookies) {
+                builder = builder.header(header::COOKIE, cookies);
+            }
+            continue;
+        }
+        builder = builder.header(name, value);
+    }
+    builder = builder
+        .header("x-forwarded-prefix", prefix)
+        .header("x-forwarded-host", host_header(&parts.headers))
+        .body(reqwest::Body::wrap_stream(body.into_data_stream()));
+
+    let upstream = match builder.send().await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::debug!(url = %url, error = %e, "Workspace proxy request failed");
+            return (
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to reach port {} in workspace: {}", port, e),
+            )
+                .into_response();
+        }
+    };
+
+    let mut resp = Response::builder().status(upstream.status());
+    if let Some(headers) = resp.headers_mut() {
+        for (name, value) in upstream.headers().iter() {
+            if is_hop_by_hop(name) {
+                continue;
+            }
+            if name == header::SET_COOKIE {
+                let scoped = value
+                    .to_str()
+                    .ok()
+                    .filter(|v| !v.trim_start().starts_with(PREVIEW_COOKIE_PREFIX))
+                    .and_then(|v| HeaderValue::from_str(&scope_set_cookie(v, prefix)).ok());
+                if let Some(scoped) = scoped {
+                    headers.append(name, scoped);
+                }
+                continue;
+            }
+            // Keep root-relative redirects inside the proxy prefix.
+            if name == header::LOCATION {
+                if let Some(loc) = value.to_str().ok().filter(|l| l.starts_with('/')) {
+                    if let Ok(v) = HeaderValue::from_str(&format!("{}{}", prefix, loc)) {
+                        headers.append(name, v);
+                        continue;
+                    }
+                }
+            }
+            headers.append(name, value.clone());
+        }
+        headers.append(
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static(PREVIEW_CSP),
+        );
+    }
+    resp.body(Body::from_stream(upstream.bytes_stream()))
+        .unwrap_or_else(|e| (StatusCode::BAD_GATEWAY, e.to_string()).into_response())
+}
+
+fn host_header(headers: &HeaderMap) -> String {
+    headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("")
+        .to_string()
+}
+
+async fn proxy_websocket(
+    ws: WebSocketUpgrade,
+    headers: &HeaderMap,
+    host: &str,
+    port: u16,
+    upstream_path: &str,
+) -> Response {
+    use tungstenite::client::IntoClientRequest;
+
+    // Forward the application's subprotocols, but never our auth token.
+    let protocols: Vec<String> = headers
+        .get("sec-websocket-protocol")
+        .and_then(|v| v.to_str().ok())
+        .map(|raw| {
+            raw.split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty() && !p.starts_with("jwt.") && p != "sandboxed")
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let url = format!("ws://{}:{}{}", host, port, upstream_path);
+    let mut request = match url.as_str().into_client_request() {
+        Ok(r) => r,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    if !protocols.is_empty() {
+        if let Ok(v) = HeaderValue::from_str(&protocols.join(", ")) {
+            request.headers_mut().insert("sec-websocket-protocol", v);
+        }
+    }
+    if let Some(cookies) = headers
+        .get(header::COOKIE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(strip_preview_cookies)
+    {
+        if let Ok(v) = HeaderValue::from_str(&cookies) {
+            request.headers_mut().insert(header::COOKIE, v);
+        }
+    }
+
+    let (upstream, upstream_resp) = match tokio_tungstenite::connect_async(request).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            tracing::debug!(url = %url, error = %e, "Workspace proxy websocket connect failed");
+            return (
+                StatusCode::BAD_GATEWAY,
+                format!(
+                    "Failed to open websocket to port {} in workspace: {}",
+                    port, e
+                ),
+            )
+                .into_response();
+        }
+    };
+
+    // Echo back whichever subprotocol the upstream picked; keep "sandboxed"
+    // for dashboard clients that authenticated through the subprotocol.
+    let mut selected: Vec<String> = upstream_resp
+        .headers()
+        .get("sec-websocket-protocol")
+        .and_then(|v| v.to_str().ok())
+        .map(|p| vec![p.to_string()])
+        .unwrap_or_default();
+    if selected.is_empty() && extract_jwt_from_protocols(headers).is_some() {
+        selected.push("sandboxed".to_string());
+    }
+
+    ws.protocols(selected)
+        .on_upgrade(move |socket| bridge_websockets(socket, upstream))
+}
+
+async fn bridge_websockets(
+    client: WebSocket,
+    upstream: tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >,
+) {
+    let (mut client_tx, mut client_rx) = client.split();
+    let (mut upstream_tx, mut upstream_rx) = upstream.split();
+
+    let client_to_upstream = async {
+        while let Some(Ok(msg)) = client_rx.next().await {
+            let msg = match msg {
+                Message::Text(t) => tungstenite::Message::Text(t),
+                Message::Binary(b) => tungstenite::Message::Binary(b),
+                Message::Ping(p) => tungstenite::Message::Ping(p),
+                Message::Pong(p) => tungstenite::Message::Pong(p),
+                Message::Close(frame) => {
+                    let frame = frame.map(|f| tungstenite::protocol::CloseFrame {
+                        code: f.code.into(),
+                        reason: f.reason,
+                    });
+                    let _ = upstream_tx.send(tungstenite::Message::Close(frame)).await;
+                    break;
+                }
+            };
+            if upstream_tx.send(msg).await.is_err() {
+                break;
+            }
+        }
+    };
+
+    let upstream_to_client = async {
+        while let Some(Ok(msg)) = upstream_rx.next().await {
+            let msg = match msg {
+                tungstenite::Message::Text(t) => Message::Text(t),
+                tungstenite::Message::Binary(b) => Message::Binary(b),
+                tungstenite::Message::Ping(p) => Message::Ping(p),
+                tungstenite::Message::Pong(p) => Message::Pong(p),
+                tungstenite::Message::Close(frame) => {
+                    let frame = frame.map(|f| axum::extract::ws::CloseFrame {
+                        code: f.code.into(),
+                        reason: f.reason,
+                    });
+                    let _ = client_tx.send(Message::Close(frame)).await;
+                    break;
+                }
+                tungstenite::Message::Frame(_) => continue,
+            };
+            if client_tx.send(msg).await.is_err() {
+                break;
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = client_to_upstream => {},
+        _ = upstream_to_client => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_NET_TCP: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:1538 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 12345 1 0000000000000000 100 0 0 10 0
+   1: 00000000:0BB8 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 12346 1 0000000000000000 100 0 0 10 0
+   2: 0100007F:0BB8 0100007F:A2C4 01 00000000:00000000 00:00000000 00000000     0        0 12347 1 0000000000000000 20 4 30 10 -1
+";
+
+    #[test]
+    fn test_parse_proc_net_tcp_listeners_only() {
+        let listeners = parse_proc_net_tcp(PROC_NET_TCP);
+        assert_eq!(
+            listeners,
+            vec![
+                (("127.0.0.1".to_string(), true), 5432),
+                (("0.0.0.0".to_string(), false), 3000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_proc_addr_ipv6() {
+        assert_eq!(
+            decode_proc_addr("00000000000000000000000000000000"),
+            Some(("::".to_string(), false))
+        );
+        assert_eq!(
+            decode_proc_addr("00000000000000000000000001000000"),
+            Some(("::1".to_string(), true))
+        );
+    }
+
+    #[test]
+    fn test_parse_ip_addr_output() {
+        let out = "2: host0    inet 10.22.0.5/28 brd 10.22.0.15 scope global dynamic host0\\       valid_lft 3500sec preferred_lft 3500sec\n";
+        assert_eq!(parse_ip_addr_output(out), Some("10.22.0.5".to_string()));
+        assert_eq!(parse_ip_addr_output(""), None);
+    }
+
+    #[test]
+    fn test_split_preview_token() {
+        let (token, rest) = split_preview_token(Some("a=1&preview_token=abc&b=2"));
+        assert_eq!(token.as_deref(), Some("abc"));
+        assert_eq!(rest.as_deref(), Some("a=1&b=2"));
+
+        let (token, rest) = split_preview_token(Some("preview_token=abc"));
+        assert_eq!(token.as_deref(), Some("abc"));
+        assert_eq!(rest, None);
+    }
+
+    #[test]
+    fn test_preview_token_scoped_to_workspace_and_port() {
+        let ws = Uuid::new_v4();
+        let (token, _) = issue_preview_token("secret", ws, 3000, 60, "alice").unwrap();
+        assert!(verify_preview_token(&token, "secret", ws, 3000).is_some());
+        assert!(verify_preview_token(&token, "secret", ws, 3001).is_none());
+        assert!(verify_preview_token(&token, "secret", Uuid::new_v4(), 3000).is_none());
+        assert!(verify_preview_token(&token, "other", ws, 3000).is_none());
+        // A preview token must not be usable as a dashboard session token.
+        assert!(jsonwebtoken::decode::<serde_json::Value>(
+            &token,
+            &DecodingKey::from_secret(b"secret"),
+            &Validation::default(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_strip_preview_cookies() {
+        assert_eq!(
+            strip_preview_cookies("a=1; sandboxed_preview_3000=tok; b=2").as_deref(),
+            Some("a=1; b=2")
+        );
+        assert_eq!(strip_preview_cookies("sandboxed_preview_3000=tok"), None);
+    }
+
+    #[test]
+    fn test_scope_set_cookie() {
+        assert_eq!(
+            scope_set_cookie("sid=abc; Path=/; Domain=example.com; HttpOnly", "/p"),
+            "sid=abc; HttpOnly; Path=/p/"
+        );
+        assert_eq!(scope_set_cookie("theme=dark", "/p"), "theme=dark; Path=/p/");
+    }
+
+    #[test]
+    fn test_is_secure_request() {
+        let proxy: IpAddr = "127.0.0.1".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let trusted = [proxy];
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_static("dash.example.com"));
+        headers.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.7"));
+        assert!(!is_secure_request(&headers, Some(proxy), &trusted));
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+        assert!(is_secure_request(&headers, Some(proxy), &trusted));
+        assert!(is_secure_request(
+            &headers,
+            Some("::ffff:127.0.0.1".parse().unwrap()),
+            &trusted
+        ));
+        // Any other client cannot claim HTTPS.
+        assert!(!is_secure_request(&headers, Some(client), &trusted));
+        assert!(!is_secure_request(&headers, None, &trusted));
+
+        // A browser on this host talks to us directly over loopback.
+        let mut local = HeaderMap::new();
+        local.insert(header::HOST, HeaderValue::from_static("localhost:3000"));
+        assert!(is_secure_request(&local, Some(proxy), &trusted));
+        assert!(is_secure_request(&local, Some("::1".parse().unwrap()), &[]));
+        // Host is client-controlled and does not make a remote client local.
+        assert!(!is_secure_request(&local, Some(client), &[]));
+        // Nor does a loopback proxy forwarding plain HTTP for a remote client.
+        local.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.7"));
+        assert!(!is_secure_request(&local, Some(proxy), &trusted));
+    }
+}
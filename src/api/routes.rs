@@ -46,11 +46,13 @@ use super::console;
 use super::control;
 use super::desktop;
 use super::desktop_stream;
+use super::disk;
 use super::fs;
 use super::library as library_api;
 use super::mcp as mcp_api;
 use super::monitoring;
 use super::opencode as opencode_api;
+use super::proxy;
 use super::secrets as secrets_api;
 use super::settings as settings_api;
 use super::system as system_api;
@@ -90,6 +92,8 @@ pub struct AppState {
     pub backend_registry: Arc<RwLock<BackendRegistry>>,
     /// Backend configuration store
     pub backend_configs: Arc<crate::backend_config::BackendConfigStore>,
+    /// Disk usage accounting, quotas and GC
+    pub disk: Arc<crate::disk_usage::DiskManager>,
 }
 
 /// Start the HTTP server.
@@ -98,8 +102,25 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
     // Start monitoring background collector early so clients get history immediately
     monitoring::init_monitoring();
 
+    // Initialize secrets store
+    let secrets = match crate::secrets::SecretsStore::new(&config.working_dir).await {
+        Ok(store) => {
+            tracing::info!("Secrets store initialized");
+            Some(Arc::new(store))
+        }
+        Err(e) => {
+            tracing::warn!("Failed to initialize secrets store: {}", e);
+            None
+        }
+    };
+
     // Initialize MCP registry
     let mcp = Arc::new(McpRegistry::new(&config.working_dir).await);
+    // OAuth tokens of remote MCPs live in the secrets store, which also
+    // resolves `${secret:registry/key}` references at mission launch
+    if let Some(secrets) = &secrets {
+        mcp.set_secrets(Arc::clone(secrets));
+    }
     if let Err(e) = crate::opencode_config::ensure_global_config(&mcp).await {
         tracing::warn!("Failed to ensure OpenCode global config: {}", e);
     }
@@ -110,9 +131,28 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
             mcp_clone.refresh_all().await;
         });
     }
+    // Keep stdio MCPs running
+    mcp.start_supervisor();
+    if config.mcp_gateway {
+        let base_url = std::env::var("SANDBOXED_SH_API_URL")
+            .unwrap_or_else(|_| format!("http://127.0.0.1:{}", config.port));
+        mcp.enable_gateway(crate::mcp::gateway::GatewaySettings {
+            base_url,
+            secret: config.auth.jwt_secret.clone(),
+        });
+    }
+    mcp.warn_unenforced_limits().await;
 
     // Initialize workspace store (loads from disk and recovers orphaned containers)
-    let workspaces = Arc::new(workspace::WorkspaceStore::new(config.working_dir.clone()).await);
+    let workspaces =
+        Arc::new(workspace::WorkspaceStore::new(config.working_dir.clone(), secrets.clone()).await);
+    // Start managed background services of ready workspaces
+    {
+        let workspaces_clone = Arc::clone(&workspaces);
+        tokio::spawn(async move {
+            workspaces_clone.start_ready_services().await;
+        });
+    }
 
     // Initialize OpenCode connection store
     let opencode_connections = Arc::new(
@@ -133,18 +173,6 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
     );
     let pending_oauth = Arc::new(RwLock::new(HashMap::new()));
 
-    // Initialize secrets store
-    let secrets = match crate::secrets::SecretsStore::new(&config.working_dir).await {
-        Ok(store) => {
-            tracing::info!("Secrets store initialized");
-            Some(Arc::new(store))
-        }
-        Err(e) => {
-            tracing::warn!("Failed to initialize secrets store: {}", e);
-            None
-        }
-    };
-
     // Initialize console session pool for WebSocket reconnection
     let console_pool = Arc::new(console::SessionPool::new());
     Arc::clone(&console_pool).start_cleanup_task();
@@ -152,6 +180,9 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
     // Initialize global settings store
     let settings = Arc::new(crate::settings::SettingsStore::new(&config.working_dir).await);
 
+    // Initialize disk usage manager (quotas, GC policy, usage history)
+    let disk = Arc::new(crate::disk_usage::DiskManager::new(&config.working_dir).await);
+
     // Initialize backend config store (persisted settings).
     // Probe each CLI binary so backends whose CLI is missing default to disabled.
     // Persisted configs are preserved — this only affects fresh installs or new backends.
@@ -248,8 +279,22 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
         let library_clone = Arc::clone(&library);
         let library_path = config.library_path.clone();
         let workspaces_clone = Arc::clone(&workspaces);
+        let library_layers = settings.get_library_layers().await;
+        let secret_scan = settings.secret_scan_mode().await;
+        let skills_registry = settings.skills_registry().await;
         tokio::spawn(async move {
-            match crate::library::LibraryStore::new(library_path, &library_remote).await {
+            let store = match crate::library::LibraryStore::new(library_path, &library_remote).await
+            {
+                Ok(store) => {
+                    store
+                        .with_secret_scan(secret_scan)
+                        .with_skills_registry(skills_registry)
+                        .with_layers(&library_layers)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            match store {
                 Ok(store) => {
                     if let Ok(plugins) = store.get_plugins().await {
                         if let Err(e) = crate::opencode_config::sync_global_plugins(&plugins).await
@@ -288,6 +333,16 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
         tracing::info!("Configuration library disabled (no remote configured)");
     }
 
+    // Secrets are masked in mission events before they are stored or streamed
+    let redactor = Arc::new(crate::secrets::SecretRedactor::new(
+        Arc::clone(&workspaces),
+        Arc::clone(&library),
+        Arc::clone(&ai_providers),
+        secrets.clone(),
+    ));
+    redactor.start_refresh();
+    mcp.set_redactor(Arc::clone(&redactor));
+
     // Spawn the single global control session actor.
     let control_state = control::ControlHub::new(
         config.clone(),
@@ -296,6 +351,7 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
         Arc::clone(&workspaces),
         Arc::clone(&library),
         secrets.clone(),
+        redactor,
     );
 
     let state = Arc::new(AppState {
@@ -315,6 +371,7 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
         settings,
         backend_registry,
         backend_configs,
+        disk,
     });
 
     // Start background desktop session cleanup task
@@ -325,6 +382,9 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
         });
     }
 
+    // Start background disk usage sampling and GC
+    tokio::spawn(disk::disk_maintenance_loop(Arc::clone(&state)));
+
     let public_routes = Router::new()
         .route("/api/health", get(health))
         .route("/api/auth/login", post(auth::login))
@@ -341,7 +401,18 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
             get(desktop_stream::desktop_stream_ws),
         )
         // WebSocket system monitoring uses subprotocol-based auth
-        .route("/api/monitoring/ws", get(monitoring::monitoring_ws));
+        .route("/api/monitoring/ws", get(monitoring::monitoring_ws))
+        // MCP gateway authorizes each request with its mission token
+        .route("/api/mcp/gateway/:mission_id", post(mcp_api::mcp_gateway))
+        // Workspace dev server proxy authorizes each request itself (JWT or preview link)
+        .route(
+            "/api/workspaces/:id/proxy/:port",
+            axum::routing::any(proxy::proxy_root),
+        )
+        .route(
+            "/api/workspaces/:id/proxy/:port/*path",
+            axum::routing::any(proxy::proxy_path),
+        );
 
     // File upload routes with increased body limit (10GB)
     let upload_route = Router::new()
@@ -415,6 +486,10 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
             "/api/control/missions/:id/parallel",
             post(control::start_mission_parallel),
         )
+        .route(
+            "/api/control/missions/:id/mcp-policy",
+            axum::routing::put(control::set_mission_mcp_policy),
+        )
         .route(
             "/api/control/missions/:id",
             axum::routing::delete(control::delete_mission),
@@ -424,6 +499,10 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
             "/api/control/missions/cleanup",
             post(control::cleanup_empty_missions),
         )
+        .route(
+            "/api/control/missions/events/scrub",
+            post(control::scrub_mission_events),
+        )
         // Parallel execution endpoints
         .route("/api/control/running", get(control::list_running_missions))
         .route(
@@ -448,12 +527,38 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
         .route("/api/mcp", get(mcp_api::list_mcps))
         .route("/api/mcp", post(mcp_api::add_mcp))
         .route("/api/mcp/refresh", post(mcp_api::refresh_all_mcps))
+        .route("/api/mcp/stats", get(mcp_api::get_mcp_stats))
         .route("/api/mcp/:id", get(mcp_api::get_mcp))
         .route("/api/mcp/:id", axum::routing::delete(mcp_api::remove_mcp))
         .route("/api/mcp/:id", axum::routing::patch(mcp_api::update_mcp))
         .route("/api/mcp/:id/enable", post(mcp_api::enable_mcp))
         .route("/api/mcp/:id/disable", post(mcp_api::disable_mcp))
         .route("/api/mcp/:id/refresh", post(mcp_api::refresh_mcp))
+        .route("/api/mcp/:id/logs", get(mcp_api::get_mcp_logs))
+        .route(
+            "/api/mcp/:id/limits",
+            axum::routing::put(mcp_api::set_mcp_limits),
+        )
+        .route(
+            "/api/mcp/:id/oauth/authorize",
+            post(mcp_api::begin_mcp_oauth),
+        )
+        .route(
+            "/api/mcp/:id/oauth/callback",
+            post(mcp_api::complete_mcp_oauth),
+        )
+        .route(
+            "/api/mcp/:id/oauth",
+            axum::routing::delete(mcp_api::revoke_mcp_oauth),
+        )
+        .route("/api/mcp/:id/resources", get(mcp_api::list_resources))
+        .route("/api/mcp/:id/resources/read", get(mcp_api::read_resource))
+        .route(
+            "/api/mcp/:id/resources/subscribe",
+            post(mcp_api::subscribe_resource),
+        )
+        .route("/api/mcp/:id/prompts", get(mcp_api::list_prompts))
+        .route("/api/mcp/:id/prompts/:name", post(mcp_api::get_prompt))
         // Tools management endpoints
         .route("/api/tools", get(mcp_api::list_tools))
         .route("/api/tools/:name/toggle", post(mcp_api::toggle_tool))
@@ -485,6 +590,9 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
         .nest("/api/secrets", secrets_api::routes())
         // Global settings endpoints
         .nest("/api/settings", settings_api::routes())
+        // Disk usage and GC endpoints
+        .nest("/api/disk", disk::routes())
+        .route("/api/monitoring/disk", get(monitoring::disk_usage_trend))
         // Desktop session management endpoints
         .nest("/api/desktop", desktop::routes())
         // System component management endpoints
@@ -523,11 +631,14 @@ pub async fn serve(config: Config) -> anyhow::Result<()> {
 
     // Setup graceful shutdown on SIGTERM/SIGINT
     let shutdown_state = Arc::clone(&state);
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async move {
-            shutdown_signal(shutdown_state).await;
-        })
-        .await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        shutdown_signal(shutdown_state).await;
+    })
+    .await?;
 
     Ok(())
 }
@@ -558,6 +669,11 @@ async fn shutdown_signal(state: Arc<AppState>) {
 
     tracing::info!("Shutdown signal received, marking running missions as interrupted...");
 
+    // Stop managed workspace services and MCP instances so they don't
+    // outlive the server
+    state.workspaces.services().stop_all().await;
+    state.mcp.stop_all_workspaces().await;
+
     // Send graceful shutdown command to all control sessions
     let sessions = state.control.all_sessions().await;
     if sessions.is_empty() {
@@ -3,7 +3,7 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::Json,
     routing::{delete, get, post, put},
@@ -13,8 +13,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::library::env_crypto;
 use crate::secrets::{
-    InitializeKeysResult, InitializeRequest, RegistryInfo, SecretInfo, SecretsStatus, SecretsStore,
-    SetSecretRequest, UnlockRequest,
+    InitializeKeysResult, InitializeRequest, RegistryInfo, SecretAuditEntry, SecretInfo,
+    SecretsStatus, SecretsStore, SetSecretRequest, UnlockRequest,
 };
 
 use super::routes::AppState;
@@ -39,6 +39,7 @@ pub fn routes() -> Router<Arc<AppState>> {
         .route("/registries/:name/:key", post(set_secret))
         .route("/registries/:name/:key", delete(delete_secret))
         .route("/registries/:name/:key/reveal", get(reveal_secret))
+        .route("/audit", get(get_audit_log))
 }
 
 /// Response for encryption status.
@@ -362,6 +363,10 @@ async fn unlock(
         .await
         .map_err(|e| (StatusCode::UNAUTHORIZED, e.to_string()))?;
 
+    // OAuth MCPs could not read their tokens while the store was locked
+    let mcp = Arc::clone(&state.mcp);
+    tokio::spawn(async move { mcp.refresh_oauth().await });
+
     Ok(Json(serde_json::json!({ "success": true })))
 }
 
@@ -525,3 +530,34 @@ async fn delete_secret(
 
     Ok(Json(serde_json::json!({ "success": true })))
 }
+
+#[derive(Debug, Deserialize)]
+struct AuditQuery {
+    #[serde(default)]
+    offset: usize,
+    #[serde(default = "default_audit_limit")]
+    limit: usize,
+}
+
+fn default_audit_limit() -> usize {
+    100
+}
+
+/// GET /api/secrets/audit
+/// Resolutions of `${secret:registry/key}` references, newest first,
+/// paged with `offset` and `limit`.
+async fn get_audit_log(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<SecretAuditEntry>>, (StatusCode, String)> {
+    let secrets = state.secrets.as_ref().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Secrets system not available".to_string(),
+    ))?;
+
+    secrets
+        .audit_log(query.offset, query.limit)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
@@ -13,7 +13,9 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 
+use crate::library::{LibraryLayerConfig, SecretScanMode};
 use crate::settings::Settings;
+use crate::skills_registry::RegistrySource;
 use crate::workspace;
 
 use super::routes::AppState;
@@ -23,6 +25,7 @@ pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/", get(get_settings).put(update_settings))
         .route("/library-remote", put(update_library_remote))
+        .route("/library-layers", put(update_library_layers))
         .route("/backup", get(download_backup))
         .route("/restore", post(restore_backup))
 }
@@ -31,21 +34,39 @@ pub fn routes() -> Router<Arc<AppState>> {
 #[derive(Debug, Serialize)]
 pub struct SettingsResponse {
     pub library_remote: Option<String>,
+    pub library_layers: Vec<LibraryLayerConfig>,
+    pub validate_library_on_commit: bool,
+    pub secret_scan: SecretScanMode,
+    pub skills_registry: RegistrySource,
 }
 
 impl From<Settings> for SettingsResponse {
     fn from(settings: Settings) -> Self {
         Self {
             library_remote: settings.library_remote,
+            library_layers: settings.library_layers,
+            validate_library_on_commit: settings.validate_library_on_commit,
+            secret_scan: settings.secret_scan,
+            skills_registry: settings.skills_registry,
         }
     }
 }
 
 /// Request to update all settings.
+///
+/// Fields other than `library_remote` are left unchanged when omitted.
 #[derive(Debug, Deserialize)]
 pub struct UpdateSettingsRequest {
     #[serde(default)]
     pub library_remote: Option<String>,
+    #[serde(default)]
+    pub library_layers: Option<Vec<LibraryLayerConfig>>,
+    #[serde(default)]
+    pub validate_library_on_commit: Option<bool>,
+    #[serde(default)]
+    pub secret_scan: Option<SecretScanMode>,
+    #[serde(default)]
+    pub skills_registry: Option<RegistrySource>,
 }
 
 /// Request to update library remote specifically.
@@ -55,6 +76,24 @@ pub struct UpdateLibraryRemoteRequest {
     pub library_remote: Option<String>,
 }
 
+/// Request to update the overlay library layers.
+#[derive(Debug, Deserialize)]
+pub struct UpdateLibraryLayersRequest {
+    /// Layers stacked on top of the primary library, lowest precedence first.
+    pub library_layers: Vec<LibraryLayerConfig>,
+}
+
+/// Response after updating library layers.
+#[derive(Debug, Serialize)]
+pub struct UpdateLibraryLayersResponse {
+    pub library_layers: Vec<LibraryLayerConfig>,
+    /// Whether the library was reinitialized.
+    pub library_reinitialized: bool,
+    /// Error message if library initialization failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub library_error: Option<String>,
+}
+
 /// Response after updating library remote.
 #[derive(Debug, Serialize)]
 pub struct UpdateLibraryRemoteResponse {
@@ -79,9 +118,22 @@ async fn update_settings(
     State(state): State<Arc<AppState>>,
     Json(req): Json<UpdateSettingsRequest>,
 ) -> Result<Json<SettingsResponse>, (StatusCode, String)> {
-    let new_settings = Settings {
-        library_remote: req.library_remote,
-    };
+    let mut new_settings = state.settings.get().await;
+    new_settings.library_remote = req.library_remote;
+    if let Some(layers) = req.library_layers {
+        crate::library::layers::validate_layer_configs(&layers)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        new_settings.library_layers = layers;
+    }
+    if let Some(validate) = req.validate_library_on_commit {
+        new_settings.validate_library_on_commit = validate;
+    }
+    if let Some(mode) = req.secret_scan {
+        new_settings.secret_scan = mode;
+    }
+    if let Some(registry) = req.skills_registry {
+        new_settings.skills_registry = registry;
+    }
 
     state
         .settings
@@ -134,11 +186,56 @@ async fn update_library_remote(
     }))
 }
 
+/// PUT /api/settings/library-layers
+/// Update the overlay library layers and reinitialize the library.
+async fn update_library_layers(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<UpdateLibraryLayersRequest>,
+) -> Result<Json<UpdateLibraryLayersResponse>, (StatusCode, String)> {
+    crate::library::layers::validate_layer_configs(&req.library_layers)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let changed = state
+        .settings
+        .set_library_layers(req.library_layers.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // Layers only apply on top of a configured primary library
+    let remote = state.settings.get_library_remote().await;
+    let (library_reinitialized, library_error) = match (changed, remote) {
+        (true, Some(remote)) => match reinitialize_library(&state, &remote).await {
+            Ok(()) => (true, None),
+            Err(e) => (false, Some(e)),
+        },
+        _ => (false, None),
+    };
+
+    Ok(Json(UpdateLibraryLayersResponse {
+        library_layers: req.library_layers,
+        library_reinitialized,
+        library_error,
+    }))
+}
+
 /// Reinitialize the library with a new remote URL.
 async fn reinitialize_library(state: &Arc<AppState>, remote: &str) -> Result<(), String> {
     let library_path = state.config.library_path.clone();
+    let layers = state.settings.get_library_layers().await;
+    let secret_scan = state.settings.secret_scan_mode().await;
+    let skills_registry = state.settings.skills_registry().await;
 
-    match crate::library::LibraryStore::new(library_path, remote).await {
+    let store = match crate::library::LibraryStore::new(library_path, remote).await {
+        Ok(store) => {
+            store
+                .with_secret_scan(secret_scan)
+                .with_skills_registry(skills_registry)
+                .with_layers(&layers)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match store {
         Ok(store) => {
             // Sync OpenCode plugins
             if let Ok(plugins) = store.get_plugins().await {
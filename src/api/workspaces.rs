@@ -7,7 +7,7 @@
 //! - Delete workspace
 
 use axum::{
-    extract::{Path as AxumPath, State},
+    extract::{Path as AxumPath, Query, State},
     http::StatusCode,
     routing::{delete, get, post, put},
     Json, Router,
@@ -18,9 +18,12 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::harness_config::{HarnessConfigFiles, HarnessDriftReport, HarnessSecrets};
 use crate::library::WorkspaceTemplate;
+use crate::mcp::{McpPolicies, McpWorkspaceInstance};
 use crate::nspawn::NspawnDistro;
 use crate::workspace::{self, TailscaleMode, Workspace, WorkspaceStatus, WorkspaceType};
+use crate::workspace_services::{self, ServiceDefinition, ServiceStatus};
 
 /// Create workspace routes.
 pub fn routes() -> Router<Arc<super::routes::AppState>> {
@@ -33,10 +36,30 @@ pub fn routes() -> Router<Arc<super::routes::AppState>> {
         .route("/:id/build", post(build_workspace))
         .route("/:id/sync", post(sync_workspace))
         .route("/:id/exec", post(exec_workspace_command))
+        // Dev server previews (the proxy itself is mounted in routes.rs)
+        .route("/:id/ports", get(super::proxy::list_ports))
+        .route(
+            "/:id/preview-links",
+            post(super::proxy::create_preview_link),
+        )
+        // Managed background services
+        .route("/:id/services", get(list_services))
+        .route("/:id/services/:name/start", post(start_service))
+        .route("/:id/services/:name/stop", post(stop_service))
+        .route("/:id/services/:name/restart", post(restart_service))
+        .route("/:id/services/:name/logs", get(get_service_logs))
+        // Workspace-scoped stdio MCPs running inside the workspace
+        .route("/:id/mcps", get(list_workspace_mcps))
+        .route("/:id/mcps/:mcp_id/start", post(start_workspace_mcp))
+        .route("/:id/mcps/:mcp_id/stop", post(stop_workspace_mcp))
+        .route("/:id/mcps/:mcp_id/logs", get(get_workspace_mcp_logs))
         // Debug endpoints for template development
         .route("/:id/debug", get(get_workspace_debug))
         .route("/:id/rerun-init", post(rerun_init_script))
         .route("/:id/init-log", get(get_init_log))
+        // Generated harness config (opencode.json, .claude/, AGENTS.md, ...)
+        .route("/:id/harness-config", get(get_harness_config))
+        .route("/:id/harness-config/drift", get(get_harness_config_drift))
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -75,6 +98,9 @@ pub struct CreateWorkspaceRequest {
     /// Empty = use default MCPs (those with `default_enabled = true`).
     #[serde(default)]
     pub mcps: Vec<String>,
+    /// Background services (appended to the template's services)
+    #[serde(default)]
+    pub services: Vec<ServiceDefinition>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -102,6 +128,8 @@ pub struct UpdateWorkspaceRequest {
     pub tailscale_mode: Option<TailscaleMode>,
     /// MCP server names to enable for this workspace.
     pub mcps: Option<Vec<String>>,
+    /// Background services (replaces the current list)
+    pub services: Option<Vec<ServiceDefinition>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -124,6 +152,7 @@ pub struct WorkspaceResponse {
     pub tailscale_mode: Option<TailscaleMode>,
     pub mcps: Vec<String>,
     pub config_profile: Option<String>,
+    pub services: Vec<ServiceDefinition>,
 }
 
 impl From<Workspace> for WorkspaceResponse {
@@ -147,6 +176,7 @@ impl From<Workspace> for WorkspaceResponse {
             tailscale_mode: w.tailscale_mode,
             mcps: w.mcps,
             config_profile: w.config_profile,
+            services: w.services,
         }
     }
 }
@@ -277,7 +307,7 @@ async fn create_workspace(
 
         template_data = Some(
             library
-                .get_workspace_template(template_name)
+                .resolve_workspace_template(template_name)
                 .await
                 .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?,
         );
@@ -378,6 +408,14 @@ async fn create_workspace(
         .as_ref()
         .and_then(|t| t.config_profile.clone());
 
+    // Services: template services first, then request services
+    let mut services = template_data
+        .as_ref()
+        .map(|t| t.services.clone())
+        .unwrap_or_default();
+    services.extend(req.services.clone());
+    workspace_services::validate_services(&services).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
     let mut workspace = match workspace_type {
         WorkspaceType::Host => Workspace {
             id: Uuid::new_v4(),
@@ -399,6 +437,7 @@ async fn create_workspace(
             tailscale_mode,
             mcps: mcps.clone(),
             config_profile: config_profile.clone(),
+            services,
         },
         WorkspaceType::Container => {
             let mut ws = Workspace::new_container(req.name, path);
@@ -413,6 +452,7 @@ async fn create_workspace(
             ws.tailscale_mode = tailscale_mode;
             ws.mcps = mcps;
             ws.config_profile = config_profile;
+            ws.services = services;
             ws
         }
     };
@@ -457,6 +497,7 @@ async fn create_workspace(
         // Spawn build task
         let workspaces_store = Arc::clone(&state.workspaces);
         let working_dir = state.config.working_dir.clone();
+        let secrets = state.secrets.clone();
         let mut workspace_for_build = workspace.clone();
         // Get library for init script assembly
         let library = {
@@ -471,6 +512,7 @@ async fn create_workspace(
                 false, // don't force rebuild
                 &working_dir,
                 library.as_deref(),
+                secrets.as_deref(),
             )
             .await;
 
@@ -593,6 +635,20 @@ async fn update_workspace(
         workspace.mcps = mcps;
     }
 
+    // Update services if provided (running services keep their old definition
+    // until restarted; removed services are stopped, as they could no longer
+    // be managed)
+    if let Some(services) = req.services {
+        workspace_services::validate_services(&services)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        state
+            .workspaces
+            .services()
+            .stop_removed(workspace.id, &services)
+            .await;
+        workspace.services = services;
+    }
+
     // Save the updated workspace
     state.workspaces.update(workspace.clone()).await;
 
@@ -684,6 +740,7 @@ async fn delete_workspace(
     }
 
     if state.workspaces.delete(id).await {
+        state.mcp.stop_workspace(id).await;
         Ok((
             StatusCode::OK,
             format!("Workspace {} deleted successfully", id),
@@ -817,10 +874,14 @@ async fn build_workspace(
     // Set status to Building immediately to prevent concurrent builds
     workspace.status = WorkspaceStatus::Building;
     state.workspaces.update(workspace.clone()).await;
+    // MCP instances run in the container being rebuilt; they start again on
+    // their next call
+    state.mcp.stop_workspace(id).await;
 
     // Run the container build in the background so long builds aren't tied to the HTTP request
     let workspaces_store = Arc::clone(&state.workspaces);
     let working_dir = state.config.working_dir.clone();
+    let secrets = state.secrets.clone();
     let mut workspace_for_build = workspace.clone();
     // Get library for init script assembly
     let library = {
@@ -835,6 +896,7 @@ async fn build_workspace(
             force_rebuild,
             &working_dir,
             library.as_deref(),
+            secrets.as_deref(),
         )
         .await;
 
@@ -1503,6 +1565,373 @@ async fn rerun_init_script(
     }))
 }
 
+// ─────────────────────────────────────────────────────────────────────────────
+// Managed Services
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceLogsQuery {
+    /// Number of lines to return (default: 200, max: 1000)
+    pub lines: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceLogsResponse {
+    pub name: String,
+    pub lines: Vec<String>,
+}
+
+async fn get_workspace_or_404(
+    state: &super::routes::AppState,
+    id: Uuid,
+) -> Result<Workspace, (StatusCode, String)> {
+    state
+        .workspaces
+        .get(id)
+        .await
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Workspace {} not found", id)))
+}
+
+fn ensure_service_declared(workspace: &Workspace, name: &str) -> Result<(), (StatusCode, String)> {
+    if workspace.services.iter().any(|s| s.name == name) {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            format!("Service {} not found in workspace {}", name, workspace.name),
+        ))
+    }
+}
+
+/// GET /api/workspaces/:id/services - List services and their status.
+async fn list_services(
+    State(state): State<Arc<super::routes::AppState>>,
+    AxumPath(id): AxumPath<Uuid>,
+) -> Result<Json<Vec<ServiceStatus>>, (StatusCode, String)> {
+    let workspace = get_workspace_or_404(&state, id).await?;
+    Ok(Json(state.workspaces.services().list(&workspace).await))
+}
+
+/// POST /api/workspaces/:id/services/:name/start - Start a service.
+async fn start_service(
+    State(state): State<Arc<super::routes::AppState>>,
+    AxumPath((id, name)): AxumPath<(Uuid, String)>,
+) -> Result<Json<ServiceStatus>, (StatusCode, String)> {
+    let workspace = get_workspace_or_404(&state, id).await?;
+    ensure_service_declared(&workspace, &name)?;
+    state
+        .workspaces
+        .services()
+        .start(&workspace, &name)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+/// POST /api/workspaces/:id/services/:name/stop - Stop a service.
+async fn stop_service(
+    State(state): State<Arc<super::routes::AppState>>,
+    AxumPath((id, name)): AxumPath<(Uuid, String)>,
+) -> Result<Json<ServiceStatus>, (StatusCode, String)> {
+    let workspace = get_workspace_or_404(&state, id).await?;
+    ensure_service_declared(&workspace, &name)?;
+    let services = state.workspaces.services();
+    services
+        .stop(id, &name)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    service_status(&state, &workspace, &name).await
+}
+
+/// POST /api/workspaces/:id/services/:name/restart - Restart a service.
+async fn restart_service(
+    State(state): State<Arc<super::routes::AppState>>,
+    AxumPath((id, name)): AxumPath<(Uuid, String)>,
+) -> Result<Json<ServiceStatus>, (StatusCode, String)> {
+    let workspace = get_workspace_or_404(&state, id).await?;
+    ensure_service_declared(&workspace, &name)?;
+    let services = state.workspaces.services();
+    // Not running is fine for a restart.
+    let _ = services.stop(id, &name).await;
+    services
+        .start(&workspace, &name)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+/// GET /api/workspaces/:id/services/:name/logs - Tail service logs.
+async fn get_service_logs(
+    State(state): State<Arc<super::routes::AppState>>,
+    AxumPath((id, name)): AxumPath<(Uuid, String)>,
+    Query(query): Query<ServiceLogsQuery>,
+) -> Result<Json<ServiceLogsResponse>, (StatusCode, String)> {
+    let workspace = get_workspace_or_404(&state, id).await?;
+    ensure_service_declared(&workspace, &name)?;
+    let lines = query.lines.unwrap_or(200).min(1000);
+    let lines = state
+        .workspaces
+        .services()
+        .logs(id, &name, lines)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(ServiceLogsResponse { name, lines }))
+}
+
+async fn service_status(
+    state: &super::routes::AppState,
+    workspace: &Workspace,
+    name: &str,
+) -> Result<Json<ServiceStatus>, (StatusCode, String)> {
+    state
+        .workspaces
+        .services()
+        .list(workspace)
+        .await
+        .into_iter()
+        .find(|s| s.name == name)
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Service {} not found", name)))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Workspace MCPs
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// The workspace's instance of a workspace-scoped stdio MCP it uses.
+async fn workspace_mcp(
+    state: &super::routes::AppState,
+    workspace: &Workspace,
+    mcp_id: Uuid,
+) -> Result<McpWorkspaceInstance, (StatusCode, String)> {
+    state
+        .mcp
+        .workspace_instances(workspace)
+        .await
+        .into_iter()
+        .find(|i| i.mcp_id == mcp_id)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!(
+                    "MCP {} is not a workspace MCP of workspace {}",
+                    mcp_id, workspace.name
+                ),
+            )
+        })
+}
+
+/// GET /api/workspaces/:id/mcps - List workspace-scoped stdio MCPs and their
+/// instances in the workspace.
+async fn list_workspace_mcps(
+    State(state): State<Arc<super::routes::AppState>>,
+    AxumPath(id): AxumPath<Uuid>,
+) -> Result<Json<Vec<McpWorkspaceInstance>>, (StatusCode, String)> {
+    let workspace = get_workspace_or_404(&state, id).await?;
+    Ok(Json(state.mcp.workspace_instances(&workspace).await))
+}
+
+/// POST /api/workspaces/:id/mcps/:mcp_id/start - (Re)start an MCP inside the
+/// workspace and discover its tools.
+async fn start_workspace_mcp(
+    State(state): State<Arc<super::routes::AppState>>,
+    AxumPath((id, mcp_id)): AxumPath<(Uuid, Uuid)>,
+) -> Result<Json<McpWorkspaceInstance>, (StatusCode, String)> {
+    let workspace = get_workspace_or_404(&state, id).await?;
+    workspace_mcp(&state, &workspace, mcp_id).await?;
+    state
+        .mcp
+        .start_in_workspace(mcp_id, &workspace)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+/// POST /api/workspaces/:id/mcps/:mcp_id/stop - Stop an MCP's instance.
+async fn stop_workspace_mcp(
+    State(state): State<Arc<super::routes::AppState>>,
+    AxumPath((id, mcp_id)): AxumPath<(Uuid, Uuid)>,
+) -> Result<Json<McpWorkspaceInstance>, (StatusCode, String)> {
+    let workspace = get_workspace_or_404(&state, id).await?;
+    workspace_mcp(&state, &workspace, mcp_id).await?;
+    state.mcp.stop_in_workspace(mcp_id, id).await;
+    workspace_mcp(&state, &workspace, mcp_id).await.map(Json)
+}
+
+/// GET /api/workspaces/:id/mcps/:mcp_id/logs - Tail an MCP instance's stderr.
+async fn get_workspace_mcp_logs(
+    State(state): State<Arc<super::routes::AppState>>,
+    AxumPath((id, mcp_id)): AxumPath<(Uuid, Uuid)>,
+    Query(query): Query<ServiceLogsQuery>,
+) -> Result<Json<ServiceLogsResponse>, (StatusCode, String)> {
+    let workspace = get_workspace_or_404(&state, id).await?;
+    let instance = workspace_mcp(&state, &workspace, mcp_id).await?;
+    let mut lines = state.mcp.workspace_stderr_lines(mcp_id, id).await;
+    let keep = query.lines.unwrap_or(200).min(1000);
+    lines.drain(..lines.len().saturating_sub(keep));
+    Ok(Json(ServiceLogsResponse {
+        name: instance.name,
+        lines,
+    }))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Harness Config Preview
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct HarnessConfigQuery {
+    /// Backend to render for: `opencode`, `claudecode` or `amp` (default:
+    /// the mission's backend, then `opencode`)
+    pub backend: Option<String>,
+    /// Config profile (default: the mission's profile, then the workspace's,
+    /// then `default`)
+    pub profile: Option<String>,
+    /// Render for this mission's directory instead of the workspace root;
+    /// required for drift, as harness files are only written for missions
+    pub mission_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HarnessConfigResponse {
+    pub backend: String,
+    pub profile: Option<String>,
+    /// Directory the files are written to
+    pub directory: PathBuf,
+    #[serde(flatten)]
+    pub config: HarnessConfigFiles,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HarnessDriftResponse {
+    pub backend: String,
+    pub profile: Option<String>,
+    pub directory: PathBuf,
+    #[serde(flatten)]
+    pub report: HarnessDriftReport,
+}
+
+/// Render the harness config a workspace would get, without writing it.
+async fn render_harness_config(
+    state: &super::routes::AppState,
+    id: Uuid,
+    query: HarnessConfigQuery,
+) -> Result<(String, Option<String>, PathBuf, HarnessConfigFiles), (StatusCode, String)> {
+    let workspace = get_workspace_or_404(state, id).await?;
+    let mission = match query.mission_id {
+        Some(mission_id) => {
+            let mission = state
+                .control
+                .mission_owner(mission_id)
+                .await
+                .map(|(_, mission)| mission)
+                .filter(|mission| mission.workspace_id == workspace.id)
+                .ok_or_else(|| {
+                    (
+                        StatusCode::NOT_FOUND,
+                        format!("Mission {} not found in this workspace", mission_id),
+                    )
+                })?;
+            Some(mission)
+        }
+        None => None,
+    };
+    let backend = query
+        .backend
+        .or_else(|| mission.as_ref().map(|m| m.backend.clone()))
+        .unwrap_or_else(|| "opencode".to_string());
+    if !matches!(backend.as_str(), "opencode" | "claudecode" | "amp") {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown backend: {}", backend),
+        ));
+    }
+    let profile = query
+        .profile
+        .or_else(|| mission.as_ref().and_then(|m| m.config_profile.clone()))
+        .or_else(|| workspace.config_profile.clone());
+    let directory = match query.mission_id {
+        Some(mission_id) => workspace::mission_workspace_dir_for_root(&workspace.path, mission_id),
+        None => workspace.path.clone(),
+    };
+
+    let library_guard = state.library.read().await;
+    let library = library_guard.as_ref().map(|l| l.as_ref());
+    let tool_policy = match mission {
+        Some(mission) => {
+            McpPolicies::resolve(
+                library,
+                &state.config,
+                mission.agent.as_deref(),
+                mission.mcp_policy.as_ref(),
+            )
+            .await
+        }
+        None => McpPolicies::default(),
+    };
+    let config = workspace::render_workspace_harness_config(
+        &workspace,
+        &state.mcp,
+        library,
+        query.mission_id,
+        &tool_policy,
+        &directory,
+        &backend,
+        None,
+        profile.as_deref(),
+        &format!("harness-preview-{}", workspace.name),
+        HarnessSecrets::Placeholders,
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to render harness config: {}", e),
+        )
+    })?;
+
+    Ok((backend, profile, directory, config))
+}
+
+/// GET /api/workspaces/:id/harness-config - Preview the generated harness config files.
+async fn get_harness_config(
+    State(state): State<Arc<super::routes::AppState>>,
+    AxumPath(id): AxumPath<Uuid>,
+    Query(query): Query<HarnessConfigQuery>,
+) -> Result<Json<HarnessConfigResponse>, (StatusCode, String)> {
+    let (backend, profile, directory, config) = render_harness_config(&state, id, query).await?;
+    Ok(Json(HarnessConfigResponse {
+        backend,
+        profile,
+        directory,
+        config,
+    }))
+}
+
+/// GET /api/workspaces/:id/harness-config/drift - Compare the generated config with disk.
+async fn get_harness_config_drift(
+    State(state): State<Arc<super::routes::AppState>>,
+    AxumPath(id): AxumPath<Uuid>,
+    Query(query): Query<HarnessConfigQuery>,
+) -> Result<Json<HarnessDriftResponse>, (StatusCode, String)> {
+    // Harness files are written to mission directories only, so the
+    // workspace root would report every file as missing.
+    if query.mission_id.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "mission_id is required to check harness config drift".to_string(),
+        ));
+    }
+    let (backend, profile, directory, config) = render_harness_config(&state, id, query).await?;
+    let report = config.drift(&directory).await;
+    Ok(Json(HarnessDriftResponse {
+        backend,
+        profile,
+        directory,
+        report,
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -51,6 +51,7 @@ struct JsonRpcError {
 
 #[derive(Debug, Deserialize)]
 struct RuntimeWorkspace {
+    workspace_id: Option<String>,
     workspace_root: Option<String>,
     workspace_type: Option<String>,
     working_dir: Option<String>,
@@ -581,6 +582,87 @@ impl Tool for UpdateInitScriptTool {
     }
 }
 
+/// Tool for inspecting the workspace's managed background services via the backend API.
+struct WorkspaceServicesTool;
+
+#[async_trait]
+impl Tool for WorkspaceServicesTool {
+    fn name(&self) -> &str {
+        "workspace_services"
+    }
+
+    fn description(&self) -> &str {
+        "List the managed background services of the current workspace (dev servers, \
+         databases, watchers) with their state and health. Pass a service name to also \
+         get the tail of its logs."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": {
+                    "type": "string",
+                    "description": "Optional service name to fetch logs for"
+                },
+                "lines": {
+                    "type": "integer",
+                    "description": "Number of log lines to return (default: 100)"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, args: Value, _working_dir: &Path) -> anyhow::Result<String> {
+        let workspace_id = load_runtime_workspace()
+            .and_then(|state| state.workspace_id)
+            .ok_or_else(|| anyhow::anyhow!("Current workspace id is unknown"))?;
+
+        let api_base = std::env::var("SANDBOXED_SH_API_URL")
+            .unwrap_or_else(|_| "http://127.0.0.1:3000".to_string());
+        let auth_token = std::env::var("SANDBOXED_SH_API_TOKEN").ok();
+
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()?;
+
+        let url = match args["name"].as_str() {
+            Some(name) => {
+                if name.contains("..") || name.contains('/') || name.contains('\\') {
+                    return Err(anyhow::anyhow!(
+                        "Invalid service name: contains path separators or '..'"
+                    ));
+                }
+                let lines = args["lines"].as_u64().unwrap_or(100);
+                format!(
+                    "{}/api/workspaces/{}/services/{}/logs?lines={}",
+                    api_base, workspace_id, name, lines
+                )
+            }
+            None => format!("{}/api/workspaces/{}/services", api_base, workspace_id),
+        };
+
+        let mut request = client.get(&url);
+        if let Some(token) = auth_token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+
+        if status.is_success() {
+            Ok(body)
+        } else {
+            Err(anyhow::anyhow!(
+                "Failed to query workspace services: {} - {}",
+                status,
+                body
+            ))
+        }
+    }
+}
+
 fn tool_set() -> HashMap<String, Arc<dyn Tool>> {
     let mut tools: HashMap<String, Arc<dyn Tool>> = HashMap::new();
 
@@ -596,6 +678,10 @@ fn tool_set() -> HashMap<String, Arc<dyn Tool>> {
         "update_init_script".to_string(),
         Arc::new(UpdateInitScriptTool),
     );
+    tools.insert(
+        "workspace_services".to_string(),
+        Arc::new(WorkspaceServicesTool),
+    );
 
     tools
 }
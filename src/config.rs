@@ -22,9 +22,12 @@
 //! and search anywhere on the machine. The `WORKING_DIR` is just the default for relative paths.
 
 use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::path::PathBuf;
 use thiserror::Error;
 
+use crate::mcp::McpPolicies;
+
 #[derive(Debug, Error)]
 pub enum ConfigError {
     #[error("Missing required environment variable: {0}")]
@@ -225,6 +228,21 @@ pub struct Config {
     /// Path to the configuration library git repo.
     /// Default: {working_dir}/.sandboxed-sh/library
     pub library_path: PathBuf,
+
+    /// Serve one MCP gateway per mission instead of writing each global MCP
+    /// into harness configs (see [`crate::mcp::gateway`]). Without it, MCPs
+    /// that a tool policy constrains by argument are left out of harness
+    /// configs, since nothing else can enforce those rules, and MCP call
+    /// limits and telemetry do not cover harness tool calls.
+    pub mcp_gateway: bool,
+
+    /// Peers whose `X-Forwarded-Proto` header is believed (the TLS-terminating
+    /// reverse proxy). Defaults to loopback; other clients cannot claim HTTPS.
+    pub trusted_proxies: Vec<IpAddr>,
+
+    /// MCP tool policies of the mission a turn runs for; set per turn like
+    /// `opencode_agent`, empty outside missions
+    pub mcp_policies: McpPolicies,
 }
 
 /// API auth configuration.
@@ -454,6 +472,23 @@ impl Config {
 
         let context = ContextConfig::from_env();
 
+        let mcp_gateway = std::env::var("SANDBOXED_SH_MCP_GATEWAY")
+            .ok()
+            .map(|v| {
+                parse_bool(&v).map_err(|e| {
+                    ConfigError::InvalidValue("SANDBOXED_SH_MCP_GATEWAY".to_string(), e)
+                })
+            })
+            .transpose()?
+            .unwrap_or(false);
+
+        let trusted_proxies = match std::env::var("SANDBOXED_SH_TRUSTED_PROXIES") {
+            Ok(v) => parse_ip_list(&v).map_err(|e| {
+                ConfigError::InvalidValue("SANDBOXED_SH_TRUSTED_PROXIES".to_string(), e)
+            })?,
+            Err(_) => default_trusted_proxies(),
+        };
+
         // Library configuration
         // Note: library_remote is now managed via the settings module (persisted to disk)
         let library_path = std::env::var("LIBRARY_PATH")
@@ -475,6 +510,9 @@ impl Config {
             opencode_agent,
             opencode_permissive,
             library_path,
+            mcp_gateway,
+            trusted_proxies,
+            mcp_policies: McpPolicies::default(),
         })
     }
 
@@ -496,10 +534,33 @@ impl Config {
             opencode_agent: None,
             opencode_permissive: true,
             library_path,
+            mcp_gateway: false,
+            trusted_proxies: default_trusted_proxies(),
+            mcp_policies: McpPolicies::default(),
         }
     }
 }
 
+fn default_trusted_proxies() -> Vec<IpAddr> {
+    vec![
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(Ipv6Addr::LOCALHOST),
+    ]
+}
+
+/// Comma-separated IP addresses; an empty value yields an empty list.
+fn parse_ip_list(value: &str) -> Result<Vec<IpAddr>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse::<IpAddr>()
+                .map_err(|_| format!("expected an IP address, got: {}", s))
+        })
+        .collect()
+}
+
 fn parse_bool(value: &str) -> Result<bool, String> {
     match value.trim().to_lowercase().as_str() {
         "1" | "true" | "t" | "yes" | "y" | "on" => Ok(true),
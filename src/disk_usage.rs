@@ -0,0 +1,898 @@
+//! Disk usage accounting and garbage collection for workspaces.
+//!
+//! Mission work directories (`workspaces/mission-*`), container rootfses and
+//! search indexes (`.sandboxed_sh/index`) grow without bound. The
+//! [`DiskManager`]:
+//! - Measures per-workspace and per-mission usage; the scheduled scan also
+//!   records an hourly trend sample (on-demand scans leave history untouched)
+//! - Checks usage against configurable per-workspace quotas
+//! - Garbage-collects work directories of finished missions older than
+//!   `retain_days` (or the oldest ones when a workspace is over quota),
+//!   either deleting them or archiving them to a zip first
+//!
+//! Pinned missions are never collected, and each mission is reloaded from
+//! the store right before its directory is removed, so a mission resumed or
+//! pinned after the scan is left alone. Policy, pins and usage history are
+//! persisted at `{working_dir}/.sandboxed-sh/disk.json`.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::api::control::MissionStatus;
+use crate::api::mission_store::{Mission, MissionStore};
+use crate::workspace::{self, Workspace, WorkspaceType};
+
+/// Maximum number of usage samples kept (hourly samples for 30 days).
+const MAX_USAGE_SAMPLES: usize = 24 * 30;
+
+fn default_retain_days() -> u64 {
+    14
+}
+
+fn default_gc_statuses() -> Vec<MissionStatus> {
+    vec![MissionStatus::Completed, MissionStatus::Failed]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Policy
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// What GC does with a collected mission directory.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GcAction {
+    /// Zip the directory into `.sandboxed-sh/archives/` before removing it
+    #[default]
+    Archive,
+    /// Remove the directory
+    Delete,
+}
+
+/// Garbage collection policy for mission work directories.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcPolicy {
+    /// Run GC automatically in the background
+    #[serde(default)]
+    pub enabled: bool,
+    /// Keep directories of missions updated within this many days
+    #[serde(default = "default_retain_days")]
+    pub retain_days: u64,
+    /// What to do with collected directories
+    #[serde(default)]
+    pub action: GcAction,
+    /// Mission statuses eligible for collection
+    #[serde(default = "default_gc_statuses")]
+    pub statuses: Vec<MissionStatus>,
+    /// Also collect the oldest eligible missions younger than `retain_days`
+    /// while a workspace is over its quota
+    #[serde(default = "default_true")]
+    pub collect_over_quota: bool,
+}
+
+impl Default for GcPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retain_days: default_retain_days(),
+            action: GcAction::default(),
+            statuses: default_gc_statuses(),
+            collect_over_quota: true,
+        }
+    }
+}
+
+/// Disk quotas and GC policy.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiskPolicy {
+    /// Quota applied to workspaces without an explicit quota
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_workspace_quota_bytes: Option<u64>,
+    /// Per-workspace quotas
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub workspace_quotas: HashMap<Uuid, u64>,
+    #[serde(default)]
+    pub gc: GcPolicy,
+}
+
+impl DiskPolicy {
+    /// Quota for a workspace, if any.
+    pub fn quota_for(&self, workspace_id: Uuid) -> Option<u64> {
+        self.workspace_quotas
+            .get(&workspace_id)
+            .copied()
+            .or(self.default_workspace_quota_bytes)
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Usage Report
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Disk usage of a single mission work directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct MissionDiskUsage {
+    /// Mission owning the directory (`None` if no mission record matches)
+    pub mission_id: Option<Uuid>,
+    pub dir_name: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub status: Option<MissionStatus>,
+    pub updated_at: Option<String>,
+    pub pinned: bool,
+}
+
+/// Disk usage of a workspace.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceDiskUsage {
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub workspace_type: WorkspaceType,
+    pub path: PathBuf,
+    /// Container rootfs size, or mission directories + index for host workspaces
+    pub total_bytes: u64,
+    /// Size of all mission and task work directories
+    pub work_dirs_bytes: u64,
+    /// Size of `.sandboxed_sh/index` directories
+    pub index_bytes: u64,
+    pub quota_bytes: Option<u64>,
+    pub over_quota: bool,
+    /// Mission directories, largest first
+    pub missions: Vec<MissionDiskUsage>,
+}
+
+/// Disk usage across all workspaces.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskUsageReport {
+    pub generated_at: DateTime<Utc>,
+    pub total_bytes: u64,
+    /// Cached rootfs tarballs (`.sandboxed-sh/cache`)
+    pub cache_bytes: u64,
+    /// Archived mission directories (`.sandboxed-sh/archives`)
+    pub archive_bytes: u64,
+    pub workspaces: Vec<WorkspaceDiskUsage>,
+}
+
+/// A point in the usage trend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSample {
+    pub timestamp: DateTime<Utc>,
+    pub total_bytes: u64,
+    #[serde(default)]
+    pub workspaces: HashMap<Uuid, u64>,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// GC Report
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Why a mission directory was selected for collection.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GcReason {
+    /// Older than `retain_days`
+    Expired,
+    /// Collected to bring an over-quota workspace back under its quota
+    OverQuota,
+}
+
+/// A mission directory selected for collection.
+#[derive(Debug, Clone, Serialize)]
+pub struct GcCandidate {
+    pub mission_id: Uuid,
+    pub workspace_id: Uuid,
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub status: MissionStatus,
+    pub updated_at: String,
+    pub reason: GcReason,
+    /// Archive written for this directory (archive action, not dry run)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of a GC run.
+#[derive(Debug, Clone, Serialize)]
+pub struct GcReport {
+    pub dry_run: bool,
+    pub action: GcAction,
+    pub candidates: Vec<GcCandidate>,
+    /// Bytes freed (or that would be freed for a dry run)
+    pub freed_bytes: u64,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Manager
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DiskState {
+    #[serde(default)]
+    policy: DiskPolicy,
+    #[serde(default)]
+    pinned: BTreeSet<Uuid>,
+    #[serde(default)]
+    history: Vec<UsageSample>,
+}
+
+/// Tracks disk usage, quotas, pinned missions and runs GC.
+pub struct DiskManager {
+    working_dir: PathBuf,
+    storage_path: PathBuf,
+    state: RwLock<DiskState>,
+}
+
+impl DiskManager {
+    /// Create a manager, loading persisted state if available.
+    pub async fn new(working_dir: &Path) -> Self {
+        let storage_path = working_dir.join(".sandboxed-sh/disk.json");
+        let state = match tokio::fs::read_to_string(&storage_path).await {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                tracing::warn!(
+                    "Failed to parse {}: {}, using defaults",
+                    storage_path.display(),
+                    e
+                );
+                DiskState::default()
+            }),
+            Err(_) => DiskState::default(),
+        };
+
+        Self {
+            working_dir: working_dir.to_path_buf(),
+            storage_path,
+            state: RwLock::new(state),
+        }
+    }
+
+    async fn save(&self) -> Result<(), std::io::Error> {
+        let contents = {
+            let state = self.state.read().await;
+            serde_json::to_string_pretty(&*state)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?
+        };
+        if let Some(parent) = self.storage_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&self.storage_path, contents).await
+    }
+
+    /// Directory where archived mission directories are written.
+    pub fn archives_dir(&self) -> PathBuf {
+        self.working_dir.join(".sandboxed-sh").join("archives")
+    }
+
+    pub async fn policy(&self) -> DiskPolicy {
+        self.state.read().await.policy.clone()
+    }
+
+    pub async fn set_policy(&self, policy: DiskPolicy) -> Result<(), std::io::Error> {
+        self.state.write().await.policy = policy;
+        self.save().await
+    }
+
+    pub async fn pinned(&self) -> Vec<Uuid> {
+        self.state.read().await.pinned.iter().copied().collect()
+    }
+
+    /// Pin or unpin a mission. Returns whether the pin set changed.
+    pub async fn set_pinned(&self, mission_id: Uuid, pinned: bool) -> Result<bool, std::io::Error> {
+        let changed = {
+            let mut state = self.state.write().await;
+            if pinned {
+                state.pinned.insert(mission_id)
+            } else {
+                state.pinned.remove(&mission_id)
+            }
+        };
+        if changed {
+            self.save().await?;
+        }
+        Ok(changed)
+    }
+
+    /// Usage samples, oldest first.
+    pub async fn history(&self) -> Vec<UsageSample> {
+        self.state.read().await.history.clone()
+    }
+
+    /// Measure disk usage without recording it.
+    pub async fn scan(&self, workspaces: &[Workspace], missions: &[Mission]) -> DiskUsageReport {
+        let (policy, pinned) = {
+            let state = self.state.read().await;
+            (state.policy.clone(), state.pinned.clone())
+        };
+        let workspaces = workspaces.to_vec();
+        let missions = missions.to_vec();
+        let working_dir = self.working_dir.clone();
+        let archives_dir = self.archives_dir();
+
+        tokio::task::spawn_blocking(move || {
+            build_report(
+                &working_dir,
+                &archives_dir,
+                &workspaces,
+                &missions,
+                &policy,
+                &pinned,
+            )
+        })
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Disk usage scan panicked: {}", e);
+            DiskUsageReport {
+                generated_at: Utc::now(),
+                total_bytes: 0,
+                cache_bytes: 0,
+                archive_bytes: 0,
+                workspaces: Vec::new(),
+            }
+        })
+    }
+
+    /// Record `report` as a trend sample (scheduled scans only, so samples
+    /// stay hourly).
+    pub async fn record_sample(&self, report: &DiskUsageReport) {
+        {
+            let mut state = self.state.write().await;
+            state.history.push(UsageSample {
+                timestamp: report.generated_at,
+                total_bytes: report.total_bytes,
+                workspaces: report
+                    .workspaces
+                    .iter()
+                    .map(|w| (w.workspace_id, w.total_bytes))
+                    .collect(),
+            });
+            let excess = state.history.len().saturating_sub(MAX_USAGE_SAMPLES);
+            state.history.drain(..excess);
+        }
+        if let Err(e) = self.save().await {
+            tracing::warn!("Failed to persist disk usage history: {}", e);
+        }
+    }
+
+    /// Collect mission directories according to the GC policy.
+    ///
+    /// With `dry_run`, only reports what would be collected.
+    pub async fn gc(
+        &self,
+        workspaces: &[Workspace],
+        missions: &[Mission],
+        store: &dyn MissionStore,
+        dry_run: bool,
+    ) -> GcReport {
+        let report = self.scan(workspaces, missions).await;
+        self.gc_with_report(&report, missions, store, dry_run).await
+    }
+
+    /// Collect mission directories according to the GC policy, based on a
+    /// usage report that was just measured.
+    pub async fn gc_with_report(
+        &self,
+        report: &DiskUsageReport,
+        missions: &[Mission],
+        store: &dyn MissionStore,
+        dry_run: bool,
+    ) -> GcReport {
+        let policy = self.policy().await;
+        let now = Utc::now();
+        let mut candidates = select_candidates(report, missions, &policy.gc, now);
+
+        if !dry_run {
+            let mut collected = Vec::with_capacity(candidates.len());
+            for mut candidate in candidates {
+                // The scan may be stale: the mission could have been resumed
+                // or pinned since.
+                let current = match store.get_mission(candidate.mission_id).await {
+                    Ok(mission) => mission,
+                    Err(e) => {
+                        candidate.error = Some(format!("Failed to reload mission: {}", e));
+                        collected.push(candidate);
+                        continue;
+                    }
+                };
+                let pinned = self
+                    .state
+                    .read()
+                    .await
+                    .pinned
+                    .contains(&candidate.mission_id);
+                if pinned
+                    || !current
+                        .is_some_and(|m| still_collectable(&m, candidate.reason, &policy.gc, now))
+                {
+                    tracing::info!(
+                        mission_id = %candidate.mission_id,
+                        "Mission changed since the disk scan, not collecting it"
+                    );
+                    continue;
+                }
+
+                let action = policy.gc.action;
+                let archives_dir = self.archives_dir();
+                let result = tokio::task::spawn_blocking(move || {
+                    let result = collect(&candidate, action, &archives_dir);
+                    (candidate, result)
+                })
+                .await;
+                let Ok((mut candidate, result)) = result else {
+                    continue;
+                };
+                match result {
+                    Ok(archive_path) => candidate.archive_path = archive_path,
+                    Err(e) => {
+                        tracing::warn!(
+                            mission_id = %candidate.mission_id,
+                            path = %candidate.path.display(),
+                            error = %e,
+                            "Failed to collect mission directory"
+                        );
+                        candidate.error = Some(e.to_string());
+                    }
+                }
+                collected.push(candidate);
+            }
+            candidates = collected;
+        }
+
+        let freed_bytes = candidates
+            .iter()
+            .filter(|c| c.error.is_none())
+            .map(|c| c.bytes)
+            .sum();
+
+        GcReport {
+            dry_run,
+            action: policy.gc.action,
+            candidates,
+            freed_bytes,
+        }
+    }
+}
+
+/// Total size of regular files below `path` (symlinks are not followed).
+pub fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+fn index_dir(root: &Path) -> PathBuf {
+    root.join(".sandboxed_sh").join("index")
+}
+
+fn build_report(
+    working_dir: &Path,
+    archives_dir: &Path,
+    workspaces: &[Workspace],
+    missions: &[Mission],
+    policy: &DiskPolicy,
+    pinned: &BTreeSet<Uuid>,
+) -> DiskUsageReport {
+    // Mission directories are named after the first 8 characters of the id,
+    // which several missions of a workspace may share.
+    let mut missions_by_short_id: HashMap<(Uuid, String), Vec<&Mission>> = HashMap::new();
+    for m in missions {
+        missions_by_short_id
+            .entry((m.workspace_id, m.id.to_string()[..8].to_string()))
+            .or_default()
+            .push(m);
+    }
+
+    let mut usages = Vec::with_capacity(workspaces.len());
+    for ws in workspaces {
+        let work_root = workspace::workspaces_root_for(&ws.path);
+        let mut mission_usages = Vec::new();
+        let mut work_dirs_bytes = 0;
+        let mut index_bytes = dir_size(&index_dir(&ws.path));
+
+        if let Ok(entries) = std::fs::read_dir(&work_root) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if !path.is_dir() {
+                    continue;
+                }
+                let dir_name = entry.file_name().to_string_lossy().to_string();
+                let bytes = dir_size(&path);
+                work_dirs_bytes += bytes;
+                index_bytes += dir_size(&index_dir(&path));
+
+                let Some(short_id) = dir_name.strip_prefix("mission-") else {
+                    continue;
+                };
+                // An ambiguous directory is reported without a mission, so
+                // it is never collected on behalf of the wrong one.
+                let mission = match missions_by_short_id
+                    .get(&(ws.id, short_id.to_string()))
+                    .map(Vec::as_slice)
+                {
+                    Some([mission]) => Some(*mission),
+                    Some([]) | None => None,
+                    Some(matches) => {
+                        tracing::warn!(
+                            path = %path.display(),
+                            missions = matches.len(),
+                            "Mission directory matches several missions"
+                        );
+                        None
+                    }
+                };
+                mission_usages.push(MissionDiskUsage {
+                    mission_id: mission.map(|m| m.id),
+                    dir_name,
+                    path,
+                    bytes,
+                    status: mission.map(|m| m.status),
+                    updated_at: mission.map(|m| m.updated_at.clone()),
+                    pinned: mission.is_some_and(|m| pinned.contains(&m.id)),
+                });
+            }
+        }
+        mission_usages.sort_by_key(|m| std::cmp::Reverse(m.bytes));
+
+        let total_bytes = match ws.workspace_type {
+            WorkspaceType::Container => dir_size(&ws.path),
+            WorkspaceType::Host => work_dirs_bytes + dir_size(&index_dir(&ws.path)),
+        };
+        let quota_bytes = policy.quota_for(ws.id);
+
+        usages.push(WorkspaceDiskUsage {
+            workspace_id: ws.id,
+            name: ws.name.clone(),
+            workspace_type: ws.workspace_type,
+            path: ws.path.clone(),
+            total_bytes,
+            work_dirs_bytes,
+            index_bytes,
+            quota_bytes,
+            over_quota: quota_bytes.is_some_and(|q| total_bytes > q),
+            missions: mission_usages,
+        });
+    }
+
+    let cache_bytes = dir_size(&working_dir.join(".sandboxed-sh").join("cache"));
+    let archive_bytes = dir_size(archives_dir);
+    let total_bytes =
+        usages.iter().map(|w| w.total_bytes).sum::<u64>() + cache_bytes + archive_bytes;
+
+    DiskUsageReport {
+        generated_at: Utc::now(),
+        total_bytes,
+        cache_bytes,
+        archive_bytes,
+        workspaces: usages,
+    }
+}
+
+/// Updated time of a mission whose status is collected by `gc`.
+fn collectable_since(mission: &Mission, gc: &GcPolicy) -> Option<DateTime<Utc>> {
+    if !gc.statuses.contains(&mission.status) {
+        return None;
+    }
+    DateTime::parse_from_rfc3339(&mission.updated_at)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Whether `mission`, freshly loaded, still qualifies for collection for
+/// `reason`.
+fn still_collectable(
+    mission: &Mission,
+    reason: GcReason,
+    gc: &GcPolicy,
+    now: DateTime<Utc>,
+) -> bool {
+    let Some(updated_at) = collectable_since(mission, gc) else {
+        return false;
+    };
+    match reason {
+        GcReason::Expired => updated_at < now - ChronoDuration::days(gc.retain_days as i64),
+        GcReason::OverQuota => true,
+    }
+}
+
+fn select_candidates(
+    report: &DiskUsageReport,
+    missions: &[Mission],
+    gc: &GcPolicy,
+    now: DateTime<Utc>,
+) -> Vec<GcCandidate> {
+    let missions: HashMap<Uuid, &Mission> = missions.iter().map(|m| (m.id, m)).collect();
+    let cutoff = now - ChronoDuration::days(gc.retain_days as i64);
+    let mut candidates = Vec::new();
+
+    for ws in &report.workspaces {
+        // Eligible directories, oldest first.
+        let mut eligible: Vec<(DateTime<Utc>, &MissionDiskUsage, &Mission)> = ws
+            .missions
+            .iter()
+            .filter(|usage| !usage.pinned)
+            .filter_map(|usage| {
+                let mission = missions.get(&usage.mission_id?)?;
+                let updated_at = collectable_since(mission, gc)?;
+                Some((updated_at, usage, *mission))
+            })
+            .collect();
+        eligible.sort_by_key(|(updated_at, _, _)| *updated_at);
+
+        let mut remaining = ws.total_bytes;
+        for (updated_at, usage, mission) in eligible {
+            let reason = if updated_at < cutoff {
+                GcReason::Expired
+            } else if gc.collect_over_quota && ws.quota_bytes.is_some_and(|q| remaining > q) {
+                GcReason::OverQuota
+            } else {
+                continue;
+            };
+            remaining = remaining.saturating_sub(usage.bytes);
+            candidates.push(GcCandidate {
+                mission_id: mission.id,
+                workspace_id: ws.workspace_id,
+                path: usage.path.clone(),
+                bytes: usage.bytes,
+                status: mission.status,
+                updated_at: mission.updated_at.clone(),
+                reason,
+                archive_path: None,
+                error: None,
+            });
+        }
+    }
+
+    candidates
+}
+
+/// Archive (if requested) and remove a mission directory.
+fn collect(
+    candidate: &GcCandidate,
+    action: GcAction,
+    archives_dir: &Path,
+) -> anyhow::Result<Option<PathBuf>> {
+    let archive_path = match action {
+        GcAction::Archive => {
+            std::fs::create_dir_all(archives_dir)?;
+            let archive_path = archives_dir.join(format!("mission-{}.zip", candidate.mission_id));
+            archive_dir(&candidate.path, &archive_path)?;
+            Some(archive_path)
+        }
+        GcAction::Delete => None,
+    };
+    std::fs::remove_dir_all(&candidate.path)?;
+    Ok(archive_path)
+}
+
+fn archive_dir(dir: &Path, archive_path: &Path) -> anyhow::Result<()> {
+    use zip::write::SimpleFileOptions;
+
+    let tmp_path = archive_path.with_extension("zip.partial");
+    let file = std::fs::File::create(&tmp_path)?;
+    let mut writer = zip::ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    for entry in walkdir::WalkDir::new(dir).follow_links(false) {
+        let entry = entry?;
+        let rel = entry.path().strip_prefix(dir)?;
+        if rel.as_os_str().is_empty() {
+            continue;
+        }
+        let name = rel.to_string_lossy().replace('\\', "/");
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            writer.add_directory(name, options)?;
+        } else if file_type.is_symlink() {
+            let target = std::fs::read_link(entry.path())?;
+            writer.add_symlink(name, target.to_string_lossy(), options)?;
+        } else if file_type.is_file() {
+            writer.start_file(name, options)?;
+            let mut src = std::fs::File::open(entry.path())?;
+            std::io::copy(&mut src, &mut writer)?;
+        }
+    }
+    writer.finish()?.flush()?;
+    std::fs::rename(&tmp_path, archive_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mission_store::InMemoryMissionStore;
+
+    fn mission(id: Uuid, workspace_id: Uuid, status: MissionStatus, days_old: i64) -> Mission {
+        let updated_at = (Utc::now() - ChronoDuration::days(days_old)).to_rfc3339();
+        Mission {
+            id,
+            status,
+            title: None,
+            workspace_id,
+            workspace_name: None,
+            agent: None,
+            model_override: None,
+            backend: "opencode".to_string(),
+            config_profile: None,
+            history: Vec::new(),
+            created_at: updated_at.clone(),
+            updated_at,
+            interrupted_at: None,
+            resumable: false,
+            desktop_sessions: Vec::new(),
+            session_id: None,
+            terminal_reason: None,
+            mcp_policy: None,
+        }
+    }
+
+    /// A mission in `store` with `status`, as a scan `days_old` would see it.
+    async fn stored_mission(
+        store: &InMemoryMissionStore,
+        workspace_id: Uuid,
+        status: MissionStatus,
+        days_old: i64,
+    ) -> Mission {
+        let created = store
+            .create_mission(None, Some(workspace_id), None, None, None, None)
+            .await
+            .unwrap();
+        store
+            .update_mission_status(created.id, status)
+            .await
+            .unwrap();
+        mission(created.id, workspace_id, status, days_old)
+    }
+
+    fn write_mission_dir(root: &Path, id: Uuid, bytes: usize) -> PathBuf {
+        let dir = workspace::mission_workspace_dir_for_root(root, id);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/data.bin"), vec![0u8; bytes]).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn gc_skips_recent_active_and_pinned_missions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws = Workspace::default_host(tmp.path().to_path_buf());
+
+        let old = mission(Uuid::new_v4(), ws.id, MissionStatus::Completed, 30);
+        let recent = mission(Uuid::new_v4(), ws.id, MissionStatus::Failed, 1);
+        let active = mission(Uuid::new_v4(), ws.id, MissionStatus::Active, 30);
+        let pinned = mission(Uuid::new_v4(), ws.id, MissionStatus::Completed, 30);
+        for m in [&old, &recent, &active, &pinned] {
+            write_mission_dir(&ws.path, m.id, 100);
+        }
+        let missions = vec![old.clone(), recent, active, pinned.clone()];
+
+        let manager = DiskManager::new(tmp.path()).await;
+        manager.set_pinned(pinned.id, true).await.unwrap();
+
+        let store = InMemoryMissionStore::new();
+        let report = manager
+            .gc(std::slice::from_ref(&ws), &missions, &store, true)
+            .await;
+        assert!(report.dry_run);
+        assert_eq!(report.candidates.len(), 1);
+        assert_eq!(report.candidates[0].mission_id, old.id);
+        assert_eq!(report.candidates[0].reason, GcReason::Expired);
+        assert_eq!(report.freed_bytes, 100);
+        // Dry run leaves the directory in place
+        assert!(report.candidates[0].path.exists());
+        // Only scheduled scans record trend samples
+        assert!(manager.history().await.is_empty());
+        let usage = manager.scan(std::slice::from_ref(&ws), &missions).await;
+        assert!(manager.history().await.is_empty());
+        manager.record_sample(&usage).await;
+        assert_eq!(manager.history().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn gc_archives_and_collects_over_quota() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws = Workspace::default_host(tmp.path().to_path_buf());
+
+        let store = InMemoryMissionStore::new();
+        let older = stored_mission(&store, ws.id, MissionStatus::Completed, 3).await;
+        let newer = stored_mission(&store, ws.id, MissionStatus::Completed, 2).await;
+        let older_dir = write_mission_dir(&ws.path, older.id, 600);
+        let newer_dir = write_mission_dir(&ws.path, newer.id, 600);
+        let missions = vec![older.clone(), newer];
+
+        let manager = DiskManager::new(tmp.path()).await;
+        let mut policy = DiskPolicy::default();
+        policy.workspace_quotas.insert(ws.id, 1000);
+        manager.set_policy(policy).await.unwrap();
+
+        let report = manager
+            .gc(std::slice::from_ref(&ws), &missions, &store, false)
+            .await;
+        assert_eq!(report.candidates.len(), 1);
+        let candidate = &report.candidates[0];
+        assert_eq!(candidate.mission_id, older.id);
+        assert_eq!(candidate.reason, GcReason::OverQuota);
+        assert!(candidate.error.is_none());
+        assert!(!older_dir.exists());
+        assert!(newer_dir.exists());
+
+        let archive = candidate.archive_path.as_ref().unwrap();
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(archive).unwrap()).unwrap();
+        assert_eq!(zip.by_name("src/data.bin").unwrap().size(), 600);
+    }
+
+    #[tokio::test]
+    async fn gc_reloads_missions_before_collecting() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws = Workspace::default_host(tmp.path().to_path_buf());
+        let store = InMemoryMissionStore::new();
+        let resumed = stored_mission(&store, ws.id, MissionStatus::Completed, 30).await;
+        let pinned = stored_mission(&store, ws.id, MissionStatus::Completed, 30).await;
+        let resumed_dir = write_mission_dir(&ws.path, resumed.id, 100);
+        let pinned_dir = write_mission_dir(&ws.path, pinned.id, 100);
+        let missions = vec![resumed.clone(), pinned.clone()];
+
+        let manager = DiskManager::new(tmp.path()).await;
+        let report = manager.scan(std::slice::from_ref(&ws), &missions).await;
+        // Both change after the scan
+        store
+            .update_mission_status(resumed.id, MissionStatus::Active)
+            .await
+            .unwrap();
+        manager.set_pinned(pinned.id, true).await.unwrap();
+
+        let report = manager
+            .gc_with_report(&report, &missions, &store, false)
+            .await;
+        assert!(report.candidates.is_empty());
+        assert!(resumed_dir.exists());
+        assert!(pinned_dir.exists());
+    }
+
+    #[test]
+    fn ambiguous_mission_dirs_are_not_attributed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let ws = Workspace::default_host(tmp.path().to_path_buf());
+        let id = Uuid::new_v4();
+        let mut twin_bytes = *id.as_bytes();
+        twin_bytes[15] ^= 1;
+        let twin = Uuid::from_bytes(twin_bytes);
+        let unique = Uuid::new_v4();
+        for m in [id, unique] {
+            write_mission_dir(&ws.path, m, 10);
+        }
+        let missions: Vec<Mission> = [id, twin, unique]
+            .into_iter()
+            .map(|m| mission(m, ws.id, MissionStatus::Completed, 30))
+            .collect();
+
+        let report = build_report(
+            tmp.path(),
+            &tmp.path().join("archives"),
+            std::slice::from_ref(&ws),
+            &missions,
+            &DiskPolicy::default(),
+            &BTreeSet::new(),
+        );
+        let attributed: Vec<Option<Uuid>> = report.workspaces[0]
+            .missions
+            .iter()
+            .map(|m| m.mission_id)
+            .collect();
+        assert_eq!(attributed.len(), 2);
+        assert!(attributed.contains(&None));
+        assert!(attributed.contains(&Some(unique)));
+    }
+}
@@ -0,0 +1,43 @@
+//! Wildcard matching shared by the library secret scan, MCP tool policies and
+//! the file search tools.
+
+/// Match `pattern` against `text`, where `*` matches any run of characters
+/// (including `/`).
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if text.len() < first.len() + last.len() || !text.starts_with(first) || !text.ends_with(last) {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("get_*", "get_issue"));
+        assert!(glob_match("*_issue", "get_issue"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(glob_match("/workspace/*", "/workspace/a/b"));
+        assert!(!glob_match("/workspace/*", "/etc/passwd"));
+        assert!(!glob_match("a*a", "a"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*b*c", "axxcyyb"));
+        assert!(glob_match("exact", "exact"));
+        assert!(!glob_match("exact", "exactly"));
+    }
+}
@@ -0,0 +1,347 @@
+//! Rendered harness configuration files and drift detection.
+//!
+//! The OpenCode / Claude Code / Amp config writers in [`crate::workspace`]
+//! render into a [`HarnessConfigFiles`] set before anything touches disk, so
+//! the same set can be previewed through the API or compared against what is
+//! currently in a workspace directory:
+//! - `modified`: the file was edited by hand (or generated from older inputs)
+//! - `missing`: the file was never written or has been deleted
+//! - `stale`: a file inside a directory the harness owns (e.g. `.claude/skills`)
+//!   that the current library would no longer write
+//!
+//! Previews and drift checks render with [`HarnessSecrets::Placeholders`]:
+//! secret references stay as written and OAuth / gateway tokens become
+//! placeholders, so no secret is read or returned. When checking drift, each
+//! placeholder matches whatever value a launched mission got for it.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::library::harness_import::list_files;
+
+/// How secrets end up in rendered harness config files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HarnessSecrets {
+    /// Resolve secret references and issue tokens, for a launching mission.
+    Resolve,
+    /// Keep secret references and use token placeholders, for previews.
+    Placeholders,
+}
+
+/// Placeholder for the OAuth access token of the MCP `name`.
+pub fn oauth_placeholder(name: &str) -> String {
+    format!("${{oauth:{}}}", name)
+}
+
+/// Placeholder for the gateway token of a mission.
+pub const GATEWAY_TOKEN_PLACEHOLDER: &str = "${gateway:token}";
+
+/// Secret references and token placeholders in rendered content.
+fn placeholder_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\$\{(?:secret|oauth|gateway):[^}]+\}").expect("Invalid regex"))
+}
+
+/// Whether `actual` is `expected` with each placeholder replaced by some value.
+fn matches_rendered(expected: &str, actual: &str) -> bool {
+    if !placeholder_regex().is_match(expected) {
+        return expected == actual;
+    }
+    let literals: Vec<&str> = placeholder_regex().split(expected).collect();
+    let (first, rest) = literals.split_first().expect("split yields a literal");
+    let (last, middle) = rest
+        .split_last()
+        .expect("placeholder has a following literal");
+    let Some(mut remaining) = actual.strip_prefix(first) else {
+        return false;
+    };
+    for literal in middle {
+        match remaining.find(literal) {
+            Some(index) => remaining = &remaining[index + literal.len()..],
+            None => return false,
+        }
+    }
+    remaining.ends_with(last)
+}
+
+/// Harness config files rendered for a workspace directory.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HarnessConfigFiles {
+    /// File contents keyed by path relative to the workspace directory.
+    pub files: BTreeMap<String, String>,
+    /// Directories replaced wholesale on write; entries not in `files` are removed.
+    pub managed_dirs: Vec<String>,
+}
+
+impl HarnessConfigFiles {
+    /// Add (or replace) a file.
+    pub fn insert(&mut self, path: impl Into<String>, content: impl Into<String>) {
+        self.files.insert(path.into(), content.into());
+    }
+
+    /// Mark a directory as owned by the harness config.
+    pub fn manage_dir(&mut self, dir: impl Into<String>) {
+        let dir = dir.into();
+        if !self.managed_dirs.contains(&dir) {
+            self.managed_dirs.push(dir);
+        }
+    }
+
+    /// Write the files to `workspace_dir`.
+    ///
+    /// A running harness may read the directory at any time, so nothing is
+    /// deleted first: each managed directory is built next to its final
+    /// location and renamed into place, and every other file is written to a
+    /// temporary file and renamed over the old one.
+    pub async fn write_to(&self, workspace_dir: &Path) -> anyhow::Result<()> {
+        for dir in &self.managed_dirs {
+            let prefix = format!("{}/", dir);
+            let files = self
+                .files
+                .iter()
+                .filter_map(|(path, content)| Some((path.strip_prefix(&prefix)?, content)));
+            replace_dir(&workspace_dir.join(dir), files).await?;
+        }
+
+        for (relative_path, content) in &self.files {
+            if self.managed_dir_of(relative_path).is_some() {
+                continue;
+            }
+            let path = workspace_dir.join(relative_path);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let tmp = sibling_path(&path, "tmp");
+            tokio::fs::write(&tmp, content).await?;
+            if let Err(e) = tokio::fs::rename(&tmp, &path).await {
+                let _ = tokio::fs::remove_file(&tmp).await;
+                return Err(e.into());
+            }
+        }
+
+        tracing::debug!(
+            workspace = %workspace_dir.display(),
+            files = self.files.len(),
+            "Wrote harness config files"
+        );
+
+        Ok(())
+    }
+
+    /// The managed directory `relative_path` is written into, if any.
+    fn managed_dir_of(&self, relative_path: &str) -> Option<&str> {
+        self.managed_dirs
+            .iter()
+            .map(String::as_str)
+            .find(|dir| relative_path.starts_with(&format!("{}/", dir)))
+    }
+
+    /// Pack the files into a ZIP archive.
+    pub fn to_zip(&self) -> anyhow::Result<Vec<u8>> {
+        use std::io::Write;
+
+        let mut buf = Vec::new();
+        {
+            let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut buf));
+            let options = zip::write::SimpleFileOptions::default();
+            for (relative_path, content) in &self.files {
+                zip.start_file(relative_path.as_str(), options)?;
+                zip.write_all(content.as_bytes())?;
+            }
+            zip.finish()?;
+        }
+        Ok(buf)
+    }
+
+    /// Compare the rendered files with the contents of `workspace_dir`.
+    pub async fn drift(&self, workspace_dir: &Path) -> HarnessDriftReport {
+        let mut files = Vec::new();
+
+        for (relative_path, expected) in &self.files {
+            let status = match tokio::fs::read_to_string(workspace_dir.join(relative_path)).await {
+                Ok(actual) if matches_rendered(expected, &actual) => HarnessDriftStatus::InSync,
+                Ok(_) => HarnessDriftStatus::Modified,
+                Err(_) => HarnessDriftStatus::Missing,
+            };
+            files.push(HarnessFileDrift {
+                path: relative_path.clone(),
+                status,
+            });
+        }
+
+        for dir in &self.managed_dirs {
+            for file in list_files(workspace_dir, dir).await {
+                let relative_path = format!("{}/{}", dir, file);
+                if !self.files.contains_key(&relative_path) {
+                    files.push(HarnessFileDrift {
+                        path: relative_path,
+                        status: HarnessDriftStatus::Stale,
+                    });
+                }
+            }
+        }
+
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        HarnessDriftReport {
+            in_sync: files.iter().all(|f| f.status == HarnessDriftStatus::InSync),
+            files,
+        }
+    }
+}
+
+/// Drift state of a single harness config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HarnessDriftStatus {
+    InSync,
+    Modified,
+    Missing,
+    Stale,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HarnessFileDrift {
+    pub path: String,
+    pub status: HarnessDriftStatus,
+}
+
+/// Result of comparing rendered harness config with a workspace directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct HarnessDriftReport {
+    /// True when every rendered file matches and no stale files exist.
+    pub in_sync: bool,
+    pub files: Vec<HarnessFileDrift>,
+}
+
+/// `path` with a unique `.<kind>-<uuid>` suffix, in the same directory so
+/// that renaming it to `path` stays on one filesystem.
+fn sibling_path(path: &Path, kind: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}-{}", kind, uuid::Uuid::new_v4()));
+    path.with_file_name(name)
+}
+
+/// Replace the directory `target` with one holding exactly `files` (paths
+/// relative to `target`), without it ever being partially written.
+async fn replace_dir<'a>(
+    target: &Path,
+    files: impl Iterator<Item = (&'a str, &'a String)>,
+) -> anyhow::Result<()> {
+    let staging = sibling_path(target, "tmp");
+    let staged = async {
+        tokio::fs::create_dir_all(&staging).await?;
+        for (relative_path, content) in files {
+            let path = staging.join(relative_path);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, content).await?;
+        }
+        anyhow::Ok(())
+    };
+    if let Err(e) = staged.await {
+        let _ = tokio::fs::remove_dir_all(&staging).await;
+        return Err(e);
+    }
+
+    // A non-empty directory cannot be renamed over, so move the old one
+    // aside first; it is only missing between the two renames.
+    let old = sibling_path(target, "old");
+    let had_old = match tokio::fs::rename(target, &old).await {
+        Ok(()) => true,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(e) => {
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+            return Err(e.into());
+        }
+    };
+    if let Err(e) = tokio::fs::rename(&staging, target).await {
+        if had_old {
+            let _ = tokio::fs::rename(&old, target).await;
+        }
+        let _ = tokio::fs::remove_dir_all(&staging).await;
+        return Err(e.into());
+    }
+    if had_old {
+        let _ = tokio::fs::remove_dir_all(&old).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_drift_against_workspace() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut rendered = HarnessConfigFiles::default();
+        rendered.insert("opencode.json", "{}");
+        rendered.insert(".claude/settings.local.json", "{\"a\":1}");
+        rendered.manage_dir(".claude/skills");
+        rendered.insert(".claude/skills/lint/SKILL.md", "lint");
+
+        rendered.write_to(tmp.path()).await.unwrap();
+        let report = rendered.drift(tmp.path()).await;
+        assert!(report.in_sync, "{:?}", report);
+
+        // Hand edit, deleted file and a skill left over from an older sync
+        std::fs::write(tmp.path().join("opencode.json"), "{\"edited\":true}").unwrap();
+        std::fs::remove_file(tmp.path().join(".claude/settings.local.json")).unwrap();
+        std::fs::create_dir_all(tmp.path().join(".claude/skills/old")).unwrap();
+        std::fs::write(tmp.path().join(".claude/skills/old/SKILL.md"), "old").unwrap();
+
+        let report = rendered.drift(tmp.path()).await;
+        assert!(!report.in_sync);
+        let statuses: Vec<(&str, HarnessDriftStatus)> = report
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), f.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (".claude/settings.local.json", HarnessDriftStatus::Missing),
+                (".claude/skills/lint/SKILL.md", HarnessDriftStatus::InSync),
+                (".claude/skills/old/SKILL.md", HarnessDriftStatus::Stale),
+                ("opencode.json", HarnessDriftStatus::Modified),
+            ]
+        );
+
+        // Writing again clears the managed directory
+        rendered.write_to(tmp.path()).await.unwrap();
+        assert!(!tmp.path().join(".claude/skills/old").exists());
+        assert!(rendered.drift(tmp.path()).await.in_sync);
+        // and leaves no staging directories or temporary files behind
+        let mut names: Vec<String> = std::fs::read_dir(tmp.path().join(".claude"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["settings.local.json", "skills"]);
+    }
+
+    #[test]
+    fn placeholders_match_resolved_values() {
+        let expected = format!(
+            "{{\"TOKEN\":\"${{secret:api-keys/github}}\",\"auth\":\"Bearer {}\"}}",
+            oauth_placeholder("linear")
+        );
+        assert!(matches_rendered(
+            &expected,
+            "{\"TOKEN\":\"ghp_123\",\"auth\":\"Bearer tok\"}"
+        ));
+        assert!(!matches_rendered(
+            &expected,
+            "{\"TOKEN\":\"ghp_123\",\"other\":\"Bearer tok\"}"
+        ));
+        assert!(!matches_rendered(&expected, "{}"));
+        // Without placeholders the content must match exactly
+        assert!(matches_rendered("{}", "{}"));
+        assert!(!matches_rendered("{}", "{ }"));
+    }
+}
@@ -0,0 +1,433 @@
+//! Export library items to agent harness layouts used outside sandboxed.sh.
+//!
+//! A selection of skills, commands and agents (plus the project rules from a
+//! config profile's `.claudecode/CLAUDE.md`) is rendered into a
+//! [`HarnessConfigFiles`] set for the target tool and downloaded as a ZIP
+//! archive. Claude Code, OpenCode and Amp reuse the workspace renderers;
+//! tools without a skills concept get skills inlined into their instruction
+//! file. Encrypted values are replaced with `[ENCRYPTED]`.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::harness_config::HarnessConfigFiles;
+use crate::library::env_crypto::redact_encrypted_tags;
+use crate::library::{parse_frontmatter, LibraryStore};
+use crate::workspace::{
+    ensure_skill_name_in_frontmatter, format_yaml_description, render_amp_skills,
+    render_claudecode_commands, render_claudecode_skills, AgentContent, CommandContent,
+    SkillContent,
+};
+
+/// Target layout of a library export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    /// `.claude/{skills,commands,agents}` and `CLAUDE.md`
+    Claude,
+    /// `.opencode/{skill,command,agent}` and `AGENTS.md`
+    Opencode,
+    /// `.agents/{skills,commands}` and `AGENTS.md`
+    Amp,
+    /// `.cursor/rules/*.mdc` and `.cursor/commands`
+    Cursor,
+    /// A single `AGENTS.md`
+    AgentsMd,
+    /// `AGENTS.md` and `.codex/prompts`
+    Codex,
+    /// `GEMINI.md` and `.gemini/commands/*.toml`
+    Gemini,
+    /// The library's own layout (`skill/`, `command/`, `agent/`)
+    Zip,
+}
+
+impl ExportFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Claude => "claude",
+            Self::Opencode => "opencode",
+            Self::Amp => "amp",
+            Self::Cursor => "cursor",
+            Self::AgentsMd => "agents-md",
+            Self::Codex => "codex",
+            Self::Gemini => "gemini",
+            Self::Zip => "zip",
+        }
+    }
+}
+
+/// Items to export. `None` exports every item of that kind.
+#[derive(Debug, Clone, Default)]
+pub struct ExportSelection {
+    pub skills: Option<Vec<String>>,
+    pub commands: Option<Vec<String>>,
+    pub agents: Option<Vec<String>>,
+    /// Config profile providing the rules (default: "default")
+    pub profile: Option<String>,
+}
+
+/// Library content loaded for an export.
+pub struct ExportItems {
+    pub skills: Vec<SkillContent>,
+    pub commands: Vec<CommandContent>,
+    pub agents: Vec<AgentContent>,
+    pub profile: String,
+    /// Project rules (`CLAUDE.md` of the profile)
+    pub rules: Option<String>,
+}
+
+/// Load the selected items, with encrypted values redacted.
+///
+/// Fails if an explicitly selected item does not exist.
+pub async fn collect_export_items(
+    library: &LibraryStore,
+    selection: &ExportSelection,
+) -> Result<ExportItems> {
+    let skill_names = match &selection.skills {
+        Some(names) => names.clone(),
+        None => library
+            .list_skills()
+            .await?
+            .into_iter()
+            .map(|s| s.name)
+            .collect(),
+    };
+    let mut skills = Vec::new();
+    for name in &skill_names {
+        let skill = library.get_skill(name).await?;
+        skills.push(SkillContent {
+            name: skill.name,
+            description: skill.description,
+            content: redact_encrypted_tags(&skill.content),
+            files: skill
+                .files
+                .into_iter()
+                .map(|f| (f.path, redact_encrypted_tags(&f.content)))
+                .collect(),
+        });
+    }
+
+    let command_names = match &selection.commands {
+        Some(names) => names.clone(),
+        None => library
+            .list_commands()
+            .await?
+            .into_iter()
+            .map(|c| c.name)
+            .collect(),
+    };
+    let mut commands = Vec::new();
+    for name in &command_names {
+        let command = library.get_command(name).await?;
+        commands.push(CommandContent {
+            name: command.name,
+            description: command.description,
+            content: redact_encrypted_tags(&command.content),
+        });
+    }
+
+    let agent_names = match &selection.agents {
+        Some(names) => names.clone(),
+        None => library
+            .list_library_agents()
+            .await?
+            .into_iter()
+            .map(|a| a.name)
+            .collect(),
+    };
+    let mut agents = Vec::new();
+    for name in &agent_names {
+        let agent = library.get_library_agent(name).await?;
+        agents.push(AgentContent {
+            name: agent.name,
+            content: redact_encrypted_tags(&agent.content),
+        });
+    }
+
+    let profile = selection
+        .profile
+        .clone()
+        .unwrap_or_else(|| "default".to_string());
+    let rules = library
+        .get_config_profile_file(&profile, ".claudecode/CLAUDE.md")
+        .await
+        .ok()
+        .map(|rules| redact_encrypted_tags(&rules));
+
+    Ok(ExportItems {
+        skills,
+        commands,
+        agents,
+        profile,
+        rules,
+    })
+}
+
+/// Markdown body without YAML frontmatter.
+fn body(content: &str) -> &str {
+    parse_frontmatter(content).1.trim()
+}
+
+/// Instruction document (`AGENTS.md`, `GEMINI.md`, ...) with rules and
+/// skills inlined for tools that have no skill discovery.
+fn instructions_document(items: &ExportItems, inline_skills: bool) -> Option<String> {
+    let mut doc = String::new();
+    if let Some(rules) = &items.rules {
+        doc.push_str(rules.trim_end());
+        doc.push_str("\n\n");
+    }
+    if inline_skills && !items.skills.is_empty() {
+        doc.push_str("## Skills\n\n");
+        for skill in &items.skills {
+            doc.push_str(&format!("### {}\n\n", skill.name));
+            if let Some(description) = &skill.description {
+                doc.push_str(&format!("{}\n\n", description));
+            }
+            doc.push_str(body(&skill.content));
+            doc.push_str("\n\n");
+        }
+    }
+    let doc = doc.trim_end();
+    (!doc.is_empty()).then(|| format!("{}\n", doc))
+}
+
+/// Quote a string as a TOML basic string.
+fn toml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Skill reference files below `dir`.
+fn insert_skill_files(files: &mut HarnessConfigFiles, dir: &str, skill: &SkillContent) {
+    for (relative_path, content) in &skill.files {
+        files.insert(format!("{}/{}", dir, relative_path), content.clone());
+    }
+}
+
+/// Render the items into the layout of `format`.
+pub fn render_export(format: ExportFormat, items: &ExportItems) -> HarnessConfigFiles {
+    let mut files = HarnessConfigFiles::default();
+    match format {
+        ExportFormat::Claude => {
+            if !items.skills.is_empty() {
+                render_claudecode_skills(&mut files, &items.skills);
+            }
+            render_claudecode_commands(&mut files, &items.commands);
+            for agent in &items.agents {
+                files.insert(
+                    format!(".claude/agents/{}.md", agent.name),
+                    agent.content.clone(),
+                );
+            }
+            if let Some(rules) = &items.rules {
+                files.insert("CLAUDE.md", rules.clone());
+            }
+        }
+        ExportFormat::Opencode => {
+            for skill in &items.skills {
+                let dir = format!(".opencode/skill/{}", skill.name);
+                files.insert(
+                    format!("{}/SKILL.md", dir),
+                    ensure_skill_name_in_frontmatter(&skill.content, &skill.name),
+                );
+                insert_skill_files(&mut files, &dir, skill);
+            }
+            for command in &items.commands {
+                files.insert(
+                    format!(".opencode/command/{}.md", command.name),
+                    command.content.clone(),
+                );
+            }
+            for agent in &items.agents {
+                files.insert(
+                    format!(".opencode/agent/{}.md", agent.name),
+                    agent.content.clone(),
+                );
+            }
+            if let Some(doc) = instructions_document(items, false) {
+                files.insert("AGENTS.md", doc);
+            }
+        }
+        ExportFormat::Amp => {
+            render_amp_skills(&mut files, &items.skills);
+            for command in &items.commands {
+                files.insert(
+                    format!(".agents/commands/{}.md", command.name),
+                    command.content.clone(),
+                );
+            }
+            if let Some(doc) = instructions_document(items, false) {
+                files.insert("AGENTS.md", doc);
+            }
+        }
+        ExportFormat::Cursor => {
+            for skill in &items.skills {
+                let description = skill
+                    .description
+                    .as_deref()
+                    .map(format_yaml_description)
+                    .unwrap_or_default();
+                files.insert(
+                    format!(".cursor/rules/{}.mdc", skill.name),
+                    format!(
+                        "---\ndescription: {}\nalwaysApply: false\n---\n\n{}\n",
+                        description,
+                        body(&skill.content)
+                    ),
+                );
+                insert_skill_files(&mut files, &format!(".cursor/rules/{}", skill.name), skill);
+            }
+            for command in &items.commands {
+                files.insert(
+                    format!(".cursor/commands/{}.md", command.name),
+                    format!("{}\n", body(&command.content)),
+                );
+            }
+            if let Some(rules) = &items.rules {
+                files.insert(
+                    ".cursor/rules/project.mdc",
+                    format!("---\nalwaysApply: true\n---\n\n{}", rules),
+                );
+            }
+        }
+        ExportFormat::AgentsMd => {
+            if let Some(doc) = instructions_document(items, true) {
+                files.insert("AGENTS.md", doc);
+            }
+        }
+        ExportFormat::Codex => {
+            if let Some(doc) = instructions_document(items, true) {
+                files.insert("AGENTS.md", doc);
+            }
+            for command in &items.commands {
+                files.insert(
+                    format!(".codex/prompts/{}.md", command.name),
+                    command.content.clone(),
+                );
+            }
+        }
+        ExportFormat::Gemini => {
+            if let Some(doc) = instructions_document(items, true) {
+                files.insert("GEMINI.md", doc);
+            }
+            for command in &items.commands {
+                let mut toml = String::new();
+                if let Some(description) = &command.description {
+                    toml.push_str(&format!("description = {}\n", toml_string(description)));
+                }
+                toml.push_str(&format!(
+                    "prompt = {}\n",
+                    toml_string(body(&command.content))
+                ));
+                files.insert(format!(".gemini/commands/{}.toml", command.name), toml);
+            }
+        }
+        ExportFormat::Zip => {
+            for skill in &items.skills {
+                let dir = format!("skill/{}", skill.name);
+                files.insert(format!("{}/SKILL.md", dir), skill.content.clone());
+                insert_skill_files(&mut files, &dir, skill);
+            }
+            for command in &items.commands {
+                files.insert(
+                    format!("command/{}.md", command.name),
+                    command.content.clone(),
+                );
+            }
+            for agent in &items.agents {
+                files.insert(format!("agent/{}.md", agent.name), agent.content.clone());
+            }
+            if let Some(rules) = &items.rules {
+                files.insert(
+                    format!("configs/{}/.claudecode/CLAUDE.md", items.profile),
+                    rules.clone(),
+                );
+            }
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> ExportItems {
+        ExportItems {
+            skills: vec![SkillContent {
+                name: "lint".to_string(),
+                description: Some("Lint: fix style".to_string()),
+                content: "---\ndescription: \"Lint: fix style\"\n---\nRun [ENCRYPTED] lint.\n"
+                    .to_string(),
+                files: vec![("refs/rules.md".to_string(), "rules".to_string())],
+            }],
+            commands: vec![CommandContent {
+                name: "deploy".to_string(),
+                description: Some("Deploy \"prod\"".to_string()),
+                content: "---\ndescription: Deploy \"prod\"\n---\nShip it\n".to_string(),
+            }],
+            agents: vec![AgentContent {
+                name: "reviewer".to_string(),
+                content: "---\ndescription: Review\n---\nReview.\n".to_string(),
+            }],
+            profile: "default".to_string(),
+            rules: Some("# Project rules\n".to_string()),
+        }
+    }
+
+    fn paths(files: &HarnessConfigFiles) -> Vec<&str> {
+        files.files.keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn renders_tool_layouts() {
+        let items = items();
+
+        let claude = render_export(ExportFormat::Claude, &items);
+        assert_eq!(
+            paths(&claude),
+            vec![
+                ".claude/agents/reviewer.md",
+                ".claude/commands/deploy.md",
+                ".claude/skills/lint/SKILL.md",
+                ".claude/skills/lint/refs/rules.md",
+                "CLAUDE.md",
+            ]
+        );
+
+        let cursor = render_export(ExportFormat::Cursor, &items);
+        assert_eq!(
+            cursor.files[".cursor/rules/lint.mdc"],
+            "---\ndescription: \"Lint: fix style\"\nalwaysApply: false\n---\n\nRun [ENCRYPTED] lint.\n"
+        );
+        assert!(cursor.files[".cursor/rules/project.mdc"].contains("alwaysApply: true"));
+        assert_eq!(cursor.files[".cursor/commands/deploy.md"], "Ship it\n");
+
+        let gemini = render_export(ExportFormat::Gemini, &items);
+        assert_eq!(
+            gemini.files[".gemini/commands/deploy.toml"],
+            "description = \"Deploy \\\"prod\\\"\"\nprompt = \"Ship it\"\n"
+        );
+        let doc = &gemini.files["GEMINI.md"];
+        assert!(doc.starts_with("# Project rules\n\n## Skills\n\n### lint\n"));
+
+        let zip = render_export(ExportFormat::Zip, &items);
+        assert!(zip
+            .files
+            .contains_key("configs/default/.claudecode/CLAUDE.md"));
+        assert!(!zip.to_zip().unwrap().is_empty());
+    }
+}
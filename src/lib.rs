@@ -40,6 +40,10 @@ pub mod backend;
 pub mod backend_config;
 pub mod config;
 pub mod cost;
+pub mod disk_usage;
+pub mod glob;
+pub mod harness_config;
+pub mod harness_export;
 pub mod library;
 pub mod mcp;
 pub mod nspawn;
@@ -52,6 +56,7 @@ pub mod task;
 pub mod tools;
 pub mod workspace;
 pub mod workspace_exec;
+pub mod workspace_services;
 
 pub use ai_providers::{AIProvider, AIProviderStore, ProviderType};
 pub use config::Config;
@@ -0,0 +1,240 @@
+//! Which library items a write, pull or merge changed.
+//!
+//! Changes are reported per item so that only the workspaces referencing them
+//! need to be resynced. After a pull, the changed items are derived from the
+//! files that differ between the old and the new `HEAD`; MCP servers share one
+//! file, so `mcp/servers.json` is compared server by server.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::layers::{LayeredItemKind, MCP_SERVERS_FILE};
+use super::{
+    git, LibraryStore, McpServer, AGENT_DIR, COMMAND_DIR, CONFIGS_DIR, INIT_SCRIPT_DIR, SKILL_DIR,
+    WORKSPACE_TEMPLATE_DIR,
+};
+
+/// A library item that was added, modified or deleted.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct LibraryItemRef {
+    pub kind: LayeredItemKind,
+    pub name: String,
+}
+
+impl LibraryItemRef {
+    pub fn new(kind: LayeredItemKind, name: impl Into<String>) -> Self {
+        Self {
+            kind,
+            name: name.into(),
+        }
+    }
+
+    /// The item a file (relative to a layer root) belongs to.
+    ///
+    /// Returns `None` for files outside of items, including `mcp/servers.json`.
+    pub fn from_relative_path(path: &str) -> Option<Self> {
+        let (dir, rest) = path.split_once('/')?;
+        let (kind, name) = match dir {
+            SKILL_DIR => (LayeredItemKind::Skill, rest.split('/').next()?),
+            INIT_SCRIPT_DIR => (LayeredItemKind::InitScript, rest.split('/').next()?),
+            CONFIGS_DIR => (LayeredItemKind::ConfigProfile, rest.split('/').next()?),
+            COMMAND_DIR => (LayeredItemKind::Command, rest.strip_suffix(".md")?),
+            AGENT_DIR => (LayeredItemKind::Agent, rest.strip_suffix(".md")?),
+            WORKSPACE_TEMPLATE_DIR => (
+                LayeredItemKind::WorkspaceTemplate,
+                rest.strip_suffix(".json")?,
+            ),
+            _ => return None,
+        };
+        if name.is_empty() || name.contains('/') {
+            return None;
+        }
+        Some(Self::new(kind, name))
+    }
+}
+
+/// Parse `mcp/servers.json` content, treating missing or invalid files as empty.
+fn parse_mcp_servers(content: Option<&str>) -> BTreeMap<String, serde_json::Value> {
+    content
+        .and_then(|c| serde_json::from_str(c).ok())
+        .unwrap_or_default()
+}
+
+/// MCP server names whose definition differs between two server maps.
+fn changed_mcp_servers(
+    old: &BTreeMap<String, serde_json::Value>,
+    new: &BTreeMap<String, serde_json::Value>,
+) -> BTreeSet<String> {
+    old.keys()
+        .chain(new.keys())
+        .filter(|name| old.get(*name) != new.get(*name))
+        .cloned()
+        .collect()
+}
+
+/// MCP servers added, modified or removed between two sets of definitions.
+pub fn mcp_server_changes(
+    old: &HashMap<String, McpServer>,
+    new: &HashMap<String, McpServer>,
+) -> Vec<LibraryItemRef> {
+    fn to_values(servers: &HashMap<String, McpServer>) -> BTreeMap<String, serde_json::Value> {
+        servers
+            .iter()
+            .map(|(name, server)| {
+                (
+                    name.clone(),
+                    serde_json::to_value(server).unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    changed_mcp_servers(&to_values(old), &to_values(new))
+        .into_iter()
+        .map(|name| LibraryItemRef::new(LayeredItemKind::Mcp, name))
+        .collect()
+}
+
+impl LibraryStore {
+    /// Current `HEAD` commit, or `None` for a repository without commits.
+    pub async fn head_revision(&self) -> Option<String> {
+        git::resolve_revision(&self.path, "HEAD").await.ok()
+    }
+
+    /// Items changed between `revision` and the current `HEAD`.
+    ///
+    /// Without a revision (the repository had no commits), every item is
+    /// reported.
+    pub async fn changed_items_since(&self, revision: Option<&str>) -> Result<Vec<LibraryItemRef>> {
+        let Some(head) = self.head_revision().await else {
+            return Ok(Vec::new());
+        };
+        let files = match revision {
+            Some(revision) if revision == head => return Ok(Vec::new()),
+            Some(revision) => git::changed_paths(&self.path, revision, &head).await?,
+            None => git::all_files(&self.path).await?,
+        };
+
+        let mut items = BTreeSet::new();
+        for file in &files {
+            if file == MCP_SERVERS_FILE {
+                let old = match revision {
+                    Some(revision) => git::show_file(&self.path, revision, file).await?,
+                    None => None,
+                };
+                let new = git::show_file(&self.path, &head, file).await?;
+                let old = parse_mcp_servers(old.as_deref());
+                let new = parse_mcp_servers(new.as_deref());
+                for name in changed_mcp_servers(&old, &new) {
+                    items.insert(LibraryItemRef::new(LayeredItemKind::Mcp, name));
+                }
+            } else if let Some(item) = LibraryItemRef::from_relative_path(file) {
+                items.insert(item);
+            }
+        }
+        Ok(items.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_files_to_items() {
+        let item = |path: &str| LibraryItemRef::from_relative_path(path);
+        assert_eq!(
+            item("skill/lint/references/guide.md"),
+            Some(LibraryItemRef::new(LayeredItemKind::Skill, "lint"))
+        );
+        assert_eq!(
+            item("command/deploy.md"),
+            Some(LibraryItemRef::new(LayeredItemKind::Command, "deploy"))
+        );
+        assert_eq!(
+            item("workspace-template/node.json"),
+            Some(LibraryItemRef::new(
+                LayeredItemKind::WorkspaceTemplate,
+                "node"
+            ))
+        );
+        assert_eq!(
+            item("configs/default/opencode/oh-my-opencode.json"),
+            Some(LibraryItemRef::new(
+                LayeredItemKind::ConfigProfile,
+                "default"
+            ))
+        );
+        assert_eq!(item("command/nested/deploy.md"), None);
+        assert_eq!(item("mcp/servers.json"), None);
+        assert_eq!(item("README.md"), None);
+    }
+
+    #[tokio::test]
+    async fn reports_items_changed_since_revision() {
+        async fn git(dir: &std::path::Path, args: &[&str]) {
+            let status = tokio::process::Command::new("git")
+                .current_dir(dir)
+                .args(args)
+                .status()
+                .await
+                .unwrap();
+            assert!(status.success(), "git {:?} failed", args);
+        }
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        git(dir, &["init", "-q"]).await;
+        git(dir, &["config", "user.name", "Test"]).await;
+        git(dir, &["config", "user.email", "test@example.com"]).await;
+        let store = LibraryStore::with_test_store(dir.to_path_buf()).await;
+
+        let servers = |github_args: &str| -> HashMap<String, McpServer> {
+            serde_json::from_str(&format!(
+                r#"{{"github": {{"type": "local", "command": ["gh-mcp"{}]}},
+                    "fs": {{"type": "local", "command": ["fs-mcp"]}}}}"#,
+                github_args
+            ))
+            .unwrap()
+        };
+        store.save_command("deploy", "v1\n").await.unwrap();
+        store.save_command("test", "v1\n").await.unwrap();
+        store.save_mcp_servers(&servers("")).await.unwrap();
+        store.commit("Seed", None).await.unwrap();
+        let seed = store.head_revision().await.unwrap();
+        assert!(store
+            .changed_items_since(Some(&seed))
+            .await
+            .unwrap()
+            .is_empty());
+
+        store.save_command("deploy", "v2\n").await.unwrap();
+        store
+            .save_skill("lint", "---\ndescription: lint\n---\n")
+            .await
+            .unwrap();
+        store
+            .save_mcp_servers(&servers(r#", "--ro""#))
+            .await
+            .unwrap();
+        store.commit("Update", None).await.unwrap();
+
+        assert_eq!(
+            store.changed_items_since(Some(&seed)).await.unwrap(),
+            vec![
+                LibraryItemRef::new(LayeredItemKind::Skill, "lint"),
+                LibraryItemRef::new(LayeredItemKind::Command, "deploy"),
+                LibraryItemRef::new(LayeredItemKind::Mcp, "github"),
+            ]
+        );
+        assert_eq!(
+            mcp_server_changes(&servers(""), &HashMap::new()),
+            vec![
+                LibraryItemRef::new(LayeredItemKind::Mcp, "fs"),
+                LibraryItemRef::new(LayeredItemKind::Mcp, "github"),
+            ]
+        );
+    }
+}
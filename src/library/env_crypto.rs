@@ -393,6 +393,17 @@ pub fn strip_encrypted_tags(content: &str) -> String {
     re.replace_all(&content, "$1").to_string()
 }
 
+/// Replace every encrypted value (including failed ones) with `[ENCRYPTED]`.
+///
+/// Used when content leaves sandboxed.sh, e.g. library exports.
+pub fn redact_encrypted_tags(content: &str) -> String {
+    let re_failed = regex::Regex::new(FAILED_ENCRYPTED_TAG_REGEX).expect("Invalid regex");
+    let content = re_failed.replace_all(content, "[ENCRYPTED]");
+
+    let re = regex::Regex::new(ANY_ENCRYPTED_TAG_REGEX).expect("Invalid regex");
+    re.replace_all(&content, "[ENCRYPTED]").to_string()
+}
+
 /// Encrypt all unversioned <encrypted>value</encrypted> tags in content.
 /// Transforms <encrypted>plaintext</encrypted> to <encrypted v="1">ciphertext</encrypted>.
 pub fn encrypt_content_tags(key: &[u8; KEY_LENGTH], content: &str) -> Result<String> {
@@ -848,6 +859,16 @@ Keys:
         assert_eq!(stripped, content);
     }
 
+    #[test]
+    fn test_redact_encrypted_tags() {
+        let content =
+            "A: <encrypted>sk-1</encrypted> B: <encrypted-failed v=\"1\">x</encrypted-failed>";
+        assert_eq!(
+            redact_encrypted_tags(content),
+            "A: [ENCRYPTED] B: [ENCRYPTED]"
+        );
+    }
+
     #[test]
     fn test_strip_encrypted_tags_empty() {
         assert_eq!(strip_encrypted_tags(""), "");
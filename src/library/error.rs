@@ -0,0 +1,25 @@
+//! Typed library errors.
+//!
+//! Library operations return `anyhow` errors. Failures that callers must tell
+//! apart, e.g. to pick an HTTP status, carry a [`LibraryError`] that can be
+//! recovered with `downcast_ref`; anything else is an internal error.
+
+use super::secret_scan::SecretScanReport;
+
+/// A library failure that callers handle differently from internal errors.
+#[derive(Debug, thiserror::Error)]
+pub enum LibraryError {
+    /// The item, revision, layer or file does not exist
+    #[error("{0}")]
+    NotFound(String),
+    /// The request or the library content cannot be used as given
+    #[error("{0}")]
+    Invalid(String),
+    /// The library is in a state that does not allow the operation: a merge
+    /// in progress, local changes, items that already exist
+    #[error("{0}")]
+    Conflict(String),
+    /// Plaintext secrets blocked a commit or push
+    #[error("{}", .0.error_message())]
+    SecretsDetected(SecretScanReport),
+}
@@ -4,7 +4,8 @@ use anyhow::{Context, Result};
 use std::path::Path;
 use tokio::process::Command;
 
-use super::types::LibraryStatus;
+use super::types::{ConflictFile, LibraryCommit, LibraryStatus};
+use super::LibraryError;
 
 /// Get the GIT_SSH_COMMAND value for git operations.
 ///
@@ -194,6 +195,7 @@ pub async fn status(path: &Path) -> Result<LibraryStatus> {
         ahead,
         behind,
         modified_files,
+        failed_layers: Vec::new(),
     })
 }
 
@@ -245,7 +247,8 @@ pub async fn pull(path: &Path) -> Result<(), PullError> {
             return Err(PullError::DivergedHistory {
                 message: format!(
                     "Local and remote histories have diverged (likely due to a force push). \
-                     Use 'Force Pull' to reset to remote or 'Force Push' to overwrite remote. \
+                     Use 'Merge' to combine both histories, 'Force Pull' to reset to remote \
+                     or 'Force Push' to overwrite remote. \
                      Git error: {}",
                     stderr.trim()
                 ),
@@ -321,6 +324,226 @@ pub async fn force_push(path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Outcome of merging the remote branch into the local one.
+#[derive(Debug)]
+pub enum MergeOutcome {
+    /// Merge completed (or nothing to merge).
+    Merged,
+    /// Merge stopped on conflicts in these files.
+    Conflicts(Vec<String>),
+}
+
+/// Fetch and merge the remote tracking branch into the current branch.
+///
+/// On conflicts the merge is left in progress so the conflicted files can be
+/// resolved with [`resolve_file`] and finished with [`complete_merge`].
+pub async fn merge_remote(path: &Path, author: Option<&GitAuthor>) -> Result<MergeOutcome> {
+    tracing::info!(path = %path.display(), "Merging remote library changes");
+
+    if merge_in_progress(path).await {
+        anyhow::bail!(LibraryError::Conflict(
+            "A merge is already in progress".to_string()
+        ));
+    }
+    let (clean, _) = get_status(path).await?;
+    if !clean {
+        anyhow::bail!(LibraryError::Conflict(
+            "Commit or discard local changes before merging".to_string()
+        ));
+    }
+
+    let mut cmd = Command::new("git");
+    cmd.current_dir(path).args(["fetch", "origin"]);
+    apply_ssh_config(&mut cmd);
+    let output = cmd.output().await.context("Failed to execute git fetch")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git fetch failed: {}", stderr);
+    }
+
+    let branch = get_branch(path).await?;
+    let mut cmd = Command::new("git");
+    cmd.current_dir(path);
+    apply_author_env(&mut cmd, author);
+    let output = cmd
+        .args(["merge", "--no-edit", &format!("origin/{}", branch)])
+        .output()
+        .await
+        .context("Failed to execute git merge")?;
+
+    if output.status.success() {
+        return Ok(MergeOutcome::Merged);
+    }
+
+    let conflicts = conflicted_files(path).await?;
+    if conflicts.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git merge failed: {}", stderr);
+    }
+    Ok(MergeOutcome::Conflicts(conflicts))
+}
+
+/// Whether a merge is waiting to be completed.
+pub async fn merge_in_progress(path: &Path) -> bool {
+    Command::new("git")
+        .current_dir(path)
+        .args(["rev-parse", "-q", "--verify", "MERGE_HEAD"])
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Files with unresolved merge conflicts.
+pub async fn conflicted_files(path: &Path) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["diff", "--name-only", "--diff-filter=U", "-z"])
+        .output()
+        .await
+        .context("Failed to list conflicted files")?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(|f| f.to_string())
+        .collect())
+}
+
+/// Base, ours and theirs versions of a conflicted file.
+pub async fn conflict_file(path: &Path, file: &str) -> Result<ConflictFile> {
+    let stage = |n: u8| {
+        let spec = format!(":{}:{}", n, file);
+        async move {
+            let output = Command::new("git")
+                .current_dir(path)
+                .arg("show")
+                .arg(spec)
+                .output()
+                .await
+                .ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+        }
+    };
+
+    Ok(ConflictFile {
+        path: file.to_string(),
+        base: stage(1).await,
+        ours: stage(2).await,
+        theirs: stage(3).await,
+        merged: tokio::fs::read_to_string(path.join(file)).await.ok(),
+    })
+}
+
+/// Mark a conflicted file as resolved with `content`, or delete it if `None`.
+pub async fn resolve_file(path: &Path, file: &str, content: Option<&str>) -> Result<()> {
+    if !conflicted_files(path).await?.iter().any(|f| f == file) {
+        anyhow::bail!(LibraryError::Conflict(format!(
+            "File is not conflicted: {}",
+            file
+        )));
+    }
+
+    let args: Vec<&str> = match content {
+        Some(content) => {
+            tokio::fs::write(path.join(file), content)
+                .await
+                .context("Failed to write resolved file")?;
+            vec!["add", "--", file]
+        }
+        None => vec!["rm", "-q", "--ignore-unmatch", "--", file],
+    };
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(&args)
+        .output()
+        .await
+        .context("Failed to stage resolved file")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git {} failed: {}", args[0], stderr);
+    }
+    Ok(())
+}
+
+/// Commit an in-progress merge once every conflict is resolved.
+pub async fn complete_merge(
+    path: &Path,
+    message: Option<&str>,
+    author: Option<&GitAuthor>,
+) -> Result<()> {
+    if !merge_in_progress(path).await {
+        anyhow::bail!(LibraryError::Conflict("No merge in progress".to_string()));
+    }
+    let remaining = conflicted_files(path).await?;
+    if !remaining.is_empty() {
+        anyhow::bail!(LibraryError::Conflict(format!(
+            "Unresolved conflicts remain: {}",
+            remaining.join(", ")
+        )));
+    }
+
+    // Stage files changed after resolving, e.g. encrypted skill files
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["add", "-A"])
+        .output()
+        .await
+        .context("Failed to execute git add")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git add failed: {}", stderr);
+    }
+
+    let mut cmd = Command::new("git");
+    cmd.current_dir(path);
+    apply_author_env(&mut cmd, author);
+    cmd.arg("commit");
+    match message {
+        Some(message) => cmd.args(["-m", message]),
+        None => cmd.arg("--no-edit"),
+    };
+    let output = cmd.output().await.context("Failed to execute git commit")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git commit failed: {}", stderr);
+    }
+    Ok(())
+}
+
+/// Abort an in-progress merge, restoring the pre-merge state.
+pub async fn abort_merge(path: &Path) -> Result<()> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["merge", "--abort"])
+        .output()
+        .await
+        .context("Failed to execute git merge --abort")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git merge --abort failed: {}", stderr);
+    }
+    Ok(())
+}
+
+/// Attribute a merge commit to `author` when both name and email are known.
+fn apply_author_env(cmd: &mut Command, author: Option<&GitAuthor>) {
+    if let Some(GitAuthor {
+        name: Some(name),
+        email: Some(email),
+    }) = author
+    {
+        cmd.env("GIT_AUTHOR_NAME", name)
+            .env("GIT_AUTHOR_EMAIL", email);
+    }
+}
+
 /// Git author configuration for commits.
 #[derive(Debug, Clone, Default)]
 pub struct GitAuthor {
@@ -392,6 +615,220 @@ pub async fn push(path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Resolve a user-supplied revision to a full commit hash.
+pub async fn resolve_revision(path: &Path, revision: &str) -> Result<String> {
+    // Never let a revision be parsed as an option
+    if revision.is_empty() || revision.starts_with('-') {
+        anyhow::bail!(LibraryError::Invalid(format!(
+            "Invalid revision: {}",
+            revision
+        )));
+    }
+
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{}^{{commit}}", revision))
+        .output()
+        .await
+        .context("Failed to execute git rev-parse")?;
+
+    if !output.status.success() {
+        anyhow::bail!(LibraryError::NotFound(format!(
+            "Revision not found: {}",
+            revision
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// List commits touching `pathspec`, newest first.
+pub async fn log(path: &Path, pathspec: &str, limit: usize) -> Result<Vec<LibraryCommit>> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args([
+            "log",
+            "--format=%H%x1f%an%x1f%ae%x1f%aI%x1f%s%x1e",
+            &format!("--max-count={}", limit),
+            "--",
+            pathspec,
+        ])
+        .output()
+        .await
+        .context("Failed to execute git log")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // A repository without commits has no history
+        if stderr.contains("does not have any commits") {
+            return Ok(Vec::new());
+        }
+        anyhow::bail!("git log failed: {}", stderr);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_matches('\n').split('\x1f');
+            Some(LibraryCommit {
+                hash: fields.next().filter(|h| !h.is_empty())?.to_string(),
+                author_name: fields.next()?.to_string(),
+                author_email: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                subject: fields.next()?.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Unified diff of `pathspec` between two revisions.
+///
+/// Without `to`, diffs against the working tree.
+pub async fn diff(path: &Path, from: &str, to: Option<&str>, pathspec: &str) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(path).args(["diff", "--no-color", from]);
+    if let Some(to) = to {
+        cmd.arg(to);
+    }
+    cmd.args(["--", pathspec]);
+    let output = cmd.output().await.context("Failed to execute git diff")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git diff failed: {}", stderr);
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Content of `file` at `revision`, or `None` if it did not exist there.
+pub async fn show_file(path: &Path, revision: &str, file: &str) -> Result<Option<String>> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .arg("show")
+        .arg(format!("{}:{}", revision, file))
+        .output()
+        .await
+        .context("Failed to execute git show")?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+}
+
+/// Restore `pathspec` to its state at `revision` and stage the result.
+///
+/// Files added after `revision` are removed; if the path did not exist at
+/// `revision`, the item is deleted. Uncommitted changes to the path would be
+/// lost, so unless `force` is set the restore is refused while there are any.
+pub async fn restore_path(path: &Path, revision: &str, pathspec: &str, force: bool) -> Result<()> {
+    if !force {
+        let output = Command::new("git")
+            .current_dir(path)
+            .args([
+                "status",
+                "--porcelain",
+                "--untracked-files=all",
+                "--",
+                pathspec,
+            ])
+            .output()
+            .await
+            .context("Failed to get git status")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git status failed: {}", stderr);
+        }
+        if !output.stdout.is_empty() {
+            anyhow::bail!(LibraryError::Conflict(format!(
+                "{} has uncommitted changes; commit them or revert with force",
+                pathspec
+            )));
+        }
+    }
+
+    tracing::info!(
+        path = %path.display(),
+        revision = %revision,
+        pathspec = %pathspec,
+        "Reverting library item"
+    );
+
+    let existed = Command::new("git")
+        .current_dir(path)
+        .args(["cat-file", "-e"])
+        .arg(format!("{}:{}", revision, pathspec))
+        .output()
+        .await
+        .context("Failed to execute git cat-file")?
+        .status
+        .success();
+
+    // Without `force` the path has no local changes, so `-f` loses nothing
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["rm", "-r", "-q", "-f", "--ignore-unmatch", "--", pathspec])
+        .output()
+        .await
+        .context("Failed to execute git rm")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git rm failed: {}", stderr);
+    }
+    // Untracked leftovers are not touched by git rm
+    let full_path = path.join(pathspec);
+    if full_path.is_dir() {
+        tokio::fs::remove_dir_all(&full_path).await.ok();
+    } else if full_path.exists() {
+        tokio::fs::remove_file(&full_path).await.ok();
+    }
+
+    if existed {
+        let output = Command::new("git")
+            .current_dir(path)
+            .args(["checkout", revision, "--", pathspec])
+            .output()
+            .await
+            .context("Failed to execute git checkout")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("git checkout failed: {}", stderr);
+        }
+    }
+
+    Ok(())
+}
+
+/// Commit only `pathspec`, leaving other changes uncommitted.
+pub async fn commit_path(
+    path: &Path,
+    pathspec: &str,
+    message: &str,
+    author: Option<&GitAuthor>,
+) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(path).args(["commit", "-m", message]);
+    if let Some(author) = author {
+        if let (Some(name), Some(email)) = (&author.name, &author.email) {
+            cmd.args(["--author", &format!("{} <{}>", name, email)]);
+        }
+    }
+    cmd.args(["--", pathspec]);
+    let output = cmd.output().await.context("Failed to execute git commit")?;
+
+    // Exit code 1 means nothing to commit, which is fine
+    if !output.status.success() && output.status.code() != Some(1) {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git commit failed: {}", stderr);
+    }
+
+    Ok(())
+}
+
 /// Clone a git repository to a path.
 pub async fn clone(path: &Path, remote: &str) -> Result<()> {
     tracing::info!(remote = %remote, path = %path.display(), "Cloning repository");
@@ -508,6 +945,273 @@ pub async fn sparse_clone(path: &Path, remote: &str, subpath: &str) -> Result<()
     Ok(())
 }
 
+/// Shallow-fetch one revision of `remote` into a repository at `path`,
+/// initializing it if needed. Nothing is checked out.
+///
+/// Fetches the remote HEAD when `revision` is `None`. Returns the commit hash.
+pub async fn fetch_revision(path: &Path, remote: &str, revision: Option<&str>) -> Result<String> {
+    if revision.is_some_and(|r| r.starts_with('-')) {
+        anyhow::bail!("Invalid revision: {}", revision.unwrap_or_default());
+    }
+
+    if !path.join(".git").exists() {
+        tokio::fs::create_dir_all(path).await?;
+        for args in [
+            vec!["init", "-q"],
+            vec!["remote", "add", "--", "origin", remote],
+        ] {
+            let output = Command::new("git")
+                .current_dir(path)
+                .args(&args)
+                .output()
+                .await
+                .context("Failed to initialize git repo")?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                anyhow::bail!("git {} failed: {}", args[0], stderr);
+            }
+        }
+    }
+
+    let mut cmd = Command::new("git");
+    cmd.current_dir(path).args([
+        "fetch",
+        "-q",
+        "--depth",
+        "1",
+        "origin",
+        revision.unwrap_or("HEAD"),
+    ]);
+    apply_ssh_config(&mut cmd);
+    let output = cmd.output().await.context("Failed to fetch")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git fetch failed: {}", stderr.trim());
+    }
+
+    resolve_revision(path, "FETCH_HEAD").await
+}
+
+/// Files in the tree of `revision`, without checking it out.
+pub async fn list_tree(path: &Path, revision: &str) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["ls-tree", "-r", "-z", "--name-only", revision])
+        .output()
+        .await
+        .context("Failed to execute git ls-tree")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git ls-tree failed: {}", stderr);
+    }
+
+    Ok(split_nul(&output.stdout))
+}
+
+/// Check out only `subpath` (everything when empty) of an already fetched `revision`.
+pub async fn sparse_checkout(path: &Path, revision: &str, subpath: &str) -> Result<()> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["config", "core.sparseCheckout", "true"])
+        .output()
+        .await
+        .context("Failed to enable sparse checkout")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git config failed: {}", stderr);
+    }
+
+    let sparse_checkout_path = path.join(".git/info/sparse-checkout");
+    if let Some(parent) = sparse_checkout_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let pattern = if subpath.is_empty() {
+        "/*\n".to_string()
+    } else {
+        format!("/{}/\n", subpath)
+    };
+    tokio::fs::write(&sparse_checkout_path, pattern).await?;
+
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["checkout", "-q", "--detach", revision])
+        .output()
+        .await
+        .context("Failed to checkout")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git checkout failed: {}", stderr);
+    }
+
+    Ok(())
+}
+
+/// Commit hash of the default branch of `remote`, without fetching it.
+pub async fn remote_head(remote: &str) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.args(["ls-remote", "--", remote, "HEAD"]);
+    apply_ssh_config(&mut cmd);
+    let output = cmd
+        .output()
+        .await
+        .context("Failed to execute git ls-remote")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git ls-remote failed: {}", stderr.trim());
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("Remote {} has no HEAD", remote))
+}
+
+/// Create or refresh a bare mirror of `remote` at `path`.
+///
+/// Returns the commit hash of the mirrored HEAD.
+pub async fn mirror(path: &Path, remote: &str) -> Result<String> {
+    let mut cmd = Command::new("git");
+    if path.join("HEAD").exists() {
+        cmd.current_dir(path).args(["remote", "update", "--prune"]);
+    } else {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        cmd.args(["clone", "-q", "--mirror", "--", remote])
+            .arg(path);
+    }
+    apply_ssh_config(&mut cmd);
+    let output = cmd.output().await.context("Failed to mirror repository")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git mirror of {} failed: {}", remote, stderr.trim());
+    }
+
+    resolve_revision(path, "HEAD").await
+}
+
+/// Split NUL-separated git output into paths.
+fn split_nul(stdout: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stdout)
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(|f| f.to_string())
+        .collect()
+}
+
+/// Tracked and untracked (non-ignored) files in the working tree.
+pub async fn all_files(path: &Path) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args([
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ])
+        .output()
+        .await
+        .context("Failed to execute git ls-files")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git ls-files failed: {}", stderr);
+    }
+
+    let mut files = split_nul(&output.stdout);
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Files a commit of all changes would add or modify (deletions excluded).
+pub async fn changed_files(path: &Path) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["status", "--porcelain", "-z", "--untracked-files=all"])
+        .output()
+        .await
+        .context("Failed to execute git status")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git status failed: {}", stderr);
+    }
+
+    // Entries are "XY path"; renames are followed by an extra "from" entry
+    let mut files = Vec::new();
+    let mut entries = split_nul(&output.stdout).into_iter();
+    while let Some(entry) = entries.next() {
+        if entry.len() < 4 {
+            continue;
+        }
+        let (status, file) = entry.split_at(3);
+        if status.starts_with('R') || status.starts_with('C') {
+            entries.next();
+        }
+        if !status.contains('D') {
+            files.push(file.to_string());
+        }
+    }
+    Ok(files)
+}
+
+/// Files added, modified or deleted between two revisions.
+pub async fn changed_paths(path: &Path, from: &str, to: &str) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["diff", "--name-only", "--no-renames", "-z", from, to])
+        .output()
+        .await
+        .context("Failed to execute git diff")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git diff failed: {}", stderr);
+    }
+
+    Ok(split_nul(&output.stdout))
+}
+
+/// Files touched by commits not yet on the upstream branch, as of HEAD.
+///
+/// Without an upstream, every tracked file is returned.
+pub async fn unpushed_files(path: &Path) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args([
+            "diff",
+            "--name-only",
+            "-z",
+            "--diff-filter=d",
+            "@{u}",
+            "HEAD",
+        ])
+        .output()
+        .await
+        .context("Failed to execute git diff")?;
+
+    if output.status.success() {
+        return Ok(split_nul(&output.stdout));
+    }
+
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["ls-files", "-z"])
+        .output()
+        .await
+        .context("Failed to execute git ls-files")?;
+    Ok(split_nul(&output.stdout))
+}
+
 // Helper functions
 
 async fn get_branch(path: &Path) -> Result<String> {
@@ -0,0 +1,785 @@
+//! Import existing `.claude` / `.opencode` project directories.
+//!
+//! A project directory (local path, uploaded ZIP archive or git repository) is
+//! scanned for harness artifacts, which map onto library items:
+//! - `.claude/skills/<name>/`, `.opencode/skill(s)/<name>/` → skills
+//! - `.claude/commands/**/*.md`, `.opencode/command(s)/*.md` → commands
+//!   (nested Claude commands are flattened, `frontend/build.md` → `frontend-build`)
+//! - `.claude/agents/*.md`, `.opencode/agent(s)/*.md` → agents
+//! - `CLAUDE.md` → `.claudecode/CLAUDE.md` in a config profile
+//! - `oh-my-opencode.json` → `.opencode/settings.json` in a config profile
+//!
+//! Command and agent frontmatter is converted to the library format (Claude's
+//! `tools: Read, Grep` list becomes an OpenCode tool mapping); artifacts that
+//! cannot be converted are reported with an `error` and block the import.
+//! Symlinks are never followed, so an imported repository cannot pull host
+//! files into the library.
+//!
+//! Every item is checked against the library before anything is written, so
+//! name conflicts can be reviewed (`dry_run`) and either resolved or overwritten.
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use super::{
+    git, LayeredItemKind, LibraryError, LibraryItemRef, LibraryStore, AGENT_DIR, COMMAND_DIR,
+    CONFIGS_DIR, DEFAULT_PROFILE, SKILL_DIR,
+};
+use crate::workspace::{
+    ensure_claudecode_skill_frontmatter, normalize_agent_frontmatter, normalize_command_frontmatter,
+};
+
+/// Where harness artifacts are imported from.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HarnessImportSource {
+    /// A directory on the server
+    Path { path: PathBuf },
+    /// A git repository, optionally a subdirectory of it
+    Git {
+        url: String,
+        #[serde(default)]
+        path: Option<String>,
+    },
+    /// A ZIP archive of a project directory (uploaded)
+    #[serde(skip)]
+    Archive(Vec<u8>),
+}
+
+/// Options for a harness import.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HarnessImportOptions {
+    /// Config profile receiving `CLAUDE.md` / `oh-my-opencode.json` (default: "default")
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Only report what would be imported
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Replace library items that already exist
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// Why an artifact cannot be imported as-is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HarnessImportConflict {
+    /// The library already has an item (or profile file) with this name
+    Exists,
+    /// Another artifact in the source maps to the same item; it is skipped
+    Duplicate,
+}
+
+/// A harness artifact and the library item it becomes.
+#[derive(Debug, Clone, Serialize)]
+pub struct HarnessImportItem {
+    pub kind: LayeredItemKind,
+    pub name: String,
+    /// Artifact path relative to the imported directory
+    pub source: String,
+    /// Library path written (relative to the layer root)
+    pub target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<HarnessImportConflict>,
+    /// Why the artifact cannot be converted to a library item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of a harness import.
+#[derive(Debug, Clone, Serialize)]
+pub struct HarnessImportReport {
+    pub items: Vec<HarnessImportItem>,
+    /// False for dry runs
+    pub applied: bool,
+}
+
+impl HarnessImportReport {
+    /// Library items written by the import.
+    pub fn changed_items(&self) -> Vec<LibraryItemRef> {
+        if !self.applied {
+            return Vec::new();
+        }
+        let mut items: Vec<LibraryItemRef> = self
+            .items
+            .iter()
+            .filter(|item| item.conflict != Some(HarnessImportConflict::Duplicate))
+            .map(|item| LibraryItemRef::new(item.kind, &item.name))
+            .collect();
+        items.sort();
+        items.dedup();
+        items
+    }
+}
+
+/// `root/relative`, if none of its components is a symlink and it resolves
+/// to a path under `root`. Imported repositories may contain symlinks to host
+/// files (`CLAUDE.md -> ~/.ssh/id_rsa`), which must never reach the library.
+async fn resolve_within(root: &Path, relative: &str) -> Result<PathBuf> {
+    let mut path = root.to_path_buf();
+    for component in Path::new(relative).components() {
+        let Component::Normal(part) = component else {
+            anyhow::bail!(LibraryError::Invalid(format!(
+                "Invalid import path: {}",
+                relative
+            )));
+        };
+        path.push(part);
+        if fs::symlink_metadata(&path).await?.file_type().is_symlink() {
+            anyhow::bail!(LibraryError::Invalid(format!(
+                "Refusing to import symlink {}",
+                relative
+            )));
+        }
+    }
+    let canonical_root = fs::canonicalize(root).await?;
+    if !fs::canonicalize(&path).await?.starts_with(&canonical_root) {
+        anyhow::bail!(LibraryError::Invalid(format!(
+            "Import path escapes the project directory: {}",
+            relative
+        )));
+    }
+    Ok(path)
+}
+
+/// Files below `root/dir`, as `/`-separated paths relative to `root/dir`.
+/// Symlinks are skipped.
+pub(crate) async fn list_files(root: &Path, dir: &str) -> Vec<String> {
+    let base = match resolve_within(root, dir).await {
+        Ok(base) => base,
+        Err(e) => {
+            if root.join(dir).exists() {
+                tracing::warn!(dir, error = %e, "Skipping harness directory");
+            }
+            return Vec::new();
+        }
+    };
+    let mut found = Vec::new();
+    let mut pending = vec![base.clone()];
+    while let Some(current) = pending.pop() {
+        let Ok(mut entries) = fs::read_dir(&current).await else {
+            continue;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            match fs::symlink_metadata(&path).await.map(|m| m.file_type()) {
+                Ok(file_type) if file_type.is_symlink() => {
+                    tracing::warn!(path = %path.display(), "Skipping symlink while importing");
+                }
+                Ok(file_type) if file_type.is_dir() => pending.push(path),
+                Ok(file_type) if file_type.is_file() => {
+                    if let Ok(relative) = path.strip_prefix(&base) {
+                        found.push(relative.to_string_lossy().replace('\\', "/"));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Whether `name` can be used as a library item name.
+fn is_valid_name(name: &str) -> bool {
+    LibraryStore::validate_name(name).is_ok()
+}
+
+/// Scan a project directory for harness artifacts (without checking the library).
+async fn scan(root: &Path, profile: &str) -> Vec<HarnessImportItem> {
+    let mut items = Vec::new();
+    let mut push = |kind: LayeredItemKind, name: String, source: String, target: String| {
+        items.push(HarnessImportItem {
+            kind,
+            name,
+            source,
+            target,
+            conflict: None,
+            error: None,
+        });
+    };
+
+    for dir in [".claude/skills", ".opencode/skill", ".opencode/skills"] {
+        for file in list_files(root, dir).await {
+            let Some(name) = file.strip_suffix("/SKILL.md") else {
+                continue;
+            };
+            if !is_valid_name(name) {
+                continue;
+            }
+            push(
+                LayeredItemKind::Skill,
+                name.to_string(),
+                format!("{}/{}", dir, name),
+                LayeredItemKind::Skill.relative_path(name),
+            );
+        }
+    }
+
+    for (dir, nested) in [
+        (".claude/commands", true),
+        (".opencode/command", false),
+        (".opencode/commands", false),
+    ] {
+        for file in list_files(root, dir).await {
+            let Some(stem) = file.strip_suffix(".md") else {
+                continue;
+            };
+            if stem.contains('/') && !nested {
+                continue;
+            }
+            let name = stem.replace('/', "-");
+            if !is_valid_name(&name) {
+                continue;
+            }
+            let target = LayeredItemKind::Command.relative_path(&name);
+            push(
+                LayeredItemKind::Command,
+                name,
+                format!("{}/{}", dir, file),
+                target,
+            );
+        }
+    }
+
+    for dir in [".claude/agents", ".opencode/agent", ".opencode/agents"] {
+        for file in list_files(root, dir).await {
+            let Some(name) = file.strip_suffix(".md") else {
+                continue;
+            };
+            if name.contains('/') || !is_valid_name(name) {
+                continue;
+            }
+            push(
+                LayeredItemKind::Agent,
+                name.to_string(),
+                format!("{}/{}", dir, file),
+                LayeredItemKind::Agent.relative_path(name),
+            );
+        }
+    }
+
+    let profile_files = [
+        ("CLAUDE.md", ".claudecode/CLAUDE.md"),
+        (".claude/CLAUDE.md", ".claudecode/CLAUDE.md"),
+        ("oh-my-opencode.json", ".opencode/settings.json"),
+        (".opencode/oh-my-opencode.json", ".opencode/settings.json"),
+    ];
+    for (source, file) in profile_files {
+        let is_file = match resolve_within(root, source).await {
+            Ok(path) => path.is_file(),
+            Err(e) => {
+                if fs::symlink_metadata(root.join(source)).await.is_ok() {
+                    tracing::warn!(source, error = %e, "Skipping harness file");
+                }
+                false
+            }
+        };
+        if is_file {
+            push(
+                LayeredItemKind::ConfigProfile,
+                profile.to_string(),
+                source.to_string(),
+                format!("{}/{}/{}", CONFIGS_DIR, profile, file),
+            );
+        }
+    }
+
+    items
+}
+
+/// Content of an imported command or agent, converted to the library format.
+async fn convert_markdown_item(root: &Path, item: &HarnessImportItem) -> Result<String> {
+    let content = fs::read_to_string(resolve_within(root, &item.source).await?).await?;
+    match item.kind {
+        LayeredItemKind::Agent => normalize_agent_frontmatter(&content),
+        _ => normalize_command_frontmatter(&content),
+    }
+}
+
+/// Extract a ZIP archive into `dest`.
+///
+/// A single top-level folder (as in GitHub archive downloads) is stripped,
+/// unless it is a harness directory itself.
+fn extract_archive(data: &[u8], dest: &Path) -> Result<()> {
+    use std::io::Read;
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data))
+        .map_err(|e| LibraryError::Invalid(format!("Invalid ZIP file: {}", e)))?;
+
+    let mut roots = HashSet::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        let Some(path) = entry.enclosed_name() else {
+            continue;
+        };
+        let mut components = path.components();
+        let first = components.next().map(|c| c.as_os_str().to_owned());
+        let is_file_at_root = components.next().is_none() && !entry.is_dir();
+        roots.insert(if is_file_at_root { None } else { first });
+    }
+    let prefix = match roots.into_iter().collect::<Vec<_>>().as_slice() {
+        [Some(root)] if root != ".claude" && root != ".opencode" => Some(PathBuf::from(root)),
+        _ => None,
+    };
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(path) = entry.enclosed_name() else {
+            continue;
+        };
+        let relative = match &prefix {
+            Some(prefix) => path.strip_prefix(prefix).unwrap_or(&path).to_path_buf(),
+            None => path,
+        };
+        if relative.as_os_str().is_empty() || entry.is_dir() {
+            continue;
+        }
+        let target = dest.join(&relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut contents = Vec::new();
+        entry
+            .read_to_end(&mut contents)
+            .context("Failed to read file from ZIP")?;
+        std::fs::write(&target, contents)?;
+    }
+    Ok(())
+}
+
+impl LibraryStore {
+    /// Import harness artifacts from `source`.
+    ///
+    /// Fails without writing anything if items already exist and
+    /// `overwrite` is not set; a dry run reports those conflicts instead.
+    pub async fn import_harness(
+        &self,
+        source: HarnessImportSource,
+        options: &HarnessImportOptions,
+        layer: Option<&str>,
+    ) -> Result<HarnessImportReport> {
+        let profile = options.profile.as_deref().unwrap_or(DEFAULT_PROFILE);
+        Self::validate_name(profile)?;
+
+        let staging = match &source {
+            HarnessImportSource::Path { .. } => None,
+            _ => Some(
+                std::env::temp_dir()
+                    .join(format!("sandboxed-harness-import-{}", uuid::Uuid::new_v4())),
+            ),
+        };
+        let result = async {
+            let root = match (&source, &staging) {
+                (HarnessImportSource::Path { path }, _) => {
+                    if !path.is_dir() {
+                        anyhow::bail!(LibraryError::Invalid(format!(
+                            "Import path not found: {}",
+                            path.display()
+                        )));
+                    }
+                    path.clone()
+                }
+                (HarnessImportSource::Git { url, path }, Some(staging)) => {
+                    git::clone(staging, url).await?;
+                    match path.as_deref().filter(|p| !p.is_empty()) {
+                        Some(path) => resolve_within(staging, path).await?,
+                        None => staging.clone(),
+                    }
+                }
+                (HarnessImportSource::Archive(data), Some(staging)) => {
+                    fs::create_dir_all(staging).await?;
+                    extract_archive(data, staging)?;
+                    staging.clone()
+                }
+                _ => unreachable!("remote sources are staged"),
+            };
+            self.import_harness_dir(&root, profile, options, layer)
+                .await
+        }
+        .await;
+
+        if let Some(staging) = staging {
+            let _ = fs::remove_dir_all(&staging).await;
+        }
+        result
+    }
+
+    async fn import_harness_dir(
+        &self,
+        root: &Path,
+        profile: &str,
+        options: &HarnessImportOptions,
+        layer: Option<&str>,
+    ) -> Result<HarnessImportReport> {
+        let mut items = scan(root, profile).await;
+        let mut seen = HashSet::new();
+        for item in &mut items {
+            if !seen.insert(item.target.clone()) {
+                item.conflict = Some(HarnessImportConflict::Duplicate);
+            } else if self.read_path(&item.target).exists() {
+                item.conflict = Some(HarnessImportConflict::Exists);
+            }
+            if matches!(item.kind, LayeredItemKind::Command | LayeredItemKind::Agent) {
+                if let Err(e) = convert_markdown_item(root, item).await {
+                    item.error = Some(format!("{:#}", e));
+                }
+            }
+        }
+
+        let existing: Vec<&str> = items
+            .iter()
+            .filter(|item| item.conflict == Some(HarnessImportConflict::Exists))
+            .map(|item| item.target.as_str())
+            .collect();
+        if options.dry_run {
+            return Ok(HarnessImportReport {
+                items,
+                applied: false,
+            });
+        }
+        let invalid: Vec<String> = items
+            .iter()
+            .filter_map(|item| {
+                let error = item.error.as_ref()?;
+                Some(format!("{} ({})", item.source, error))
+            })
+            .collect();
+        if !invalid.is_empty() {
+            anyhow::bail!(LibraryError::Invalid(format!(
+                "Cannot convert harness artifacts: {}",
+                invalid.join(", ")
+            )));
+        }
+        if !existing.is_empty() && !options.overwrite {
+            anyhow::bail!(LibraryError::Conflict(format!(
+                "Library items already exist (set overwrite to replace them): {}",
+                existing.join(", ")
+            )));
+        }
+
+        for item in &items {
+            if item.conflict == Some(HarnessImportConflict::Duplicate) {
+                continue;
+            }
+            let store = self.write_layer(layer, item.kind, &item.name)?;
+            store
+                .write_harness_item(root, item)
+                .await
+                .with_context(|| {
+                    format!("Failed to import {} from {}", item.target, item.source)
+                })?;
+        }
+
+        tracing::info!(
+            root = %root.display(),
+            items = items.len(),
+            "Imported harness artifacts into library"
+        );
+
+        Ok(HarnessImportReport {
+            items,
+            applied: true,
+        })
+    }
+
+    /// Write one imported artifact into this layer.
+    async fn write_harness_item(&self, root: &Path, item: &HarnessImportItem) -> Result<()> {
+        let source = resolve_within(root, &item.source).await?;
+        match item.kind {
+            LayeredItemKind::Skill => {
+                let target_dir = self.path.join(SKILL_DIR).join(&item.name);
+                if target_dir.exists() {
+                    fs::remove_dir_all(&target_dir).await?;
+                }
+                Self::copy_dir_recursive(&source, &target_dir).await?;
+
+                let skill_md = target_dir.join("SKILL.md");
+                let content = fs::read_to_string(&skill_md).await?;
+                let normalized = ensure_claudecode_skill_frontmatter(&content, &item.name, None);
+                if normalized != content {
+                    fs::write(&skill_md, normalized).await?;
+                }
+                self.encrypt_skill_file(&item.name).await
+            }
+            LayeredItemKind::Command | LayeredItemKind::Agent => {
+                let dir = match item.kind {
+                    LayeredItemKind::Command => COMMAND_DIR,
+                    _ => AGENT_DIR,
+                };
+                fs::create_dir_all(self.path.join(dir)).await?;
+                let content = convert_markdown_item(root, item).await?;
+                fs::write(self.path.join(&item.target), content).await?;
+                Ok(())
+            }
+            LayeredItemKind::ConfigProfile => {
+                let prefix = format!("{}/{}/", CONFIGS_DIR, item.name);
+                let file = item.target.strip_prefix(&prefix).unwrap_or(&item.target);
+                let content = fs::read_to_string(&source).await?;
+                self.save_config_profile_file(&item.name, file, &content)
+                    .await
+            }
+            LayeredItemKind::Mcp
+            | LayeredItemKind::InitScript
+            | LayeredItemKind::WorkspaceTemplate => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[tokio::test]
+    async fn imports_harness_directories() {
+        let project = tempfile::tempdir().unwrap();
+        let root = project.path();
+        write(root, ".claude/skills/lint/SKILL.md", "Lint the code\n");
+        write(root, ".claude/skills/lint/refs/rules.md", "rules\n");
+        write(root, ".claude/commands/deploy.md", "Deploy\n");
+        write(root, ".claude/commands/frontend/build.md", "Build\n");
+        write(
+            root,
+            ".claude/agents/reviewer.md",
+            "---\ndescription: Review\n---\n",
+        );
+        write(
+            root,
+            ".opencode/agent/reviewer.md",
+            "---\ndescription: Other\n---\n",
+        );
+        write(root, "CLAUDE.md", "# Project\n");
+        write(root, "oh-my-opencode.json", "{}");
+
+        let library_dir = tempfile::tempdir().unwrap();
+        let library = LibraryStore::with_test_store(library_dir.path().to_path_buf()).await;
+        library.save_command("deploy", "Existing\n").await.unwrap();
+
+        let source = || HarnessImportSource::Path {
+            path: root.to_path_buf(),
+        };
+        let dry_run = HarnessImportOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let report = library
+            .import_harness(source(), &dry_run, None)
+            .await
+            .unwrap();
+        let summary: Vec<(&str, Option<HarnessImportConflict>)> = report
+            .items
+            .iter()
+            .map(|item| (item.target.as_str(), item.conflict))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("skill/lint", None),
+                ("command/deploy.md", Some(HarnessImportConflict::Exists)),
+                ("command/frontend-build.md", None),
+                ("agent/reviewer.md", None),
+                ("agent/reviewer.md", Some(HarnessImportConflict::Duplicate)),
+                ("configs/default/.claudecode/CLAUDE.md", None),
+                ("configs/default/.opencode/settings.json", None),
+            ]
+        );
+        assert!(!report.applied);
+        assert!(report.changed_items().is_empty());
+
+        // Conflicts abort the import unless overwrite is set
+        let err = library
+            .import_harness(source(), &HarnessImportOptions::default(), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("command/deploy.md"));
+        assert!(!library_dir.path().join("skill/lint").exists());
+
+        let overwrite = HarnessImportOptions {
+            overwrite: true,
+            ..Default::default()
+        };
+        let report = library
+            .import_harness(source(), &overwrite, None)
+            .await
+            .unwrap();
+        assert!(report.applied);
+        assert_eq!(report.changed_items().len(), 5);
+
+        let skill = library.get_skill("lint").await.unwrap();
+        assert!(skill.content.starts_with("---\nname: lint\n"));
+        assert!(library_dir.path().join("skill/lint/refs/rules.md").exists());
+        assert_eq!(
+            library.get_command("deploy").await.unwrap().content,
+            "Deploy\n"
+        );
+        assert_eq!(
+            library
+                .get_library_agent("reviewer")
+                .await
+                .unwrap()
+                .description,
+            Some("Review".to_string())
+        );
+        assert_eq!(
+            library
+                .get_config_profile_file("default", ".claudecode/CLAUDE.md")
+                .await
+                .unwrap(),
+            "# Project\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn converts_claude_agent_tools() {
+        let project = tempfile::tempdir().unwrap();
+        let root = project.path();
+        write(
+            root,
+            ".claude/agents/reviewer.md",
+            "---\ndescription: Review: carefully\ntools: Read, Grep, mcp__github__search\n---\nBody\n",
+        );
+
+        let library_dir = tempfile::tempdir().unwrap();
+        let library = LibraryStore::with_test_store(library_dir.path().to_path_buf()).await;
+        let source = || HarnessImportSource::Path {
+            path: root.to_path_buf(),
+        };
+        library
+            .import_harness(source(), &HarnessImportOptions::default(), None)
+            .await
+            .unwrap();
+
+        let agent = library.get_library_agent("reviewer").await.unwrap();
+        assert!(agent
+            .content
+            .contains("description: \"Review: carefully\"\n"));
+        assert!(agent.content.ends_with("---\nBody\n"));
+        let mut tools: Vec<(&str, bool)> = agent
+            .tools
+            .iter()
+            .map(|(name, enabled)| (name.as_str(), *enabled))
+            .collect();
+        tools.sort();
+        assert_eq!(
+            tools,
+            vec![
+                ("*", false),
+                ("github_search", true),
+                ("grep", true),
+                ("read", true)
+            ]
+        );
+
+        // Tools that have no OpenCode equivalent are reported and block the import
+        write(
+            root,
+            ".claude/agents/deployer.md",
+            "---\ntools: Bash(git:*)\n---\n",
+        );
+        let dry_run = HarnessImportOptions {
+            dry_run: true,
+            overwrite: true,
+            ..Default::default()
+        };
+        let report = library
+            .import_harness(source(), &dry_run, None)
+            .await
+            .unwrap();
+        let deployer = report
+            .items
+            .iter()
+            .find(|item| item.target == "agent/deployer.md")
+            .unwrap();
+        assert!(deployer.error.as_deref().unwrap().contains("Bash(git:*)"));
+
+        let overwrite = HarnessImportOptions {
+            overwrite: true,
+            ..Default::default()
+        };
+        let err = library
+            .import_harness(source(), &overwrite, None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("deployer.md"));
+        assert!(library.get_library_agent("deployer").await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn skips_symlinked_artifacts() {
+        let host = tempfile::tempdir().unwrap();
+        write(host.path(), "id_rsa", "PRIVATE KEY\n");
+        write(host.path(), ".claude/commands/secret.md", "Host command\n");
+        write(host.path(), ".claude/CLAUDE.md", "Host memory\n");
+
+        let project = tempfile::tempdir().unwrap();
+        let root = project.path();
+        write(root, ".opencode/command/deploy.md", "Deploy\n");
+        std::os::unix::fs::symlink(host.path().join("id_rsa"), root.join("CLAUDE.md")).unwrap();
+        std::os::unix::fs::symlink(host.path().join(".claude"), root.join(".claude")).unwrap();
+        std::os::unix::fs::symlink(
+            host.path().join("id_rsa"),
+            root.join(".opencode/command/key.md"),
+        )
+        .unwrap();
+
+        let library_dir = tempfile::tempdir().unwrap();
+        let library = LibraryStore::with_test_store(library_dir.path().to_path_buf()).await;
+        let report = library
+            .import_harness(
+                HarnessImportSource::Path {
+                    path: root.to_path_buf(),
+                },
+                &HarnessImportOptions::default(),
+                None,
+            )
+            .await
+            .unwrap();
+        let targets: Vec<&str> = report
+            .items
+            .iter()
+            .map(|item| item.target.as_str())
+            .collect();
+        assert_eq!(targets, vec!["command/deploy.md"]);
+        assert!(library.get_command("secret").await.is_err());
+        assert!(library.get_command("key").await.is_err());
+        assert!(library
+            .get_config_profile_file("default", ".claudecode/CLAUDE.md")
+            .await
+            .is_err());
+
+        // Artifacts read through a symlinked directory are rejected outright
+        assert!(resolve_within(root, ".claude/commands/secret.md")
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn extracts_archive_without_single_root_folder() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = zip::ZipWriter::new(std::io::Cursor::new(&mut buf));
+            let options = zip::write::SimpleFileOptions::default();
+            for path in ["repo-main/.claude/commands/a.md", "repo-main/CLAUDE.md"] {
+                writer.start_file(path, options).unwrap();
+                std::io::Write::write_all(&mut writer, b"x").unwrap();
+            }
+            writer.finish().unwrap();
+        }
+
+        let dest = tempfile::tempdir().unwrap();
+        extract_archive(&buf, dest.path()).unwrap();
+        assert!(dest.path().join(".claude/commands/a.md").exists());
+        assert!(dest.path().join("CLAUDE.md").exists());
+    }
+}
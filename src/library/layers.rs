@@ -0,0 +1,467 @@
+//! Library overlay layers.
+//!
+//! The primary library (the `base` layer) can be overlaid with additional git
+//! repositories, e.g. a shared org library as base with team and personal
+//! libraries on top. Skills, commands, agents, MCPs, init scripts, workspace
+//! templates and config profiles resolve through the stack: an item in a later
+//! layer shadows a same-named item in an earlier one.
+//!
+//! Each layer is its own `LibraryStore`, so writes and git operations (status,
+//! sync, commit, push) apply to one layer at a time. Layers are cloned into
+//! `library-layers/<name>` next to the primary library.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    git, LibraryError, LibraryStore, McpServer, AGENT_DIR, COMMAND_DIR, CONFIGS_DIR,
+    INIT_SCRIPT_DIR, SKILL_DIR, WORKSPACE_TEMPLATE_DIR,
+};
+
+/// Name of the primary library layer.
+pub const BASE_LAYER: &str = "base";
+
+/// Relative path of the MCP server definitions in a layer.
+pub(super) const MCP_SERVERS_FILE: &str = "mcp/servers.json";
+
+/// How long a layer that failed to clone is left out before cloning it again.
+const LAYER_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// An overlay layer stacked on top of the primary library.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryLayerConfig {
+    /// Layer name (e.g. "team", "personal")
+    pub name: String,
+    /// Git remote URL
+    pub remote: String,
+}
+
+/// An overlay layer that could not be cloned and is left out of the stack.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedLayer {
+    /// Layer name
+    pub name: String,
+    /// Git remote URL
+    pub remote: String,
+    /// Why the clone failed
+    pub error: String,
+    /// When the clone failed
+    pub failed_at: DateTime<Utc>,
+}
+
+/// Validate overlay layer configs (names must be unique and not `base`).
+pub fn validate_layer_configs(layers: &[LibraryLayerConfig]) -> Result<()> {
+    let mut seen = HashSet::new();
+    for layer in layers {
+        LibraryStore::validate_name(&layer.name)?;
+        if layer.name == BASE_LAYER {
+            anyhow::bail!("Layer name '{}' is reserved", BASE_LAYER);
+        }
+        if layer.remote.trim().is_empty() {
+            anyhow::bail!("Layer '{}' has no remote", layer.name);
+        }
+        if !seen.insert(layer.name.as_str()) {
+            anyhow::bail!("Duplicate layer name '{}'", layer.name);
+        }
+    }
+    Ok(())
+}
+
+/// Directory holding the overlay layer clones for a library.
+pub fn layers_root(library_path: &Path) -> PathBuf {
+    library_path.with_file_name("library-layers")
+}
+
+/// Kind of library item resolved through the overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayeredItemKind {
+    Skill,
+    Command,
+    Agent,
+    Mcp,
+    InitScript,
+    WorkspaceTemplate,
+    ConfigProfile,
+}
+
+impl LayeredItemKind {
+    /// Path of the item relative to a layer root.
+    ///
+    /// MCP servers share one file per layer, so `name` is ignored for them.
+    pub fn relative_path(self, name: &str) -> String {
+        match self {
+            Self::Skill => format!("{}/{}", SKILL_DIR, name),
+            Self::Command => format!("{}/{}.md", COMMAND_DIR, name),
+            Self::Agent => format!("{}/{}.md", AGENT_DIR, name),
+            Self::Mcp => MCP_SERVERS_FILE.to_string(),
+            Self::InitScript => format!("{}/{}", INIT_SCRIPT_DIR, name),
+            Self::WorkspaceTemplate => format!("{}/{}.json", WORKSPACE_TEMPLATE_DIR, name),
+            Self::ConfigProfile => format!("{}/{}", CONFIGS_DIR, name),
+        }
+    }
+}
+
+/// Where a library item comes from.
+#[derive(Debug, Clone, Serialize)]
+pub struct LayeredItem {
+    pub kind: LayeredItemKind,
+    pub name: String,
+    /// Layer providing the effective item
+    pub layer: String,
+    /// Lower layers whose same-named item is shadowed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub shadowed: Vec<String>,
+}
+
+/// Merge per-layer item lists (lowest layer first), sorted by name.
+/// Items of later layers shadow same-named items of earlier ones.
+pub(super) fn overlay_by_name<T>(per_layer: Vec<Vec<T>>, name: impl Fn(&T) -> &str) -> Vec<T> {
+    let mut merged = BTreeMap::new();
+    for items in per_layer {
+        for item in items {
+            merged.insert(name(&item).to_string(), item);
+        }
+    }
+    merged.into_values().collect()
+}
+
+impl LibraryStore {
+    /// Stack overlay layers on top of this library, cloning them if needed.
+    ///
+    /// Layers that cannot be cloned are skipped with a warning so the rest of
+    /// the library stays usable; they are listed in [`Self::failed_layers`].
+    pub async fn with_layers(mut self, configs: &[LibraryLayerConfig]) -> Result<Self> {
+        validate_layer_configs(configs)?;
+        self.configured_layers = configs.to_vec();
+        let root = layers_root(&self.path);
+
+        for config in configs {
+            let path = root.join(&config.name);
+            let cloned = async {
+                git::clone_if_needed(&path, &config.remote).await?;
+                git::ensure_remote(&path, &config.remote).await
+            }
+            .await;
+            if let Err(e) = cloned {
+                tracing::warn!(
+                    layer = %config.name,
+                    remote = %config.remote,
+                    error = %e,
+                    "Failed to initialize library layer, skipping it"
+                );
+                self.failed_layers.push(FailedLayer {
+                    name: config.name.clone(),
+                    remote: config.remote.clone(),
+                    error: e.to_string(),
+                    failed_at: Utc::now(),
+                });
+                continue;
+            }
+            self.layers.push(LibraryStore {
+                path,
+                remote: config.remote.clone(),
+                name: config.name.clone(),
+                layers: Vec::new(),
+                configured_layers: Vec::new(),
+                failed_layers: Vec::new(),
+                secret_scan: self.secret_scan,
+                registry: self.registry.clone(),
+            });
+        }
+
+        Ok(self)
+    }
+
+    /// Name of this layer.
+    pub fn layer_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Configs of the overlay layers stacked on this library.
+    pub fn layer_configs(&self) -> Vec<LibraryLayerConfig> {
+        self.layers
+            .iter()
+            .map(|layer| LibraryLayerConfig {
+                name: layer.name.clone(),
+                remote: layer.remote.clone(),
+            })
+            .collect()
+    }
+
+    /// Configs of the overlay layers requested for this library, including
+    /// layers that were skipped because they could not be cloned.
+    pub fn configured_layers(&self) -> &[LibraryLayerConfig] {
+        &self.configured_layers
+    }
+
+    /// Overlay layers that could not be cloned.
+    pub fn failed_layers(&self) -> &[FailedLayer] {
+        &self.failed_layers
+    }
+
+    /// Whether a layer failed to clone long enough ago to try again.
+    pub fn layer_retry_due(&self) -> bool {
+        let now = Utc::now();
+        self.failed_layers.iter().any(|layer| {
+            (now - layer.failed_at)
+                .to_std()
+                .is_ok_and(|age| age >= LAYER_RETRY_AFTER)
+        })
+    }
+
+    /// All layers, lowest precedence (this library) first.
+    pub fn layer_stack(&self) -> Vec<&LibraryStore> {
+        std::iter::once(self).chain(self.layers.iter()).collect()
+    }
+
+    /// Look up a layer by name.
+    pub fn layer(&self, name: &str) -> Option<&LibraryStore> {
+        self.layer_stack()
+            .into_iter()
+            .find(|layer| layer.name == name)
+    }
+
+    /// Topmost layer containing `relative_path`, falling back to this library.
+    pub fn layer_for(&self, relative_path: &str) -> &LibraryStore {
+        self.layers
+            .iter()
+            .rev()
+            .find(|layer| layer.path.join(relative_path).exists())
+            .unwrap_or(self)
+    }
+
+    /// Resolve a path for reading through the overlay.
+    pub(super) fn read_path(&self, relative_path: &str) -> PathBuf {
+        self.layer_for(relative_path).path.join(relative_path)
+    }
+
+    /// Layer a write to an item should go to.
+    ///
+    /// An explicit layer name wins; otherwise the layer currently providing the
+    /// item is edited in place, and new items go to this library.
+    pub fn write_layer(
+        &self,
+        layer: Option<&str>,
+        kind: LayeredItemKind,
+        name: &str,
+    ) -> Result<&LibraryStore> {
+        match layer {
+            Some(layer_name) => self.layer(layer_name).ok_or_else(|| {
+                LibraryError::NotFound(format!("Library layer not found: {}", layer_name)).into()
+            }),
+            None => Ok(self.layer_for(&kind.relative_path(name))),
+        }
+    }
+
+    /// Save the effective MCP server set, writing only what `layer_name` adds.
+    ///
+    /// Entries identical to what lower layers already provide, and entries
+    /// owned by higher layers, are left out so they are not copied around.
+    pub async fn save_layer_mcp_servers(
+        &self,
+        layer_name: &str,
+        servers: &HashMap<String, McpServer>,
+    ) -> Result<()> {
+        let stack = self.layer_stack();
+        let index = stack
+            .iter()
+            .position(|layer| layer.name == layer_name)
+            .ok_or_else(|| anyhow::anyhow!("Library layer not found: {}", layer_name))?;
+
+        let mut below = HashMap::new();
+        for layer in &stack[..index] {
+            below.extend(layer.get_layer_mcp_servers().await?);
+        }
+        let mut above = HashSet::new();
+        for layer in &stack[index + 1..] {
+            above.extend(layer.get_layer_mcp_servers().await?.into_keys());
+        }
+
+        let own: HashMap<String, McpServer> = servers
+            .iter()
+            .filter(|(name, _)| !above.contains(*name))
+            .filter(|(name, server)| match below.get(*name) {
+                Some(existing) => {
+                    serde_json::to_value(existing).ok() != serde_json::to_value(server).ok()
+                }
+                None => true,
+            })
+            .map(|(name, server)| (name.clone(), server.clone()))
+            .collect();
+
+        stack[index].save_mcp_servers(&own).await
+    }
+
+    /// Report which layer every item comes from and what it shadows.
+    pub async fn layered_items(&self) -> Result<Vec<LayeredItem>> {
+        let mut found: BTreeMap<(LayeredItemKind, String), Vec<String>> = BTreeMap::new();
+
+        for layer in self.layer_stack() {
+            let mut names: Vec<(LayeredItemKind, String)> = Vec::new();
+            names.extend(
+                layer
+                    .list_layer_skills()
+                    .await?
+                    .into_iter()
+                    .map(|i| (LayeredItemKind::Skill, i.name)),
+            );
+            names.extend(
+                layer
+                    .list_layer_commands()
+                    .await?
+                    .into_iter()
+                    .map(|i| (LayeredItemKind::Command, i.name)),
+            );
+            names.extend(
+                layer
+                    .list_layer_library_agents()
+                    .await?
+                    .into_iter()
+                    .map(|i| (LayeredItemKind::Agent, i.name)),
+            );
+            names.extend(
+                layer
+                    .get_layer_mcp_servers()
+                    .await?
+                    .into_keys()
+                    .map(|name| (LayeredItemKind::Mcp, name)),
+            );
+            names.extend(
+                layer
+                    .list_layer_init_scripts()
+                    .await?
+                    .into_iter()
+                    .map(|i| (LayeredItemKind::InitScript, i.name)),
+            );
+            names.extend(
+                layer
+                    .list_layer_workspace_templates()
+                    .await?
+                    .into_iter()
+                    .map(|i| (LayeredItemKind::WorkspaceTemplate, i.name)),
+            );
+            names.extend(
+                layer
+                    .list_layer_config_profiles()
+                    .await?
+                    .into_iter()
+                    .map(|i| (LayeredItemKind::ConfigProfile, i.name)),
+            );
+
+            for key in names {
+                found.entry(key).or_default().push(layer.name.clone());
+            }
+        }
+
+        Ok(found
+            .into_iter()
+            .map(|((kind, name), mut layers)| {
+                let layer = layers.pop().unwrap_or_else(|| BASE_LAYER.to_string());
+                layers.reverse();
+                LayeredItem {
+                    kind,
+                    name,
+                    layer,
+                    shadowed: layers,
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn store(path: &Path, name: &str) -> LibraryStore {
+        let mut store = LibraryStore::with_test_store(path.to_path_buf()).await;
+        store.name = name.to_string();
+        store
+    }
+
+    #[tokio::test]
+    async fn later_layers_shadow_earlier_ones() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base_path = tmp.path().join("library");
+        let team_path = tmp.path().join("library-layers/team");
+
+        let mut base = store(&base_path, BASE_LAYER).await;
+        let team = store(&team_path, "team").await;
+        base.save_skill("shared", "---\ndescription: org\n---\n")
+            .await
+            .unwrap();
+        base.save_skill("org-only", "---\ndescription: org only\n---\n")
+            .await
+            .unwrap();
+        team.save_skill("shared", "---\ndescription: team\n---\n")
+            .await
+            .unwrap();
+        base.layers.push(team);
+
+        let skills = base.list_skills().await.unwrap();
+        let names: Vec<_> = skills.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["org-only", "shared"]);
+        assert_eq!(skills[1].description.as_deref(), Some("team"));
+
+        let skill = base.get_skill("shared").await.unwrap();
+        assert!(skill.content.contains("team"));
+
+        let items = base.layered_items().await.unwrap();
+        let shared = items.iter().find(|i| i.name == "shared").unwrap();
+        assert_eq!(shared.layer, "team");
+        assert_eq!(shared.shadowed, vec![BASE_LAYER.to_string()]);
+
+        // Edits go to the providing layer unless a layer is given
+        let target = base
+            .write_layer(None, LayeredItemKind::Skill, "shared")
+            .unwrap();
+        assert_eq!(target.layer_name(), "team");
+        let target = base
+            .write_layer(None, LayeredItemKind::Skill, "new-skill")
+            .unwrap();
+        assert_eq!(target.layer_name(), BASE_LAYER);
+        assert!(base
+            .write_layer(Some("missing"), LayeredItemKind::Skill, "x")
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn unreachable_layers_are_skipped_but_recorded() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = store(&tmp.path().join("library"), BASE_LAYER).await;
+        let configs = vec![LibraryLayerConfig {
+            name: "team".to_string(),
+            remote: format!("file://{}", tmp.path().join("missing.git").display()),
+        }];
+
+        let mut base = base.with_layers(&configs).await.unwrap();
+        assert!(base.layer_configs().is_empty());
+        assert!(base.layer("team").is_none());
+        // The store still matches the settings, so it is not rebuilt per request
+        assert_eq!(base.configured_layers(), configs.as_slice());
+        assert_eq!(base.failed_layers().len(), 1);
+        assert_eq!(base.failed_layers()[0].name, "team");
+        // ...until the clone is due to be retried
+        assert!(!base.layer_retry_due());
+        base.failed_layers[0].failed_at -= chrono::Duration::seconds(301);
+        assert!(base.layer_retry_due());
+    }
+
+    #[test]
+    fn validates_layer_configs() {
+        let layer = |name: &str| LibraryLayerConfig {
+            name: name.to_string(),
+            remote: "git@example.com:org/lib.git".to_string(),
+        };
+        assert!(validate_layer_configs(&[layer("team"), layer("personal")]).is_ok());
+        assert!(validate_layer_configs(&[layer("base")]).is_err());
+        assert!(validate_layer_configs(&[layer("team"), layer("team")]).is_err());
+        assert!(validate_layer_configs(&[layer("../x")]).is_err());
+    }
+}
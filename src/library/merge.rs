@@ -0,0 +1,227 @@
+//! Three-way merge of diverged library history.
+//!
+//! Instead of discarding one side with force pull / force push, the remote
+//! branch is merged into the local one. Conflicted files are exposed with
+//! their base, ours and theirs versions, resolved one by one and the merge is
+//! then completed (or aborted).
+
+use anyhow::Result;
+
+use super::secret_scan::ScanScope;
+use super::{git, GitAuthor, LibraryError, LibraryStore, MergeStatus};
+
+impl LibraryStore {
+    /// Current merge state, including conflicted files.
+    pub async fn merge_status(&self) -> Result<MergeStatus> {
+        let in_progress = git::merge_in_progress(&self.path).await;
+        let mut conflicts = Vec::new();
+        if in_progress {
+            for file in git::conflicted_files(&self.path).await? {
+                conflicts.push(git::conflict_file(&self.path, &file).await?);
+            }
+        }
+        Ok(MergeStatus {
+            in_progress,
+            conflicts,
+        })
+    }
+
+    /// Merge the remote branch into the local one.
+    ///
+    /// Returns the merge state: when `in_progress` is true the conflicts must be
+    /// resolved with [`resolve_conflict`](Self::resolve_conflict) before
+    /// [`complete_merge`](Self::complete_merge).
+    pub async fn merge(&self, author: Option<&GitAuthor>) -> Result<MergeStatus> {
+        match git::merge_remote(&self.path, author).await? {
+            git::MergeOutcome::Merged => {
+                self.encrypt_all_skill_files().await?;
+                Ok(MergeStatus {
+                    in_progress: false,
+                    conflicts: Vec::new(),
+                })
+            }
+            git::MergeOutcome::Conflicts(files) => {
+                tracing::info!(
+                    path = %self.path.display(),
+                    conflicts = files.len(),
+                    "Library merge stopped on conflicts"
+                );
+                self.merge_status().await
+            }
+        }
+    }
+
+    /// Resolve one conflicted file with `content`, or delete it with `None`.
+    pub async fn resolve_conflict(&self, file: &str, content: Option<&str>) -> Result<MergeStatus> {
+        if file.is_empty()
+            || file.starts_with('/')
+            || file.split('/').any(|part| part == ".." || part == ".git")
+        {
+            anyhow::bail!(LibraryError::Invalid(format!(
+                "Invalid file path: {}",
+                file
+            )));
+        }
+        git::resolve_file(&self.path, file, content).await?;
+        self.merge_status().await
+    }
+
+    /// Fail while a merge is in progress; commit, push and sync must wait
+    /// until it is completed or aborted.
+    pub(super) async fn ensure_no_merge(&self) -> Result<()> {
+        if git::merge_in_progress(&self.path).await {
+            anyhow::bail!(LibraryError::Conflict(
+                "A merge is in progress; complete or abort it first".to_string()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Commit the merge once all conflicts are resolved.
+    ///
+    /// Skill files are encrypted and the merge is scanned for plaintext
+    /// secrets before it is committed, like a regular commit.
+    pub async fn complete_merge(
+        &self,
+        message: Option<&str>,
+        author: Option<&GitAuthor>,
+    ) -> Result<()> {
+        if !git::merge_in_progress(&self.path).await {
+            anyhow::bail!(LibraryError::Conflict("No merge in progress".to_string()));
+        }
+        let remaining = git::conflicted_files(&self.path).await?;
+        if !remaining.is_empty() {
+            anyhow::bail!(LibraryError::Conflict(format!(
+                "Unresolved conflicts remain: {}",
+                remaining.join(", ")
+            )));
+        }
+        self.encrypt_all_skill_files().await?;
+        self.enforce_secret_scan(ScanScope::Uncommitted).await?;
+        git::complete_merge(&self.path, message, author).await
+    }
+
+    /// Abort the merge and restore the pre-merge state.
+    pub async fn abort_merge(&self) -> Result<()> {
+        git::abort_merge(&self.path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use tokio::process::Command;
+
+    async fn git(dir: &Path, args: &[&str]) {
+        let output = Command::new("git")
+            .current_dir(dir)
+            .args(args)
+            .output()
+            .await
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    async fn clone(remote: &Path, path: &Path) -> LibraryStore {
+        let store = LibraryStore::new(path.to_path_buf(), &remote.to_string_lossy())
+            .await
+            .unwrap();
+        git(path, &["config", "user.name", "Test"]).await;
+        git(path, &["config", "user.email", "test@example.com"]).await;
+        store
+    }
+
+    #[tokio::test]
+    async fn merges_diverged_history_with_conflict_resolution() {
+        let tmp = tempfile::tempdir().unwrap();
+        let remote = tmp.path().join("remote.git");
+        std::fs::create_dir_all(&remote).unwrap();
+        git(&remote, &["init", "-q", "--bare", "-b", "main"]).await;
+
+        // Seed the remote through a first clone
+        let a = clone(&remote, &tmp.path().join("a")).await;
+        git(a.path(), &["checkout", "-q", "-b", "main"]).await;
+        a.save_command("shared", "---\ndescription: shared\n---\nbase\n")
+            .await
+            .unwrap();
+        a.commit("Seed", None).await.unwrap();
+        git(a.path(), &["push", "-q", "-u", "origin", "main"]).await;
+
+        let b = clone(&remote, &tmp.path().join("b")).await;
+
+        // Both sides edit the same command and add different skills
+        a.save_command("shared", "---\ndescription: shared\n---\nfrom a\n")
+            .await
+            .unwrap();
+        a.save_skill("only-a", "---\ndescription: a\n---\n")
+            .await
+            .unwrap();
+        a.commit("Edit on a", None).await.unwrap();
+        a.push().await.unwrap();
+
+        b.save_command("shared", "---\ndescription: shared\n---\nfrom b\n")
+            .await
+            .unwrap();
+        b.save_skill("only-b", "---\ndescription: b\n---\n")
+            .await
+            .unwrap();
+        b.commit("Edit on b", None).await.unwrap();
+        assert!(b
+            .sync()
+            .await
+            .unwrap_err()
+            .to_string()
+            .starts_with("DIVERGED_HISTORY"));
+
+        let author = GitAuthor::new(Some("Bob".into()), Some("bob@example.com".into()));
+        let status = b.merge(Some(&author)).await.unwrap();
+        assert!(status.in_progress);
+        assert_eq!(status.conflicts.len(), 1);
+        let conflict = &status.conflicts[0];
+        assert_eq!(conflict.path, "command/shared.md");
+        assert!(conflict.base.as_deref().unwrap().contains("base"));
+        assert!(conflict.ours.as_deref().unwrap().contains("from b"));
+        assert!(conflict.theirs.as_deref().unwrap().contains("from a"));
+        assert!(conflict.merged.as_deref().unwrap().contains("<<<<<<<"));
+
+        // Completing with conflicts left fails
+        assert!(b.complete_merge(None, Some(&author)).await.is_err());
+        // Commit, push and sync wait for the merge
+        assert!(b.commit("Mid-merge", None).await.is_err());
+        assert!(b.push().await.is_err());
+        assert!(b.sync().await.is_err());
+        assert!(b.merge_status().await.unwrap().in_progress);
+
+        let status = b
+            .resolve_conflict(
+                "command/shared.md",
+                Some("---\ndescription: shared\n---\nfrom a and b\n"),
+            )
+            .await
+            .unwrap();
+        assert!(status.conflicts.is_empty());
+        b.complete_merge(None, Some(&author)).await.unwrap();
+        assert!(!b.merge_status().await.unwrap().in_progress);
+        assert!(b.status().await.unwrap().clean);
+
+        let names: Vec<_> = b
+            .list_skills()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["only-a", "only-b"]);
+        let command = b.get_command("shared").await.unwrap();
+        assert!(command.content.contains("from a and b"));
+
+        // The merged history can be pushed without force
+        b.push().await.unwrap();
+    }
+}
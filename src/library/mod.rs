@@ -13,10 +13,21 @@
 //!   - `.ampcode/` - Amp settings (settings.json)
 //!   - `.sandboxed-sh/` - Sandboxed config (config.json)
 
+pub mod changes;
 pub mod env_crypto;
+mod error;
 mod git;
+pub mod harness_import;
+pub mod history;
+pub mod layers;
+mod merge;
 pub mod rename;
+pub mod secret_scan;
+pub mod skills_lock;
+pub mod skills_mirror;
+mod template_inheritance;
 pub mod types;
+pub mod validate;
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
@@ -24,13 +35,25 @@ use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
+use crate::skills_registry::RegistrySource;
+
+pub use changes::LibraryItemRef;
+pub use error::LibraryError;
 pub use git::GitAuthor;
+pub use harness_import::{HarnessImportOptions, HarnessImportReport, HarnessImportSource};
+pub use layers::{FailedLayer, LayeredItem, LayeredItemKind, LibraryLayerConfig, BASE_LAYER};
+pub use secret_scan::{SecretFinding, SecretScanMode, SecretScanReport};
+pub use skills_lock::{LockInstallReport, LockedSkill, SkillUpdate, SkillsLock};
 pub use types::*;
+pub use validate::{Diagnostic, Severity, ValidationReport};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct WorkspaceTemplateConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<String>,
+    /// Parent template this one is applied on top of (see `template_inheritance`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extends: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -60,6 +83,9 @@ struct WorkspaceTemplateConfig {
     /// Config profile to use for workspaces created from this template.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     config_profile: Option<String>,
+    /// Background services started when the workspace is ready.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    services: Vec<crate::workspace_services::ServiceDefinition>,
 }
 
 // Directory constants (OpenCode-aligned structure)
@@ -72,12 +98,40 @@ const WORKSPACE_TEMPLATE_DIR: &str = "workspace-template";
 const CONFIGS_DIR: &str = "configs";
 const DEFAULT_PROFILE: &str = "default";
 
+/// Sort profiles by name, but put "default" first.
+fn sort_profiles(profiles: &mut [ConfigProfileSummary]) {
+    profiles.sort_by(|a, b| {
+        if a.name == DEFAULT_PROFILE {
+            std::cmp::Ordering::Less
+        } else if b.name == DEFAULT_PROFILE {
+            std::cmp::Ordering::Greater
+        } else {
+            a.name.cmp(&b.name)
+        }
+    });
+}
+
 /// Store for managing the configuration library.
+///
+/// Reads resolve through the overlay layers (see [`layers`]); writes and git
+/// operations act on this store's own repository.
 pub struct LibraryStore {
     /// Path to the library directory
     path: PathBuf,
     /// Git remote URL
     remote: String,
+    /// Layer name (`base` for the primary library)
+    name: String,
+    /// Overlay layers stacked on top of this library, later layers shadow earlier ones
+    layers: Vec<LibraryStore>,
+    /// Overlay layers requested for this library, including ones that failed to clone
+    configured_layers: Vec<LibraryLayerConfig>,
+    /// Overlay layers that failed to clone
+    failed_layers: Vec<FailedLayer>,
+    /// Plaintext secret handling on commit and push
+    secret_scan: SecretScanMode,
+    /// Where registry skills are fetched from
+    registry: RegistrySource,
 }
 
 impl LibraryStore {
@@ -90,6 +144,12 @@ impl LibraryStore {
         Ok(Self {
             path,
             remote: remote.to_string(),
+            name: BASE_LAYER.to_string(),
+            layers: Vec::new(),
+            configured_layers: Vec::new(),
+            failed_layers: Vec::new(),
+            secret_scan: SecretScanMode::default(),
+            registry: RegistrySource::default(),
         })
     }
 
@@ -109,7 +169,9 @@ impl LibraryStore {
 
     /// Get the current git status of the library.
     pub async fn status(&self) -> Result<LibraryStatus> {
-        git::status(&self.path).await
+        let mut status = git::status(&self.path).await?;
+        status.failed_layers = self.failed_layers.clone();
+        Ok(status)
     }
 
     /// Pull latest changes from remote.
@@ -119,11 +181,15 @@ impl LibraryStore {
     /// (e.g., after a force push on the remote). In this case, use `force_sync` to
     /// reset the local branch to match remote.
     pub async fn sync(&self) -> Result<()> {
+        self.ensure_no_merge().await?;
         match git::pull(&self.path).await {
             Ok(()) => {}
             Err(git::PullError::DivergedHistory { message }) => {
-                // Return a specific error that the API layer can detect
-                anyhow::bail!("DIVERGED_HISTORY: {}", message);
+                // The API layer reports this as a conflict
+                anyhow::bail!(LibraryError::Conflict(format!(
+                    "DIVERGED_HISTORY: {}",
+                    message
+                )));
             }
             Err(git::PullError::Other(e)) => {
                 return Err(e);
@@ -150,6 +216,8 @@ impl LibraryStore {
     /// Force push: overwrite remote with local changes.
     /// Use this when you want to keep local changes and discard remote history.
     pub async fn force_push(&self) -> Result<()> {
+        self.enforce_secret_scan(secret_scan::ScanScope::Unpushed)
+            .await?;
         git::force_push(&self.path).await
     }
 
@@ -185,21 +253,69 @@ impl LibraryStore {
     }
 
     /// Commit all changes with a message and optional author.
+    ///
+    /// Fails with `SECRETS_DETECTED` if plaintext secrets are found (see [`secret_scan`]).
     pub async fn commit(&self, message: &str, author: Option<&git::GitAuthor>) -> Result<()> {
+        // `git add -A` would stage conflict markers and finish the merge
+        self.ensure_no_merge().await?;
+        self.enforce_secret_scan(secret_scan::ScanScope::Uncommitted)
+            .await?;
         git::commit(&self.path, message, author).await
     }
 
     /// Push changes to remote.
+    ///
+    /// Fails with `SECRETS_DETECTED` if unpushed commits contain plaintext secrets.
     pub async fn push(&self) -> Result<()> {
+        self.ensure_no_merge().await?;
+        self.enforce_secret_scan(secret_scan::ScanScope::Unpushed)
+            .await?;
         git::push(&self.path).await
     }
 
+    /// Set how plaintext secrets are handled on commit and push, for all layers.
+    pub fn with_secret_scan(mut self, mode: SecretScanMode) -> Self {
+        self.secret_scan = mode;
+        for layer in &mut self.layers {
+            layer.secret_scan = mode;
+        }
+        self
+    }
+
+    /// How plaintext secrets are handled on commit and push.
+    pub fn secret_scan_mode(&self) -> SecretScanMode {
+        self.secret_scan
+    }
+
+    /// Set where registry skills are fetched from, for all layers.
+    pub fn with_skills_registry(mut self, source: RegistrySource) -> Self {
+        for layer in &mut self.layers {
+            layer.registry = source.clone();
+        }
+        self.registry = source;
+        self
+    }
+
+    /// Where registry skills are fetched from.
+    pub fn skills_registry(&self) -> &RegistrySource {
+        &self.registry
+    }
+
     // ─────────────────────────────────────────────────────────────────────────
     // MCP Servers (mcp/servers.json)
     // ─────────────────────────────────────────────────────────────────────────
 
-    /// Get all MCP server definitions.
+    /// Get all MCP server definitions, merged across layers.
     pub async fn get_mcp_servers(&self) -> Result<HashMap<String, McpServer>> {
+        let mut servers = HashMap::new();
+        for layer in self.layer_stack() {
+            servers.extend(layer.get_layer_mcp_servers().await?);
+        }
+        Ok(servers)
+    }
+
+    /// Get the MCP server definitions of this layer only.
+    async fn get_layer_mcp_servers(&self) -> Result<HashMap<String, McpServer>> {
         let path = self.path.join("mcp/servers.json");
 
         if !path.exists() {
@@ -251,6 +367,17 @@ impl LibraryStore {
 
     /// List all skills with their summaries.
     pub async fn list_skills(&self) -> Result<Vec<SkillSummary>> {
+        let mut per_layer = Vec::new();
+        for layer in self.layer_stack() {
+            per_layer.push(layer.list_layer_skills().await?);
+        }
+        Ok(layers::overlay_by_name(per_layer, |item: &SkillSummary| {
+            &item.name
+        }))
+    }
+
+    /// List all skills with their summaries in this layer only.
+    async fn list_layer_skills(&self) -> Result<Vec<SkillSummary>> {
         let skills_dir = self.skills_dir();
 
         if !skills_dir.exists() {
@@ -317,7 +444,7 @@ impl LibraryStore {
     /// to <encrypted>...</encrypted> format for display/editing.
     pub async fn get_skill(&self, name: &str) -> Result<Skill> {
         Self::validate_name(name)?;
-        let skill_dir = self.skills_dir().join(name);
+        let skill_dir = self.read_path(&format!("{}/{}", SKILL_DIR, name));
         let skill_md = skill_dir.join("SKILL.md");
 
         if !skill_md.exists() {
@@ -520,7 +647,7 @@ impl LibraryStore {
         Ok(())
     }
 
-    /// Delete a skill and its directory.
+    /// Delete a skill and its directory, dropping it from `skills.lock`.
     pub async fn delete_skill(&self, name: &str) -> Result<()> {
         Self::validate_name(name)?;
 
@@ -532,7 +659,7 @@ impl LibraryStore {
                 .context("Failed to delete skill directory")?;
         }
 
-        Ok(())
+        self.unlock_skill(name).await
     }
 
     /// Validate that a name doesn't contain path traversal sequences.
@@ -540,17 +667,21 @@ impl LibraryStore {
     fn validate_name(name: &str) -> Result<()> {
         // Reject empty names
         if name.is_empty() {
-            anyhow::bail!("Name cannot be empty");
+            anyhow::bail!(LibraryError::Invalid("Name cannot be empty".to_string()));
         }
 
         // Reject path traversal sequences
         if name.contains("..") || name.contains('/') || name.contains('\\') {
-            anyhow::bail!("Name contains invalid characters");
+            anyhow::bail!(LibraryError::Invalid(
+                "Name contains invalid characters".to_string()
+            ));
         }
 
         // Reject names that start with a dot (hidden files)
         if name.starts_with('.') {
-            anyhow::bail!("Name cannot start with a dot");
+            anyhow::bail!(LibraryError::Invalid(
+                "Name cannot start with a dot".to_string()
+            ));
         }
 
         Ok(())
@@ -597,7 +728,7 @@ impl LibraryStore {
     /// For .md files, encrypted tags are decrypted for display.
     pub async fn get_skill_reference(&self, skill_name: &str, ref_path: &str) -> Result<String> {
         Self::validate_name(skill_name)?;
-        let skill_dir = self.skills_dir().join(skill_name);
+        let skill_dir = self.read_path(&format!("{}/{}", SKILL_DIR, skill_name));
         let file_path = skill_dir.join(ref_path);
 
         // Validate path doesn't escape skill directory
@@ -816,8 +947,14 @@ impl LibraryStore {
     }
 
     /// Recursively copy a directory.
+    ///
+    /// Symlinks are skipped rather than followed, so a repository or archive
+    /// cannot pull host files into the library.
     #[async_recursion::async_recursion]
     async fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
+        if fs::symlink_metadata(src).await?.file_type().is_symlink() {
+            anyhow::bail!("Refusing to copy symlinked directory {}", src.display());
+        }
         fs::create_dir_all(dst).await?;
 
         let mut entries = fs::read_dir(src).await?;
@@ -831,10 +968,12 @@ impl LibraryStore {
                 continue;
             }
 
-            let metadata = fs::metadata(&entry_path).await?;
-            if metadata.is_dir() {
+            let file_type = fs::symlink_metadata(&entry_path).await?.file_type();
+            if file_type.is_symlink() {
+                tracing::warn!(path = %entry_path.display(), "Skipping symlink while copying");
+            } else if file_type.is_dir() {
                 Self::copy_dir_recursive(&entry_path, &dst_path).await?;
-            } else {
+            } else if file_type.is_file() {
                 fs::copy(&entry_path, &dst_path).await?;
             }
         }
@@ -853,6 +992,18 @@ impl LibraryStore {
 
     /// List all commands with their summaries.
     pub async fn list_commands(&self) -> Result<Vec<CommandSummary>> {
+        let mut per_layer = Vec::new();
+        for layer in self.layer_stack() {
+            per_layer.push(layer.list_layer_commands().await?);
+        }
+        Ok(layers::overlay_by_name(
+            per_layer,
+            |item: &CommandSummary| &item.name,
+        ))
+    }
+
+    /// List all commands with their summaries in this layer only.
+    async fn list_layer_commands(&self) -> Result<Vec<CommandSummary>> {
         let commands_dir = self.commands_dir();
 
         if !commands_dir.exists() {
@@ -903,7 +1054,7 @@ impl LibraryStore {
     /// Get a command by name with full content.
     pub async fn get_command(&self, name: &str) -> Result<Command> {
         Self::validate_name(name)?;
-        let command_path = self.commands_dir().join(format!("{}.md", name));
+        let command_path = self.read_path(&format!("{}/{}.md", COMMAND_DIR, name));
 
         if !command_path.exists() {
             anyhow::bail!("Command not found: {}", name);
@@ -1001,6 +1152,18 @@ impl LibraryStore {
 
     /// List all library agents with their summaries.
     pub async fn list_library_agents(&self) -> Result<Vec<LibraryAgentSummary>> {
+        let mut per_layer = Vec::new();
+        for layer in self.layer_stack() {
+            per_layer.push(layer.list_layer_library_agents().await?);
+        }
+        Ok(layers::overlay_by_name(
+            per_layer,
+            |item: &LibraryAgentSummary| &item.name,
+        ))
+    }
+
+    /// List all library agents with their summaries in this layer only.
+    async fn list_layer_library_agents(&self) -> Result<Vec<LibraryAgentSummary>> {
         let agents_dir = self.path.join(AGENT_DIR);
 
         if !agents_dir.exists() {
@@ -1044,10 +1207,35 @@ impl LibraryStore {
         Ok(agents)
     }
 
+    /// Whether a library agent named `name` exists in any layer.
+    pub fn has_library_agent(&self, name: &str) -> bool {
+        Self::validate_name(name).is_ok()
+            && self
+                .read_path(&format!("{}/{}.md", AGENT_DIR, name))
+                .exists()
+    }
+
+    /// Whether the library cloned at `library_path` (or one of its overlay
+    /// layers) defines an agent named `name`, without loading the library.
+    pub fn has_cloned_agent(library_path: &Path, name: &str) -> bool {
+        if Self::validate_name(name).is_err() {
+            return false;
+        }
+        let file = format!("{}/{}.md", AGENT_DIR, name);
+        library_path.join(&file).exists()
+            || std::fs::read_dir(layers::layers_root(library_path))
+                .map(|entries| {
+                    entries
+                        .flatten()
+                        .any(|entry| entry.path().join(&file).exists())
+                })
+                .unwrap_or(false)
+    }
+
     /// Get a library agent by name with full content and parsed metadata.
     pub async fn get_library_agent(&self, name: &str) -> Result<LibraryAgent> {
         Self::validate_name(name)?;
-        let agent_path = self.path.join(AGENT_DIR).join(format!("{}.md", name));
+        let agent_path = self.read_path(&format!("{}/{}.md", AGENT_DIR, name));
 
         if !agent_path.exists() {
             anyhow::bail!("Library agent not found: {}", name);
@@ -1062,6 +1250,7 @@ impl LibraryStore {
         let model = extract_model(&frontmatter);
         let tools = extract_tools(&frontmatter);
         let permissions = extract_permissions(&frontmatter);
+        let mcp_policy = extract_mcp_policy(&frontmatter);
 
         Ok(LibraryAgent {
             name: name.to_string(),
@@ -1071,6 +1260,7 @@ impl LibraryStore {
             model,
             tools,
             permissions,
+            mcp_policy,
         })
     }
 
@@ -1110,6 +1300,18 @@ impl LibraryStore {
 
     /// List all workspace templates with their summaries.
     pub async fn list_workspace_templates(&self) -> Result<Vec<WorkspaceTemplateSummary>> {
+        let mut per_layer = Vec::new();
+        for layer in self.layer_stack() {
+            per_layer.push(layer.list_layer_workspace_templates().await?);
+        }
+        Ok(layers::overlay_by_name(
+            per_layer,
+            |item: &WorkspaceTemplateSummary| &item.name,
+        ))
+    }
+
+    /// List all workspace templates with their summaries in this layer only.
+    async fn list_layer_workspace_templates(&self) -> Result<Vec<WorkspaceTemplateSummary>> {
         let templates_dir = self.path.join(WORKSPACE_TEMPLATE_DIR);
 
         if !templates_dir.exists() {
@@ -1152,9 +1354,11 @@ impl LibraryStore {
                 .as_ref()
                 .and_then(|c| c.name.clone())
                 .unwrap_or_else(|| name.clone());
+            let extends = config.as_ref().and_then(|c| c.extends.clone());
 
             templates.push(WorkspaceTemplateSummary {
                 name: template_name,
+                extends,
                 description,
                 distro,
                 skills,
@@ -1167,26 +1371,41 @@ impl LibraryStore {
         Ok(templates)
     }
 
-    /// Get a workspace template by name with full content.
+    /// Get a workspace template by name with full content, as stored.
     /// Env vars are decrypted if a PRIVATE_KEY is configured; plaintext values pass through.
+    ///
+    /// Use [`resolve_workspace_template`](Self::resolve_workspace_template) to
+    /// apply the `extends` chain.
     pub async fn get_workspace_template(&self, name: &str) -> Result<WorkspaceTemplate> {
+        let config = self.read_workspace_template_config(name).await?;
+        self.workspace_template_from_config(name, config).await
+    }
+
+    /// Read and parse a workspace template file.
+    async fn read_workspace_template_config(&self, name: &str) -> Result<WorkspaceTemplateConfig> {
         Self::validate_name(name)?;
-        let template_path = self
-            .path
-            .join(WORKSPACE_TEMPLATE_DIR)
-            .join(format!("{}.json", name));
+        let template_path = self.read_path(&format!("{}/{}.json", WORKSPACE_TEMPLATE_DIR, name));
 
         if !template_path.exists() {
-            anyhow::bail!("Workspace template not found: {}", name);
+            anyhow::bail!(LibraryError::NotFound(format!(
+                "Workspace template not found: {}",
+                name
+            )));
         }
 
         let content = fs::read_to_string(&template_path)
             .await
             .context("Failed to read workspace template file")?;
 
-        let config: WorkspaceTemplateConfig =
-            serde_json::from_str(&content).context("Failed to parse workspace template file")?;
+        serde_json::from_str(&content).context("Failed to parse workspace template file")
+    }
 
+    /// Build a template from its config, decrypting env vars.
+    async fn workspace_template_from_config(
+        &self,
+        name: &str,
+        config: WorkspaceTemplateConfig,
+    ) -> Result<WorkspaceTemplate> {
         // Decrypt env vars if we have a key configured (file or env var)
         let has_encrypted = config
             .env_vars
@@ -1250,6 +1469,7 @@ impl LibraryStore {
 
         Ok(WorkspaceTemplate {
             name: config.name.unwrap_or_else(|| name.to_string()),
+            extends: config.extends,
             description: config.description,
             path: format!("{}/{}.json", WORKSPACE_TEMPLATE_DIR, name),
             distro: config.distro,
@@ -1262,6 +1482,7 @@ impl LibraryStore {
             tailscale_mode: config.tailscale_mode,
             mcps: config.mcps,
             config_profile: config.config_profile,
+            services: config.services,
         })
     }
 
@@ -1303,6 +1524,7 @@ impl LibraryStore {
 
         let config = WorkspaceTemplateConfig {
             name: Some(name.to_string()),
+            extends: template.extends.clone(),
             description: template.description.clone(),
             distro: template.distro.clone(),
             skills: template.skills.clone(),
@@ -1314,6 +1536,7 @@ impl LibraryStore {
             tailscale_mode: template.tailscale_mode,
             mcps: template.mcps.clone(),
             config_profile: template.config_profile.clone(),
+            services: template.services.clone(),
         };
 
         let content = serde_json::to_string_pretty(&config)?;
@@ -1347,6 +1570,18 @@ impl LibraryStore {
 
     /// List all init script fragments with their summaries.
     pub async fn list_init_scripts(&self) -> Result<Vec<InitScriptSummary>> {
+        let mut per_layer = Vec::new();
+        for layer in self.layer_stack() {
+            per_layer.push(layer.list_layer_init_scripts().await?);
+        }
+        Ok(layers::overlay_by_name(
+            per_layer,
+            |item: &InitScriptSummary| &item.name,
+        ))
+    }
+
+    /// List all init script fragments with their summaries in this layer only.
+    async fn list_layer_init_scripts(&self) -> Result<Vec<InitScriptSummary>> {
         let init_scripts_dir = self.path.join(INIT_SCRIPT_DIR);
 
         if !init_scripts_dir.exists() {
@@ -1397,7 +1632,7 @@ impl LibraryStore {
     /// Get an init script fragment by name with full content.
     pub async fn get_init_script(&self, name: &str) -> Result<InitScript> {
         Self::validate_name(name)?;
-        let script_dir = self.path.join(INIT_SCRIPT_DIR).join(name);
+        let script_dir = self.read_path(&format!("{}/{}", INIT_SCRIPT_DIR, name));
         let script_sh = script_dir.join("SCRIPT.sh");
 
         if !script_sh.exists() {
@@ -1689,6 +1924,18 @@ impl LibraryStore {
 
     /// List all config profiles.
     pub async fn list_config_profiles(&self) -> Result<Vec<ConfigProfileSummary>> {
+        let mut per_layer = Vec::new();
+        for layer in self.layer_stack() {
+            per_layer.push(layer.list_layer_config_profiles().await?);
+        }
+        let mut items =
+            layers::overlay_by_name(per_layer, |item: &ConfigProfileSummary| &item.name);
+        sort_profiles(&mut items);
+        Ok(items)
+    }
+
+    /// List all config profiles in this layer only.
+    async fn list_layer_config_profiles(&self) -> Result<Vec<ConfigProfileSummary>> {
         let configs_dir = self.path.join(CONFIGS_DIR);
 
         if !configs_dir.exists() {
@@ -1720,16 +1967,7 @@ impl LibraryStore {
             });
         }
 
-        // Sort by name, but put "default" first
-        profiles.sort_by(|a, b| {
-            if a.name == DEFAULT_PROFILE {
-                std::cmp::Ordering::Less
-            } else if b.name == DEFAULT_PROFILE {
-                std::cmp::Ordering::Greater
-            } else {
-                a.name.cmp(&b.name)
-            }
-        });
+        sort_profiles(&mut profiles);
 
         Ok(profiles)
     }
@@ -1739,7 +1977,7 @@ impl LibraryStore {
     pub async fn get_config_profile(&self, name: &str) -> Result<ConfigProfile> {
         Self::validate_name(name)?;
 
-        let profile_dir = self.path.join(CONFIGS_DIR).join(name);
+        let profile_dir = self.read_path(&format!("{}/{}", CONFIGS_DIR, name));
 
         // New paths (dot-prefixed to mirror harness directories)
         let opencode_settings_path = profile_dir.join(".opencode").join("settings.json");
@@ -1969,7 +2207,7 @@ impl LibraryStore {
     ) -> Result<serde_json::Value> {
         Self::validate_name(profile)?;
 
-        let profile_dir = self.path.join(CONFIGS_DIR).join(profile);
+        let profile_dir = self.read_path(&format!("{}/{}", CONFIGS_DIR, profile));
         // Try new path first, then legacy
         let new_path = profile_dir.join(".opencode").join("oh-my-opencode.json");
         let legacy_path = profile_dir.join("opencode").join("oh-my-opencode.json");
@@ -2024,7 +2262,7 @@ impl LibraryStore {
     pub async fn get_sandboxed_config_for_profile(&self, profile: &str) -> Result<SandboxedConfig> {
         Self::validate_name(profile)?;
 
-        let profile_dir = self.path.join(CONFIGS_DIR).join(profile);
+        let profile_dir = self.read_path(&format!("{}/{}", CONFIGS_DIR, profile));
         // Try new path first, then legacy
         let new_path = profile_dir.join(".sandboxed-sh").join("config.json");
         let legacy_path = profile_dir.join("sandboxed").join("config.json");
@@ -2072,7 +2310,7 @@ impl LibraryStore {
     ) -> Result<ClaudeCodeConfig> {
         Self::validate_name(profile)?;
 
-        let profile_dir = self.path.join(CONFIGS_DIR).join(profile);
+        let profile_dir = self.read_path(&format!("{}/{}", CONFIGS_DIR, profile));
         // Try new path first, then legacy
         let new_path = profile_dir.join(".claudecode").join("settings.json");
         let legacy_path = profile_dir.join("claudecode").join("config.json");
@@ -2117,7 +2355,7 @@ impl LibraryStore {
     pub async fn get_ampcode_config_for_profile(&self, profile: &str) -> Result<AmpCodeConfig> {
         Self::validate_name(profile)?;
 
-        let profile_dir = self.path.join(CONFIGS_DIR).join(profile);
+        let profile_dir = self.read_path(&format!("{}/{}", CONFIGS_DIR, profile));
         let path = profile_dir.join(".ampcode").join("settings.json");
 
         if !path.exists() {
@@ -2156,7 +2394,7 @@ impl LibraryStore {
     pub async fn get_config_profile_file(&self, profile: &str, file_path: &str) -> Result<String> {
         Self::validate_name(profile)?;
 
-        let profile_dir = self.path.join(CONFIGS_DIR).join(profile);
+        let profile_dir = self.read_path(&format!("{}/{}", CONFIGS_DIR, profile));
         let path = profile_dir.join(file_path);
 
         if !path.exists() {
@@ -2221,7 +2459,7 @@ impl LibraryStore {
     pub async fn list_config_profile_files(&self, profile: &str) -> Result<Vec<String>> {
         Self::validate_name(profile)?;
 
-        let profile_dir = self.path.join(CONFIGS_DIR).join(profile);
+        let profile_dir = self.read_path(&format!("{}/{}", CONFIGS_DIR, profile));
         if !profile_dir.exists() {
             return Ok(Vec::new());
         }
@@ -2417,10 +2655,16 @@ impl LibraryStore {
     }
 
     #[cfg(test)]
-    async fn with_test_store(path: PathBuf) -> LibraryStore {
+    pub(crate) async fn with_test_store(path: PathBuf) -> LibraryStore {
         LibraryStore {
             path,
             remote: "test-remote".to_string(),
+            name: BASE_LAYER.to_string(),
+            layers: Vec::new(),
+            configured_layers: Vec::new(),
+            failed_layers: Vec::new(),
+            secret_scan: SecretScanMode::default(),
+            registry: RegistrySource::default(),
         }
     }
 }
@@ -2557,6 +2801,32 @@ This is the body."#;
     fn test_validate_name_rejects_empty() {
         assert!(LibraryStore::validate_name("").is_err());
     }
+
+    #[tokio::test]
+    async fn test_copy_dir_skips_symlinks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("src");
+        std::fs::create_dir_all(src.join("sub")).unwrap();
+        std::fs::write(src.join("SKILL.md"), "skill").unwrap();
+        std::fs::write(tmp.path().join("host-secret"), "secret").unwrap();
+        std::os::unix::fs::symlink(tmp.path().join("host-secret"), src.join("leak")).unwrap();
+        std::os::unix::fs::symlink(tmp.path(), src.join("sub/root")).unwrap();
+
+        let dst = tmp.path().join("dst");
+        LibraryStore::copy_dir_recursive(&src, &dst).await.unwrap();
+        assert!(dst.join("SKILL.md").exists());
+        assert!(dst.join("sub").is_dir());
+        assert!(std::fs::symlink_metadata(dst.join("leak")).is_err());
+        assert!(std::fs::symlink_metadata(dst.join("sub/root")).is_err());
+
+        std::os::unix::fs::symlink(&src, tmp.path().join("linked")).unwrap();
+        assert!(LibraryStore::copy_dir_recursive(
+            &tmp.path().join("linked"),
+            &tmp.path().join("x")
+        )
+        .await
+        .is_err());
+    }
 }
 
 #[cfg(test)]
@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use tokio::fs;
 
+use super::template_inheritance::REMOVE_PREFIX;
 use super::types::SandboxedConfig;
 use super::LibraryStore;
 
@@ -46,6 +47,9 @@ pub enum RenameChange {
     RenameFile { from: String, to: String },
     /// Update a reference in a file.
     UpdateReference {
+        /// Overlay layer holding `file`; None for the primary library
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        layer: Option<String>,
         file: String,
         field: String,
         old_value: String,
@@ -97,7 +101,12 @@ impl LibraryStore {
                 // 1. sandboxed/config.json -> hidden_agents, default_agent
                 refs.extend(self.find_agent_refs_in_config(name).await?);
             }
-            ItemType::Command | ItemType::Tool | ItemType::WorkspaceTemplate => {
+            ItemType::WorkspaceTemplate => {
+                // Workspace templates are referenced by:
+                // 1. workspace-template/*.json -> extends
+                refs.extend(self.find_template_refs_in_templates(name).await?);
+            }
+            ItemType::Command | ItemType::Tool => {
                 // These don't have direct cross-references in library files.
                 // Tools are referenced by workspaces (handled at API layer).
             }
@@ -106,38 +115,79 @@ impl LibraryStore {
         Ok(refs)
     }
 
+    /// Workspace templates of every layer, parsed, with the layer holding
+    /// them (None for this library) and their path relative to it.
+    ///
+    /// Templates of overlay layers can refer to items of lower layers, so
+    /// references are looked up in all of them, shadowed templates included.
+    async fn templates_in_layers(
+        &self,
+    ) -> Result<Vec<(Option<String>, String, serde_json::Value)>> {
+        let mut templates = Vec::new();
+        for layer in self.layer_stack() {
+            let templates_dir = layer.path.join("workspace-template");
+            if !templates_dir.exists() {
+                continue;
+            }
+            let layer_name = (!std::ptr::eq(layer, self)).then(|| layer.name.clone());
+
+            let mut entries = fs::read_dir(&templates_dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if !path.extension().map(|e| e == "json").unwrap_or(false) {
+                    continue;
+                }
+                let Ok(content) = fs::read_to_string(&path).await else {
+                    continue;
+                };
+                if let Ok(template) = serde_json::from_str::<serde_json::Value>(&content) {
+                    let rel_path = path
+                        .strip_prefix(&layer.path)
+                        .unwrap_or(&path)
+                        .to_string_lossy()
+                        .to_string();
+                    templates.push((layer_name.clone(), rel_path, template));
+                }
+            }
+        }
+        Ok(templates)
+    }
+
     /// Find references to a skill in workspace templates.
     async fn find_skill_refs_in_templates(&self, skill_name: &str) -> Result<Vec<RenameChange>> {
         let mut refs = Vec::new();
-        let templates_dir = self.path.join("workspace-template");
-
-        if !templates_dir.exists() {
-            return Ok(refs);
+        for (layer, file, template) in self.templates_in_layers().await? {
+            if let Some(skills) = template.get("skills").and_then(|s| s.as_array()) {
+                // Also match `!skill` removals of inherited skills
+                if skills.iter().any(|s| {
+                    s.as_str().map(|s| s.trim_start_matches(REMOVE_PREFIX)) == Some(skill_name)
+                }) {
+                    refs.push(RenameChange::UpdateReference {
+                        layer,
+                        file,
+                        field: "skills".to_string(),
+                        old_value: skill_name.to_string(),
+                        new_value: String::new(), // Will be filled in during rename
+                    });
+                }
+            }
         }
 
-        let mut entries = fs::read_dir(&templates_dir).await?;
-        while let Some(entry) = entries.next_entry().await? {
-            let path = entry.path();
-            if path.extension().map(|e| e == "json").unwrap_or(false) {
-                if let Ok(content) = fs::read_to_string(&path).await {
-                    if let Ok(template) = serde_json::from_str::<serde_json::Value>(&content) {
-                        if let Some(skills) = template.get("skills").and_then(|s| s.as_array()) {
-                            if skills.iter().any(|s| s.as_str() == Some(skill_name)) {
-                                let rel_path = path
-                                    .strip_prefix(&self.path)
-                                    .unwrap_or(&path)
-                                    .to_string_lossy()
-                                    .to_string();
-                                refs.push(RenameChange::UpdateReference {
-                                    file: rel_path,
-                                    field: "skills".to_string(),
-                                    old_value: skill_name.to_string(),
-                                    new_value: String::new(), // Will be filled in during rename
-                                });
-                            }
-                        }
-                    }
-                }
+        Ok(refs)
+    }
+
+    /// Find child templates extending a workspace template.
+    async fn find_template_refs_in_templates(&self, parent: &str) -> Result<Vec<RenameChange>> {
+        let mut refs = Vec::new();
+        for (layer, file, template) in self.templates_in_layers().await? {
+            if template.get("extends").and_then(|e| e.as_str()) == Some(parent) {
+                refs.push(RenameChange::UpdateReference {
+                    layer,
+                    file,
+                    field: "extends".to_string(),
+                    old_value: parent.to_string(),
+                    new_value: String::new(),
+                });
             }
         }
 
@@ -157,6 +207,7 @@ impl LibraryStore {
             if let Ok(config) = serde_json::from_str::<SandboxedConfig>(&content) {
                 if config.hidden_agents.contains(&agent_name.to_string()) {
                     refs.push(RenameChange::UpdateReference {
+                        layer: None,
                         file: "sandboxed/config.json".to_string(),
                         field: "hidden_agents".to_string(),
                         old_value: agent_name.to_string(),
@@ -165,6 +216,7 @@ impl LibraryStore {
                 }
                 if config.default_agent.as_deref() == Some(agent_name) {
                     refs.push(RenameChange::UpdateReference {
+                        layer: None,
                         file: "sandboxed/config.json".to_string(),
                         field: "default_agent".to_string(),
                         old_value: agent_name.to_string(),
@@ -279,6 +331,7 @@ impl LibraryStore {
         // Execute reference updates
         for change in &changes {
             if let RenameChange::UpdateReference {
+                layer,
                 file,
                 field,
                 old_value,
@@ -286,7 +339,7 @@ impl LibraryStore {
             } = change
             {
                 if let Err(e) = self
-                    .update_reference(file, field, old_value, new_value)
+                    .update_reference(layer.as_deref(), file, field, old_value, new_value)
                     .await
                 {
                     warnings.push(format!("Failed to update {}: {}", file, e));
@@ -341,7 +394,7 @@ impl LibraryStore {
     async fn execute_rename(
         &self,
         item_type: ItemType,
-        _old_name: &str,
+        old_name: &str,
         new_name: &str,
         old_path: &Path,
         new_path: &Path,
@@ -366,18 +419,30 @@ impl LibraryStore {
             .await
             .context("Failed to rename file/directory")?;
 
+        // Keep the skills.lock entry of registry skills
+        if item_type == ItemType::Skill {
+            self.rename_locked_skill(old_name, new_name).await?;
+        }
+
         Ok(())
     }
 
-    /// Update a reference in a file.
+    /// Update a reference in a file of `layer` (this library when None).
     async fn update_reference(
         &self,
+        layer: Option<&str>,
         file: &str,
         field: &str,
         old_value: &str,
         new_value: &str,
     ) -> Result<()> {
-        let file_path = self.path.join(file);
+        let root = match layer {
+            Some(name) => self
+                .layer(name)
+                .with_context(|| format!("Library layer not found: {}", name))?,
+            None => self,
+        };
+        let file_path = root.path.join(file);
 
         if file.ends_with(".json") {
             // JSON file (workspace template or sandboxed config)
@@ -387,13 +452,19 @@ impl LibraryStore {
             if field == "skills" || field == "hidden_agents" {
                 // Array field
                 if let Some(arr) = data.get_mut(field).and_then(|a| a.as_array_mut()) {
+                    let removal = format!("{}{}", REMOVE_PREFIX, old_value);
                     for item in arr.iter_mut() {
                         if item.as_str() == Some(old_value) {
                             *item = serde_json::Value::String(new_value.to_string());
+                        } else if item.as_str() == Some(removal.as_str()) {
+                            *item = serde_json::Value::String(format!(
+                                "{}{}",
+                                REMOVE_PREFIX, new_value
+                            ));
                         }
                     }
                 }
-            } else if field == "default_agent" {
+            } else if field == "default_agent" || field == "extends" {
                 // String field
                 if data.get(field).and_then(|v| v.as_str()) == Some(old_value) {
                     data[field] = serde_json::Value::String(new_value.to_string());
@@ -447,3 +518,61 @@ impl LibraryStore {
         Ok(content.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn write_template(root: &Path, name: &str, template: serde_json::Value) {
+        let dir = root.join("workspace-template");
+        fs::create_dir_all(&dir).await.unwrap();
+        fs::write(dir.join(format!("{}.json", name)), template.to_string())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn renaming_a_template_updates_children_in_overlay_layers() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base_path = tmp.path().join("library");
+        let team_path = tmp.path().join("library-layers/team");
+        let mut base = LibraryStore::with_test_store(base_path.clone()).await;
+        let mut team = LibraryStore::with_test_store(team_path.clone()).await;
+        team.name = "team".to_string();
+
+        write_template(&base_path, "parent", serde_json::json!({"name": "parent"})).await;
+        write_template(
+            &base_path,
+            "base-child",
+            serde_json::json!({"name": "base-child", "extends": "parent"}),
+        )
+        .await;
+        write_template(
+            &team_path,
+            "team-child",
+            serde_json::json!({"name": "team-child", "extends": "parent"}),
+        )
+        .await;
+        base.layers.push(team);
+
+        let result = base
+            .rename_item(ItemType::WorkspaceTemplate, "parent", "renamed", false)
+            .await
+            .unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+
+        for path in [
+            base_path.join("workspace-template/base-child.json"),
+            team_path.join("workspace-template/team-child.json"),
+        ] {
+            let template: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(&path).await.unwrap()).unwrap();
+            assert_eq!(template["extends"], "renamed", "{}", path.display());
+        }
+        assert!(result.changes.iter().any(|c| matches!(
+            c,
+            RenameChange::UpdateReference { layer: Some(layer), .. } if layer == "team"
+        )));
+    }
+}
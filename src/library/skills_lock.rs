@@ -0,0 +1,777 @@
+//! `skills.lock`: pinned registry skills and reproducible installs.
+//!
+//! Registry skills are fetched straight from their git repository with a
+//! shallow, sparse checkout (bun is not required). Every install records the
+//! resolved commit and a hash of the skill files in `skills.lock` at the
+//! library root, so that:
+//! - the exact same files can be reinstalled from the lock,
+//! - pending upstream changes can be previewed as a per-skill diff,
+//! - skills are upgraded one at a time.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+
+use super::{git, LibraryStore, Skill, SkillSource};
+use crate::skills_registry::RegistrySource;
+
+/// Lockfile at the library root.
+pub const SKILLS_LOCK_FILE: &str = "skills.lock";
+
+/// Provenance metadata stored next to an installed skill's SKILL.md.
+const SKILL_SOURCE_FILE: &str = ".skill-source.json";
+
+const LOCK_VERSION: u32 = 1;
+
+fn lock_version() -> u32 {
+    LOCK_VERSION
+}
+
+/// Contents of `skills.lock`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SkillsLock {
+    #[serde(default = "lock_version")]
+    pub version: u32,
+    /// Locked skills keyed by their name in the library
+    #[serde(default)]
+    pub skills: BTreeMap<String, LockedSkill>,
+}
+
+impl Default for SkillsLock {
+    fn default() -> Self {
+        Self {
+            version: LOCK_VERSION,
+            skills: BTreeMap::new(),
+        }
+    }
+}
+
+/// A registry skill pinned to a commit.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LockedSkill {
+    /// Repository identifier (e.g., "vercel-labs/agent-skills")
+    pub identifier: String,
+    /// Skill directory within the repository (empty for a root-level skill)
+    pub path: String,
+    /// Resolved commit hash
+    pub commit: String,
+    /// Hash of the skill files at `commit` (see [`content_hash`])
+    pub content_hash: String,
+}
+
+/// Pending upstream change for a locked skill.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkillUpdate {
+    pub name: String,
+    pub identifier: String,
+    pub current_commit: String,
+    pub latest_commit: String,
+    /// Whether the installed files differ from the locked content
+    pub locally_modified: bool,
+    /// Unified diff of the skill directory between the two commits
+    pub diff: String,
+    /// Why the skill could not be checked (e.g. unreachable remote); the
+    /// commit and diff fields are then empty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of installing skills from the lock.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LockInstallReport {
+    /// Skills (re)installed at their locked commit
+    pub installed: Vec<String>,
+    /// Skills already matching the lock
+    pub unchanged: Vec<String>,
+    /// Skills left alone because they have local modifications
+    pub modified: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// A skill checked out from a registry repository.
+struct FetchedSkill {
+    commit: String,
+    path: String,
+    dir: PathBuf,
+}
+
+/// Hash of the files of a skill directory, ignoring git and provenance files.
+///
+/// Reads every file, so the hashing runs on the blocking thread pool.
+pub async fn content_hash(dir: &Path) -> Result<String> {
+    let dir = dir.to_path_buf();
+    tokio::task::spawn_blocking(move || hash_dir(&dir))
+        .await
+        .context("Skill hashing task failed")?
+}
+
+fn hash_dir(dir: &Path) -> Result<String> {
+    let mut files = Vec::new();
+    for entry in walkdir::WalkDir::new(dir)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+    {
+        let entry = entry?;
+        if !entry.file_type().is_file() || entry.file_name() == SKILL_SOURCE_FILE {
+            continue;
+        }
+        let rel = entry
+            .path()
+            .strip_prefix(dir)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        files.push((rel, entry.path().to_path_buf()));
+    }
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for (rel, path) in files {
+        hasher.update(rel.as_bytes());
+        hasher.update([0]);
+        hasher.update(std::fs::read(&path)?);
+        hasher.update([0]);
+    }
+    Ok(format!("sha256:{}", hex::encode(hasher.finalize())))
+}
+
+/// Directories containing a `SKILL.md`, relative to the repository root.
+pub(super) fn skill_dirs(files: &[String]) -> Vec<String> {
+    let mut dirs: Vec<String> = files
+        .iter()
+        .filter_map(|f| {
+            if f == "SKILL.md" {
+                Some(String::new())
+            } else {
+                f.strip_suffix("/SKILL.md").map(str::to_string)
+            }
+        })
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Name of a skill directory (the repository name for a root-level skill).
+pub(super) fn skill_dir_name(identifier: &str, dir: &str) -> String {
+    let last = if dir.is_empty() {
+        identifier.trim_end_matches('/').trim_end_matches(".git")
+    } else {
+        dir
+    };
+    last.rsplit('/').next().unwrap_or(last).to_string()
+}
+
+/// Pick the directory of `skill_name` in a repository.
+fn find_skill_path(identifier: &str, files: &[String], skill_name: Option<&str>) -> Result<String> {
+    let dirs = skill_dirs(files);
+    let mut matches: Vec<&String> = match skill_name {
+        Some(name) => dirs
+            .iter()
+            .filter(|d| skill_dir_name(identifier, d) == name)
+            .collect(),
+        None if dirs.len() == 1 => dirs.iter().collect(),
+        None => {
+            let names: Vec<String> = dirs.iter().map(|d| skill_dir_name(identifier, d)).collect();
+            anyhow::bail!(
+                "Repository {} contains several skills, pick one of: {}",
+                identifier,
+                names.join(", ")
+            );
+        }
+    };
+    // Prefer the shallowest match (e.g. `skills/foo` over `examples/skills/foo`)
+    matches.sort_by_key(|d| d.matches('/').count());
+    matches.first().map(|d| d.to_string()).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Skill {} not found in repository {}",
+            skill_name.unwrap_or_default(),
+            identifier
+        )
+    })
+}
+
+fn validate_repo_path(path: &str) -> Result<()> {
+    if path.starts_with('/') || path.split('/').any(|p| p == ".." || p == ".git") {
+        anyhow::bail!("Invalid skill path in repository: {}", path);
+    }
+    Ok(())
+}
+
+/// Fetch one skill of a registry repository into `work_dir`.
+///
+/// `path` skips the lookup by name; `revision` defaults to the remote HEAD.
+async fn fetch_skill(
+    work_dir: &Path,
+    source: &RegistrySource,
+    identifier: &str,
+    skill_name: Option<&str>,
+    path: Option<&str>,
+    revision: Option<&str>,
+) -> Result<FetchedSkill> {
+    crate::skills_registry::validate_identifier(identifier)?;
+    let url = source.repo_url(identifier);
+    let commit = git::fetch_revision(work_dir, &url, revision).await?;
+    let path = match path {
+        Some(path) => path.to_string(),
+        None => find_skill_path(
+            identifier,
+            &git::list_tree(work_dir, &commit).await?,
+            skill_name,
+        )?,
+    };
+    validate_repo_path(&path)?;
+    git::sparse_checkout(work_dir, &commit, &path).await?;
+
+    let dir = if path.is_empty() {
+        work_dir.to_path_buf()
+    } else {
+        work_dir.join(&path)
+    };
+    // Symlinked path components could point anywhere on the host
+    let inside = match (dir.canonicalize(), work_dir.canonicalize()) {
+        (Ok(dir), Ok(root)) => dir.starts_with(root),
+        _ => false,
+    };
+    if !inside || !dir.join("SKILL.md").is_file() {
+        anyhow::bail!("No SKILL.md at '{}' in {} at {}", path, identifier, commit);
+    }
+    Ok(FetchedSkill { commit, path, dir })
+}
+
+/// Temporary checkout directory for a registry fetch, outside the library so
+/// that commits of the library never pick it up.
+fn registry_work_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("sandboxed-registry-{}", uuid::Uuid::new_v4()))
+}
+
+/// Skill directory names available in a registry repository.
+pub async fn list_repo_skills(source: &RegistrySource, identifier: &str) -> Result<Vec<String>> {
+    crate::skills_registry::validate_identifier(identifier)?;
+    let work_dir = registry_work_dir();
+    let result = async {
+        let url = source.repo_url(identifier);
+        let commit = git::fetch_revision(&work_dir, &url, None).await?;
+        let files = git::list_tree(&work_dir, &commit).await?;
+        Ok(skill_dirs(&files)
+            .iter()
+            .map(|d| skill_dir_name(identifier, d))
+            .collect())
+    }
+    .await;
+    let _ = fs::remove_dir_all(&work_dir).await;
+    result
+}
+
+impl LibraryStore {
+    fn skills_lock_path(&self) -> PathBuf {
+        self.path.join(SKILLS_LOCK_FILE)
+    }
+
+    /// Read `skills.lock` (empty if the file does not exist).
+    pub async fn skills_lock(&self) -> Result<SkillsLock> {
+        let path = self.skills_lock_path();
+        if !path.exists() {
+            return Ok(SkillsLock::default());
+        }
+        let content = fs::read_to_string(&path)
+            .await
+            .context("Failed to read skills.lock")?;
+        serde_json::from_str(&content).context("Failed to parse skills.lock")
+    }
+
+    async fn save_skills_lock(&self, lock: &SkillsLock) -> Result<()> {
+        let content = serde_json::to_string_pretty(lock)?;
+        fs::write(self.skills_lock_path(), format!("{}\n", content))
+            .await
+            .context("Failed to write skills.lock")
+    }
+
+    /// Drop a skill from `skills.lock`, if present.
+    pub(super) async fn unlock_skill(&self, name: &str) -> Result<()> {
+        let mut lock = self.skills_lock().await?;
+        if lock.skills.remove(name).is_some() {
+            self.save_skills_lock(&lock).await?;
+        }
+        Ok(())
+    }
+
+    /// Move a `skills.lock` entry after a skill rename.
+    pub(super) async fn rename_locked_skill(&self, old_name: &str, new_name: &str) -> Result<()> {
+        let mut lock = self.skills_lock().await?;
+        if let Some(entry) = lock.skills.remove(old_name) {
+            lock.skills.insert(new_name.to_string(), entry);
+            self.save_skills_lock(&lock).await?;
+        }
+        Ok(())
+    }
+
+    /// Whether an installed skill differs from its locked content.
+    async fn is_locally_modified(&self, name: &str, locked: &LockedSkill) -> Result<bool> {
+        let dir = self.skills_dir().join(name);
+        if !fs::try_exists(dir.join("SKILL.md")).await? {
+            return Ok(false);
+        }
+        Ok(content_hash(&dir).await? != locked.content_hash)
+    }
+
+    /// Copy a fetched skill into the library and write its provenance file.
+    async fn place_fetched_skill(
+        &self,
+        name: &str,
+        identifier: &str,
+        fetched: &FetchedSkill,
+    ) -> Result<LockedSkill> {
+        let target_dir = self.skills_dir().join(name);
+        let previous = self.read_skill_source(&target_dir).await;
+        if target_dir.exists() {
+            fs::remove_dir_all(&target_dir)
+                .await
+                .context("Failed to replace skill directory")?;
+        }
+        Self::copy_dir_recursive(&fetched.dir, &target_dir).await?;
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let (installed_at, updated_at) = match previous {
+            Some(SkillSource::SkillsRegistry { installed_at, .. }) => (installed_at, Some(now)),
+            _ => (Some(now), None),
+        };
+        let source = SkillSource::SkillsRegistry {
+            identifier: identifier.to_string(),
+            skill_name: Some(skill_dir_name(identifier, &fetched.path)),
+            version: Some(fetched.commit.clone()),
+            installed_at,
+            updated_at,
+        };
+        fs::write(
+            target_dir.join(SKILL_SOURCE_FILE),
+            serde_json::to_string_pretty(&source)?,
+        )
+        .await?;
+
+        Ok(LockedSkill {
+            identifier: identifier.to_string(),
+            path: fetched.path.clone(),
+            commit: fetched.commit.clone(),
+            content_hash: content_hash(&fetched.dir).await?,
+        })
+    }
+
+    async fn read_skill_source(&self, skill_dir: &Path) -> Option<SkillSource> {
+        let content = fs::read_to_string(skill_dir.join(SKILL_SOURCE_FILE))
+            .await
+            .ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Install a skill from a registry repository and pin it in `skills.lock`.
+    ///
+    /// `skill_name` selects the skill in multi-skill repositories, `target_name`
+    /// is the name in the library (defaults to the skill's directory name) and
+    /// `revision` pins a branch, tag or commit (defaults to the remote HEAD).
+    pub async fn install_registry_skill(
+        &self,
+        identifier: &str,
+        skill_name: Option<&str>,
+        target_name: Option<&str>,
+        revision: Option<&str>,
+    ) -> Result<Skill> {
+        for name in target_name.iter().chain(skill_name.iter()) {
+            Self::validate_name(name)?;
+        }
+        let work_dir = registry_work_dir();
+
+        let result = async {
+            let fetched = fetch_skill(
+                &work_dir,
+                &self.registry,
+                identifier,
+                skill_name,
+                None,
+                revision,
+            )
+            .await?;
+            let name = target_name
+                .map(str::to_string)
+                .unwrap_or_else(|| skill_dir_name(identifier, &fetched.path));
+            Self::validate_name(&name)?;
+            if self.skills_dir().join(&name).exists() {
+                anyhow::bail!("Skill '{}' already exists", name);
+            }
+            let locked = self
+                .place_fetched_skill(&name, identifier, &fetched)
+                .await?;
+            Ok((name, locked))
+        }
+        .await;
+        let _ = fs::remove_dir_all(&work_dir).await;
+        let (name, locked) = result?;
+
+        let mut lock = self.skills_lock().await?;
+        lock.skills.insert(name.clone(), locked);
+        self.save_skills_lock(&lock).await?;
+
+        self.encrypt_skill_file(&name).await?;
+        self.get_skill(&name).await
+    }
+
+    /// Install every skill of `skills.lock` at its locked commit.
+    ///
+    /// Skills matching the lock are left untouched. Locally modified skills are
+    /// only overwritten with `force`. Fails per skill (reported in `errors`) if
+    /// the fetched content does not match the locked hash.
+    pub async fn install_locked_skills(&self, force: bool) -> Result<LockInstallReport> {
+        let lock = self.skills_lock().await?;
+        let mut report = LockInstallReport::default();
+
+        for (name, locked) in &lock.skills {
+            if let Err(e) = Self::validate_name(name) {
+                report.errors.push(format!("{}: {}", name, e));
+                continue;
+            }
+            let dir = self.skills_dir().join(name);
+            if dir.join("SKILL.md").exists() {
+                if !self.is_locally_modified(name, locked).await? {
+                    report.unchanged.push(name.clone());
+                    continue;
+                }
+                if !force {
+                    report.modified.push(name.clone());
+                    continue;
+                }
+            }
+
+            let work_dir = registry_work_dir();
+            let result = async {
+                let fetched = fetch_skill(
+                    &work_dir,
+                    &self.registry,
+                    &locked.identifier,
+                    None,
+                    Some(&locked.path),
+                    Some(&locked.commit),
+                )
+                .await?;
+                let hash = content_hash(&fetched.dir).await?;
+                if hash != locked.content_hash {
+                    anyhow::bail!(
+                        "content hash mismatch at {} (expected {}, got {})",
+                        locked.commit,
+                        locked.content_hash,
+                        hash
+                    );
+                }
+                self.place_fetched_skill(name, &locked.identifier, &fetched)
+                    .await
+            }
+            .await;
+            let _ = fs::remove_dir_all(&work_dir).await;
+
+            match result {
+                Ok(_) => {
+                    self.encrypt_skill_file(name).await?;
+                    report.installed.push(name.clone());
+                }
+                Err(e) => report.errors.push(format!("{}: {}", name, e)),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Locked skills whose files changed upstream, with a diff of each.
+    ///
+    /// Skills that cannot be checked are reported with an `error` instead of
+    /// failing the whole check.
+    pub async fn check_skill_updates(&self) -> Result<Vec<SkillUpdate>> {
+        let lock = self.skills_lock().await?;
+        let mut updates = Vec::new();
+
+        for (name, locked) in &lock.skills {
+            match self.check_skill_update(name, locked).await {
+                Ok(Some(update)) => updates.push(update),
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!(skill = %name, error = %e, "Failed to check skill for updates");
+                    updates.push(SkillUpdate {
+                        name: name.clone(),
+                        identifier: locked.identifier.clone(),
+                        current_commit: locked.commit.clone(),
+                        latest_commit: String::new(),
+                        locally_modified: self
+                            .is_locally_modified(name, locked)
+                            .await
+                            .unwrap_or(false),
+                        diff: String::new(),
+                        error: Some(format!("{:#}", e)),
+                    });
+                }
+            }
+        }
+
+        Ok(updates)
+    }
+
+    /// Pending upstream change of one locked skill, if any.
+    async fn check_skill_update(
+        &self,
+        name: &str,
+        locked: &LockedSkill,
+    ) -> Result<Option<SkillUpdate>> {
+        crate::skills_registry::validate_identifier(&locked.identifier)?;
+        let url = self.registry.repo_url(&locked.identifier);
+        let latest = git::remote_head(&url)
+            .await
+            .with_context(|| format!("Failed to check {} for updates", name))?;
+        if latest == locked.commit {
+            return Ok(None);
+        }
+
+        let work_dir = registry_work_dir();
+        let diff = async {
+            git::fetch_revision(&work_dir, &url, Some(&locked.commit)).await?;
+            git::fetch_revision(&work_dir, &url, Some(&latest)).await?;
+            let pathspec = if locked.path.is_empty() {
+                "."
+            } else {
+                locked.path.as_str()
+            };
+            git::diff(&work_dir, &locked.commit, Some(&latest), pathspec).await
+        }
+        .await;
+        let _ = fs::remove_dir_all(&work_dir).await;
+        let diff = diff.with_context(|| format!("Failed to diff {}", name))?;
+
+        // Other parts of the repository changed, not this skill
+        if diff.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(SkillUpdate {
+            name: name.to_string(),
+            identifier: locked.identifier.clone(),
+            current_commit: locked.commit.clone(),
+            latest_commit: latest,
+            locally_modified: self.is_locally_modified(name, locked).await?,
+            diff,
+            error: None,
+        }))
+    }
+
+    /// Upgrade one locked skill to `revision` (defaults to the remote HEAD).
+    ///
+    /// Refuses to overwrite local modifications unless `force` is set.
+    pub async fn upgrade_skill(
+        &self,
+        name: &str,
+        revision: Option<&str>,
+        force: bool,
+    ) -> Result<Skill> {
+        Self::validate_name(name)?;
+        let mut lock = self.skills_lock().await?;
+        let locked =
+            lock.skills.get(name).cloned().ok_or_else(|| {
+                anyhow::anyhow!("Skill '{}' is not in {}", name, SKILLS_LOCK_FILE)
+            })?;
+        if !force && self.is_locally_modified(name, &locked).await? {
+            anyhow::bail!(
+                "Skill '{}' has local modifications; upgrade with force to overwrite them",
+                name
+            );
+        }
+
+        let work_dir = registry_work_dir();
+        let result = async {
+            let fetched = fetch_skill(
+                &work_dir,
+                &self.registry,
+                &locked.identifier,
+                None,
+                Some(&locked.path),
+                revision,
+            )
+            .await?;
+            self.place_fetched_skill(name, &locked.identifier, &fetched)
+                .await
+        }
+        .await;
+        let _ = fs::remove_dir_all(&work_dir).await;
+
+        lock.skills.insert(name.to_string(), result?);
+        self.save_skills_lock(&lock).await?;
+
+        self.encrypt_skill_file(name).await?;
+        self.get_skill(name).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::process::Command;
+
+    async fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .await
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    async fn upstream_commit(repo: &Path, files: &[(&str, &str)], message: &str) -> String {
+        for (path, content) in files {
+            let path = repo.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        git(repo, &["add", "-A"]).await;
+        git(repo, &["commit", "-q", "-m", message]).await;
+        git(repo, &["rev-parse", "HEAD"]).await
+    }
+
+    #[test]
+    fn finds_skill_directories() {
+        let files: Vec<String> = [
+            "README.md",
+            "skills/pdf/SKILL.md",
+            "skills/pdf/forms.md",
+            "examples/skills/pdf/SKILL.md",
+            "skills/docx/SKILL.md",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(
+            find_skill_path("acme/skills", &files, Some("pdf")).unwrap(),
+            "skills/pdf"
+        );
+        assert!(find_skill_path("acme/skills", &files, None).is_err());
+        assert!(find_skill_path("acme/skills", &files, Some("xlsx")).is_err());
+
+        let root = vec!["SKILL.md".to_string()];
+        assert_eq!(find_skill_path("acme/notes", &root, None).unwrap(), "");
+        assert_eq!(skill_dir_name("acme/notes", ""), "notes");
+    }
+
+    #[tokio::test]
+    async fn installs_checks_and_upgrades_from_lock() {
+        let tmp = tempfile::tempdir().unwrap();
+        let upstream = tmp.path().join("upstream");
+        std::fs::create_dir_all(&upstream).unwrap();
+        git(&upstream, &["init", "-q", "-b", "main"]).await;
+        let v1 = upstream_commit(
+            &upstream,
+            &[
+                ("skills/pdf/SKILL.md", "---\ndescription: pdf\n---\nv1\n"),
+                ("skills/docx/SKILL.md", "---\ndescription: docx\n---\n"),
+            ],
+            "v1",
+        )
+        .await;
+
+        // Serve `acme/skills` from a mirror, as local paths are not valid
+        // identifiers
+        let mirror = tmp.path().join("mirror");
+        let mirror_repo = mirror.join("github.com/acme/skills.git");
+        let upstream_path = upstream.to_string_lossy().to_string();
+        git::mirror(&mirror_repo, &upstream_path).await.unwrap();
+
+        let library = tmp.path().join("library");
+        std::fs::create_dir_all(&library).unwrap();
+        let mut store = LibraryStore::with_test_store(library.clone()).await;
+        store.registry = RegistrySource::Mirror { path: mirror };
+        let identifier = "acme/skills".to_string();
+        assert!(store
+            .install_registry_skill(&upstream_path, Some("pdf"), None, None)
+            .await
+            .is_err());
+
+        let skill = store
+            .install_registry_skill(&identifier, Some("pdf"), None, None)
+            .await
+            .unwrap();
+        assert_eq!(skill.name, "pdf");
+        assert!(skill.content.contains("v1"));
+        assert!(store
+            .install_registry_skill(&identifier, Some("x/../../escape"), None, None)
+            .await
+            .is_err());
+
+        let lock = store.skills_lock().await.unwrap();
+        let locked = &lock.skills["pdf"];
+        assert_eq!(locked.commit, v1);
+        assert_eq!(locked.path, "skills/pdf");
+        assert!(matches!(
+            skill.source,
+            SkillSource::SkillsRegistry { version: Some(ref v), .. } if *v == v1
+        ));
+
+        // Upstream changes another skill only: no update for pdf
+        upstream_commit(&upstream, &[("skills/docx/SKILL.md", "changed")], "docx").await;
+        git::mirror(&mirror_repo, &upstream_path).await.unwrap();
+        assert!(store.check_skill_updates().await.unwrap().is_empty());
+
+        let v3 = upstream_commit(
+            &upstream,
+            &[("skills/pdf/SKILL.md", "---\ndescription: pdf\n---\nv3\n")],
+            "v3",
+        )
+        .await;
+        git::mirror(&mirror_repo, &upstream_path).await.unwrap();
+        let updates = store.check_skill_updates().await.unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].latest_commit, v3);
+        assert!(updates[0].diff.contains("-v1") && updates[0].diff.contains("+v3"));
+        assert!(!updates[0].locally_modified);
+        assert!(updates[0].error.is_none());
+
+        // Reinstalling from the lock restores the pinned version
+        std::fs::remove_dir_all(library.join("skill/pdf")).unwrap();
+        let report = store.install_locked_skills(false).await.unwrap();
+        assert_eq!(report.installed, vec!["pdf"]);
+        assert!(store.get_skill("pdf").await.unwrap().content.contains("v1"));
+
+        // Local edits block upgrades unless forced
+        std::fs::write(library.join("skill/pdf/SKILL.md"), "local edit").unwrap();
+        let report = store.install_locked_skills(false).await.unwrap();
+        assert_eq!(report.modified, vec!["pdf"]);
+        assert!(store.upgrade_skill("pdf", None, false).await.is_err());
+
+        let skill = store.upgrade_skill("pdf", None, true).await.unwrap();
+        assert!(skill.content.contains("v3"));
+        assert_eq!(store.skills_lock().await.unwrap().skills["pdf"].commit, v3);
+
+        // An unreachable remote is reported per skill
+        let mut lock = store.skills_lock().await.unwrap();
+        lock.skills.insert(
+            "gone".to_string(),
+            LockedSkill {
+                identifier: "--upload-pack=touch".to_string(),
+                ..lock.skills["pdf"].clone()
+            },
+        );
+        store.save_skills_lock(&lock).await.unwrap();
+        let updates = store.check_skill_updates().await.unwrap();
+        let gone = updates.iter().find(|u| u.name == "gone").unwrap();
+        assert!(gone
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Invalid registry identifier"));
+        store.unlock_skill("gone").await.unwrap();
+
+        store.delete_skill("pdf").await.unwrap();
+        assert!(store.skills_lock().await.unwrap().skills.is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+//! Local mirror of skill registry repositories.
+//!
+//! A mirror directory holds a bare `git clone --mirror` of each repository
+//! (laid out by [`skills_registry::mirror_repo_path`]) and an `index.json`
+//! listing the skills they contain. With a `mirror` registry source, search
+//! reads that index and installs fetch from the local repositories, so
+//! neither needs network access.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::git;
+use super::skills_lock::{skill_dir_name, skill_dirs};
+use super::types::{extract_description, parse_frontmatter};
+use crate::skills_registry::{self, RegistryIndex, RegistryIndexEntry, INDEX_FILE};
+
+/// Outcome of mirroring one repository.
+#[derive(Debug, Clone, Serialize)]
+pub struct MirroredRepo {
+    pub identifier: String,
+    /// Mirrored HEAD commit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Number of skills indexed from the repository
+    pub skills: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of [`build_mirror`].
+#[derive(Debug, Clone, Serialize)]
+pub struct MirrorReport {
+    pub repos: Vec<MirroredRepo>,
+    /// Total number of skills in the index afterwards
+    pub skills: usize,
+}
+
+/// Mirror `identifiers` into `root` and update its index.
+///
+/// Repositories already in the mirror are updated; an empty list refreshes
+/// every repository in the current index. A repository that cannot be
+/// fetched keeps its previous index entries and is reported with an error.
+pub async fn build_mirror(root: &Path, identifiers: &[String]) -> Result<MirrorReport> {
+    let index_path = root.join(INDEX_FILE);
+    let mut index = if index_path.exists() {
+        RegistryIndex::load(&index_path).await?
+    } else {
+        RegistryIndex::default()
+    };
+
+    let identifiers: Vec<String> = if identifiers.is_empty() {
+        index
+            .skills
+            .iter()
+            .map(|e| e.identifier.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    } else {
+        identifiers.iter().map(|i| i.trim().to_string()).collect()
+    };
+
+    let mut repos = Vec::new();
+    for identifier in identifiers {
+        match mirror_repo(root, &identifier).await {
+            Ok((commit, entries)) => {
+                index.skills.retain(|e| e.identifier != identifier);
+                repos.push(MirroredRepo {
+                    identifier,
+                    commit: Some(commit),
+                    skills: entries.len(),
+                    error: None,
+                });
+                index.skills.extend(entries);
+            }
+            Err(e) => {
+                tracing::warn!(identifier = %identifier, error = %e, "Failed to mirror skill repository");
+                repos.push(MirroredRepo {
+                    identifier,
+                    commit: None,
+                    skills: 0,
+                    error: Some(e.to_string()),
+                });
+            }
+        }
+    }
+
+    index.skills.sort_by(|a, b| {
+        a.identifier
+            .cmp(&b.identifier)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    index.generated_at = Some(chrono::Utc::now().to_rfc3339());
+    index.save(&index_path).await?;
+
+    Ok(MirrorReport {
+        repos,
+        skills: index.skills.len(),
+    })
+}
+
+/// Mirror one repository and index the skills at its HEAD.
+async fn mirror_repo(root: &Path, identifier: &str) -> Result<(String, Vec<RegistryIndexEntry>)> {
+    // Same identifiers as installs: no `file://` or local paths
+    skills_registry::validate_identifier(identifier)?;
+    let path = skills_registry::mirror_repo_path(root, identifier)
+        .ok_or_else(|| anyhow::anyhow!("Cannot mirror repository '{}'", identifier))?;
+    let commit = git::mirror(&path, &skills_registry::repo_url(identifier)).await?;
+
+    let files = git::list_tree(&path, &commit).await?;
+    let mut entries = Vec::new();
+    for dir in skill_dirs(&files) {
+        let skill_md = if dir.is_empty() {
+            "SKILL.md".to_string()
+        } else {
+            format!("{}/SKILL.md", dir)
+        };
+        let description = git::show_file(&path, &commit, &skill_md)
+            .await?
+            .and_then(|content| extract_description(&parse_frontmatter(&content).0));
+        entries.push(RegistryIndexEntry {
+            identifier: identifier.to_string(),
+            name: skill_dir_name(identifier, &dir),
+            path: dir,
+            description,
+            commit: Some(commit.clone()),
+        });
+    }
+    Ok((commit, entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skills_registry::RegistrySource;
+    use axum::extract::Path as UrlPath;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use axum::Router;
+    use tokio::process::Command;
+
+    async fn git(dir: &Path, args: &[&str]) {
+        let output = Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .await
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    #[tokio::test]
+    async fn builds_searches_and_installs_from_mirror() {
+        let tmp = tempfile::tempdir().unwrap();
+        let upstream = tmp.path().join("upstream");
+        for (path, content) in [
+            (
+                "skills/pdf/SKILL.md",
+                "---\ndescription: Work with PDF files\n---\n",
+            ),
+            (
+                "skills/docx/SKILL.md",
+                "---\ndescription: Word documents\n---\n",
+            ),
+        ] {
+            let path = upstream.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        git(&upstream, &["init", "-q", "-b", "main"]).await;
+        git(&upstream, &["add", "-A"]).await;
+        git(&upstream, &["commit", "-q", "-m", "init"]).await;
+
+        // Serve the repository over git's dumb HTTP protocol
+        let served = tmp.path().join("skills.git");
+        git(
+            tmp.path(),
+            &[
+                "clone",
+                "-q",
+                "--bare",
+                &upstream.to_string_lossy(),
+                "skills.git",
+            ],
+        )
+        .await;
+        git(&served, &["update-server-info"]).await;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let identifier = format!("http://{}/skills.git", listener.local_addr().unwrap());
+        let app = Router::new().route(
+            "/skills.git/*path",
+            get(move |UrlPath(path): UrlPath<String>| {
+                let file = served.join(path);
+                async move {
+                    match tokio::fs::read(file).await {
+                        Ok(bytes) => bytes.into_response(),
+                        Err(_) => StatusCode::NOT_FOUND.into_response(),
+                    }
+                }
+            }),
+        );
+        let server = tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mirror = tmp.path().join("mirror");
+        let report = build_mirror(&mirror, std::slice::from_ref(&identifier))
+            .await
+            .unwrap();
+        assert_eq!(report.skills, 2);
+        assert!(
+            report.repos[0].error.is_none(),
+            "{:?}",
+            report.repos[0].error
+        );
+
+        // Local repositories are not mirrored
+        let local = format!("file://{}", upstream.display());
+        let report = build_mirror(&mirror, &[local]).await.unwrap();
+        assert!(report.repos[0].error.is_some());
+        assert_eq!(report.skills, 2);
+
+        // Search works from the mirror index
+        let source = RegistrySource::Mirror {
+            path: mirror.clone(),
+        };
+        let results = skills_registry::search(&source, tmp.path(), "pdf")
+            .await
+            .unwrap();
+        assert_eq!(results[0].name, "pdf");
+        assert_eq!(
+            results[0].description.as_deref(),
+            Some("Work with PDF files")
+        );
+
+        // Installs fetch from the mirror even once upstream is gone
+        server.abort();
+        let _ = server.await;
+        assert!(source
+            .repo_url(&identifier)
+            .starts_with(&*mirror.to_string_lossy()));
+        let skills = skills_registry::list_repo_skills(&source, &identifier)
+            .await
+            .unwrap();
+        assert_eq!(skills, vec!["docx", "pdf"]);
+
+        // Refreshing with upstream unavailable keeps the existing entries
+        let report = build_mirror(&mirror, &[]).await.unwrap();
+        assert!(report.repos[0].error.is_some());
+        assert_eq!(report.skills, 2);
+    }
+}
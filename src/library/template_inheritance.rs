@@ -0,0 +1,247 @@
+//! Workspace template inheritance (`"extends": "<parent>"`).
+//!
+//! A template is resolved by applying it on top of its fully resolved parent:
+//! - maps (`env_vars`) merge, the child wins on conflicting keys
+//! - lists (`skills`, `init_scripts`, `mcps`, `encrypted_keys`) append; an
+//!   entry prefixed with `!` removes an inherited entry instead
+//! - `services` merge by service name, the child wins
+//! - scalars override when set in the child (`init_script` when non-empty)
+
+use anyhow::Result;
+
+use super::{
+    LibraryError, LibraryStore, WorkspaceTemplate, WorkspaceTemplateConfig, WORKSPACE_TEMPLATE_DIR,
+};
+use crate::workspace_services::ServiceDefinition;
+
+/// Prefix of list entries that remove an inherited entry.
+pub const REMOVE_PREFIX: char = '!';
+
+/// Maximum length of an inheritance chain.
+const MAX_EXTENDS_DEPTH: usize = 16;
+
+/// Append `child` entries to `parent`, applying `!entry` removals.
+fn merge_list(parent: Vec<String>, child: Vec<String>) -> Vec<String> {
+    let mut merged: Vec<String> = parent
+        .into_iter()
+        .filter(|item| !item.starts_with(REMOVE_PREFIX))
+        .collect();
+    for item in child {
+        if let Some(removed) = item.strip_prefix(REMOVE_PREFIX) {
+            merged.retain(|existing| existing != removed);
+        } else if !merged.contains(&item) {
+            merged.push(item);
+        }
+    }
+    merged
+}
+
+/// Merge services by name; child definitions replace inherited ones.
+fn merge_services(
+    parent: Vec<ServiceDefinition>,
+    child: Vec<ServiceDefinition>,
+) -> Vec<ServiceDefinition> {
+    let mut merged = parent;
+    for service in child {
+        match merged.iter_mut().find(|s| s.name == service.name) {
+            Some(existing) => *existing = service,
+            None => merged.push(service),
+        }
+    }
+    merged
+}
+
+impl WorkspaceTemplateConfig {
+    /// Apply this (child) template on top of its resolved `parent`.
+    fn merge_onto(self, parent: WorkspaceTemplateConfig) -> WorkspaceTemplateConfig {
+        let mut env_vars = parent.env_vars;
+        env_vars.extend(self.env_vars);
+
+        WorkspaceTemplateConfig {
+            name: self.name,
+            extends: None,
+            description: self.description.or(parent.description),
+            distro: self.distro.or(parent.distro),
+            skills: merge_list(parent.skills, self.skills),
+            env_vars,
+            encrypted_keys: merge_list(parent.encrypted_keys, self.encrypted_keys),
+            init_scripts: merge_list(parent.init_scripts, self.init_scripts),
+            init_script: if self.init_script.is_empty() {
+                parent.init_script
+            } else {
+                self.init_script
+            },
+            shared_network: self.shared_network.or(parent.shared_network),
+            tailscale_mode: self.tailscale_mode.or(parent.tailscale_mode),
+            mcps: merge_list(parent.mcps, self.mcps),
+            config_profile: self.config_profile.or(parent.config_profile),
+            services: merge_services(parent.services, self.services),
+        }
+    }
+}
+
+impl LibraryStore {
+    /// Inheritance chain of a template, starting with the template itself.
+    ///
+    /// Fails on unknown parents, cycles and chains deeper than 16 templates.
+    pub async fn workspace_template_chain(&self, name: &str) -> Result<Vec<String>> {
+        Ok(self
+            .workspace_template_config_chain(name)
+            .await?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    async fn workspace_template_config_chain(
+        &self,
+        name: &str,
+    ) -> Result<Vec<(String, WorkspaceTemplateConfig)>> {
+        let mut chain: Vec<(String, WorkspaceTemplateConfig)> = Vec::new();
+        let mut current = name.to_string();
+        loop {
+            if chain.iter().any(|(n, _)| *n == current) {
+                let names: Vec<&str> = chain.iter().map(|(n, _)| n.as_str()).collect();
+                anyhow::bail!(LibraryError::Invalid(format!(
+                    "Workspace template inheritance cycle: {} -> {}",
+                    names.join(" -> "),
+                    current
+                )));
+            }
+            if chain.len() >= MAX_EXTENDS_DEPTH {
+                anyhow::bail!(LibraryError::Invalid(format!(
+                    "Workspace template '{}' extends more than {} templates",
+                    name, MAX_EXTENDS_DEPTH
+                )));
+            }
+            if let Some((child, _)) = chain.last() {
+                let path = self.read_path(&format!("{}/{}.json", WORKSPACE_TEMPLATE_DIR, current));
+                if Self::validate_name(&current).is_err() || !path.exists() {
+                    anyhow::bail!(LibraryError::Invalid(format!(
+                        "Workspace template '{}' extends unknown template '{}'",
+                        child, current
+                    )));
+                }
+            }
+
+            let config = self.read_workspace_template_config(&current).await?;
+            let parent = config.extends.clone();
+            chain.push((current, config));
+            match parent {
+                Some(parent) => current = parent,
+                None => return Ok(chain),
+            }
+        }
+    }
+
+    /// Template config with its whole inheritance chain applied.
+    pub(super) async fn resolve_workspace_template_config(
+        &self,
+        name: &str,
+    ) -> Result<WorkspaceTemplateConfig> {
+        let chain = self.workspace_template_config_chain(name).await?;
+        Ok(chain.into_iter().rev().fold(
+            WorkspaceTemplateConfig::default(),
+            |resolved, (_, child)| child.merge_onto(resolved),
+        ))
+    }
+
+    /// Get a workspace template with its inheritance chain resolved.
+    ///
+    /// This is what workspaces are built from; [`get_workspace_template`]
+    /// returns the template as stored (with `extends` and `!` removals).
+    ///
+    /// [`get_workspace_template`]: Self::get_workspace_template
+    pub async fn resolve_workspace_template(&self, name: &str) -> Result<WorkspaceTemplate> {
+        let config = self.resolve_workspace_template_config(name).await?;
+        self.workspace_template_from_config(name, config).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_lists_with_removals() {
+        let parent = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let child = vec!["!b".to_string(), "d".to_string(), "a".to_string()];
+        assert_eq!(merge_list(parent, child), vec!["a", "c", "d"]);
+        // Removals never leak into a resolved root template
+        assert_eq!(
+            merge_list(Vec::new(), vec!["!x".to_string(), "y".to_string()]),
+            vec!["y"]
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_inheritance_chain() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = LibraryStore::with_test_store(tmp.path().to_path_buf()).await;
+        let dir = tmp.path().join(WORKSPACE_TEMPLATE_DIR);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("base.json"),
+            r#"{
+  "distro": "ubuntu-noble",
+  "skills": ["lint", "heavy"],
+  "env_vars": {"LOG": "info", "REGION": "eu"},
+  "init_scripts": ["base"],
+  "shared_network": false,
+  "services": [{"name": "db", "command": "postgres"}]
+}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("team.json"),
+            r#"{
+  "extends": "base",
+  "skills": ["!heavy", "review"],
+  "env_vars": {"LOG": "debug"},
+  "init_scripts": ["node"],
+  "services": [{"name": "db", "command": "postgres -c fsync=off"}]
+}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("app.json"),
+            r#"{"extends": "team", "distro": "debian-bookworm", "mcps": ["github"]}"#,
+        )
+        .unwrap();
+
+        let resolved = store.resolve_workspace_template("app").await.unwrap();
+        assert_eq!(resolved.name, "app");
+        assert_eq!(resolved.extends, None);
+        assert_eq!(resolved.distro.as_deref(), Some("debian-bookworm"));
+        assert_eq!(resolved.skills, vec!["lint", "review"]);
+        assert_eq!(resolved.env_vars["LOG"], "debug");
+        assert_eq!(resolved.env_vars["REGION"], "eu");
+        assert_eq!(resolved.init_scripts, vec!["base", "node"]);
+        assert_eq!(resolved.shared_network, Some(false));
+        assert_eq!(resolved.mcps, vec!["github"]);
+        assert_eq!(resolved.services.len(), 1);
+        assert_eq!(resolved.services[0].command, "postgres -c fsync=off");
+
+        // The stored template keeps its own fields only
+        let raw = store.get_workspace_template("team").await.unwrap();
+        assert_eq!(raw.extends.as_deref(), Some("base"));
+        assert_eq!(raw.skills, vec!["!heavy", "review"]);
+
+        assert_eq!(
+            store.workspace_template_chain("app").await.unwrap(),
+            vec!["app", "team", "base"]
+        );
+
+        std::fs::write(dir.join("base.json"), r#"{"extends": "app"}"#).unwrap();
+        let err = store.resolve_workspace_template("app").await.unwrap_err();
+        assert!(err.to_string().contains("cycle"), "{}", err);
+
+        std::fs::write(dir.join("base.json"), r#"{"extends": "missing"}"#).unwrap();
+        let err = store.resolve_workspace_template("app").await.unwrap_err();
+        assert!(
+            err.to_string().contains("unknown template 'missing'"),
+            "{}",
+            err
+        );
+    }
+}
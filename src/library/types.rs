@@ -3,7 +3,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::mcp::McpToolPolicy;
 use crate::workspace::TailscaleMode;
+use crate::workspace_services::ServiceDefinition;
 
 // ─────────────────────────────────────────────────────────────────────────────
 // MCP Server Types (OpenCode-aligned format)
@@ -110,6 +112,9 @@ pub struct LibraryAgent {
     /// Permission levels: {"bash": "ask", "write": "allow"}
     #[serde(default)]
     pub permissions: HashMap<String, String>,
+    /// MCP tool policy from the `mcp` frontmatter field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mcp_policy: Option<McpToolPolicy>,
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -121,6 +126,9 @@ pub struct LibraryAgent {
 pub struct WorkspaceTemplateSummary {
     /// Template name
     pub name: String,
+    /// Parent template (if any)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
     /// Description from template file
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
@@ -142,6 +150,11 @@ pub struct WorkspaceTemplateSummary {
 pub struct WorkspaceTemplate {
     /// Template name
     pub name: String,
+    /// Parent template whose settings this template is applied on top of.
+    /// Lists append (entries prefixed with `!` remove inherited ones), maps
+    /// merge and scalars override.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
     /// Optional description
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
@@ -183,6 +196,9 @@ pub struct WorkspaceTemplate {
     /// Defaults to "default" if not specified.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub config_profile: Option<String>,
+    /// Background services started when the workspace is ready.
+    #[serde(default)]
+    pub services: Vec<ServiceDefinition>,
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
@@ -376,6 +392,48 @@ pub struct LibraryStatus {
     pub behind: u32,
     /// List of modified/untracked files
     pub modified_files: Vec<String>,
+    /// Overlay layers that could not be cloned and are left out
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed_layers: Vec<super::FailedLayer>,
+}
+
+/// A commit touching a library item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryCommit {
+    /// Full commit hash
+    pub hash: String,
+    /// Author name
+    pub author_name: String,
+    /// Author email
+    pub author_email: String,
+    /// Author date (RFC 3339)
+    pub date: String,
+    /// Commit subject line
+    pub subject: String,
+}
+
+/// A file with merge conflicts and its three-way versions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictFile {
+    /// Path relative to the library root
+    pub path: String,
+    /// Common ancestor version (absent if the file was added on both sides)
+    pub base: Option<String>,
+    /// Local version (absent if deleted locally)
+    pub ours: Option<String>,
+    /// Remote version (absent if deleted on the remote)
+    pub theirs: Option<String>,
+    /// Working tree content with conflict markers
+    pub merged: Option<String>,
+}
+
+/// State of a library merge.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeStatus {
+    /// True while a merge waits for conflict resolution
+    pub in_progress: bool,
+    /// Files that still have conflicts
+    pub conflicts: Vec<ConflictFile>,
 }
 
 /// Migration report showing what changed during library structure migration.
@@ -769,6 +827,22 @@ pub fn extract_permissions(frontmatter: &Option<serde_yaml::Value>) -> HashMap<S
         .unwrap_or_default()
 }
 
+/// Extract the MCP tool policy from YAML frontmatter.
+pub fn extract_mcp_policy(frontmatter: &Option<serde_yaml::Value>) -> Option<McpToolPolicy> {
+    let value = frontmatter.as_ref()?.get("mcp")?;
+    match serde_yaml::from_value::<McpToolPolicy>(value.clone()) {
+        Ok(policy) => Some(policy),
+        // Fail closed: an agent meant to be restricted is not left unrestricted
+        Err(e) => {
+            tracing::warn!(
+                "Invalid MCP tool policy in agent frontmatter, denying every tool: {}",
+                e
+            );
+            Some(McpToolPolicy::deny_all())
+        }
+    }
+}
+
 /// Extract string array from YAML frontmatter field.
 pub fn extract_string_array(frontmatter: &Option<serde_yaml::Value>, field: &str) -> Vec<String> {
     frontmatter
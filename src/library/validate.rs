@@ -0,0 +1,674 @@
+//! Library validation.
+//!
+//! Walks every item type of the (overlaid) library and reports schema problems
+//! and broken cross-references — e.g. a template naming an init script that
+//! does not exist — before a mission trips over them.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use serde::Serialize;
+use tokio::fs;
+
+use super::{
+    extract_params, extract_string_array, LayeredItemKind, LibraryStore, McpServer,
+    WorkspaceTemplateConfig, PLUGINS_FILE,
+};
+use crate::nspawn::NspawnDistro;
+
+/// Severity of a validation finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single validation finding.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Kind of the offending item (absent for library-wide files)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<LayeredItemKind>,
+    /// Item name (or file name for library-wide files)
+    pub item: String,
+    /// File path relative to the layer root
+    pub file: String,
+    /// Layer the file comes from
+    pub layer: String,
+    /// 1-based line number, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// Result of validating the library.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    pub errors: usize,
+    pub warnings: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    /// Whether the library has no errors (warnings are allowed).
+    pub fn is_ok(&self) -> bool {
+        self.errors == 0
+    }
+
+    /// One-line summary followed by the errors, for error messages.
+    pub fn summary(&self) -> String {
+        let mut out = format!("Library validation failed with {} error(s)", self.errors);
+        for diag in self
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+        {
+            match diag.line {
+                Some(line) => out.push_str(&format!("\n{}:{}: {}", diag.file, line, diag.message)),
+                None => out.push_str(&format!("\n{}: {}", diag.file, diag.message)),
+            }
+        }
+        out
+    }
+}
+
+/// Collects diagnostics, resolving each file to the layer that provides it.
+struct Collector<'a> {
+    store: &'a LibraryStore,
+    report: ValidationReport,
+}
+
+impl Collector<'_> {
+    fn push(
+        &mut self,
+        severity: Severity,
+        kind: Option<LayeredItemKind>,
+        item: &str,
+        file: &str,
+        line: Option<usize>,
+        message: impl Into<String>,
+    ) {
+        match severity {
+            Severity::Error => self.report.errors += 1,
+            Severity::Warning => self.report.warnings += 1,
+        }
+        self.report.diagnostics.push(Diagnostic {
+            severity,
+            kind,
+            item: item.to_string(),
+            file: file.to_string(),
+            layer: self.store.layer_for(file).layer_name().to_string(),
+            line,
+            message: message.into(),
+        });
+    }
+
+    fn error(
+        &mut self,
+        kind: LayeredItemKind,
+        item: &str,
+        file: &str,
+        line: Option<usize>,
+        message: impl Into<String>,
+    ) {
+        self.push(Severity::Error, Some(kind), item, file, line, message);
+    }
+
+    fn warning(
+        &mut self,
+        kind: LayeredItemKind,
+        item: &str,
+        file: &str,
+        line: Option<usize>,
+        message: impl Into<String>,
+    ) {
+        self.push(Severity::Warning, Some(kind), item, file, line, message);
+    }
+}
+
+/// Whether a template list entry removes an inherited entry (`!name`).
+fn is_removal(entry: &str) -> bool {
+    entry.starts_with(super::template_inheritance::REMOVE_PREFIX)
+}
+
+/// 1-based line of the first occurrence of `needle` in `content`.
+fn find_line(content: &str, needle: &str) -> Option<usize> {
+    content
+        .lines()
+        .position(|line| line.contains(needle))
+        .map(|idx| idx + 1)
+}
+
+/// Check YAML frontmatter syntax.
+///
+/// `parse_frontmatter` silently falls back to a lenient parser, so syntax
+/// errors would otherwise go unnoticed. Returns `(line, message)` on error.
+fn check_frontmatter(content: &str) -> Option<(usize, String)> {
+    let normalized = content.replace("\r\n", "\n");
+    let rest = normalized.strip_prefix("---")?;
+    let Some(end) = rest.find("\n---") else {
+        return Some((1, "Frontmatter is not closed with '---'".to_string()));
+    };
+    match serde_yaml::from_str::<serde_yaml::Value>(&rest[..end]) {
+        Ok(serde_yaml::Value::Mapping(_)) | Ok(serde_yaml::Value::Null) => None,
+        Ok(_) => Some((1, "Frontmatter must be a YAML mapping".to_string())),
+        Err(e) => {
+            // The YAML starts right after the opening `---` on line 1
+            let line = e.location().map(|loc| loc.line()).unwrap_or(1);
+            Some((line, format!("Invalid frontmatter: {}", e)))
+        }
+    }
+}
+
+impl LibraryStore {
+    /// Validate the effective library (all layers merged).
+    pub async fn validate(&self) -> Result<ValidationReport> {
+        let mut c = Collector {
+            store: self,
+            report: ValidationReport::default(),
+        };
+
+        let skills: HashSet<String> = self
+            .list_skills()
+            .await?
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        let init_scripts: HashSet<String> = self
+            .list_init_scripts()
+            .await?
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        let profiles: HashSet<String> = self
+            .list_config_profiles()
+            .await?
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        let mcps = self.validate_mcp_servers(&mut c).await?;
+
+        self.validate_skills(&mut c).await?;
+        self.validate_commands(&mut c).await?;
+        self.validate_agents(&mut c, &skills, &mcps).await?;
+        self.validate_init_scripts(&mut c).await?;
+        self.validate_workspace_templates(&mut c, &skills, &init_scripts, &mcps, &profiles)
+            .await?;
+        self.validate_config_profiles(&mut c).await?;
+        self.validate_plugins(&mut c).await;
+
+        Ok(c.report)
+    }
+
+    /// Check `mcp/servers.json` of every layer; returns the effective MCP names.
+    async fn validate_mcp_servers(&self, c: &mut Collector<'_>) -> Result<HashSet<String>> {
+        let file = LayeredItemKind::Mcp.relative_path("");
+        let mut names = HashSet::new();
+
+        for layer in self.layer_stack() {
+            let path = layer.path.join(&file);
+            let Ok(content) = fs::read_to_string(&path).await else {
+                continue;
+            };
+            let push = |c: &mut Collector<'_>, severity, item: &str, line, message: String| {
+                c.report.diagnostics.push(Diagnostic {
+                    severity,
+                    kind: Some(LayeredItemKind::Mcp),
+                    item: item.to_string(),
+                    file: file.clone(),
+                    layer: layer.name.clone(),
+                    line,
+                    message,
+                });
+                match severity {
+                    Severity::Error => c.report.errors += 1,
+                    Severity::Warning => c.report.warnings += 1,
+                }
+            };
+
+            let entries: HashMap<String, serde_json::Value> = match serde_json::from_str(&content) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    push(
+                        c,
+                        Severity::Error,
+                        "servers.json",
+                        Some(e.line()),
+                        format!("Invalid JSON: {}", e),
+                    );
+                    continue;
+                }
+            };
+
+            for (name, value) in entries {
+                let line = find_line(&content, &format!("\"{}\"", name));
+                names.insert(name.clone());
+                match serde_json::from_value::<McpServer>(value) {
+                    Err(e) => push(
+                        c,
+                        Severity::Error,
+                        &name,
+                        line,
+                        format!("Invalid MCP server definition: {}", e),
+                    ),
+                    Ok(McpServer::Local { command, .. }) => {
+                        if command.first().is_none_or(|cmd| cmd.trim().is_empty()) {
+                            push(
+                                c,
+                                Severity::Error,
+                                &name,
+                                line,
+                                "Local MCP server has an empty command".to_string(),
+                            );
+                        }
+                    }
+                    Ok(McpServer::Remote { url, .. }) => {
+                        if !(url.starts_with("http://") || url.starts_with("https://")) {
+                            push(
+                                c,
+                                Severity::Error,
+                                &name,
+                                line,
+                                format!("Remote MCP server URL must be http(s): {}", url),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(names)
+    }
+
+    async fn validate_skills(&self, c: &mut Collector<'_>) -> Result<()> {
+        // Directories without SKILL.md are silently skipped by listing
+        let mut seen = HashSet::new();
+        for layer in self.layer_stack().into_iter().rev() {
+            let Ok(mut entries) = fs::read_dir(layer.skills_dir()).await else {
+                continue;
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !entry.path().is_dir() || name.starts_with('.') || !seen.insert(name.clone()) {
+                    continue;
+                }
+                if !entry.path().join("SKILL.md").exists() {
+                    c.warning(
+                        LayeredItemKind::Skill,
+                        &name,
+                        &LayeredItemKind::Skill.relative_path(&name),
+                        None,
+                        "Skill directory has no SKILL.md and is ignored",
+                    );
+                }
+            }
+        }
+
+        for skill in self.list_skills().await? {
+            let file = format!("{}/SKILL.md", skill.path);
+            let content = fs::read_to_string(self.read_path(&file)).await?;
+            if let Some((line, message)) = check_frontmatter(&content) {
+                c.error(
+                    LayeredItemKind::Skill,
+                    &skill.name,
+                    &file,
+                    Some(line),
+                    message,
+                );
+                continue;
+            }
+            let (frontmatter, _) = super::parse_frontmatter(&content);
+            if skill.description.is_none() {
+                c.warning(
+                    LayeredItemKind::Skill,
+                    &skill.name,
+                    &file,
+                    Some(1),
+                    "Skill has no description in its frontmatter",
+                );
+            }
+            if let Some(fm_name) = super::extract_name(&frontmatter) {
+                if fm_name != skill.name {
+                    c.warning(
+                        LayeredItemKind::Skill,
+                        &skill.name,
+                        &file,
+                        find_line(&content, "name:"),
+                        format!(
+                            "Frontmatter name '{}' does not match directory '{}'",
+                            fm_name, skill.name
+                        ),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn validate_commands(&self, c: &mut Collector<'_>) -> Result<()> {
+        for command in self.list_commands().await? {
+            let file = command.path.clone();
+            let content = fs::read_to_string(self.read_path(&file)).await?;
+            if let Some((line, message)) = check_frontmatter(&content) {
+                c.error(
+                    LayeredItemKind::Command,
+                    &command.name,
+                    &file,
+                    Some(line),
+                    message,
+                );
+                continue;
+            }
+
+            let (frontmatter, _) = super::parse_frontmatter(&content);
+            let params = extract_params(&frontmatter);
+            let mut declared = HashSet::new();
+            for param in &params {
+                if param.name.trim().is_empty() {
+                    c.error(
+                        LayeredItemKind::Command,
+                        &command.name,
+                        &file,
+                        find_line(&content, "params:"),
+                        "Command parameter has an empty name",
+                    );
+                } else if !declared.insert(param.name.as_str()) {
+                    c.error(
+                        LayeredItemKind::Command,
+                        &command.name,
+                        &file,
+                        find_line(&content, &param.name),
+                        format!("Duplicate command parameter '{}'", param.name),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn validate_agents(
+        &self,
+        c: &mut Collector<'_>,
+        skills: &HashSet<String>,
+        mcps: &HashSet<String>,
+    ) -> Result<()> {
+        for agent in self.list_library_agents().await? {
+            let file = agent.path.clone();
+            let content = fs::read_to_string(self.read_path(&file)).await?;
+            if let Some((line, message)) = check_frontmatter(&content) {
+                c.error(
+                    LayeredItemKind::Agent,
+                    &agent.name,
+                    &file,
+                    Some(line),
+                    message,
+                );
+                continue;
+            }
+
+            let (frontmatter, _) = super::parse_frontmatter(&content);
+            for skill in extract_string_array(&frontmatter, "skills") {
+                if !skills.contains(&skill) {
+                    c.error(
+                        LayeredItemKind::Agent,
+                        &agent.name,
+                        &file,
+                        find_line(&content, &skill),
+                        format!("Agent references missing skill '{}'", skill),
+                    );
+                }
+            }
+            for mcp in extract_string_array(&frontmatter, "mcps") {
+                if !mcps.contains(&mcp) {
+                    c.error(
+                        LayeredItemKind::Agent,
+                        &agent.name,
+                        &file,
+                        find_line(&content, &mcp),
+                        format!("Agent references missing MCP server '{}'", mcp),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn validate_init_scripts(&self, c: &mut Collector<'_>) -> Result<()> {
+        for script in self.list_init_scripts().await? {
+            let content = fs::read_to_string(self.read_path(&script.path)).await?;
+            if content.trim().is_empty() {
+                c.warning(
+                    LayeredItemKind::InitScript,
+                    &script.name,
+                    &script.path,
+                    None,
+                    "Init script is empty",
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn validate_workspace_templates(
+        &self,
+        c: &mut Collector<'_>,
+        skills: &HashSet<String>,
+        init_scripts: &HashSet<String>,
+        mcps: &HashSet<String>,
+        profiles: &HashSet<String>,
+    ) -> Result<()> {
+        for template in self.list_workspace_templates().await? {
+            let file = template.path.clone();
+            let content = fs::read_to_string(self.read_path(&file)).await?;
+            let kind = LayeredItemKind::WorkspaceTemplate;
+            let name = template.name.as_str();
+
+            let config: WorkspaceTemplateConfig = match serde_json::from_str(&content) {
+                Ok(config) => config,
+                Err(e) => {
+                    c.error(
+                        kind,
+                        name,
+                        &file,
+                        Some(e.line()),
+                        format!("Invalid template: {}", e),
+                    );
+                    continue;
+                }
+            };
+
+            // Resolve the `extends` chain; inherited env vars count for encrypted keys
+            let mut env_keys: HashSet<String> = config.env_vars.keys().cloned().collect();
+            if config.extends.is_some() {
+                let file_stem = file
+                    .trim_start_matches("workspace-template/")
+                    .trim_end_matches(".json");
+                match self.resolve_workspace_template_config(file_stem).await {
+                    Ok(resolved) => env_keys.extend(resolved.env_vars.into_keys()),
+                    Err(e) => c.error(
+                        kind,
+                        name,
+                        &file,
+                        find_line(&content, "\"extends\""),
+                        e.to_string(),
+                    ),
+                }
+            }
+
+            if let Some(distro) = config.distro.as_deref() {
+                if NspawnDistro::parse(distro).is_none() {
+                    c.error(
+                        kind,
+                        name,
+                        &file,
+                        find_line(&content, "\"distro\""),
+                        format!(
+                            "Unknown distro '{}'. Supported: {}",
+                            distro,
+                            NspawnDistro::supported_values().join(", ")
+                        ),
+                    );
+                }
+            }
+            for skill in config.skills.iter().filter(|s| !is_removal(s)) {
+                if !skills.contains(skill) {
+                    c.error(
+                        kind,
+                        name,
+                        &file,
+                        find_line(&content, &format!("\"{}\"", skill)),
+                        format!("Template references missing skill '{}'", skill),
+                    );
+                }
+            }
+            for script in config.init_scripts.iter().filter(|s| !is_removal(s)) {
+                if !init_scripts.contains(script) {
+                    c.error(
+                        kind,
+                        name,
+                        &file,
+                        find_line(&content, &format!("\"{}\"", script)),
+                        format!("Template references missing init script '{}'", script),
+                    );
+                }
+            }
+            for mcp in config.mcps.iter().filter(|s| !is_removal(s)) {
+                if !mcps.contains(mcp) {
+                    c.error(
+                        kind,
+                        name,
+                        &file,
+                        find_line(&content, &format!("\"{}\"", mcp)),
+                        format!("Template references missing MCP server '{}'", mcp),
+                    );
+                }
+            }
+            if let Some(profile) = config.config_profile.as_deref() {
+                if !profiles.contains(profile) {
+                    c.error(
+                        kind,
+                        name,
+                        &file,
+                        find_line(&content, "\"config_profile\""),
+                        format!("Template references missing config profile '{}'", profile),
+                    );
+                }
+            }
+            if let Err(e) = crate::workspace_services::validate_services(&config.services) {
+                c.error(kind, name, &file, find_line(&content, "\"services\""), e);
+            }
+            for key in config.encrypted_keys.iter().filter(|k| !is_removal(k)) {
+                if !env_keys.contains(key) {
+                    c.warning(
+                        kind,
+                        name,
+                        &file,
+                        find_line(&content, "\"encrypted_keys\""),
+                        format!("Encrypted key '{}' has no matching env var", key),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn validate_config_profiles(&self, c: &mut Collector<'_>) -> Result<()> {
+        for profile in self.list_config_profiles().await? {
+            for rel in self.list_config_profile_files(&profile.name).await? {
+                if !rel.ends_with(".json") {
+                    continue;
+                }
+                let file = format!("{}/{}", profile.path, rel);
+                let content = fs::read_to_string(self.read_path(&file)).await?;
+                if let Err(e) = serde_json::from_str::<serde_json::Value>(&content) {
+                    c.error(
+                        LayeredItemKind::ConfigProfile,
+                        &profile.name,
+                        &file,
+                        Some(e.line()),
+                        format!("Invalid JSON: {}", e),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn validate_plugins(&self, c: &mut Collector<'_>) {
+        let Ok(content) = fs::read_to_string(self.path.join(PLUGINS_FILE)).await else {
+            return;
+        };
+        if let Err(e) = serde_json::from_str::<HashMap<String, super::Plugin>>(&content) {
+            c.push(
+                Severity::Error,
+                None,
+                PLUGINS_FILE,
+                PLUGINS_FILE,
+                Some(e.line()),
+                format!("Invalid plugins file: {}", e),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_broken_items_with_lines() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = LibraryStore::with_test_store(tmp.path().to_path_buf()).await;
+
+        store
+            .save_skill("broken", "---\ndescription: [unclosed\n---\nbody\n")
+            .await
+            .unwrap();
+        store
+            .save_command(
+                "review",
+                "---\ndescription: Review\nparams: [repo, repo]\n---\nReview $ARGUMENTS\n",
+            )
+            .await
+            .unwrap();
+        fs::create_dir_all(tmp.path().join("mcp")).await.unwrap();
+        fs::write(
+            tmp.path().join("mcp/servers.json"),
+            "{\n  \"web\": {\"type\": \"remote\", \"url\": \"ftp://x\"}\n}\n",
+        )
+        .await
+        .unwrap();
+        fs::create_dir_all(tmp.path().join("workspace-template"))
+            .await
+            .unwrap();
+        fs::write(
+            tmp.path().join("workspace-template/dev.json"),
+            "{\n  \"init_scripts\": [\"missing\"]\n}\n",
+        )
+        .await
+        .unwrap();
+
+        let report = store.validate().await.unwrap();
+        assert!(!report.is_ok());
+
+        let find = |file: &str, needle: &str| {
+            report
+                .diagnostics
+                .iter()
+                .find(|d| d.file == file && d.message.contains(needle))
+                .unwrap_or_else(|| panic!("no diagnostic for {} / {}", file, needle))
+        };
+        assert_eq!(
+            find("skill/broken/SKILL.md", "frontmatter").severity,
+            Severity::Error
+        );
+        assert_eq!(find("command/review.md", "Duplicate").line, Some(3));
+        assert_eq!(find("mcp/servers.json", "http(s)").line, Some(2));
+        assert_eq!(
+            find("workspace-template/dev.json", "init script").line,
+            Some(2)
+        );
+    }
+}
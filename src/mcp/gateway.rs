@@ -0,0 +1,707 @@
+//! Per-mission MCP gateway.
+//!
+//! Instead of every harness launching or connecting to each MCP on its own,
+//! sandboxed.sh can serve a single Streamable HTTP endpoint per mission
+//! (`/api/mcp/gateway/:mission_id`) that proxies to the global MCPs the
+//! mission's workspace allows, through the [`McpRegistry`] connections, and
+//! to the instances of workspace-scoped stdio MCPs inside that workspace.
+//! Credentials of those MCPs stay on the host: the harness config only gets
+//! the gateway URL and a token bound to the mission. The token is only
+//! accepted while the mission is active (see [`GatewayTokens`]).
+//!
+//! Other workspace-scoped MCPs are still written into the harness config
+//! individually.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex as StdMutex;
+
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::policy::McpPolicies;
+use super::registry::McpRegistry;
+use super::types::{McpScope, McpServerConfig, McpTool, McpTransport};
+use crate::harness_config::{HarnessSecrets, GATEWAY_TOKEN_PLACEHOLDER};
+use crate::workspace::Workspace;
+
+/// Name of the harness config entry pointing at the gateway.
+pub const GATEWAY_MCP_NAME: &str = "sandboxed-gateway";
+
+/// Where harnesses reach the gateway and how its tokens are signed.
+#[derive(Debug, Clone)]
+pub struct GatewaySettings {
+    /// Base URL of the sandboxed.sh API as seen from workspaces
+    pub base_url: String,
+    /// JWT secret; tokens are only issued and checked when set
+    pub secret: Option<String>,
+}
+
+impl GatewaySettings {
+    /// Gateway endpoint of a mission.
+    pub fn endpoint(&self, mission_id: Uuid) -> String {
+        format!(
+            "{}/api/mcp/gateway/{}",
+            self.base_url.trim_end_matches('/'),
+            mission_id
+        )
+    }
+}
+
+/// Claims of a gateway token.
+#[derive(Debug, Serialize, Deserialize)]
+struct GatewayClaims {
+    /// Mission the token grants gateway access to
+    mis: Uuid,
+    /// Nonce of the mission's current run
+    nonce: Uuid,
+}
+
+/// Gateway tokens are signed with a key derived from the JWT secret so they
+/// can never be replayed as dashboard session tokens.
+fn signing_key(secret: &str) -> Vec<u8> {
+    format!("{}:mcp-gateway", secret).into_bytes()
+}
+
+/// Issue the gateway token of a mission run identified by `nonce`.
+///
+/// Tokens carry no timestamps, so rendering the harness config twice gives
+/// the same file.
+fn issue_token(secret: &str, mission_id: Uuid, nonce: Uuid) -> anyhow::Result<String> {
+    Ok(jsonwebtoken::encode(
+        &Header::default(),
+        &GatewayClaims {
+            mis: mission_id,
+            nonce,
+        },
+        &EncodingKey::from_secret(&signing_key(secret)),
+    )?)
+}
+
+/// Check that `token` is the gateway token of `mission_id` for `nonce`.
+fn verify_token(token: &str, secret: &str, mission_id: Uuid, nonce: Uuid) -> bool {
+    let mut validation = Validation::default();
+    validation.required_spec_claims.clear();
+    validation.validate_exp = false;
+    jsonwebtoken::decode::<GatewayClaims>(
+        token,
+        &DecodingKey::from_secret(&signing_key(secret)),
+        &validation,
+    )
+    .is_ok_and(|data| data.claims.mis == mission_id && data.claims.nonce == nonce)
+}
+
+/// Gateway tokens of the missions currently allowed to use the gateway.
+///
+/// Each mission run gets a nonce when its harness config is first rendered;
+/// [`Self::revoke`] drops it once the mission stops, so tokens left behind
+/// in the workspace stop working and the next run gets a new one.
+#[derive(Debug, Default)]
+pub struct GatewayTokens {
+    nonces: StdMutex<HashMap<Uuid, Uuid>>,
+}
+
+impl GatewayTokens {
+    /// Token of the current run of `mission_id`, starting a run if needed.
+    pub fn issue(&self, secret: &str, mission_id: Uuid) -> anyhow::Result<String> {
+        let nonce = *self
+            .nonces
+            .lock()
+            .unwrap()
+            .entry(mission_id)
+            .or_insert_with(Uuid::new_v4);
+        issue_token(secret, mission_id, nonce)
+    }
+
+    /// Check `token` against the current run of `mission_id`.
+    pub fn verify(&self, token: &str, secret: &str, mission_id: Uuid) -> bool {
+        let nonce = self.nonces.lock().unwrap().get(&mission_id).copied();
+        nonce.is_some_and(|nonce| verify_token(token, secret, mission_id, nonce))
+    }
+
+    /// Invalidate the tokens of `mission_id`.
+    pub fn revoke(&self, mission_id: Uuid) {
+        self.nonces.lock().unwrap().remove(&mission_id);
+    }
+}
+
+/// Whether the gateway proxies `config` rather than the harness reaching it
+/// directly.
+pub fn proxied(config: &McpServerConfig) -> bool {
+    config.scope == McpScope::Global || McpRegistry::runs_in_workspace(config)
+}
+
+/// Replace the MCPs the gateway proxies in a workspace's harness MCP list
+/// with a single entry for the mission's gateway.
+/// With [`HarnessSecrets::Placeholders`] no token is issued.
+pub fn gateway_configs(
+    configs: Vec<McpServerConfig>,
+    settings: &GatewaySettings,
+    tokens: &GatewayTokens,
+    mission_id: Uuid,
+    secrets: HarnessSecrets,
+) -> anyhow::Result<Vec<McpServerConfig>> {
+    let (proxied, mut configs): (Vec<_>, Vec<_>) = configs.into_iter().partition(proxied);
+    if proxied.is_empty() {
+        return Ok(configs);
+    }
+
+    let mut headers = HashMap::new();
+    if let Some(secret) = settings.secret.as_deref() {
+        let token = match secrets {
+            HarnessSecrets::Resolve => tokens.issue(secret, mission_id)?,
+            HarnessSecrets::Placeholders => GATEWAY_TOKEN_PLACEHOLDER.to_string(),
+        };
+        headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+    }
+    let mut gateway =
+        McpServerConfig::new(GATEWAY_MCP_NAME.to_string(), settings.endpoint(mission_id));
+    // Stable ID so the rendered config does not change between renders
+    gateway.id = mission_id;
+    gateway.transport = McpTransport::Http {
+        endpoint: settings.endpoint(mission_id),
+        headers,
+    };
+    gateway.description = Some(format!(
+        "Gateway to {}",
+        proxied
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    configs.push(gateway);
+    Ok(configs)
+}
+
+/// A tool call made through the gateway, reported before and after it runs.
+#[derive(Debug, Clone)]
+pub enum GatewayEvent {
+    Call {
+        call_id: String,
+        name: String,
+        arguments: Value,
+    },
+    Result {
+        call_id: String,
+        name: String,
+        output: Result<String, String>,
+    },
+}
+
+/// MCP server side of the gateway for one mission.
+pub struct McpGateway<'a> {
+    registry: &'a McpRegistry,
+    mission_id: Uuid,
+    /// Workspace of the mission
+    workspace: &'a Workspace,
+    /// MCPs the mission may use, by ID, with their names
+    allowed: HashMap<Uuid, String>,
+    /// Allowed MCPs that run as instances inside the workspace
+    in_workspace: HashSet<Uuid>,
+    /// Tool policies of the mission
+    policies: McpPolicies,
+}
+
+impl<'a> McpGateway<'a> {
+    pub fn new<'c>(
+        registry: &'a McpRegistry,
+        mission_id: Uuid,
+        workspace: &'a Workspace,
+        allowed: impl IntoIterator<Item = &'c McpServerConfig>,
+        policies: McpPolicies,
+    ) -> Self {
+        let allowed: Vec<_> = allowed.into_iter().filter(|c| proxied(c)).collect();
+        Self {
+            registry,
+            mission_id,
+            workspace,
+            in_workspace: allowed
+                .iter()
+                .filter(|c| McpRegistry::runs_in_workspace(c))
+                .map(|c| c.id)
+                .collect(),
+            allowed: allowed.iter().map(|c| (c.id, c.name.clone())).collect(),
+            policies,
+        }
+    }
+
+    /// Tools of the registry's connections and of the workspace instances,
+    /// paired with their unprefixed names.
+    async fn tools(&self) -> Vec<(McpTool, String)> {
+        let mut tools = self.registry.prefixed_tools().await;
+        for &mcp_id in &self.in_workspace {
+            match self.registry.workspace_tools(mcp_id, self.workspace).await {
+                Ok(instance_tools) => tools.extend(instance_tools),
+                Err(e) => {
+                    tracing::warn!(mcp = %mcp_id, error = %e, "Workspace MCP unavailable to the gateway")
+                }
+            }
+        }
+        tools
+    }
+
+    /// Name of the MCP serving `tool` if the mission may call it.
+    fn permitted(&self, tool: &McpTool, original: &str) -> Option<&str> {
+        let server = self.allowed.get(&tool.mcp_id)?;
+        (tool.enabled && self.policies.allows_tool(server, original)).then_some(server.as_str())
+    }
+
+    /// Handle one JSON-RPC message; returns the response for requests and
+    /// `None` for notifications.
+    pub async fn handle(
+        &self,
+        message: Value,
+        on_event: &(dyn Fn(GatewayEvent) + Send + Sync),
+    ) -> Option<Value> {
+        let id = message.get("id").filter(|id| !id.is_null())?.clone();
+        let method = message.get("method").and_then(|m| m.as_str());
+        if method.is_none() && (message.get("result").is_some() || message.get("error").is_some()) {
+            // A response to a request of ours; the gateway sends none
+            return None;
+        }
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            Some("initialize") => Ok(json!({
+                "protocolVersion": super::registry::MCP_PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": {
+                    "name": GATEWAY_MCP_NAME,
+                    "version": env!("CARGO_PKG_VERSION")
+                }
+            })),
+            Some("ping") => Ok(json!({})),
+            Some("tools/list") => Ok(self.list_tools().await),
+            Some("tools/call") => self.call_tool(&id, params, on_event).await,
+            Some(other) => Err((-32601, format!("Method not found: {}", other))),
+            None => Err((-32600, "Invalid request".to_string())),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message }
+            }),
+        })
+    }
+
+    async fn list_tools(&self) -> Value {
+        let tools: Vec<Value> = self
+            .tools()
+            .await
+            .into_iter()
+            .filter(|(tool, original)| self.permitted(tool, original).is_some())
+            .map(|(tool, _)| {
+                json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                    "inputSchema": tool.parameters_schema
+                })
+            })
+            .collect();
+        json!({ "tools": tools })
+    }
+
+    async fn call_tool(
+        &self,
+        id: &Value,
+        params: Value,
+        on_event: &(dyn Fn(GatewayEvent) + Send + Sync),
+    ) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(|n| n.as_str())
+            .ok_or((-32602, "Missing tool name".to_string()))?;
+        let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+        let (tool, original) = self
+            .tools()
+            .await
+            .into_iter()
+            .find(|(tool, original)| tool.name == name && self.permitted(tool, original).is_some())
+            .ok_or_else(|| (-32602, format!("Unknown tool: {}", name)))?;
+        let server = self.permitted(&tool, &original).unwrap_or_default();
+
+        let call_id = format!("mcp-gateway-{}", id).replace('"', "");
+        on_event(GatewayEvent::Call {
+            call_id: call_id.clone(),
+            name: tool.name.clone(),
+            arguments: arguments.clone(),
+        });
+        // Denied calls are reported like failed ones so they show up in the
+        // mission's events
+        let output = match self.policies.check_call(server, &original, &arguments) {
+            Ok(()) if self.in_workspace.contains(&tool.mcp_id) => self
+                .registry
+                .call_tool_in_workspace(
+                    tool.mcp_id,
+                    self.workspace,
+                    &original,
+                    arguments,
+                    Some(self.mission_id),
+                )
+                .await
+                .map_err(|e| e.to_string()),
+            Ok(()) => self
+                .registry
+                .call_tool_for_mission(self.mission_id, tool.mcp_id, &original, arguments)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(denied) => Err(denied),
+        };
+        on_event(GatewayEvent::Result {
+            call_id,
+            name: tool.name,
+            output: output.clone(),
+        });
+
+        // Tool failures are results the model should see, not protocol errors
+        Ok(match output {
+            Ok(text) => json!({ "content": [{ "type": "text", "text": text }], "isError": false }),
+            Err(e) => json!({ "content": [{ "type": "text", "text": e }], "isError": true }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::{AddMcpRequest, McpStatus};
+    use std::sync::Mutex as StdMutex;
+
+    /// Answers every request with a result that works for `initialize`,
+    /// `tools/list` and `tools/call`.
+    const FAKE_SERVER: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9][0-9]*\).*/\1/p')
+  [ -n "$id" ] && printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2025-03-26","capabilities":{},"tools":[{"name":"lookup","description":"Look up","inputSchema":{"type":"object"}}],"content":[{"type":"text","text":"found"}]}}\n' "$id"
+done
+"#;
+
+    fn settings(secret: Option<&str>) -> GatewaySettings {
+        GatewaySettings {
+            base_url: "http://127.0.0.1:3000/".to_string(),
+            secret: secret.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn tokens_are_bound_to_the_mission_run() {
+        let tokens = GatewayTokens::default();
+        let mission = Uuid::new_v4();
+        let token = tokens.issue("secret", mission).unwrap();
+        assert_eq!(token, tokens.issue("secret", mission).unwrap());
+        assert!(tokens.verify(&token, "secret", mission));
+        assert!(!tokens.verify(&token, "secret", Uuid::new_v4()));
+        assert!(!tokens.verify(&token, "other", mission));
+
+        // Once the mission stops, its token is no longer accepted and the
+        // next run gets a different one
+        tokens.revoke(mission);
+        assert!(!tokens.verify(&token, "secret", mission));
+        let next = tokens.issue("secret", mission).unwrap();
+        assert_ne!(token, next);
+        assert!(!tokens.verify(&token, "secret", mission));
+        assert!(tokens.verify(&next, "secret", mission));
+    }
+
+    #[test]
+    fn replaces_global_mcps_with_gateway_entry() {
+        let mission = Uuid::new_v4();
+        let global = McpServerConfig::new("github".to_string(), "https://x".to_string());
+        let mut local = McpServerConfig::new_stdio(
+            "workspace".to_string(),
+            "workspace-mcp".to_string(),
+            Vec::new(),
+            HashMap::new(),
+        );
+        local.scope = McpScope::Workspace;
+        let mut remote = McpServerConfig::new("remote".to_string(), "https://y".to_string());
+        remote.scope = McpScope::Workspace;
+
+        let tokens = GatewayTokens::default();
+        let configs = gateway_configs(
+            vec![global, local, remote],
+            &settings(Some("secret")),
+            &tokens,
+            mission,
+            HarnessSecrets::Resolve,
+        )
+        .unwrap();
+        let names: Vec<_> = configs.iter().map(|c| c.name.as_str()).collect();
+        // Workspace-scoped stdio MCPs are proxied to their workspace instance
+        assert_eq!(names, vec!["remote", GATEWAY_MCP_NAME]);
+        assert_eq!(
+            configs[1].description.as_deref(),
+            Some("Gateway to github, workspace")
+        );
+        let McpTransport::Http { endpoint, headers } = &configs[1].transport else {
+            panic!("gateway entry should use HTTP");
+        };
+        assert_eq!(
+            endpoint,
+            &format!("http://127.0.0.1:3000/api/mcp/gateway/{}", mission)
+        );
+        let token = headers["Authorization"].strip_prefix("Bearer ").unwrap();
+        assert!(tokens.verify(token, "secret", mission));
+
+        // Previews get a placeholder instead of a token
+        let global = McpServerConfig::new("github".to_string(), "https://x".to_string());
+        let configs = gateway_configs(
+            vec![global],
+            &settings(Some("secret")),
+            &tokens,
+            mission,
+            HarnessSecrets::Placeholders,
+        )
+        .unwrap();
+        let McpTransport::Http { headers, .. } = &configs[0].transport else {
+            panic!("gateway entry should use HTTP");
+        };
+        assert_eq!(
+            headers["Authorization"],
+            format!("Bearer {}", GATEWAY_TOKEN_PLACEHOLDER)
+        );
+
+        // Nothing to proxy: no gateway entry
+        let configs = gateway_configs(
+            Vec::new(),
+            &settings(None),
+            &tokens,
+            mission,
+            HarnessSecrets::Resolve,
+        )
+        .unwrap();
+        assert!(configs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn proxies_allowed_tools_and_reports_calls() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = McpRegistry::new(tmp.path()).await;
+        let mut ids = Vec::new();
+        for name in ["Search", "Other"] {
+            let id = registry
+                .add(AddMcpRequest {
+                    name: name.to_string(),
+                    transport: McpTransport::Stdio {
+                        command: "sh".to_string(),
+                        args: vec!["-c".to_string(), FAKE_SERVER.to_string()],
+                        env: HashMap::new(),
+                    },
+                    description: None,
+                    scope: None,
+                    default_enabled: None,
+                })
+                .await
+                .unwrap()
+                .config
+                .id;
+            let state = registry.refresh(id).await.unwrap();
+            assert_eq!(state.status, McpStatus::Connected, "{:?}", state.error);
+            ids.push(id);
+        }
+
+        let search = registry.get(ids[0]).await.unwrap().config;
+        let mission = Uuid::new_v4();
+        let workspace = Workspace::default_host(tmp.path().to_path_buf());
+        let gateway = McpGateway::new(
+            &registry,
+            mission,
+            &workspace,
+            [&search],
+            McpPolicies::default(),
+        );
+        let events = StdMutex::new(Vec::new());
+        let on_event = |event: GatewayEvent| events.lock().unwrap().push(event);
+
+        let request = |id: u64, method: &str, params: Value| json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let listed = gateway
+            .handle(request(1, "tools/list", Value::Null), &on_event)
+            .await
+            .unwrap();
+        assert_eq!(listed["result"]["tools"][0]["name"], "search_lookup");
+        assert_eq!(listed["result"]["tools"].as_array().unwrap().len(), 1);
+
+        let called = gateway
+            .handle(
+                request(2, "tools/call", json!({ "name": "search_lookup" })),
+                &on_event,
+            )
+            .await
+            .unwrap();
+        assert_eq!(called["result"]["content"][0]["text"], "found");
+        assert!(matches!(
+            &events.lock().unwrap()[..],
+            [
+                GatewayEvent::Call { .. },
+                GatewayEvent::Result { output: Ok(_), .. }
+            ]
+        ));
+        // Calls are counted in the mission's telemetry
+        let stats = registry.tool_stats(&crate::mcp::McpStatsQuery {
+            mission_id: Some(mission),
+            ..Default::default()
+        });
+        assert_eq!((stats[0].tool.as_str(), stats[0].calls), ("lookup", 1));
+
+        // Rate limits are reported to the agent as tool errors
+        let limits: crate::mcp::McpLimits =
+            serde_json::from_value(json!({ "tools": { "lookup": { "calls_per_minute": 1 } } }))
+                .unwrap();
+        registry.set_limits(search.id, limits).await.unwrap();
+        let call = |id: u64| {
+            gateway.handle(
+                request(id, "tools/call", json!({ "name": "search_lookup" })),
+                &on_event,
+            )
+        };
+        assert_eq!(call(5).await.unwrap()["result"]["isError"], false);
+        let limited = call(6).await.unwrap();
+        assert_eq!(limited["result"]["isError"], true);
+        let text = limited["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(
+            text.starts_with("Rate limit of 1 calls per minute"),
+            "{}",
+            text
+        );
+        registry
+            .set_limits(search.id, Default::default())
+            .await
+            .unwrap();
+
+        // Tools of MCPs outside the allowlist or disabled are not reachable
+        let denied = gateway
+            .handle(
+                request(3, "tools/call", json!({ "name": "other_lookup" })),
+                &on_event,
+            )
+            .await
+            .unwrap();
+        assert_eq!(denied["error"]["code"], -32602);
+        registry.disable_tool("search_lookup").await;
+        let listed = gateway
+            .handle(request(4, "tools/list", Value::Null), &on_event)
+            .await
+            .unwrap();
+        assert!(listed["result"]["tools"].as_array().unwrap().is_empty());
+
+        // Notifications get no response
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(gateway.handle(notification, &on_event).await.is_none());
+
+        // Tool policies hide denied tools and block denied arguments
+        registry.enable_tool("search_lookup").await;
+        let policy = |allow: &str| crate::mcp::McpToolPolicy {
+            allow: vec![allow.to_string()],
+            arguments: vec![crate::mcp::McpArgumentRule {
+                tool: "search/*".to_string(),
+                argument: "query".to_string(),
+                allow: Vec::new(),
+                deny: vec!["*secret*".to_string()],
+            }],
+            ..Default::default()
+        };
+        let hidden = McpGateway::new(
+            &registry,
+            Uuid::new_v4(),
+            &workspace,
+            [&search],
+            McpPolicies::new([policy("search/other")]),
+        );
+        let listed = hidden
+            .handle(request(5, "tools/list", Value::Null), &on_event)
+            .await
+            .unwrap();
+        assert!(listed["result"]["tools"].as_array().unwrap().is_empty());
+
+        let restricted = McpGateway::new(
+            &registry,
+            Uuid::new_v4(),
+            &workspace,
+            [&search],
+            McpPolicies::new([policy("search/lookup")]),
+        );
+        let call = |id: u64, query: &str| {
+            request(
+                id,
+                "tools/call",
+                json!({ "name": "search_lookup", "arguments": { "query": query } }),
+            )
+        };
+        let allowed = restricted.handle(call(6, "docs"), &on_event).await.unwrap();
+        assert_eq!(allowed["result"]["isError"], false);
+        let blocked = restricted
+            .handle(call(7, "the secret key"), &on_event)
+            .await
+            .unwrap();
+        assert_eq!(blocked["result"]["isError"], true);
+        assert!(blocked["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("not allowed"));
+    }
+
+    #[tokio::test]
+    async fn proxies_workspace_mcps_to_their_instance() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = McpRegistry::new(tmp.path()).await;
+        let config = registry
+            .add(AddMcpRequest {
+                name: "Local".to_string(),
+                transport: McpTransport::Stdio {
+                    command: "sh".to_string(),
+                    args: vec!["-c".to_string(), FAKE_SERVER.to_string()],
+                    env: HashMap::new(),
+                },
+                description: None,
+                scope: Some(McpScope::Workspace),
+                default_enabled: None,
+            })
+            .await
+            .unwrap()
+            .config;
+
+        let mut workspace = Workspace::default_host(tmp.path().to_path_buf());
+        workspace.mcps = vec!["Local".to_string()];
+        let gateway = McpGateway::new(
+            &registry,
+            Uuid::new_v4(),
+            &workspace,
+            [&config],
+            McpPolicies::default(),
+        );
+        let on_event = |_: GatewayEvent| {};
+        let request = |id: u64, method: &str, params: Value| json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let listed = gateway
+            .handle(request(1, "tools/list", Value::Null), &on_event)
+            .await
+            .unwrap();
+        assert_eq!(listed["result"]["tools"][0]["name"], "local_lookup");
+        let called = gateway
+            .handle(
+                request(2, "tools/call", json!({ "name": "local_lookup" })),
+                &on_event,
+            )
+            .await
+            .unwrap();
+        assert_eq!(called["result"]["content"][0]["text"], "found");
+
+        // The call ran in the workspace instance, not on the host
+        let instance = registry
+            .workspace_instances(&workspace)
+            .await
+            .into_iter()
+            .find(|i| i.mcp_id == config.id)
+            .unwrap();
+        assert_eq!(instance.status, McpStatus::Connected);
+        assert_eq!(
+            registry.get(config.id).await.unwrap().status,
+            McpStatus::Disconnected
+        );
+        registry.stop_all_workspaces().await;
+    }
+}
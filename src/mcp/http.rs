@@ -0,0 +1,1361 @@
+//! Remote MCP transports.
+//!
+//! - Streamable HTTP (`http`): every JSON-RPC message is POSTed to one
+//!   endpoint, which answers with JSON or with an SSE stream that may carry
+//!   notifications before the response. The `Mcp-Session-Id` returned by
+//!   `initialize` is sent on every later request. A stream that drops before
+//!   the response is resumed with a GET carrying `Last-Event-ID`, and a session
+//!   the server no longer knows (404) is initialized again. A background GET
+//!   stream receives server-initiated messages.
+//! - Legacy HTTP+SSE (`sse`): a long-lived GET stream announces the POST
+//!   endpoint in an `endpoint` event and carries every response. A dropped
+//!   stream is reopened and the new session initialized.
+
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex, RwLock as StdRwLock};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use tokio::sync::{broadcast, oneshot, watch};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::registry::{MCP_CONNECT_TIMEOUT, MCP_REQUEST_TIMEOUT};
+use super::types::*;
+
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+const PROTOCOL_VERSION_HEADER: &str = "MCP-Protocol-Version";
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+const EVENT_STREAM: &str = "text/event-stream";
+
+/// How often a dropped event stream is reopened before giving up.
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+/// Delay before reopening a stream when the server did not send `retry`.
+const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+// ==================== Server-Sent Events ====================
+
+/// A server-sent event.
+#[derive(Debug, Clone, PartialEq)]
+struct SseEvent {
+    event: String,
+    data: String,
+}
+
+/// Incremental `text/event-stream` parser.
+#[derive(Debug, Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    event: String,
+    data: String,
+    has_data: bool,
+    /// Last event ID seen, kept across reconnections
+    last_event_id: Option<String>,
+    /// Reconnection delay requested by the server
+    retry: Option<Duration>,
+}
+
+impl SseParser {
+    /// Feed a chunk of the stream, returning the events it completes.
+    fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(event) = self.line(line.trim_end_matches('\n').trim_end_matches('\r')) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            let event = std::mem::take(&mut self.event);
+            let data = std::mem::take(&mut self.data);
+            if !std::mem::take(&mut self.has_data) {
+                return None;
+            }
+            return Some(SseEvent {
+                event: if event.is_empty() {
+                    "message".to_string()
+                } else {
+                    event
+                },
+                data,
+            });
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                if self.has_data {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => {
+                self.last_event_id = (!value.is_empty()).then(|| value.to_string());
+            }
+            "retry" => {
+                if let Ok(ms) = value.parse() {
+                    self.retry = Some(Duration::from_millis(ms));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+type ByteStream = Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>;
+
+/// Events read from an HTTP response body.
+struct SseStream {
+    body: ByteStream,
+    parser: SseParser,
+    queued: VecDeque<SseEvent>,
+    done: bool,
+}
+
+impl SseStream {
+    fn new(response: reqwest::Response) -> Self {
+        Self {
+            body: Box::pin(response.bytes_stream()),
+            parser: SseParser::default(),
+            queued: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Continue with a new response after a reconnection, keeping the last event ID.
+    fn reopen(self, response: reqwest::Response) -> Self {
+        Self {
+            parser: SseParser {
+                last_event_id: self.parser.last_event_id,
+                retry: self.parser.retry,
+                ..Default::default()
+            },
+            ..Self::new(response)
+        }
+    }
+
+    /// Next event, or `None` once the stream ended or broke.
+    async fn next(&mut self) -> Option<SseEvent> {
+        loop {
+            if let Some(event) = self.queued.pop_front() {
+                return Some(event);
+            }
+            if self.done {
+                return None;
+            }
+            match self.body.next().await {
+                Some(Ok(chunk)) => self.queued.extend(self.parser.feed(&chunk)),
+                Some(Err(e)) => {
+                    tracing::debug!(error = %e, "MCP event stream interrupted");
+                    self.done = true;
+                }
+                None => self.done = true,
+            }
+        }
+    }
+
+    fn last_event_id(&self) -> Option<String> {
+        self.parser.last_event_id.clone()
+    }
+
+    /// Delay before reconnection attempt `attempt` (starting at 1).
+    fn reconnect_delay(&self, attempt: u32) -> Duration {
+        self.parser
+            .retry
+            .unwrap_or(DEFAULT_RECONNECT_DELAY * 2u32.pow(attempt.saturating_sub(1)))
+    }
+}
+
+fn is_event_stream(response: &reqwest::Response) -> bool {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with(EVENT_STREAM))
+}
+
+// ==================== JSON-RPC messages ====================
+
+/// A JSON-RPC message received from a server.
+pub(super) enum Incoming {
+    Response(JsonRpcResponse),
+    /// Server-to-client request (e.g. `ping`)
+    Request {
+        id: Value,
+        method: String,
+    },
+    Notification {
+        method: String,
+        params: Option<Value>,
+    },
+}
+
+/// Parse a message or batch; anything that is not JSON-RPC is skipped.
+pub(super) fn parse_messages(data: &str) -> Vec<Incoming> {
+    let Ok(value) = serde_json::from_str::<Value>(data.trim()) else {
+        tracing::debug!(data = %data.trim(), "Ignoring non JSON-RPC output from MCP server");
+        return Vec::new();
+    };
+    let values = match value {
+        Value::Array(values) => values,
+        value => vec![value],
+    };
+    values
+        .into_iter()
+        .filter_map(|value| {
+            let method = value
+                .get("method")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            match (method, value.get("id").filter(|id| !id.is_null()).cloned()) {
+                (Some(method), Some(id)) => Some(Incoming::Request { id, method }),
+                (Some(method), None) => Some(Incoming::Notification {
+                    method,
+                    params: value.get("params").cloned(),
+                }),
+                (None, _) => serde_json::from_value(value).ok().map(Incoming::Response),
+            }
+        })
+        .collect()
+}
+
+/// Result of a JSON-RPC response, or its error.
+pub(super) fn response_result(response: JsonRpcResponse) -> Result<Value> {
+    if let Some(error) = response.error {
+        anyhow::bail!("JSON-RPC error {}: {}", error.code, error.message);
+    }
+    response
+        .result
+        .ok_or_else(|| anyhow::anyhow!("No result in response"))
+}
+
+/// Answer to a server-to-client request; only `ping` is supported.
+pub(super) fn reply_to(id: Value, method: &str) -> Value {
+    if method == "ping" {
+        json!({ "jsonrpc": "2.0", "id": id, "result": {} })
+    } else {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32601, "message": format!("Method not found: {}", method) }
+        })
+    }
+}
+
+/// Ask the server for progress notifications (`_meta.progressToken`).
+pub(super) fn with_progress_token(params: Option<Value>, token: u64) -> Option<Value> {
+    let mut params = params.unwrap_or_else(|| json!({}));
+    if let Some(params) = params.as_object_mut() {
+        if let Some(meta) = params
+            .entry("_meta")
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+        {
+            meta.insert("progressToken".to_string(), json!(token));
+        }
+    }
+    Some(params)
+}
+
+/// Progress token of a `notifications/progress` message.
+pub(super) fn progress_token(params: &Option<Value>) -> Option<u64> {
+    params.as_ref()?.get("progressToken")?.as_u64()
+}
+
+/// Pass a progress notification to `progress`.
+pub(super) fn report_progress(progress: &McpProgressCallback, params: &Option<Value>) {
+    if let Some(update) = params
+        .as_ref()
+        .and_then(|p| serde_json::from_value::<McpProgress>(p.clone()).ok())
+    {
+        progress(update);
+    }
+}
+
+/// Forwards server notifications to registry subscribers.
+#[derive(Clone)]
+pub(super) struct NotificationSink {
+    mcp_id: Uuid,
+    tx: broadcast::Sender<McpNotification>,
+}
+
+impl NotificationSink {
+    pub(super) fn new(mcp_id: Uuid, tx: broadcast::Sender<McpNotification>) -> Self {
+        Self { mcp_id, tx }
+    }
+
+    fn emit(&self, method: String, params: Option<Value>) {
+        // No subscribers is fine
+        let _ = self.tx.send(McpNotification {
+            mcp_id: self.mcp_id,
+            method,
+            params,
+        });
+    }
+}
+
+fn apply_headers(
+    mut builder: reqwest::RequestBuilder,
+    headers: &HashMap<String, String>,
+) -> reqwest::RequestBuilder {
+    for (key, value) in headers {
+        builder = builder.header(key.as_str(), value.as_str());
+    }
+    builder
+}
+
+// ==================== Streamable HTTP ====================
+
+struct StreamableInner {
+    http: reqwest::Client,
+    endpoint: String,
+    /// Replaced when the OAuth access token is refreshed
+    headers: StdRwLock<HashMap<String, String>>,
+    init_params: Value,
+    session_id: StdMutex<Option<String>>,
+    protocol_version: StdMutex<Option<String>>,
+    next_id: AtomicU64,
+    sink: NotificationSink,
+}
+
+impl StreamableInner {
+    fn session_id(&self) -> Option<String> {
+        self.session_id.lock().unwrap().clone()
+    }
+
+    fn with_session(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let mut builder = apply_headers(builder, &self.headers.read().unwrap());
+        if let Some(session_id) = self.session_id() {
+            builder = builder.header(SESSION_HEADER, session_id);
+        }
+        if let Some(version) = self.protocol_version.lock().unwrap().clone() {
+            builder = builder.header(PROTOCOL_VERSION_HEADER, version);
+        }
+        builder
+    }
+
+    async fn post(&self, message: &Value) -> Result<reqwest::Response> {
+        let builder = self
+            .http
+            .post(&self.endpoint)
+            .header(
+                reqwest::header::ACCEPT,
+                "application/json, text/event-stream",
+            )
+            .timeout(MCP_REQUEST_TIMEOUT)
+            .json(message);
+        Ok(self.with_session(builder).send().await?)
+    }
+
+    async fn open_stream(&self, last_event_id: Option<&str>) -> Result<reqwest::Response> {
+        let mut builder = self
+            .http
+            .get(&self.endpoint)
+            .header(reqwest::header::ACCEPT, EVENT_STREAM);
+        if let Some(last_event_id) = last_event_id {
+            builder = builder.header(LAST_EVENT_ID_HEADER, last_event_id);
+        }
+        Ok(self.with_session(builder).send().await?)
+    }
+
+    /// Start a new session.
+    async fn initialize(&self) -> Result<InitializeResult> {
+        *self.session_id.lock().unwrap() = None;
+        *self.protocol_version.lock().unwrap() = None;
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let message = serde_json::to_value(JsonRpcRequest::new(
+            id,
+            "initialize",
+            Some(self.init_params.clone()),
+        ))?;
+        let response = self.post(&message).await?;
+        if !response.status().is_success() {
+            anyhow::bail!("HTTP {}", response.status());
+        }
+        if let Some(session_id) = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+        {
+            *self.session_id.lock().unwrap() = Some(session_id.to_string());
+        }
+
+        let result = self.read_response(id, response, None).await?;
+        let init_result: InitializeResult = serde_json::from_value(result)?;
+        *self.protocol_version.lock().unwrap() = Some(init_result.protocol_version.clone());
+
+        // No response expected, but some servers require it
+        let _ = self
+            .post(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .await;
+
+        Ok(init_result)
+    }
+
+    async fn request(
+        &self,
+        method: &str,
+        params: Option<Value>,
+        progress: Option<&McpProgressCallback>,
+    ) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let params = match progress {
+            Some(_) => with_progress_token(params, id),
+            None => params,
+        };
+        let message = serde_json::to_value(JsonRpcRequest::new(id, method, params))?;
+
+        let mut reinitialized = false;
+        loop {
+            let had_session = self.session_id().is_some();
+            let response = self.post(&message).await?;
+            if response.status() == StatusCode::NOT_FOUND && had_session && !reinitialized {
+                tracing::info!(endpoint = %self.endpoint, "MCP session expired, starting a new one");
+                self.initialize().await?;
+                reinitialized = true;
+                continue;
+            }
+            if !response.status().is_success() {
+                anyhow::bail!("HTTP {}", response.status());
+            }
+            return self.read_response(id, response, progress).await;
+        }
+    }
+
+    /// Read the response to request `id` from a JSON body or an event stream,
+    /// resuming the stream if it drops.
+    async fn read_response(
+        &self,
+        id: u64,
+        response: reqwest::Response,
+        progress: Option<&McpProgressCallback>,
+    ) -> Result<Value> {
+        if !is_event_stream(&response) {
+            let response: JsonRpcResponse = response.json().await?;
+            return response_result(response);
+        }
+
+        let mut stream = SseStream::new(response);
+        let mut attempts = 0;
+        loop {
+            while let Some(event) = stream.next().await {
+                if let Some(result) = self.dispatch(&event, Some(id), progress).await {
+                    return result;
+                }
+            }
+
+            // Only streams with event IDs can be resumed
+            let Some(last_event_id) = stream.last_event_id() else {
+                anyhow::bail!("MCP event stream closed before the response");
+            };
+            attempts += 1;
+            if attempts > MAX_RECONNECT_ATTEMPTS {
+                anyhow::bail!(
+                    "MCP event stream closed before the response ({} resume attempts)",
+                    MAX_RECONNECT_ATTEMPTS
+                );
+            }
+            tokio::time::sleep(stream.reconnect_delay(attempts)).await;
+            let response = self.open_stream(Some(&last_event_id)).await?;
+            if !response.status().is_success() {
+                anyhow::bail!(
+                    "Resuming MCP event stream failed: HTTP {}",
+                    response.status()
+                );
+            }
+            tracing::debug!(endpoint = %self.endpoint, last_event_id = %last_event_id, "Resumed MCP event stream");
+            stream = stream.reopen(response);
+        }
+    }
+
+    /// Handle one event; returns the result once the response to `id` arrives.
+    async fn dispatch(
+        &self,
+        event: &SseEvent,
+        id: Option<u64>,
+        progress: Option<&McpProgressCallback>,
+    ) -> Option<Result<Value>> {
+        if event.event != "message" {
+            return None;
+        }
+        for message in parse_messages(&event.data) {
+            match message {
+                Incoming::Response(response) if id.is_some() && response.id == id => {
+                    return Some(response_result(response));
+                }
+                Incoming::Response(response) => {
+                    tracing::debug!(id = ?response.id, "Ignoring unexpected MCP response");
+                }
+                Incoming::Request { id, method } => {
+                    let _ = self.post(&reply_to(id, &method)).await;
+                }
+                Incoming::Notification { method, params } => {
+                    if let Some(progress) = progress {
+                        if method == "notifications/progress" && progress_token(&params) == id {
+                            report_progress(progress, &params);
+                        }
+                    }
+                    self.sink.emit(method, params);
+                }
+            }
+        }
+        None
+    }
+
+    /// Receive server-initiated messages on a GET stream until the server
+    /// declines it or it keeps failing.
+    async fn listen(self: Arc<Self>) {
+        let mut last_event_id: Option<String> = None;
+        let mut failures = 0;
+        loop {
+            match self.open_stream(last_event_id.as_deref()).await {
+                Ok(response) if response.status() == StatusCode::METHOD_NOT_ALLOWED => {
+                    tracing::debug!(endpoint = %self.endpoint, "MCP server has no notification stream");
+                    return;
+                }
+                Ok(response) if response.status().is_success() && is_event_stream(&response) => {
+                    let mut stream = SseStream::new(response);
+                    stream.parser.last_event_id = last_event_id.take();
+                    while let Some(event) = stream.next().await {
+                        failures = 0;
+                        self.dispatch(&event, None, None).await;
+                    }
+                    last_event_id = stream.last_event_id();
+                }
+                Ok(response) => {
+                    tracing::debug!(endpoint = %self.endpoint, status = %response.status(), "MCP notification stream refused");
+                }
+                Err(e) => {
+                    tracing::debug!(endpoint = %self.endpoint, error = %e, "MCP notification stream failed");
+                }
+            }
+
+            failures += 1;
+            if failures > MAX_RECONNECT_ATTEMPTS {
+                return;
+            }
+            tokio::time::sleep(DEFAULT_RECONNECT_DELAY * 2u32.pow(failures - 1)).await;
+        }
+    }
+}
+
+/// Client for the Streamable HTTP transport.
+pub(super) struct StreamableHttpClient {
+    inner: Arc<StreamableInner>,
+    listener: JoinHandle<()>,
+}
+
+impl StreamableHttpClient {
+    async fn connect(
+        http: reqwest::Client,
+        endpoint: &str,
+        headers: &HashMap<String, String>,
+        init_params: Value,
+        sink: NotificationSink,
+    ) -> Result<(Self, InitializeResult)> {
+        let inner = Arc::new(StreamableInner {
+            http,
+            endpoint: endpoint.to_string(),
+            headers: StdRwLock::new(headers.clone()),
+            init_params,
+            session_id: StdMutex::new(None),
+            protocol_version: StdMutex::new(None),
+            next_id: AtomicU64::new(1),
+            sink,
+        });
+        let init_result = inner.initialize().await?;
+        let listener = tokio::spawn(Arc::clone(&inner).listen());
+        Ok((Self { inner, listener }, init_result))
+    }
+
+    /// End the session on the server.
+    async fn close(&self) {
+        self.listener.abort();
+        let Some(session_id) = self.inner.session_id.lock().unwrap().take() else {
+            return;
+        };
+        let builder = apply_headers(
+            self.inner.http.delete(&self.inner.endpoint),
+            &self.inner.headers.read().unwrap(),
+        )
+        .header(SESSION_HEADER, session_id)
+        .timeout(MCP_CONNECT_TIMEOUT);
+        // Servers may not support explicit termination (405)
+        let _ = builder.send().await;
+    }
+}
+
+impl Drop for StreamableHttpClient {
+    fn drop(&mut self) {
+        self.listener.abort();
+    }
+}
+
+// ==================== Legacy HTTP+SSE ====================
+
+struct PendingRequest {
+    tx: oneshot::Sender<JsonRpcResponse>,
+    progress: Option<McpProgressCallback>,
+}
+
+struct SseInner {
+    http: reqwest::Client,
+    endpoint: String,
+    /// Replaced when the OAuth access token is refreshed
+    headers: StdRwLock<HashMap<String, String>>,
+    init_params: Value,
+    /// POST endpoint announced by the current stream
+    post_url: watch::Sender<Option<String>>,
+    pending: StdMutex<HashMap<u64, PendingRequest>>,
+    next_id: AtomicU64,
+    sink: NotificationSink,
+}
+
+impl SseInner {
+    async fn open_stream(&self, last_event_id: Option<&str>) -> Result<reqwest::Response> {
+        let mut builder =
+            apply_headers(self.http.get(&self.endpoint), &self.headers.read().unwrap())
+                .header(reqwest::header::ACCEPT, EVENT_STREAM);
+        if let Some(last_event_id) = last_event_id {
+            builder = builder.header(LAST_EVENT_ID_HEADER, last_event_id);
+        }
+        let response = builder.send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("HTTP {}", response.status());
+        }
+        Ok(response)
+    }
+
+    async fn wait_post_url(&self) -> Result<String> {
+        let mut rx = self.post_url.subscribe();
+        let url = tokio::time::timeout(MCP_CONNECT_TIMEOUT, rx.wait_for(Option::is_some))
+            .await
+            .map_err(|_| anyhow::anyhow!("Timed out waiting for the MCP SSE endpoint"))??
+            .clone();
+        Ok(url.unwrap_or_default())
+    }
+
+    async fn post(&self, message: &Value) -> Result<()> {
+        let url = self.wait_post_url().await?;
+        let builder = apply_headers(self.http.post(&url), &self.headers.read().unwrap())
+            .timeout(MCP_REQUEST_TIMEOUT)
+            .json(message);
+        let response = builder.send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("HTTP {}", response.status());
+        }
+        Ok(())
+    }
+
+    async fn request(
+        &self,
+        method: &str,
+        params: Option<Value>,
+        progress: Option<&McpProgressCallback>,
+    ) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let params = match progress {
+            Some(_) => with_progress_token(params, id),
+            None => params,
+        };
+        let message = serde_json::to_value(JsonRpcRequest::new(id, method, params))?;
+
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(
+            id,
+            PendingRequest {
+                tx,
+                progress: progress.cloned(),
+            },
+        );
+        if let Err(e) = self.post(&message).await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+
+        match tokio::time::timeout(MCP_REQUEST_TIMEOUT, rx).await {
+            Ok(Ok(response)) => response_result(response),
+            Ok(Err(_)) => anyhow::bail!("MCP event stream closed before the response"),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                anyhow::bail!("Timeout waiting for MCP response")
+            }
+        }
+    }
+
+    async fn initialize(&self) -> Result<InitializeResult> {
+        let result = self
+            .request("initialize", Some(self.init_params.clone()), None)
+            .await?;
+        let init_result = serde_json::from_value(result)?;
+        self.post(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .await?;
+        Ok(init_result)
+    }
+
+    /// Resolve the announced POST endpoint, which must share the stream's origin.
+    fn resolve_post_url(&self, data: &str) -> Result<String> {
+        let base = reqwest::Url::parse(&self.endpoint).context("Invalid MCP SSE endpoint")?;
+        let url = base
+            .join(data.trim())
+            .context("Invalid MCP SSE endpoint event")?;
+        if url.origin() != base.origin() {
+            anyhow::bail!("MCP SSE endpoint event points to another origin: {}", url);
+        }
+        Ok(url.to_string())
+    }
+
+    async fn dispatch(&self, event: SseEvent) {
+        match event.event.as_str() {
+            "endpoint" => match self.resolve_post_url(&event.data) {
+                Ok(url) => {
+                    self.post_url.send_replace(Some(url));
+                }
+                Err(e) => {
+                    tracing::warn!(endpoint = %self.endpoint, error = %e, "Ignoring MCP SSE endpoint")
+                }
+            },
+            "message" => {
+                for message in parse_messages(&event.data) {
+                    match message {
+                        Incoming::Response(response) => {
+                            let pending = response
+                                .id
+                                .and_then(|id| self.pending.lock().unwrap().remove(&id));
+                            match pending {
+                                Some(pending) => {
+                                    let _ = pending.tx.send(response);
+                                }
+                                None => {
+                                    tracing::debug!(id = ?response.id, "Ignoring unexpected MCP response")
+                                }
+                            }
+                        }
+                        Incoming::Request { id, method } => {
+                            let _ = self.post(&reply_to(id, &method)).await;
+                        }
+                        Incoming::Notification { method, params } => {
+                            if method == "notifications/progress" {
+                                let progress = progress_token(&params).and_then(|token| {
+                                    self.pending
+                                        .lock()
+                                        .unwrap()
+                                        .get(&token)
+                                        .and_then(|p| p.progress.clone())
+                                });
+                                if let Some(progress) = progress {
+                                    report_progress(&progress, &params);
+                                }
+                            }
+                            self.sink.emit(method, params);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Read the event stream, reopening it (as a new session) when it drops.
+    async fn read(self: Arc<Self>, mut stream: SseStream) {
+        let mut attempts = 0;
+        loop {
+            while let Some(event) = stream.next().await {
+                attempts = 0;
+                self.dispatch(event).await;
+            }
+
+            // The server forgets requests of a dropped session
+            self.post_url.send_replace(None);
+            self.pending.lock().unwrap().clear();
+
+            attempts += 1;
+            if attempts > MAX_RECONNECT_ATTEMPTS {
+                tracing::warn!(endpoint = %self.endpoint, "MCP SSE stream closed, giving up reconnecting");
+                return;
+            }
+            tokio::time::sleep(stream.reconnect_delay(attempts)).await;
+            match self.open_stream(stream.last_event_id().as_deref()).await {
+                Ok(response) => {
+                    tracing::info!(endpoint = %self.endpoint, "Reconnected MCP SSE stream");
+                    stream = stream.reopen(response);
+                    let inner = Arc::clone(&self);
+                    tokio::spawn(async move {
+                        if let Err(e) = inner.initialize().await {
+                            tracing::warn!(endpoint = %inner.endpoint, error = %e, "Failed to initialize reconnected MCP session");
+                        }
+                    });
+                }
+                Err(e) => {
+                    tracing::debug!(endpoint = %self.endpoint, error = %e, "Reconnecting MCP SSE stream failed");
+                }
+            }
+        }
+    }
+}
+
+/// Client for the legacy HTTP+SSE transport.
+pub(super) struct SseClient {
+    inner: Arc<SseInner>,
+    reader: JoinHandle<()>,
+}
+
+impl SseClient {
+    async fn connect(
+        http: reqwest::Client,
+        endpoint: &str,
+        headers: &HashMap<String, String>,
+        init_params: Value,
+        sink: NotificationSink,
+    ) -> Result<(Self, InitializeResult)> {
+        let inner = Arc::new(SseInner {
+            http,
+            endpoint: endpoint.to_string(),
+            headers: StdRwLock::new(headers.clone()),
+            init_params,
+            post_url: watch::Sender::new(None),
+            pending: StdMutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            sink,
+        });
+        let response = inner.open_stream(None).await?;
+        let reader = tokio::spawn(Arc::clone(&inner).read(SseStream::new(response)));
+        let client = Self { inner, reader };
+        let init_result = client.inner.initialize().await?;
+        Ok((client, init_result))
+    }
+}
+
+impl Drop for SseClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+// ==================== Remote client ====================
+
+/// Connection to an MCP server over HTTP.
+pub(super) enum RemoteClient {
+    Streamable(StreamableHttpClient),
+    Sse(SseClient),
+}
+
+impl RemoteClient {
+    /// Connect and initialize a session.
+    pub(super) async fn connect(
+        transport: &McpTransport,
+        http: reqwest::Client,
+        init_params: Value,
+        sink: NotificationSink,
+    ) -> Result<(Self, InitializeResult)> {
+        match transport {
+            McpTransport::Http { endpoint, headers } => {
+                let endpoint = endpoint.trim_end_matches('/');
+                let (client, init_result) =
+                    StreamableHttpClient::connect(http, endpoint, headers, init_params, sink)
+                        .await?;
+                Ok((Self::Streamable(client), init_result))
+            }
+            McpTransport::Sse { endpoint, headers } => {
+                let endpoint = endpoint.trim_end_matches('/');
+                let (client, init_result) =
+                    SseClient::connect(http, endpoint, headers, init_params, sink).await?;
+                Ok((Self::Sse(client), init_result))
+            }
+            McpTransport::Stdio { .. } => anyhow::bail!("Not a remote MCP transport"),
+        }
+    }
+
+    /// Send a request and wait for its result.
+    pub(super) async fn request(
+        &self,
+        method: &str,
+        params: Option<Value>,
+        progress: Option<&McpProgressCallback>,
+    ) -> Result<Value> {
+        match self {
+            Self::Streamable(client) => client.inner.request(method, params, progress).await,
+            Self::Sse(client) => client.inner.request(method, params, progress).await,
+        }
+    }
+
+    /// Send `value` in the `key` header of later requests, e.g. a refreshed
+    /// bearer token, keeping the session.
+    pub(super) fn set_header(&self, key: &str, value: String) {
+        let headers = match self {
+            Self::Streamable(client) => &client.inner.headers,
+            Self::Sse(client) => &client.inner.headers,
+        };
+        headers.write().unwrap().insert(key.to_string(), value);
+    }
+
+    /// End the session and stop reading server events.
+    pub(super) async fn close(&self) {
+        match self {
+            Self::Streamable(client) => client.close().await,
+            Self::Sse(client) => {
+                client.reader.abort();
+                client.inner.pending.lock().unwrap().clear();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::{AddMcpRequest, McpPolicies, McpRegistry, McpStatus, McpToolPolicy};
+    use axum::body::Body;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode as HttpStatus};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicBool, AtomicUsize};
+    use tokio::sync::mpsc;
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    fn sse(body: String) -> Response {
+        ([("content-type", EVENT_STREAM)], body).into_response()
+    }
+
+    fn result(id: &Value, result: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "result": result })
+    }
+
+    fn init_result() -> Value {
+        json!({
+            "protocolVersion": "2025-03-26",
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "fake", "version": "1.0" }
+        })
+    }
+
+    fn tools_result() -> Value {
+        json!({ "tools": [
+            { "name": "slow", "description": "Reports progress", "inputSchema": { "type": "object" } },
+            { "name": "echo", "description": "Echoes", "inputSchema": { "type": "object" } }
+        ]})
+    }
+
+    fn progress_message(params: &Value) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": {
+                "progressToken": params["_meta"]["progressToken"],
+                "progress": 1,
+                "total": 2,
+                "message": "halfway"
+            }
+        })
+    }
+
+    fn text_result(id: &Value, text: &str) -> Value {
+        result(id, json!({ "content": [{ "type": "text", "text": text }] }))
+    }
+
+    async fn add_mcp(registry: &McpRegistry, transport: McpTransport) -> Uuid {
+        registry
+            .add(AddMcpRequest {
+                name: "fake".to_string(),
+                transport,
+                description: None,
+                scope: None,
+                default_enabled: None,
+            })
+            .await
+            .unwrap()
+            .config
+            .id
+    }
+
+    fn progress_recorder() -> (McpProgressCallback, Arc<StdMutex<Vec<McpProgress>>>) {
+        let seen = Arc::new(StdMutex::new(Vec::new()));
+        let recorder = Arc::clone(&seen);
+        let callback: McpProgressCallback =
+            Arc::new(move |progress| recorder.lock().unwrap().push(progress));
+        (callback, seen)
+    }
+
+    #[test]
+    fn parses_event_stream_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser
+            .feed(b": keep-alive\nid: 7\nretry: 1500\nda")
+            .is_empty());
+        let events = parser.feed(b"ta: {\"a\":\r\ndata: 1}\r\n\r\nevent: endpoint\ndata: /m\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: "message".to_string(),
+                    data: "{\"a\":\n1}".to_string()
+                },
+                SseEvent {
+                    event: "endpoint".to_string(),
+                    data: "/m".to_string()
+                },
+            ]
+        );
+        assert_eq!(parser.last_event_id.as_deref(), Some("7"));
+        assert_eq!(parser.retry, Some(Duration::from_millis(1500)));
+
+        // An ID alone (no data) updates the last event ID without an event
+        assert!(parser.feed(b"id: 8\n\n").is_empty());
+        assert_eq!(parser.last_event_id.as_deref(), Some("8"));
+    }
+
+    #[derive(Default)]
+    struct StreamableServer {
+        session: StdMutex<Option<String>>,
+        sessions_started: AtomicUsize,
+        expire: AtomicBool,
+        /// Request ID of the tool call whose stream was cut
+        interrupted: StdMutex<Option<Value>>,
+        resumed_from: StdMutex<Option<String>>,
+        notified: AtomicBool,
+    }
+
+    async fn streamable_post(
+        State(server): State<Arc<StreamableServer>>,
+        headers: HeaderMap,
+        Json(message): Json<Value>,
+    ) -> Response {
+        let Some(id) = message.get("id").cloned() else {
+            return HttpStatus::ACCEPTED.into_response();
+        };
+        let method = message["method"].as_str().unwrap_or_default();
+        if method == "initialize" {
+            let n = server.sessions_started.fetch_add(1, Ordering::SeqCst) + 1;
+            let session = format!("session-{}", n);
+            *server.session.lock().unwrap() = Some(session.clone());
+            return (
+                [(SESSION_HEADER, session)],
+                Json(result(&id, init_result())),
+            )
+                .into_response();
+        }
+
+        if server.expire.swap(false, Ordering::SeqCst) {
+            *server.session.lock().unwrap() = None;
+        }
+        let sent = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok());
+        if sent.is_none() || sent != server.session.lock().unwrap().as_deref() {
+            return HttpStatus::NOT_FOUND.into_response();
+        }
+
+        match (method, message["params"]["name"].as_str()) {
+            ("tools/list", _) => Json(result(&id, tools_result())).into_response(),
+            ("tools/call", Some("slow")) => {
+                // Progress, then the stream drops before the response
+                *server.interrupted.lock().unwrap() = Some(id);
+                sse(format!(
+                    "id: 1\ndata: {}\n\n",
+                    progress_message(&message["params"])
+                ))
+            }
+            _ => Json(text_result(&id, "echo")).into_response(),
+        }
+    }
+
+    async fn streamable_get(
+        State(server): State<Arc<StreamableServer>>,
+        headers: HeaderMap,
+    ) -> Response {
+        if let Some(last_event_id) = headers.get(LAST_EVENT_ID_HEADER) {
+            *server.resumed_from.lock().unwrap() =
+                Some(last_event_id.to_str().unwrap().to_string());
+            let id = server.interrupted.lock().unwrap().take().unwrap();
+            return sse(format!("id: 2\ndata: {}\n\n", text_result(&id, "done")));
+        }
+        if server.notified.swap(true, Ordering::SeqCst) {
+            return HttpStatus::METHOD_NOT_ALLOWED.into_response();
+        }
+        sse(format!(
+            "data: {}\n\n",
+            json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" })
+        ))
+    }
+
+    #[tokio::test]
+    async fn streamable_http_sessions_progress_and_resume() {
+        let server = Arc::new(StreamableServer::default());
+        let app = Router::new()
+            .route(
+                "/mcp",
+                post(streamable_post)
+                    .get(streamable_get)
+                    .delete(|| async { HttpStatus::OK }),
+            )
+            .with_state(Arc::clone(&server));
+        let base = serve(app).await;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = McpRegistry::new(tmp.path()).await;
+        let mut notifications = registry.subscribe_notifications();
+        let id = add_mcp(
+            &registry,
+            McpTransport::Http {
+                endpoint: format!("{}/mcp/", base),
+                headers: HashMap::new(),
+            },
+        )
+        .await;
+
+        let state = registry.refresh(id).await.unwrap();
+        assert_eq!(state.status, McpStatus::Connected, "{:?}", state.error);
+        assert_eq!(state.config.tools, vec!["slow", "echo"]);
+        assert_eq!(state.config.version.as_deref(), Some("1.0"));
+
+        // Server-initiated notification on the GET stream
+        let notification = tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(notification.mcp_id, id);
+        assert_eq!(notification.method, "notifications/tools/list_changed");
+
+        // Progress arrives before the stream drops; the response after resuming
+        let (callback, seen) = progress_recorder();
+        let output = registry
+            .call_tool_with_progress(id, "slow", json!({}), Some(callback))
+            .await
+            .unwrap();
+        assert_eq!(output, "done");
+        assert_eq!(server.resumed_from.lock().unwrap().as_deref(), Some("1"));
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![McpProgress {
+                progress: 1.0,
+                total: Some(2.0),
+                message: Some("halfway".to_string()),
+            }]
+        );
+
+        // An expired session is initialized again and the call retried
+        server.expire.store(true, Ordering::SeqCst);
+        let output = registry.call_tool(id, "echo", json!({})).await.unwrap();
+        assert_eq!(output, "echo");
+        assert_eq!(server.sessions_started.load(Ordering::SeqCst), 2);
+    }
+
+    #[derive(Default)]
+    struct LegacyServer {
+        stream: StdMutex<Option<mpsc::UnboundedSender<String>>>,
+    }
+
+    impl LegacyServer {
+        fn send(&self, message: Value) {
+            if let Some(tx) = self.stream.lock().unwrap().as_ref() {
+                let _ = tx.send(format!("data: {}\n\n", message));
+            }
+        }
+    }
+
+    async fn legacy_get(State(server): State<Arc<LegacyServer>>) -> Response {
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        tx.send("event: endpoint\ndata: /messages?session=1\n\n".to_string())
+            .unwrap();
+        *server.stream.lock().unwrap() = Some(tx);
+        let body = async_stream::stream! {
+            while let Some(chunk) = rx.recv().await {
+                yield Ok::<_, Infallible>(chunk);
+            }
+        };
+        ([("content-type", EVENT_STREAM)], Body::from_stream(body)).into_response()
+    }
+
+    async fn legacy_post(
+        State(server): State<Arc<LegacyServer>>,
+        Json(message): Json<Value>,
+    ) -> HttpStatus {
+        let Some(id) = message.get("id").cloned() else {
+            return HttpStatus::ACCEPTED;
+        };
+        let params = &message["params"];
+        match message["method"].as_str().unwrap_or_default() {
+            "initialize" => {
+                let mut init = init_result();
+                init["capabilities"]["resources"] = json!({ "subscribe": true });
+                init["capabilities"]["prompts"] = json!({});
+                server.send(result(&id, init))
+            }
+            "tools/list" => server.send(result(&id, tools_result())),
+            "resources/list" => server.send(result(
+                &id,
+                json!({ "resources": [
+                    { "uri": "file:///notes.md", "name": "notes", "mimeType": "text/markdown" }
+                ]}),
+            )),
+            "resources/read" => server.send(result(
+                &id,
+                json!({ "contents": [{ "uri": params["uri"], "text": "# Notes" }] }),
+            )),
+            "prompts/list" => server.send(result(
+                &id,
+                json!({ "prompts": [{
+                    "name": "review",
+                    "description": "Review a file",
+                    "arguments": [{ "name": "file", "required": true }]
+                }]}),
+            )),
+            "prompts/get" => server.send(result(
+                &id,
+                json!({ "messages": [
+                    { "role": "user", "content": {
+                        "type": "text",
+                        "text": format!("Review {}", params["arguments"]["file"].as_str().unwrap())
+                    }},
+                    { "role": "user", "content": { "type": "image", "data": "", "mimeType": "image/png" } }
+                ]}),
+            )),
+            _ => {
+                server.send(progress_message(&message["params"]));
+                server.send(text_result(&id, "done"));
+            }
+        }
+        HttpStatus::ACCEPTED
+    }
+
+    async fn connect_legacy(registry: &McpRegistry) -> (Uuid, McpServerState) {
+        let app = Router::new()
+            .route("/sse", axum::routing::get(legacy_get))
+            .route("/messages", post(legacy_post))
+            .with_state(Arc::new(LegacyServer::default()));
+        let base = serve(app).await;
+        let id = add_mcp(
+            registry,
+            McpTransport::Sse {
+                endpoint: format!("{}/sse", base),
+                headers: HashMap::new(),
+            },
+        )
+        .await;
+        let state = registry.refresh(id).await.unwrap();
+        assert_eq!(state.status, McpStatus::Connected, "{:?}", state.error);
+        (id, state)
+    }
+
+    #[tokio::test]
+    async fn legacy_sse_transport() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = McpRegistry::new(tmp.path()).await;
+        let (id, state) = connect_legacy(&registry).await;
+        assert_eq!(state.config.tools, vec!["slow", "echo"]);
+
+        let (callback, seen) = progress_recorder();
+        let output = registry
+            .call_tool_with_progress(id, "slow", json!({}), Some(callback))
+            .await
+            .unwrap();
+        assert_eq!(output, "done");
+        assert_eq!(seen.lock().unwrap().len(), 1);
+
+        registry.disable(id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn discovers_resources_and_prompts() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = McpRegistry::new(tmp.path()).await;
+        let (id, state) = connect_legacy(&registry).await;
+        assert_eq!(state.config.resources[0].uri, "file:///notes.md");
+        assert_eq!(
+            state.config.resources[0].mime_type.as_deref(),
+            Some("text/markdown")
+        );
+        assert!(state.config.prompts[0].arguments[0].required);
+
+        let read = registry
+            .read_resource(id, "file:///notes.md")
+            .await
+            .unwrap();
+        assert_eq!(read.contents[0].text.as_deref(), Some("# Notes"));
+
+        // Prompts are reachable as slash commands named after the MCP, for
+        // missions whose workspace and tool policy allow the MCP
+        let mut workspace = crate::workspace::Workspace::default_host(tmp.path().to_path_buf());
+        workspace.mcps = vec!["fake".to_string()];
+        let policies = McpPolicies::default();
+        let (mcp_id, prompt) = registry
+            .find_prompt_command("mcp__fake__review", &workspace, &policies)
+            .await
+            .unwrap();
+        assert_eq!(mcp_id, id);
+        assert!(registry
+            .find_prompt_command("mcp__other__review", &workspace, &policies)
+            .await
+            .is_none());
+        let denied = McpPolicies::new([McpToolPolicy {
+            deny: vec!["fake/*".to_string()],
+            ..Default::default()
+        }]);
+        assert!(registry
+            .find_prompt_command("mcp__fake__review", &workspace, &denied)
+            .await
+            .is_none());
+        workspace.mcps = vec!["github".to_string()];
+        assert!(registry
+            .find_prompt_command("mcp__fake__review", &workspace, &policies)
+            .await
+            .is_none());
+
+        let arguments = HashMap::from([("file".to_string(), "main.rs".to_string())]);
+        let result = registry
+            .get_prompt(id, &prompt.name, arguments)
+            .await
+            .unwrap();
+        assert_eq!(result.text(), "Review main.rs");
+    }
+
+    #[test]
+    fn rejects_endpoint_on_another_origin() {
+        let inner = SseInner {
+            http: reqwest::Client::new(),
+            endpoint: "https://mcp.example.com/sse".to_string(),
+            headers: StdRwLock::new(HashMap::new()),
+            init_params: Value::Null,
+            post_url: watch::Sender::new(None),
+            pending: StdMutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            sink: NotificationSink::new(Uuid::nil(), broadcast::channel(1).0),
+        };
+        assert_eq!(
+            inner.resolve_post_url("/messages?session=1").unwrap(),
+            "https://mcp.example.com/messages?session=1"
+        );
+        assert!(inner
+            .resolve_post_url("https://evil.example.com/messages")
+            .is_err());
+    }
+}
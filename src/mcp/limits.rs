@@ -0,0 +1,211 @@
+//! Rate limits and concurrency caps of MCP tool calls.
+//!
+//! Limits are configured per MCP (across all of its tools) and per tool, and
+//! are checked by [`super::McpRegistry::call_tool`] before a call is sent. A
+//! rejected call fails with an error telling the agent which limit it hit.
+//!
+//! Harnesses only call MCPs through the registry when the MCP gateway is
+//! enabled; otherwise they talk to the MCPs directly and their calls are
+//! neither limited nor counted in the telemetry.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Window of the per-minute rate limits.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Limit on the calls of an MCP or one of its tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpRateLimit {
+    /// Calls that may start within any minute
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calls_per_minute: Option<u32>,
+    /// Calls that may be in flight at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<u32>,
+}
+
+impl McpRateLimit {
+    pub fn is_empty(&self) -> bool {
+        self.calls_per_minute.is_none() && self.max_concurrent.is_none()
+    }
+}
+
+/// Call limits of an MCP, e.g.
+/// `{"calls_per_minute": 60, "tools": {"search": {"max_concurrent": 1}}}`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpLimits {
+    /// Limit across all tools of the MCP
+    #[serde(flatten)]
+    pub server: McpRateLimit,
+    /// Limits of individual tools (by tool name)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tools: HashMap<String, McpRateLimit>,
+}
+
+impl McpLimits {
+    pub fn is_empty(&self) -> bool {
+        self.server.is_empty() && self.tools.values().all(McpRateLimit::is_empty)
+    }
+}
+
+/// Usage of one limit: an MCP (no tool) or one of its tools.
+type UsageKey = (Uuid, Option<String>);
+
+#[derive(Debug, Default)]
+struct Usage {
+    /// Start times of the calls within the rate window
+    recent: VecDeque<Instant>,
+    in_flight: u32,
+}
+
+/// Tracks calls in flight and recent calls against the configured limits.
+#[derive(Debug, Default)]
+pub(super) struct CallLimiter {
+    usage: Arc<StdMutex<HashMap<UsageKey, Usage>>>,
+}
+
+/// A call admitted by the [`CallLimiter`]; releases its slots when dropped.
+#[derive(Debug)]
+pub(super) struct CallPermit {
+    usage: Arc<StdMutex<HashMap<UsageKey, Usage>>>,
+    keys: Vec<UsageKey>,
+}
+
+impl Drop for CallPermit {
+    fn drop(&mut self) {
+        let mut usage = self.usage.lock().unwrap();
+        for key in &self.keys {
+            if let Some(entry) = usage.get_mut(key) {
+                entry.in_flight = entry.in_flight.saturating_sub(1);
+            }
+        }
+    }
+}
+
+impl CallLimiter {
+    /// Admit a call of `tool` on the MCP named `server`, or say which limit
+    /// it would exceed.
+    pub(super) fn acquire(
+        &self,
+        mcp_id: Uuid,
+        server: &str,
+        tool: &str,
+        limits: &McpLimits,
+    ) -> Result<CallPermit, String> {
+        let mut checks = Vec::new();
+        if !limits.server.is_empty() {
+            checks.push(((mcp_id, None), limits.server, server.to_string()));
+        }
+        if let Some(limit) = limits.tools.get(tool).filter(|l| !l.is_empty()) {
+            checks.push((
+                (mcp_id, Some(tool.to_string())),
+                *limit,
+                format!("{}/{}", server, tool),
+            ));
+        }
+
+        let now = Instant::now();
+        let mut usage = self.usage.lock().unwrap();
+        for (key, limit, label) in &checks {
+            let entry = usage.entry(key.clone()).or_default();
+            while entry
+                .recent
+                .front()
+                .is_some_and(|start| now.duration_since(*start) >= RATE_WINDOW)
+            {
+                entry.recent.pop_front();
+            }
+            if let Some(max) = limit.max_concurrent {
+                if entry.in_flight >= max {
+                    return Err(format!(
+                        "Concurrency limit of {} calls reached for {}; try again when a call finishes",
+                        max, label
+                    ));
+                }
+            }
+            if let Some(max) = limit.calls_per_minute {
+                if entry.recent.len() >= max as usize {
+                    let retry = entry
+                        .recent
+                        .front()
+                        .map(|start| RATE_WINDOW.saturating_sub(now.duration_since(*start)))
+                        .unwrap_or_default();
+                    return Err(format!(
+                        "Rate limit of {} calls per minute reached for {}; retry in {}s",
+                        max,
+                        label,
+                        retry.as_secs().max(1)
+                    ));
+                }
+            }
+        }
+
+        for (key, _, _) in &checks {
+            let entry = usage.entry(key.clone()).or_default();
+            entry.recent.push_back(now);
+            entry.in_flight += 1;
+        }
+        Ok(CallPermit {
+            usage: Arc::clone(&self.usage),
+            keys: checks.into_iter().map(|(key, _, _)| key).collect(),
+        })
+    }
+
+    /// Forget the usage of a removed MCP.
+    pub(super) fn remove_mcp(&self, mcp_id: Uuid) {
+        self.usage
+            .lock()
+            .unwrap()
+            .retain(|(id, _), _| *id != mcp_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrency_slots_are_released() {
+        let limiter = CallLimiter::default();
+        let id = Uuid::new_v4();
+        let limits = McpLimits {
+            server: McpRateLimit {
+                max_concurrent: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let permit = limiter.acquire(id, "search", "lookup", &limits).unwrap();
+        let err = limiter.acquire(id, "search", "other", &limits).unwrap_err();
+        assert!(
+            err.contains("Concurrency limit of 1 calls reached for search"),
+            "{}",
+            err
+        );
+        drop(permit);
+        assert!(limiter.acquire(id, "search", "other", &limits).is_ok());
+    }
+
+    #[test]
+    fn tool_rate_limits_apply_to_that_tool() {
+        let limiter = CallLimiter::default();
+        let id = Uuid::new_v4();
+        let limits: McpLimits =
+            serde_json::from_str(r#"{"tools": {"lookup": {"calls_per_minute": 2}}}"#).unwrap();
+
+        for _ in 0..2 {
+            assert!(limiter.acquire(id, "search", "lookup", &limits).is_ok());
+        }
+        let err = limiter
+            .acquire(id, "search", "lookup", &limits)
+            .unwrap_err();
+        assert!(err.starts_with("Rate limit of 2 calls per minute reached for search/lookup"));
+        assert!(limiter.acquire(id, "search", "other", &limits).is_ok());
+    }
+}
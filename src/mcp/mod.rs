@@ -4,9 +4,20 @@
 //! Configurations are persisted to `{working_dir}/.sandboxed-sh/mcp/config.json`.
 
 mod config;
+pub mod gateway;
+mod http;
+mod limits;
+mod oauth;
+mod policy;
 mod registry;
+mod supervisor;
+mod telemetry;
 mod types;
 
 pub use config::McpConfigStore;
+pub use limits::{McpLimits, McpRateLimit};
+pub use oauth::McpOAuthStatus;
+pub use policy::{McpArgumentRule, McpPolicies, McpToolPolicy};
 pub use registry::McpRegistry;
+pub use telemetry::{McpStatsQuery, McpToolStats};
 pub use types::*;
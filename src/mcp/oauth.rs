@@ -0,0 +1,648 @@
+//! OAuth 2.1 authorization of remote MCP servers.
+//!
+//! Follows the MCP authorization flow:
+//! 1. The protected resource metadata of the MCP endpoint (RFC 9728) names
+//!    its authorization server; servers without it are their own.
+//! 2. The authorization server metadata (RFC 8414, or OpenID discovery)
+//!    gives the endpoints, defaulting to `/authorize`, `/token` and
+//!    `/register`.
+//! 3. sandboxed.sh registers itself as a client (RFC 7591) unless a client
+//!    ID is given.
+//! 4. The user authorizes with PKCE and pastes the redirect URL (or the
+//!    code) back; the code is exchanged for tokens, which are refreshed
+//!    before they expire.
+//!
+//! Credentials are kept encrypted in the secrets store and sent as a bearer
+//! token by the registry and in generated harness configs.
+
+use anyhow::Context;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Secrets registry holding MCP credentials.
+pub(super) const TOKENS_REGISTRY: &str = "mcp-tokens";
+/// Redirect URI used when the caller gives none; the browser fails to load
+/// it and the user pastes the URL back.
+pub(super) const DEFAULT_REDIRECT_URI: &str = "http://localhost:8976/oauth/callback";
+/// How long a started authorization waits for its code.
+pub(super) const PENDING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(600);
+/// Tokens are refreshed this many seconds before they expire.
+const REFRESH_MARGIN_SECS: i64 = 300;
+
+/// Non-secret OAuth state of an MCP, kept in its config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpOAuthStatus {
+    /// Issuer of the tokens
+    pub authorization_server: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    pub authorized_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Endpoints of an authorization server.
+#[derive(Debug, Clone)]
+pub(super) struct AuthServer {
+    pub(super) issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    registration_endpoint: Option<String>,
+    /// Scopes the protected resource asks for, space-separated
+    pub(super) scope: Option<String>,
+}
+
+/// Client registration and tokens of an MCP (stored encrypted).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct OAuthCredentials {
+    pub(super) client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+    token_endpoint: String,
+    /// Canonical URI of the MCP the tokens are bound to (RFC 8707)
+    resource: String,
+    pub(super) access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+    /// Unix timestamp; None when the server gave no lifetime
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) expires_at: Option<i64>,
+}
+
+impl OAuthCredentials {
+    /// Key of the credentials in [`TOKENS_REGISTRY`].
+    pub(super) fn secret_key(mcp_id: Uuid) -> String {
+        format!("{}/oauth", mcp_id)
+    }
+
+    /// Whether the access token expires soon and can be refreshed.
+    pub(super) fn needs_refresh(&self) -> bool {
+        self.refresh_token.is_some()
+            && self.expires_at.is_some_and(|expires| {
+                expires - REFRESH_MARGIN_SECS <= chrono::Utc::now().timestamp()
+            })
+    }
+}
+
+/// An authorization waiting for the user's code.
+#[derive(Debug, Clone)]
+pub(super) struct PendingAuthorization {
+    pub(super) server: AuthServer,
+    client_id: String,
+    client_secret: Option<String>,
+    resource: String,
+    redirect_uri: String,
+    verifier: String,
+    state: String,
+    pub(super) created_at: std::time::Instant,
+}
+
+/// `scheme://host[:port]` of a URL.
+fn origin(url: &url::Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+/// Well-known URL for `url`, inserting the suffix before its path.
+fn well_known(url: &url::Url, suffix: &str) -> String {
+    let path = url.path().trim_end_matches('/');
+    format!("{}/.well-known/{}{}", origin(url), suffix, path)
+}
+
+async fn get_json(http: &reqwest::Client, url: &str) -> Option<Value> {
+    let response = http.get(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.json().await.ok()
+}
+
+/// Find the authorization server of the MCP at `endpoint`.
+pub(super) async fn discover(http: &reqwest::Client, endpoint: &str) -> anyhow::Result<AuthServer> {
+    let endpoint = url::Url::parse(endpoint).context("Invalid MCP endpoint")?;
+
+    // Protected resource metadata, at the path-specific location first
+    let mut resource_metadata =
+        get_json(http, &well_known(&endpoint, "oauth-protected-resource")).await;
+    if resource_metadata.is_none() && !endpoint.path().trim_matches('/').is_empty() {
+        resource_metadata = get_json(
+            http,
+            &format!("{}/.well-known/oauth-protected-resource", origin(&endpoint)),
+        )
+        .await;
+    }
+    let issuer = resource_metadata
+        .as_ref()
+        .and_then(|m| m["authorization_servers"][0].as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| origin(&endpoint));
+    let scope = resource_metadata
+        .as_ref()
+        .and_then(|m| m["scopes_supported"].as_array())
+        .map(|scopes| {
+            scopes
+                .iter()
+                .filter_map(|s| s.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|s| !s.is_empty());
+
+    let issuer_url = url::Url::parse(&issuer).context("Invalid authorization server URL")?;
+    let mut metadata = None;
+    for suffix in ["oauth-authorization-server", "openid-configuration"] {
+        metadata = get_json(http, &well_known(&issuer_url, suffix)).await;
+        if metadata.is_some() {
+            break;
+        }
+    }
+    let base = issuer.trim_end_matches('/');
+    let endpoint_of = |key: &str, default: &str| {
+        metadata
+            .as_ref()
+            .and_then(|m| m[key].as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{}{}", base, default))
+    };
+    Ok(AuthServer {
+        authorization_endpoint: endpoint_of("authorization_endpoint", "/authorize"),
+        token_endpoint: endpoint_of("token_endpoint", "/token"),
+        registration_endpoint: match &metadata {
+            Some(m) => m["registration_endpoint"].as_str().map(str::to_string),
+            None => Some(format!("{}/register", base)),
+        },
+        issuer,
+        scope,
+    })
+}
+
+/// Register sandboxed.sh as a public client; returns the client ID and
+/// secret, if the server issued one.
+async fn register(
+    http: &reqwest::Client,
+    server: &AuthServer,
+    redirect_uri: &str,
+) -> anyhow::Result<(String, Option<String>)> {
+    let registration_endpoint = server.registration_endpoint.as_deref().ok_or_else(|| {
+        anyhow::anyhow!(
+            "{} does not support dynamic client registration; provide a client_id",
+            server.issuer
+        )
+    })?;
+    let response = http
+        .post(registration_endpoint)
+        .json(&json!({
+            "client_name": "sandboxed.sh",
+            "redirect_uris": [redirect_uri],
+            "grant_types": ["authorization_code", "refresh_token"],
+            "response_types": ["code"],
+            "token_endpoint_auth_method": "none"
+        }))
+        .send()
+        .await
+        .context("Client registration failed")?;
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or_default();
+    if !status.is_success() {
+        anyhow::bail!("Client registration failed ({}): {}", status, body);
+    }
+    let client_id = body["client_id"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Client registration returned no client_id"))?;
+    Ok((
+        client_id.to_string(),
+        body["client_secret"].as_str().map(str::to_string),
+    ))
+}
+
+/// Start authorizing the MCP at `endpoint`; returns the URL the user must
+/// open and the state to complete it with.
+pub(super) async fn begin(
+    http: &reqwest::Client,
+    endpoint: &str,
+    redirect_uri: &str,
+    client_id: Option<String>,
+) -> anyhow::Result<(String, PendingAuthorization)> {
+    let server = discover(http, endpoint).await?;
+    let (client_id, client_secret) = match client_id {
+        Some(client_id) => (client_id, None),
+        None => register(http, &server, redirect_uri).await?,
+    };
+    let (verifier, challenge) = generate_pkce();
+    let state = generate_state();
+
+    let mut url =
+        url::Url::parse(&server.authorization_endpoint).context("Invalid authorization URL")?;
+    {
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("code_challenge", &challenge)
+            .append_pair("code_challenge_method", "S256")
+            .append_pair("state", &state)
+            .append_pair("resource", endpoint);
+        if let Some(scope) = &server.scope {
+            query.append_pair("scope", scope);
+        }
+    }
+
+    Ok((
+        url.to_string(),
+        PendingAuthorization {
+            server,
+            client_id,
+            client_secret,
+            resource: endpoint.to_string(),
+            redirect_uri: redirect_uri.to_string(),
+            verifier,
+            state,
+            created_at: std::time::Instant::now(),
+        },
+    ))
+}
+
+/// Exchange the code (or pasted redirect URL) of a pending authorization for
+/// tokens.
+pub(super) async fn complete(
+    http: &reqwest::Client,
+    pending: &PendingAuthorization,
+    input: &str,
+) -> anyhow::Result<OAuthCredentials> {
+    let (code, state) = parse_authorization_input(input);
+    let code = code.ok_or_else(|| anyhow::anyhow!("No authorization code provided"))?;
+    match state {
+        Some(state) if state != pending.state => {
+            anyhow::bail!("OAuth state mismatch. Please start the authorization again.")
+        }
+        Some(_) => {}
+        // Providers that show the code to copy do not show the state
+        None if is_bare_code(input) => {}
+        None => anyhow::bail!(
+            "The redirect URL has no OAuth state. Paste the full redirect URL or only the code."
+        ),
+    }
+    let mut params = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", pending.redirect_uri.as_str()),
+        ("code_verifier", pending.verifier.as_str()),
+    ];
+    let credentials = OAuthCredentials {
+        client_id: pending.client_id.clone(),
+        client_secret: pending.client_secret.clone(),
+        token_endpoint: pending.server.token_endpoint.clone(),
+        resource: pending.resource.clone(),
+        access_token: String::new(),
+        refresh_token: None,
+        expires_at: None,
+    };
+    params.push(("resource", pending.resource.as_str()));
+    token_request(http, credentials, &params).await
+}
+
+/// Get a new access token with the refresh token.
+pub(super) async fn refresh(
+    http: &reqwest::Client,
+    credentials: &OAuthCredentials,
+) -> anyhow::Result<OAuthCredentials> {
+    let refresh_token = credentials
+        .refresh_token
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("No refresh token; authorize the MCP again"))?;
+    let params = [
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token.as_str()),
+        ("resource", credentials.resource.as_str()),
+    ];
+    let mut refreshed = token_request(http, credentials.clone(), &params).await?;
+    // Servers may keep the refresh token
+    if refreshed.refresh_token.is_none() {
+        refreshed.refresh_token = Some(refresh_token);
+    }
+    Ok(refreshed)
+}
+
+/// POST to the token endpoint and apply the tokens to `credentials`.
+async fn token_request(
+    http: &reqwest::Client,
+    mut credentials: OAuthCredentials,
+    params: &[(&str, &str)],
+) -> anyhow::Result<OAuthCredentials> {
+    let body = {
+        let mut body = url::form_urlencoded::Serializer::new(String::new());
+        body.extend_pairs(params.iter().copied())
+            .append_pair("client_id", &credentials.client_id);
+        if let Some(secret) = &credentials.client_secret {
+            body.append_pair("client_secret", secret);
+        }
+        body.finish()
+    };
+    let response = http
+        .post(&credentials.token_endpoint)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "application/json")
+        .body(body)
+        .send()
+        .await
+        .context("Token request failed")?;
+    let status = response.status();
+    let tokens: Value = response.json().await.unwrap_or_default();
+    if !status.is_success() {
+        anyhow::bail!("Token request failed ({}): {}", status, tokens);
+    }
+
+    credentials.access_token = tokens["access_token"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Token response has no access_token"))?
+        .to_string();
+    credentials.refresh_token = tokens["refresh_token"].as_str().map(str::to_string);
+    credentials.expires_at = tokens["expires_in"]
+        .as_i64()
+        .map(|secs| chrono::Utc::now().timestamp() + secs);
+    Ok(credentials)
+}
+
+/// Parse a pasted redirect URL, `code#state`, query string or bare code.
+fn parse_authorization_input(input: &str) -> (Option<String>, Option<String>) {
+    let value = input.trim();
+    if value.is_empty() {
+        return (None, None);
+    }
+
+    if let Ok(url) = url::Url::parse(value) {
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.to_string())
+        };
+        return (param("code"), param("state"));
+    }
+
+    if value.contains("code=") {
+        let params: std::collections::HashMap<String, String> =
+            url::form_urlencoded::parse(value.trim_start_matches('?').as_bytes())
+                .into_owned()
+                .collect();
+        return (params.get("code").cloned(), params.get("state").cloned());
+    }
+
+    match value.split_once('#') {
+        Some((code, state)) => (Some(code.to_string()), Some(state.to_string())),
+        None => (Some(value.to_string()), None),
+    }
+}
+
+/// Whether the pasted input is only a code, not a redirect URL or query.
+fn is_bare_code(input: &str) -> bool {
+    let value = input.trim();
+    url::Url::parse(value).is_err() && !value.contains("code=") && !value.contains('#')
+}
+
+/// Generate PKCE code verifier and challenge.
+fn generate_pkce() -> (String, String) {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let verifier: String = (0..43)
+        .map(|_| {
+            let idx = rng.gen_range(0..62);
+            let chars: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+            chars[idx] as char
+        })
+        .collect();
+
+    let mut hasher = Sha256::new();
+    hasher.update(verifier.as_bytes());
+    let challenge = URL_SAFE_NO_PAD.encode(hasher.finalize());
+
+    (verifier, challenge)
+}
+
+/// Generate a random OAuth state value.
+fn generate_state() -> String {
+    use rand::RngCore;
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::{AddMcpRequest, McpRegistry, McpStatus, McpTransport};
+    use crate::secrets::SecretsStore;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::{get, post};
+    use axum::{Form, Json, Router};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// Authorization server and MCP in one: discovery through protected
+    /// resource metadata, default endpoints, and a bearer-protected `/mcp`.
+    #[derive(Default)]
+    struct FakeServer {
+        base: String,
+        token_requests: Mutex<Vec<HashMap<String, String>>>,
+    }
+
+    async fn resource_metadata(State(server): State<Arc<FakeServer>>) -> Json<Value> {
+        Json(json!({
+            "resource": format!("{}/mcp", server.base),
+            "authorization_servers": [server.base],
+            "scopes_supported": ["tools"]
+        }))
+    }
+
+    async fn token(
+        State(server): State<Arc<FakeServer>>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Response {
+        server.token_requests.lock().unwrap().push(form.clone());
+        match (form["grant_type"].as_str(), form.get("code")) {
+            ("authorization_code", Some(code)) if code == "good" => Json(json!({
+                "access_token": "token-1",
+                "refresh_token": "refresh-1",
+                // Within the refresh margin: the next use refreshes it
+                "expires_in": 60
+            }))
+            .into_response(),
+            ("refresh_token", _) => {
+                Json(json!({ "access_token": "token-2", "expires_in": 3600 })).into_response()
+            }
+            _ => StatusCode::BAD_REQUEST.into_response(),
+        }
+    }
+
+    async fn mcp(headers: HeaderMap, Json(message): Json<Value>) -> Response {
+        let authorization = headers.get("authorization").and_then(|v| v.to_str().ok());
+        if !matches!(authorization, Some("Bearer token-1" | "Bearer token-2")) {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+        let Some(id) = message.get("id").cloned() else {
+            return StatusCode::ACCEPTED.into_response();
+        };
+        let result = match message["method"].as_str() {
+            Some("initialize") => json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "fake", "version": "1.0" }
+            }),
+            _ => json!({ "tools": [] }),
+        };
+        Json(json!({ "jsonrpc": "2.0", "id": id, "result": result })).into_response()
+    }
+
+    #[tokio::test]
+    async fn authorizes_and_refreshes_remote_mcp() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = Arc::new(FakeServer {
+            base: base.clone(),
+            ..Default::default()
+        });
+        let app = Router::new()
+            .route(
+                "/.well-known/oauth-protected-resource/mcp",
+                get(resource_metadata),
+            )
+            .route(
+                "/register",
+                post(|| async { Json(json!({ "client_id": "client-1" })) }),
+            )
+            .route("/token", post(token))
+            .route("/mcp", post(mcp))
+            .with_state(Arc::clone(&server));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let tmp = tempfile::tempdir().unwrap();
+        let secrets = Arc::new(SecretsStore::new(tmp.path()).await.unwrap());
+        secrets.initialize("default").await.unwrap();
+        secrets.unlock("pass").await.unwrap();
+        let registry = McpRegistry::new(tmp.path()).await;
+        registry.set_secrets(Arc::clone(&secrets));
+        let id = registry
+            .add(AddMcpRequest {
+                name: "remote".to_string(),
+                transport: McpTransport::Http {
+                    endpoint: format!("{}/mcp", base),
+                    headers: HashMap::new(),
+                },
+                description: None,
+                scope: None,
+                default_enabled: None,
+            })
+            .await
+            .unwrap()
+            .config
+            .id;
+        let state = registry.refresh(id).await.unwrap();
+        assert_eq!(state.status, McpStatus::Error);
+
+        let url = registry.begin_oauth(id, None, None).await.unwrap();
+        let url = url::Url::parse(&url).unwrap();
+        assert_eq!(
+            url.as_str().split('?').next(),
+            Some(format!("{}/authorize", base).as_str())
+        );
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(query["client_id"], "client-1");
+        assert_eq!(query["resource"], format!("{}/mcp", base));
+        assert_eq!(query["scope"], "tools");
+        assert_eq!(query["code_challenge_method"], "S256");
+
+        let redirect = format!("{}?code=good&state=wrong", DEFAULT_REDIRECT_URI);
+        assert!(registry.complete_oauth(id, &redirect).await.is_err());
+        // A redirect URL must carry the state
+        registry.begin_oauth(id, None, None).await.unwrap();
+        let redirect = format!("{}?code=good", DEFAULT_REDIRECT_URI);
+        assert!(registry.complete_oauth(id, &redirect).await.is_err());
+        let url = registry.begin_oauth(id, None, None).await.unwrap();
+        let url = url::Url::parse(&url).unwrap();
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let redirect = format!(
+            "{}?code=good&state={}",
+            DEFAULT_REDIRECT_URI, query["state"]
+        );
+        let state = registry.complete_oauth(id, &redirect).await.unwrap();
+        assert_eq!(state.status, McpStatus::Connected, "{:?}", state.error);
+        assert_eq!(
+            state.config.oauth.map(|o| o.authorization_server),
+            Some(base.clone())
+        );
+
+        // The code verifier matches the challenge sent to the browser
+        let exchange = server.token_requests.lock().unwrap()[0].clone();
+        let challenge =
+            URL_SAFE_NO_PAD.encode(Sha256::digest(exchange["code_verifier"].as_bytes()));
+        assert_eq!(challenge, query["code_challenge"]);
+
+        // Harness configs carry the (refreshed) token
+        let config = registry
+            .resolve_configs(registry.list_configs().await)
+            .await
+            .into_iter()
+            .find(|c| c.id == id)
+            .unwrap();
+        let McpTransport::Http { headers, .. } = config.transport else {
+            panic!("not an HTTP MCP");
+        };
+        assert_eq!(headers["Authorization"], "Bearer token-2");
+        let refresh = server.token_requests.lock().unwrap()[1].clone();
+        assert_eq!(refresh["refresh_token"], "refresh-1");
+        let stored = secrets
+            .get_secret(TOKENS_REGISTRY, &OAuthCredentials::secret_key(id))
+            .await
+            .unwrap();
+        assert!(stored.contains("token-2") && stored.contains("refresh-1"));
+
+        let state = registry.revoke_oauth(id).await.unwrap();
+        assert!(state.config.oauth.is_none());
+        assert!(secrets
+            .get_secret(TOKENS_REGISTRY, &OAuthCredentials::secret_key(id))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn parses_pasted_authorization_input() {
+        assert_eq!(
+            parse_authorization_input("http://localhost:8976/oauth/callback?code=abc&state=xyz"),
+            (Some("abc".to_string()), Some("xyz".to_string()))
+        );
+        assert_eq!(
+            parse_authorization_input("?code=abc&state=xyz"),
+            (Some("abc".to_string()), Some("xyz".to_string()))
+        );
+        assert_eq!(
+            parse_authorization_input(" abc#xyz "),
+            (Some("abc".to_string()), Some("xyz".to_string()))
+        );
+        assert_eq!(
+            parse_authorization_input("abc"),
+            (Some("abc".to_string()), None)
+        );
+        assert_eq!(parse_authorization_input(""), (None, None));
+        assert!(is_bare_code(" abc "));
+        assert!(!is_bare_code("?code=abc"));
+        assert!(!is_bare_code(
+            "http://localhost:8976/oauth/callback?code=abc"
+        ));
+    }
+
+    #[test]
+    fn well_known_urls_keep_the_path() {
+        let url = url::Url::parse("https://mcp.example.com/v1/mcp/").unwrap();
+        assert_eq!(
+            well_known(&url, "oauth-protected-resource"),
+            "https://mcp.example.com/.well-known/oauth-protected-resource/v1/mcp"
+        );
+        let url = url::Url::parse("https://auth.example.com").unwrap();
+        assert_eq!(
+            well_known(&url, "oauth-authorization-server"),
+            "https://auth.example.com/.well-known/oauth-authorization-server"
+        );
+    }
+}
@@ -0,0 +1,597 @@
+//! MCP tool permission policies.
+//!
+//! Library agents (under `mcp:` in their frontmatter) and missions can carry
+//! an [`McpToolPolicy`] that allows or denies individual MCP tools and
+//! constrains their arguments, e.g. a read-only GitHub MCP for a reviewer:
+//!
+//! ```yaml
+//! mcp:
+//!   allow: ["github/get_*", "github/list_*", "github/search_*"]
+//!   arguments:
+//!     - tool: "filesystem/*"
+//!       argument: path
+//!       allow: ["/workspace/*"]
+//! ```
+//!
+//! Tool patterns are `<mcp>/<tool>` with `*` wildcards; a pattern without a
+//! `/` matches the tool on any MCP. Path-like argument values are normalised
+//! (`.` and `..` segments resolved) before matching, and a call without an
+//! argument that a rule restricts with `allow` is denied. Policies are
+//! enforced by the MCP gateway. Without the gateway, harness configs can only
+//! list discovered tools as denied, so an MCP that a policy constrains by
+//! argument, or whose tools are not yet known while a policy restricts it, is
+//! left out of the harness config instead of being exposed unchecked.
+//!
+//! Policies fail closed: a library agent whose policy cannot be loaded (the
+//! library is not loaded, the file is unreadable or the policy is invalid)
+//! is denied every tool. Only agents that are not in the library, such as
+//! built-in harness agents, are unrestricted.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::types::McpServerConfig;
+use crate::config::Config;
+use crate::glob::glob_match;
+use crate::library::LibraryStore;
+
+/// Which MCP tools may be called, and with which arguments.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct McpToolPolicy {
+    /// Tool patterns that may be called; empty allows every tool
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    /// Tool patterns that may not be called; takes precedence over `allow`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+    /// Constraints on the arguments of matching tools
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<McpArgumentRule>,
+}
+
+/// Constraint on one argument of the tools matching `tool`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpArgumentRule {
+    /// Tool pattern the rule applies to
+    pub tool: String,
+    /// Argument name; fields of nested objects are separated by `.`
+    pub argument: String,
+    /// Value patterns the argument must match one of; empty allows any value
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    /// Value patterns the argument may not match
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+}
+
+impl McpToolPolicy {
+    /// Policy denying every tool, for agents whose policy cannot be loaded.
+    pub fn deny_all() -> Self {
+        Self {
+            deny: vec!["*".to_string()],
+            ..Default::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty() && self.arguments.is_empty()
+    }
+
+    fn allows_tool(&self, server: &str, tool: &str) -> bool {
+        if self.deny.iter().any(|p| tool_matches(p, server, tool)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|p| tool_matches(p, server, tool))
+    }
+
+    fn check_arguments(&self, server: &str, tool: &str, arguments: &Value) -> Result<(), String> {
+        for rule in &self.arguments {
+            if !tool_matches(&rule.tool, server, tool) {
+                continue;
+            }
+            let Some(value) = rule
+                .argument
+                .split('.')
+                .try_fold(arguments, |value, key| value.get(key))
+            else {
+                // Without the argument there is nothing to check against
+                // `allow`, so the call cannot be shown to be allowed.
+                if rule.allow.is_empty() {
+                    continue;
+                }
+                return Err(format!(
+                    "Argument '{}' of {}/{} is required by the tool policy",
+                    rule.argument, server, tool
+                ));
+            };
+            let values = match value {
+                Value::Array(items) => items.iter().map(argument_text).collect(),
+                value => vec![argument_text(value)],
+            };
+            for text in values {
+                let denied = rule.deny.iter().any(|p| glob_match(p, &text));
+                let allowed =
+                    rule.allow.is_empty() || rule.allow.iter().any(|p| glob_match(p, &text));
+                if denied || !allowed {
+                    return Err(format!(
+                        "Argument '{}' = '{}' of {}/{} is not allowed by the tool policy",
+                        rule.argument, text, server, tool
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The policies in effect for a mission: its agent's and its own.
+///
+/// A tool call must be allowed by every layer.
+#[derive(Debug, Clone, Default)]
+pub struct McpPolicies {
+    layers: Vec<McpToolPolicy>,
+}
+
+impl McpPolicies {
+    pub fn new(layers: impl IntoIterator<Item = McpToolPolicy>) -> Self {
+        Self {
+            layers: layers.into_iter().filter(|p| !p.is_empty()).collect(),
+        }
+    }
+
+    /// Policies of a mission: those of its agent (the default agent if the
+    /// mission names none) and its own.
+    ///
+    /// Mission turns and the MCP gateway both resolve through here, so the
+    /// tools listed to the harness and the calls the gateway allows come from
+    /// the same policies.
+    pub async fn resolve(
+        library: Option<&LibraryStore>,
+        config: &Config,
+        mission_agent: Option<&str>,
+        mission: Option<&McpToolPolicy>,
+    ) -> Self {
+        let mut layers = Vec::new();
+        if let Some(agent) = mission_agent.or(config.opencode_agent.as_deref()) {
+            match agent_policy(library, &config.library_path, agent).await {
+                Ok(policy) => layers.extend(policy),
+                Err(e) => {
+                    tracing::warn!(
+                        agent,
+                        error = %e,
+                        "Cannot load the MCP tool policy of the agent, denying every tool"
+                    );
+                    layers.push(McpToolPolicy::deny_all());
+                }
+            }
+        }
+        layers.extend(mission.cloned());
+        Self::new(layers)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Whether any layer constrains tool arguments, which only the gateway
+    /// can enforce.
+    pub fn has_argument_rules(&self) -> bool {
+        self.layers.iter().any(|p| !p.arguments.is_empty())
+    }
+
+    /// Whether an argument rule applies to some tool of the MCP named `server`.
+    fn has_argument_rules_for(&self, server: &str) -> bool {
+        self.layers
+            .iter()
+            .flat_map(|p| &p.arguments)
+            .any(|rule| server_matches(&rule.tool, server))
+    }
+
+    /// Whether some layer may deny a tool of the MCP named `server`.
+    fn restricts_server(&self, server: &str) -> bool {
+        self.layers.iter().any(|p| {
+            let allow_restricts =
+                !p.allow.is_empty() && !p.allow.iter().any(|a| allows_every_tool(a, server));
+            allow_restricts || p.deny.iter().any(|d| server_matches(d, server))
+        })
+    }
+
+    /// Whether `tool` of the MCP named `server` may be called at all.
+    pub fn allows_tool(&self, server: &str, tool: &str) -> bool {
+        self.layers.iter().all(|p| p.allows_tool(server, tool))
+    }
+
+    /// Check a call of `tool` with `arguments`; the error says what is denied.
+    pub fn check_call(&self, server: &str, tool: &str, arguments: &Value) -> Result<(), String> {
+        if !self.allows_tool(server, tool) {
+            return Err(format!(
+                "Tool {}/{} is not allowed by the tool policy",
+                server, tool
+            ));
+        }
+        self.layers
+            .iter()
+            .try_for_each(|p| p.check_arguments(server, tool, arguments))
+    }
+
+    /// Mark the tools each MCP may not call as denied for harness configs.
+    ///
+    /// Only discovered tools can be listed; MCPs whose tools are all denied
+    /// are dropped. Unless `gateway` enforces the policies on every call, MCPs
+    /// with argument rules, and restricted MCPs whose tools are not discovered
+    /// yet, are dropped too.
+    pub fn apply(&self, configs: Vec<McpServerConfig>, gateway: bool) -> Vec<McpServerConfig> {
+        if self.is_empty() {
+            return configs;
+        }
+        configs
+            .into_iter()
+            .filter_map(|mut config| {
+                if !gateway {
+                    if self.has_argument_rules_for(&config.name) {
+                        tracing::warn!(
+                            mcp = %config.name,
+                            "Not exposing the MCP: its argument rules need the MCP gateway (SANDBOXED_SH_MCP_GATEWAY)"
+                        );
+                        return None;
+                    }
+                    if config.tools.is_empty() && self.restricts_server(&config.name) {
+                        tracing::warn!(
+                            mcp = %config.name,
+                            "Not exposing the MCP: its tools are not discovered yet, so the tool policy cannot be applied"
+                        );
+                        return None;
+                    }
+                }
+                config.denied_tools = config
+                    .tools
+                    .iter()
+                    .filter(|tool| !self.allows_tool(&config.name, tool))
+                    .cloned()
+                    .collect();
+                let all_denied =
+                    !config.tools.is_empty() && config.denied_tools.len() == config.tools.len();
+                (!all_denied).then_some(config)
+            })
+            .collect()
+    }
+}
+
+/// Tool policy of `agent`; `None` if it has none or is not a library agent.
+///
+/// Without a loaded library (still initializing, or failed to), agents in
+/// the library clone on disk cannot be checked and are an error.
+async fn agent_policy(
+    library: Option<&LibraryStore>,
+    library_path: &Path,
+    agent: &str,
+) -> anyhow::Result<Option<McpToolPolicy>> {
+    match library {
+        Some(library) if library.has_library_agent(agent) => {
+            Ok(library.get_library_agent(agent).await?.mcp_policy)
+        }
+        Some(_) => Ok(None),
+        None if LibraryStore::has_cloned_agent(library_path, agent) => {
+            anyhow::bail!("the library is not loaded")
+        }
+        None => Ok(None),
+    }
+}
+
+/// Match a `<mcp>/<tool>` or `<tool>` pattern; MCP names are matched
+/// case-insensitively.
+fn tool_matches(pattern: &str, server: &str, tool: &str) -> bool {
+    match pattern.split_once('/') {
+        Some((server_pattern, tool_pattern)) => {
+            glob_match(&server_pattern.to_lowercase(), &server.to_lowercase())
+                && glob_match(tool_pattern, tool)
+        }
+        None => glob_match(pattern, tool),
+    }
+}
+
+/// Whether the MCP part of a tool pattern matches `server`; patterns without
+/// an MCP part match every MCP.
+fn server_matches(pattern: &str, server: &str) -> bool {
+    match pattern.split_once('/') {
+        Some((server_pattern, _)) => {
+            glob_match(&server_pattern.to_lowercase(), &server.to_lowercase())
+        }
+        None => true,
+    }
+}
+
+/// Whether an allow pattern allows every tool of `server`.
+fn allows_every_tool(pattern: &str, server: &str) -> bool {
+    match pattern.split_once('/') {
+        Some((_, tool_pattern)) => tool_pattern == "*" && server_matches(pattern, server),
+        None => pattern == "*",
+    }
+}
+
+fn argument_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => normalize_path(s),
+        other => other.to_string(),
+    }
+}
+
+/// Lexically resolve `.` and `..` segments of a path-like value, so that
+/// `/workspace/../etc/passwd` is matched as `/etc/passwd`. Values without a
+/// `/` and URLs are returned unchanged.
+fn normalize_path(value: &str) -> String {
+    if !value.contains('/') || value.contains("://") {
+        return value.to_string();
+    }
+    let absolute = value.starts_with('/');
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in value.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => match segments.last() {
+                Some(&last) if last != ".." => {
+                    segments.pop();
+                }
+                // `..` above the root stays at the root
+                _ if absolute => {}
+                _ => segments.push(".."),
+            },
+            segment => segments.push(segment),
+        }
+    }
+    let joined = segments.join("/");
+    if absolute {
+        format!("/{}", joined)
+    } else if joined.is_empty() {
+        ".".to_string()
+    } else {
+        joined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn reviewer() -> McpToolPolicy {
+        McpToolPolicy {
+            allow: vec!["github/get_*".to_string(), "github/list_*".to_string()],
+            deny: vec!["list_secrets".to_string()],
+            arguments: vec![McpArgumentRule {
+                tool: "*".to_string(),
+                argument: "repo.owner".to_string(),
+                allow: vec!["acme".to_string()],
+                deny: Vec::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn tool_patterns_allow_and_deny() {
+        let policies = McpPolicies::new([reviewer()]);
+        assert!(policies.allows_tool("GitHub", "get_issue"));
+        assert!(!policies.allows_tool("GitHub", "create_issue"));
+        assert!(!policies.allows_tool("GitHub", "list_secrets"));
+        assert!(!policies.allows_tool("gitlab", "get_issue"));
+        assert!(McpPolicies::default().allows_tool("gitlab", "anything"));
+    }
+
+    #[test]
+    fn every_layer_must_allow() {
+        let mission = McpToolPolicy {
+            deny: vec!["github/list_*".to_string()],
+            ..Default::default()
+        };
+        let policies = McpPolicies::new([reviewer(), mission.clone()]);
+        assert!(policies.allows_tool("github", "get_issue"));
+        assert!(!policies.allows_tool("github", "list_issues"));
+        assert!(policies.has_argument_rules());
+        assert!(!McpPolicies::new([mission]).has_argument_rules());
+    }
+
+    #[test]
+    fn argument_rules_constrain_calls() {
+        let policies = McpPolicies::new([reviewer()]);
+        let call = |owner: &str| json!({ "repo": { "owner": owner, "name": "x" } });
+        assert!(policies
+            .check_call("github", "get_issue", &call("acme"))
+            .is_ok());
+        let err = policies
+            .check_call("github", "get_issue", &call("other"))
+            .unwrap_err();
+        assert!(err.contains("repo.owner"), "{}", err);
+        // Calls without an argument restricted by `allow` are denied
+        let err = policies
+            .check_call("github", "get_issue", &json!({}))
+            .unwrap_err();
+        assert!(err.contains("required"), "{}", err);
+        assert!(policies
+            .check_call("github", "create_issue", &call("acme"))
+            .is_err());
+    }
+
+    #[test]
+    fn apply_marks_denied_tools() {
+        let mut github = McpServerConfig::new("github".to_string(), "https://x".to_string());
+        github.tools = vec!["get_issue".to_string(), "create_issue".to_string()];
+        let mut slack = McpServerConfig::new("slack".to_string(), "https://y".to_string());
+        slack.tools = vec!["post".to_string()];
+        let unknown = McpServerConfig::new("new".to_string(), "https://z".to_string());
+
+        let configs = McpPolicies::new([reviewer()]).apply(vec![github, slack, unknown], true);
+        let names: Vec<_> = configs.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["github", "new"]);
+        assert_eq!(configs[0].denied_tools, vec!["create_issue"]);
+    }
+
+    #[test]
+    fn apply_without_gateway_fails_closed() {
+        let mut github = McpServerConfig::new("github".to_string(), "https://x".to_string());
+        github.tools = vec!["get_issue".to_string(), "create_issue".to_string()];
+        let mut filesystem =
+            McpServerConfig::new("filesystem".to_string(), "https://y".to_string());
+        filesystem.tools = vec!["read".to_string()];
+        let undiscovered = McpServerConfig::new("gitlab".to_string(), "https://z".to_string());
+        let configs = || vec![github.clone(), filesystem.clone(), undiscovered.clone()];
+
+        // MCPs with argument rules are not exposed without the gateway
+        let policies = McpPolicies::new([McpToolPolicy {
+            arguments: vec![McpArgumentRule {
+                tool: "filesystem/*".to_string(),
+                argument: "path".to_string(),
+                allow: vec!["/workspace/*".to_string()],
+                deny: Vec::new(),
+            }],
+            ..Default::default()
+        }]);
+        let names = |configs: Vec<McpServerConfig>| -> Vec<String> {
+            configs.into_iter().map(|c| c.name).collect()
+        };
+        assert_eq!(
+            names(policies.apply(configs(), false)),
+            vec!["github", "gitlab"]
+        );
+        assert_eq!(
+            names(policies.apply(configs(), true)),
+            vec!["github", "filesystem", "gitlab"]
+        );
+
+        // Restricted MCPs whose tools are unknown are not exposed either
+        let policies = McpPolicies::new([McpToolPolicy {
+            allow: vec!["github/get_*".to_string(), "filesystem/*".to_string()],
+            ..Default::default()
+        }]);
+        let configs = policies.apply(configs(), false);
+        assert_eq!(names(configs.clone()), vec!["github", "filesystem"]);
+        assert_eq!(configs[0].denied_tools, vec!["create_issue"]);
+        assert!(configs[1].denied_tools.is_empty());
+    }
+
+    #[test]
+    fn path_arguments_are_normalised() {
+        let policies = McpPolicies::new([McpToolPolicy {
+            arguments: vec![McpArgumentRule {
+                tool: "filesystem/*".to_string(),
+                argument: "path".to_string(),
+                allow: vec!["/workspace/*".to_string()],
+                deny: vec!["*/.env".to_string()],
+            }],
+            ..Default::default()
+        }]);
+        let call = |path: &str| json!({ "path": path });
+        assert!(policies
+            .check_call("filesystem", "read", &call("/workspace/./src/main.rs"))
+            .is_ok());
+        for path in [
+            "/workspace/../etc/passwd",
+            "/workspace/a/../../etc/passwd",
+            "/workspace/src/../.env",
+        ] {
+            assert!(
+                policies
+                    .check_call("filesystem", "read", &call(path))
+                    .is_err(),
+                "{}",
+                path
+            );
+        }
+        // Deny-only rules do not require the argument
+        let deny_only = McpPolicies::new([McpToolPolicy {
+            arguments: vec![McpArgumentRule {
+                tool: "*".to_string(),
+                argument: "path".to_string(),
+                allow: Vec::new(),
+                deny: vec!["/etc/*".to_string()],
+            }],
+            ..Default::default()
+        }]);
+        assert!(deny_only
+            .check_call("filesystem", "list", &json!({}))
+            .is_ok());
+    }
+
+    fn write_agent(library_path: &Path, name: &str, frontmatter: &str) {
+        let dir = library_path.join("agent");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(format!("{}.md", name)),
+            format!("---\n{}---\nBody\n", frontmatter),
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn agent_policies_fail_closed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = Config::new(tmp.path().to_path_buf());
+        write_agent(
+            &config.library_path,
+            "reviewer",
+            "mcp:\n  allow: [\"github/get_*\"]\n",
+        );
+        write_agent(&config.library_path, "broken", "mcp:\n  allow: 3\n");
+        let library = LibraryStore::with_test_store(config.library_path.clone()).await;
+
+        let allows = |policies: McpPolicies, tool: &str| policies.allows_tool("github", tool);
+
+        let reviewer = McpPolicies::resolve(Some(&library), &config, Some("reviewer"), None).await;
+        assert!(allows(reviewer.clone(), "get_issue"));
+        assert!(!allows(reviewer, "create_issue"));
+        // Agents that are not in the library (built-in harness agents) are unrestricted
+        let build = McpPolicies::resolve(Some(&library), &config, Some("build"), None).await;
+        assert!(allows(build, "create_issue"));
+        let build = McpPolicies::resolve(None, &config, Some("build"), None).await;
+        assert!(allows(build, "create_issue"));
+        // An invalid policy denies every tool
+        let broken = McpPolicies::resolve(Some(&library), &config, Some("broken"), None).await;
+        assert!(!allows(broken, "get_issue"));
+        // A library agent cannot be checked while the library is not loaded
+        let unloaded = McpPolicies::resolve(None, &config, Some("reviewer"), None).await;
+        assert!(!allows(unloaded, "get_issue"));
+    }
+
+    #[tokio::test]
+    async fn missions_without_an_agent_use_the_default_agent() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut config = Config::new(tmp.path().to_path_buf());
+        config.opencode_agent = Some("reviewer".to_string());
+        write_agent(
+            &config.library_path,
+            "reviewer",
+            "mcp:\n  allow: [\"github/get_*\"]\n",
+        );
+        let library = LibraryStore::with_test_store(config.library_path.clone()).await;
+        let shared = std::sync::Arc::new(tokio::sync::RwLock::new(Some(std::sync::Arc::new(
+            LibraryStore::with_test_store(config.library_path.clone()).await,
+        ))));
+
+        // What the MCP gateway enforces and what a mission turn lists agree
+        let gateway = McpPolicies::resolve(Some(&library), &config, None, None).await;
+        let turn =
+            crate::api::control::with_mission_tool_policies(config.clone(), &shared, None, None)
+                .await
+                .mcp_policies;
+        for tool in ["get_issue", "create_issue"] {
+            assert_eq!(
+                gateway.allows_tool("github", tool),
+                turn.allows_tool("github", tool),
+                "{}",
+                tool
+            );
+        }
+        assert!(!gateway.allows_tool("github", "create_issue"));
+    }
+
+    #[test]
+    fn normalize_path_resolves_dot_segments() {
+        assert_eq!(normalize_path("/a/./b/../c"), "/a/c");
+        assert_eq!(normalize_path("/../etc"), "/etc");
+        assert_eq!(normalize_path("a/../../b"), "../b");
+        assert_eq!(normalize_path("a/.."), ".");
+        assert_eq!(normalize_path("acme"), "acme");
+        assert_eq!(normalize_path("https://x/../y"), "https://x/../y");
+    }
+}
@@ -1,26 +1,45 @@
 //! MCP runtime registry - manages connections and tool execution.
 //!
-//! Supports both HTTP and stdio transports:
-//! - HTTP: JSON-RPC over HTTP POST requests
-//! - Stdio: JSON-RPC over stdin/stdout with spawned child processes
+//! Supports HTTP, SSE and stdio transports:
+//! - HTTP: Streamable HTTP (JSON or SSE responses, sessions), see [`super::http`]
+//! - SSE: legacy HTTP+SSE, see [`super::http`]
+//! - Stdio: JSON-RPC over stdin/stdout with spawned child processes, kept
+//!   running by the supervisor (see [`super::supervisor`])
+//!
+//! Workspace-scoped stdio MCPs additionally run one process per workspace,
+//! started through [`WorkspaceExec`] inside the workspace (e.g. its
+//! container) and stopped with it; each instance discovers its own tools.
+//!
+//! Remote MCPs authorized with OAuth (see [`super::oauth`]) get their bearer
+//! token from the secrets store, refreshed before it expires.
 
 use std::collections::HashMap;
 use std::path::Path;
 use std::process::Stdio;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex, Weak};
 use std::time::Duration;
 
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, Command};
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{broadcast, Mutex, RwLock};
 use uuid::Uuid;
 
 use super::config::McpConfigStore;
+use super::gateway::{GatewaySettings, GatewayTokens};
+use super::http::{self, Incoming, NotificationSink, RemoteClient};
+use super::limits::{CallLimiter, McpLimits};
+use super::oauth::{self, McpOAuthStatus, OAuthCredentials, PendingAuthorization};
+use super::policy::McpPolicies;
+use super::supervisor::{self, Health, StderrLog, Supervision};
+use super::telemetry::{McpStatsQuery, McpToolStats, TelemetryStore};
 use super::types::*;
+use crate::secrets::{SecretMetadata, SecretType, SecretsStore};
+use crate::workspace::Workspace;
+use crate::workspace_exec::WorkspaceExec;
 
 /// MCP protocol version we support
-const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+pub(super) const MCP_PROTOCOL_VERSION: &str = "2025-03-26";
 
 /// Sanitize MCP server name to create a valid function name prefix.
 ///
@@ -33,6 +52,15 @@ fn sanitize_mcp_prefix(name: &str) -> String {
         .replace('-', "_")
 }
 
+/// Copy of `state` with `uptime_secs` computed from `started_at`.
+fn with_uptime(state: &McpServerState) -> McpServerState {
+    let mut state = state.clone();
+    state.uptime_secs = state
+        .started_at
+        .map(|started| (chrono::Utc::now() - started).num_seconds().max(0) as u64);
+    state
+}
+
 fn command_exists(command: &str) -> bool {
     if command.trim().is_empty() {
         return false;
@@ -72,24 +100,145 @@ struct StdioProcess {
     stdout_lines: Arc<Mutex<BufReader<tokio::process::ChildStdout>>>,
 }
 
+/// A stdio MCP process shared by its callers.
+///
+/// The request holding the process reads its stdout, so the supervisor
+/// cannot ping a busy process itself; it asks the request in flight to send
+/// the ping instead, and tells it to kill the process when the ping goes
+/// unanswered.
+struct SharedStdio {
+    process: Mutex<StdioProcess>,
+    /// When the ping sent by the request in flight went out, while it is
+    /// awaiting the answer
+    busy_ping: StdMutex<Option<tokio::time::Instant>>,
+    /// Asks the request in flight to ping the process
+    ping: tokio::sync::Notify,
+    /// Tells the request in flight to kill the process and fail
+    hung: tokio::sync::Notify,
+}
+
+impl SharedStdio {
+    fn new(process: StdioProcess) -> Self {
+        Self {
+            process: Mutex::new(process),
+            busy_ping: StdMutex::new(None),
+            ping: tokio::sync::Notify::new(),
+            hung: tokio::sync::Notify::new(),
+        }
+    }
+
+    async fn lock(&self) -> tokio::sync::MutexGuard<'_, StdioProcess> {
+        self.process.lock().await
+    }
+
+    fn try_lock(
+        &self,
+    ) -> Result<tokio::sync::MutexGuard<'_, StdioProcess>, tokio::sync::TryLockError> {
+        self.process.try_lock()
+    }
+
+    /// Health of the process while a request holds it: a ping is sent
+    /// through the request when `ping` is due, and the process counts as
+    /// hung once that ping is not answered within the ping timeout.
+    fn check_busy(&self, ping: bool) -> Health {
+        match *self.busy_ping.lock().unwrap() {
+            Some(sent) if sent.elapsed() >= supervisor::PING_TIMEOUT => {
+                self.hung.notify_waiters();
+                Health::Failed("Ping timed out during a request".to_string())
+            }
+            Some(_) => Health::Busy,
+            None => {
+                if ping {
+                    self.ping.notify_one();
+                }
+                Health::Busy
+            }
+        }
+    }
+}
+
+/// Clears the ping of a request in flight once the request is over.
+struct BusyGuard<'a>(&'a SharedStdio);
+
+impl Drop for BusyGuard<'_> {
+    fn drop(&mut self) {
+        *self.0.busy_ping.lock().unwrap() = None;
+    }
+}
+
+/// A workspace-scoped stdio MCP running inside a workspace.
+struct WorkspaceInstance {
+    /// None once the process failed to start or was found exited
+    process: Option<Arc<SharedStdio>>,
+    stderr: Arc<StderrLog>,
+    state: McpWorkspaceInstance,
+}
+
+/// Tools, resources and prompts found on a freshly connected MCP.
+struct Discovered {
+    tools: Vec<McpToolDescriptor>,
+    resources: Vec<McpResourceDescriptor>,
+    prompts: Vec<McpPromptDescriptor>,
+    version: Option<String>,
+}
+
+impl Discovered {
+    fn apply(&self, config: &mut McpServerConfig) {
+        config.tools = self.tools.iter().map(|t| t.name.clone()).collect();
+        config.tool_descriptors = self.tools.clone();
+        config.resources = self.resources.clone();
+        config.prompts = self.prompts.clone();
+        config.version = self.version.clone();
+        config.last_connected_at = Some(chrono::Utc::now());
+    }
+}
+
 /// Runtime registry for MCP servers.
 pub struct McpRegistry {
     /// Persistent configuration store
     config_store: Arc<McpConfigStore>,
     /// Runtime state for each MCP (keyed by ID)
     states: RwLock<HashMap<Uuid, McpServerState>>,
-    /// HTTP client for HTTP MCP requests
+    /// HTTP client for HTTP and SSE MCP requests
     http_client: reqwest::Client,
+    /// Sessions of HTTP and SSE MCPs (keyed by ID)
+    remote_clients: RwLock<HashMap<Uuid, Arc<RemoteClient>>>,
     /// Stdio processes for stdio MCPs (keyed by ID)
-    stdio_processes: RwLock<HashMap<Uuid, Arc<Mutex<StdioProcess>>>>,
+    stdio_processes: RwLock<HashMap<Uuid, Arc<SharedStdio>>>,
+    /// Recent stderr output of stdio MCPs (keyed by ID, kept across restarts)
+    stderr_logs: StdMutex<HashMap<Uuid, Arc<StderrLog>>>,
+    /// Restart bookkeeping of the supervisor (keyed by ID)
+    supervision: Mutex<HashMap<Uuid, Supervision>>,
+    /// Processes of workspace-scoped stdio MCPs (keyed by MCP and workspace ID)
+    workspace_instances: RwLock<HashMap<(Uuid, Uuid), WorkspaceInstance>>,
+    /// Notifications sent by HTTP and SSE MCP servers
+    notifications: broadcast::Sender<McpNotification>,
     /// Disabled tools (by name)
     disabled_tools: RwLock<std::collections::HashSet<String>>,
     /// Request ID counter for JSON-RPC
     request_id: AtomicU64,
+    /// Per-mission gateway settings, when the gateway is enabled
+    gateway: std::sync::OnceLock<GatewaySettings>,
+    /// Gateway tokens of running missions
+    gateway_tokens: GatewayTokens,
+    /// Rate limits and concurrency caps of tool calls in flight
+    limiter: CallLimiter,
+    /// Per-tool call statistics
+    telemetry: TelemetryStore,
+    /// Secrets store holding OAuth credentials, once available
+    secrets: std::sync::OnceLock<Arc<SecretsStore>>,
+    /// OAuth authorizations waiting for their code (keyed by ID)
+    pending_oauth: Mutex<HashMap<Uuid, PendingAuthorization>>,
+    /// Decrypted OAuth credentials (keyed by ID)
+    oauth_credentials: Mutex<HashMap<Uuid, OAuthSlot>>,
 }
 
-const MCP_REQUEST_TIMEOUT: Duration = Duration::from_secs(600);
-const MCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Cached OAuth credentials of one MCP; locked while they are refreshed so
+/// that a rotated refresh token is used once.
+type OAuthSlot = Arc<Mutex<Option<OAuthCredentials>>>;
+
+pub(super) const MCP_REQUEST_TIMEOUT: Duration = Duration::from_secs(600);
+pub(super) const MCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 
 impl McpRegistry {
     /// Create a new MCP registry.
@@ -104,9 +253,9 @@ impl McpRegistry {
             states.insert(config.id, McpServerState::from_config(config));
         }
 
-        // Use generous timeouts for long-running MCP tools (e.g., Minecraft launches)
+        // Requests set a generous timeout for long-running MCP tools (e.g., Minecraft
+        // launches); event streams stay open indefinitely
         let http_client = reqwest::Client::builder()
-            .timeout(MCP_REQUEST_TIMEOUT)
             .connect_timeout(MCP_CONNECT_TIMEOUT)
             .build()
             .unwrap_or_default();
@@ -115,9 +264,21 @@ impl McpRegistry {
             config_store,
             states: RwLock::new(states),
             http_client,
+            remote_clients: RwLock::new(HashMap::new()),
             stdio_processes: RwLock::new(HashMap::new()),
+            stderr_logs: StdMutex::new(HashMap::new()),
+            supervision: Mutex::new(HashMap::new()),
+            workspace_instances: RwLock::new(HashMap::new()),
+            notifications: broadcast::channel(256).0,
             disabled_tools: RwLock::new(std::collections::HashSet::new()),
             request_id: AtomicU64::new(1),
+            gateway: std::sync::OnceLock::new(),
+            gateway_tokens: GatewayTokens::default(),
+            limiter: CallLimiter::default(),
+            telemetry: TelemetryStore::load(working_dir).await,
+            secrets: std::sync::OnceLock::new(),
+            pending_oauth: Mutex::new(HashMap::new()),
+            oauth_credentials: Mutex::new(HashMap::new()),
         }
     }
 
@@ -126,6 +287,77 @@ impl McpRegistry {
         self.config_store.list().await
     }
 
+    /// Return `configs` as launched missions use them: enabled MCPs get the
+    /// bearer token of their OAuth authorization and their secret references
+    /// (`${secret:registry/key}`) resolved. Nothing is persisted.
+    ///
+    /// Pass only the MCPs a mission can use, so unrelated secrets are not
+    /// read. An MCP whose token or secrets are unavailable is logged and left
+    /// out instead of failing the whole harness config.
+    pub async fn resolve_configs(&self, configs: Vec<McpServerConfig>) -> Vec<McpServerConfig> {
+        let mut resolved = Vec::with_capacity(configs.len());
+        for config in configs {
+            if !config.enabled {
+                resolved.push(config);
+                continue;
+            }
+            match self.resolve_config(&config).await {
+                Ok(config) => resolved.push(config),
+                Err(e) => {
+                    tracing::warn!(
+                        mcp = %config.name,
+                        error = %e,
+                        "Leaving MCP out of harness config: failed to resolve its credentials"
+                    );
+                }
+            }
+        }
+        resolved
+    }
+
+    /// Return `configs` for a harness config preview: secret references are
+    /// kept as written and OAuth MCPs get a bearer token placeholder, so no
+    /// secret or token is read.
+    pub fn unresolved_configs(configs: Vec<McpServerConfig>) -> Vec<McpServerConfig> {
+        configs
+            .into_iter()
+            .map(|mut config| {
+                if config.enabled && config.oauth.is_some() {
+                    let placeholder = crate::harness_config::oauth_placeholder(&config.name);
+                    if let McpTransport::Http { headers, .. } | McpTransport::Sse { headers, .. } =
+                        &mut config.transport
+                    {
+                        headers.insert(
+                            "Authorization".to_string(),
+                            format!("Bearer {}", placeholder),
+                        );
+                    }
+                }
+                config
+            })
+            .collect()
+    }
+
+    /// A copy of `config` with its OAuth token and secret references applied.
+    async fn resolve_config(&self, config: &McpServerConfig) -> anyhow::Result<McpServerConfig> {
+        let mut config = config.clone();
+        self.apply_oauth(&mut config)
+            .await
+            .map_err(|e| anyhow::anyhow!("OAuth token unavailable: {}", e))?;
+        let secrets = self.secrets.get().map(|store| store.as_ref());
+        match &mut config.transport {
+            McpTransport::Stdio { env, .. } => {
+                let context = format!("MCP {} env", config.name);
+                *env = crate::secrets::resolve_env_refs(secrets, env, &context).await?;
+            }
+            McpTransport::Http { headers, .. } | McpTransport::Sse { headers, .. } => {
+                let context = format!("MCP {} header", config.name);
+                *headers = crate::secrets::resolve_env_refs(secrets, headers, &context).await?;
+            }
+        }
+        Ok(config)
+    }
+
     fn default_configs(working_dir: &Path) -> Vec<McpServerConfig> {
         let mut desktop_env = HashMap::new();
         if let Ok(res) = std::env::var("DESKTOP_RESOLUTION") {
@@ -276,7 +508,7 @@ impl McpRegistry {
                     .copied()
                     .filter(|flag| !args.iter().any(|arg| arg == *flag))
                     .collect(),
-                McpTransport::Http { .. } => Vec::new(),
+                McpTransport::Http { .. } | McpTransport::Sse { .. } => Vec::new(),
             };
 
             if missing_flags.is_empty() {
@@ -377,54 +609,28 @@ impl McpRegistry {
         self.request_id.fetch_add(1, Ordering::SeqCst)
     }
 
-    /// Send a JSON-RPC request via HTTP
-    async fn send_jsonrpc_http(
-        &self,
-        endpoint: &str,
-        method: &str,
-        params: Option<serde_json::Value>,
-        headers: &HashMap<String, String>,
-    ) -> anyhow::Result<serde_json::Value> {
-        let request = JsonRpcRequest::new(self.next_request_id(), method, params);
-
-        let mut req_builder = self
-            .http_client
-            .post(endpoint)
-            .header("Content-Type", "application/json");
-
-        // Add custom headers
-        for (key, value) in headers {
-            req_builder = req_builder.header(key.as_str(), value.as_str());
-        }
-
-        let response = req_builder.json(&request).send().await?;
-
-        if !response.status().is_success() {
-            anyhow::bail!("HTTP {}", response.status());
-        }
-
-        let json_response: JsonRpcResponse = response.json().await?;
-
-        if let Some(error) = json_response.error {
-            anyhow::bail!("JSON-RPC error {}: {}", error.code, error.message);
-        }
-
-        json_response
-            .result
-            .ok_or_else(|| anyhow::anyhow!("No result in response"))
-    }
-
     /// Send a JSON-RPC request via stdio
+    ///
+    /// Notifications the server sends before the response are passed to
+    /// `progress` (progress updates) or skipped.
     async fn send_jsonrpc_stdio(
         &self,
-        process: &Arc<Mutex<StdioProcess>>,
+        process: &Arc<SharedStdio>,
         method: &str,
         params: Option<serde_json::Value>,
+        progress: Option<&McpProgressCallback>,
     ) -> anyhow::Result<serde_json::Value> {
-        let request = JsonRpcRequest::new(self.next_request_id(), method, params);
+        let id = self.next_request_id();
+        let params = match progress {
+            Some(_) => http::with_progress_token(params, id),
+            None => params,
+        };
+        let request = JsonRpcRequest::new(id, method, params);
         let request_json = serde_json::to_string(&request)?;
 
-        let mut proc = process.lock().await;
+        let mut guard = process.lock().await;
+        let _busy = BusyGuard(process);
+        let proc = &mut *guard;
 
         // Write request to stdin
         proc.stdin.write_all(request_json.as_bytes()).await?;
@@ -433,33 +639,78 @@ impl McpRegistry {
 
         // Read response from stdout
         let mut stdout = proc.stdout_lines.lock().await;
-        let mut line = String::new();
-
-        // Read with timeout
-        let read_result =
-            tokio::time::timeout(MCP_REQUEST_TIMEOUT, stdout.read_line(&mut line)).await;
-
-        match read_result {
-            Ok(Ok(0)) => anyhow::bail!("MCP process closed stdout"),
-            Ok(Ok(_)) => {
-                let json_response: JsonRpcResponse = serde_json::from_str(&line)?;
-
-                if let Some(error) = json_response.error {
-                    anyhow::bail!("JSON-RPC error {}: {}", error.code, error.message);
+        let deadline = tokio::time::Instant::now() + MCP_REQUEST_TIMEOUT;
+        let hung = process.hung.notified();
+        tokio::pin!(hung);
+        // Ping sent on behalf of the supervisor, awaiting its answer
+        let mut ping_id = None;
+        // `read_until` keeps partial lines when a ping interrupts it
+        let mut buf = Vec::new();
+
+        loop {
+            // Read with timeout
+            let read_result = tokio::select! {
+                result = tokio::time::timeout_at(deadline, stdout.read_until(b'\n', &mut buf)) => result,
+                _ = process.ping.notified(), if ping_id.is_none() => {
+                    let ping = JsonRpcRequest::new(self.next_request_id(), "ping", None);
+                    proc.stdin.write_all(serde_json::to_string(&ping)?.as_bytes()).await?;
+                    proc.stdin.write_all(b"\n").await?;
+                    proc.stdin.flush().await?;
+                    ping_id = Some(ping.id);
+                    *process.busy_ping.lock().unwrap() = Some(tokio::time::Instant::now());
+                    continue;
+                }
+                _ = &mut hung => {
+                    let _ = proc.child.kill().await;
+                    anyhow::bail!("MCP process was killed after it stopped answering pings");
                 }
+            };
 
-                json_response
-                    .result
-                    .ok_or_else(|| anyhow::anyhow!("No result in response"))
+            match read_result {
+                Ok(Ok(0)) => anyhow::bail!("MCP process closed stdout"),
+                Ok(Ok(_)) => {
+                    let line = String::from_utf8_lossy(&buf).into_owned();
+                    buf.clear();
+                    for message in http::parse_messages(&line) {
+                        match message {
+                            Incoming::Response(response) if response.id == Some(id) => {
+                                return http::response_result(response);
+                            }
+                            Incoming::Response(response)
+                                if ping_id.is_some() && response.id == ping_id =>
+                            {
+                                ping_id = None;
+                                *process.busy_ping.lock().unwrap() = None;
+                            }
+                            Incoming::Response(_) => {}
+                            Incoming::Request { id, method } => {
+                                let reply = serde_json::to_string(&http::reply_to(id, &method))?;
+                                proc.stdin.write_all(reply.as_bytes()).await?;
+                                proc.stdin.write_all(b"\n").await?;
+                                proc.stdin.flush().await?;
+                            }
+                            Incoming::Notification { method, params } => {
+                                if let Some(progress) = progress {
+                                    if method == "notifications/progress"
+                                        && http::progress_token(&params) == Some(id)
+                                    {
+                                        http::report_progress(progress, &params);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                Ok(Err(e)) => anyhow::bail!("Read error: {}", e),
+                Err(_) => anyhow::bail!("Timeout waiting for MCP response"),
             }
-            Ok(Err(e)) => anyhow::bail!("Read error: {}", e),
-            Err(_) => anyhow::bail!("Timeout waiting for MCP response"),
         }
     }
 
     /// Spawn a stdio MCP process
     async fn spawn_stdio_process(
         &self,
+        id: Uuid,
         command: &str,
         args: &[String],
         env: &HashMap<String, String>,
@@ -475,8 +726,12 @@ impl McpRegistry {
             cmd.env(key, value);
         }
 
-        let mut child = cmd.spawn()?;
+        let child = cmd.spawn()?;
+        Self::attach_stdio(child, self.stderr_log(id))
+    }
 
+    /// Take over the pipes of a spawned stdio MCP, copying stderr to `log`.
+    fn attach_stdio(mut child: Child, log: Arc<StderrLog>) -> anyhow::Result<StdioProcess> {
         let stdin = child
             .stdin
             .take()
@@ -486,6 +741,10 @@ impl McpRegistry {
             .take()
             .ok_or_else(|| anyhow::anyhow!("Failed to capture stdout"))?;
 
+        if let Some(stderr) = child.stderr.take() {
+            supervisor::capture_stderr(stderr, log);
+        }
+
         let stdout_lines = Arc::new(Mutex::new(BufReader::new(stdout)));
 
         Ok(StdioProcess {
@@ -495,69 +754,32 @@ impl McpRegistry {
         })
     }
 
-    /// Initialize connection with an MCP server (HTTP)
-    async fn initialize_mcp_http(
-        &self,
-        endpoint: &str,
-        headers: &HashMap<String, String>,
-    ) -> anyhow::Result<InitializeResult> {
-        let params = InitializeParams {
+    /// Params of the `initialize` request.
+    fn initialize_params() -> InitializeParams {
+        InitializeParams {
             protocol_version: MCP_PROTOCOL_VERSION.to_string(),
             capabilities: ClientCapabilities::default(),
             client_info: ClientInfo {
                 name: "open-agent".to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
             },
-        };
-
-        let result = self
-            .send_jsonrpc_http(
-                endpoint,
-                "initialize",
-                Some(serde_json::to_value(params)?),
-                headers,
-            )
-            .await?;
-
-        let init_result: InitializeResult = serde_json::from_value(result)?;
-
-        // Send initialized notification (no response expected, but some servers require it)
-        let mut req_builder = self
-            .http_client
-            .post(endpoint)
-            .header("Content-Type", "application/json");
-
-        for (key, value) in headers {
-            req_builder = req_builder.header(key.as_str(), value.as_str());
         }
-
-        let _ = req_builder
-            .json(&serde_json::json!({
-                "jsonrpc": "2.0",
-                "method": "notifications/initialized"
-            }))
-            .send()
-            .await;
-
-        Ok(init_result)
     }
 
     /// Initialize connection with an MCP server (stdio)
     async fn initialize_mcp_stdio(
         &self,
-        process: &Arc<Mutex<StdioProcess>>,
+        process: &Arc<SharedStdio>,
     ) -> anyhow::Result<InitializeResult> {
-        let params = InitializeParams {
-            protocol_version: MCP_PROTOCOL_VERSION.to_string(),
-            capabilities: ClientCapabilities::default(),
-            client_info: ClientInfo {
-                name: "open-agent".to_string(),
-                version: env!("CARGO_PKG_VERSION").to_string(),
-            },
-        };
+        let params = Self::initialize_params();
 
         let result = self
-            .send_jsonrpc_stdio(process, "initialize", Some(serde_json::to_value(params)?))
+            .send_jsonrpc_stdio(
+                process,
+                "initialize",
+                Some(serde_json::to_value(params)?),
+                None,
+            )
             .await?;
 
         let init_result: InitializeResult = serde_json::from_value(result)?;
@@ -577,21 +799,56 @@ impl McpRegistry {
         Ok(init_result)
     }
 
+    /// Subscribe to notifications sent by HTTP and SSE MCP servers
+    /// (e.g. `notifications/tools/list_changed`).
+    pub fn subscribe_notifications(&self) -> broadcast::Receiver<McpNotification> {
+        self.notifications.subscribe()
+    }
+
+    /// Stop the stdio process or end the remote session of an MCP.
+    async fn disconnect(&self, id: Uuid) {
+        if let Some(process) = self.stdio_processes.write().await.remove(&id) {
+            let mut proc = process.lock().await;
+            let _ = proc.child.kill().await;
+        }
+        if let Some(client) = self.remote_clients.write().await.remove(&id) {
+            client.close().await;
+        }
+        if let Some(state) = self.states.write().await.get_mut(&id) {
+            state.started_at = None;
+        }
+    }
+
+    /// The stderr buffer of a stdio MCP, created on first use.
+    fn stderr_log(&self, id: Uuid) -> Arc<StderrLog> {
+        Arc::clone(self.stderr_logs.lock().unwrap().entry(id).or_default())
+    }
+
+    /// Recent stderr lines of a stdio MCP, oldest first.
+    pub fn stderr_lines(&self, id: Uuid) -> Vec<String> {
+        self.stderr_logs
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|log| log.lines())
+            .unwrap_or_default()
+    }
+
     /// List all MCP servers with their current state.
     pub async fn list(&self) -> Vec<McpServerState> {
-        self.states.read().await.values().cloned().collect()
+        self.states.read().await.values().map(with_uptime).collect()
     }
 
     /// Get a specific MCP server state.
     pub async fn get(&self, id: Uuid) -> Option<McpServerState> {
-        self.states.read().await.get(&id).cloned()
+        self.states.read().await.get(&id).map(with_uptime)
     }
 
     /// Add a new MCP server.
     /// Note: This does NOT automatically attempt to connect. Use refresh() after adding.
     pub async fn add(&self, req: AddMcpRequest) -> anyhow::Result<McpServerState> {
         let mut config = match &req.transport {
-            McpTransport::Http { endpoint, .. } => {
+            McpTransport::Http { endpoint, .. } | McpTransport::Sse { endpoint, .. } => {
                 McpServerConfig::new(req.name.clone(), endpoint.clone())
             }
             McpTransport::Stdio { command, args, env } => McpServerConfig::new_stdio(
@@ -601,6 +858,7 @@ impl McpRegistry {
                 env.clone(),
             ),
         };
+        config.transport = req.transport.clone();
         config.description = req.description;
         if let Some(scope) = req.scope {
             config.scope = scope;
@@ -628,20 +886,20 @@ impl McpRegistry {
 
     /// Remove an MCP server.
     pub async fn remove(&self, id: Uuid) -> anyhow::Result<()> {
-        // Kill stdio process if running
-        {
-            let mut processes = self.stdio_processes.write().await;
-            if let Some(process) = processes.remove(&id) {
-                let mut proc = process.lock().await;
-                let _ = proc.child.kill().await;
-            }
-        }
+        // Kill stdio processes or end the session if running
+        self.disconnect(id).await;
+        self.stop_instances(|mcp_id, _| mcp_id == id).await;
 
         // Remove from persistent store
         self.config_store.remove(id).await?;
 
         // Remove from states
         self.states.write().await.remove(&id);
+        self.stderr_logs.lock().unwrap().remove(&id);
+        self.supervision.lock().await.remove(&id);
+        self.limiter.remove_mcp(id);
+        self.telemetry.remove_mcp(id);
+        self.forget_oauth(id).await;
 
         Ok(())
     }
@@ -669,14 +927,9 @@ impl McpRegistry {
 
     /// Disable an MCP server.
     pub async fn disable(&self, id: Uuid) -> anyhow::Result<McpServerState> {
-        // Kill stdio process if running
-        {
-            let mut processes = self.stdio_processes.write().await;
-            if let Some(process) = processes.remove(&id) {
-                let mut proc = process.lock().await;
-                let _ = proc.child.kill().await;
-            }
-        }
+        // Kill stdio processes or end the session if running
+        self.disconnect(id).await;
+        self.stop_instances(|mcp_id, _| mcp_id == id).await;
 
         // Update persistent config
         let config = self.config_store.disable(id).await?;
@@ -703,13 +956,12 @@ impl McpRegistry {
         id: Uuid,
         req: super::types::UpdateMcpRequest,
     ) -> anyhow::Result<McpServerState> {
-        // Kill existing stdio process if transport might change
+        // Kill existing stdio process or session if transport might change
         if req.transport.is_some() {
-            let mut processes = self.stdio_processes.write().await;
-            if let Some(process) = processes.remove(&id) {
-                let mut proc = process.lock().await;
-                let _ = proc.child.kill().await;
-            }
+            self.disconnect(id).await;
+        }
+        if req.transport.is_some() || req.scope.is_some() || req.enabled == Some(false) {
+            self.stop_instances(|mcp_id, _| mcp_id == id).await;
         }
 
         // Update persistent config
@@ -780,22 +1032,12 @@ impl McpRegistry {
     }
 
     /// Helper to update state with success - retries a few times to handle lock contention
-    async fn update_state_success(
-        &self,
-        id: Uuid,
-        tool_descriptors: Vec<McpToolDescriptor>,
-        server_version: Option<String>,
-    ) {
-        let tool_names: Vec<String> = tool_descriptors.iter().map(|t| t.name.clone()).collect();
-
+    async fn update_state_success(&self, id: Uuid, discovered: &Discovered) {
         // Try up to 5 times with small delays to handle temporary lock contention
         for attempt in 0..5 {
             if let Ok(mut states) = self.states.try_write() {
                 if let Some(state) = states.get_mut(&id) {
-                    state.config.tools = tool_names.clone();
-                    state.config.tool_descriptors = tool_descriptors.clone();
-                    state.config.version = server_version.clone();
-                    state.config.last_connected_at = Some(chrono::Utc::now());
+                    discovered.apply(&mut state.config);
                     state.status = McpStatus::Connected;
                     state.error = None;
                 }
@@ -810,6 +1052,83 @@ impl McpRegistry {
         tracing::warn!("Failed to update MCP {} success state after retries", id);
     }
 
+    /// List the tools of a freshly initialized MCP, plus its resources and
+    /// prompts when the server advertises them, and mark it connected.
+    ///
+    /// Only a failing `tools/list` puts the MCP in the error state; servers
+    /// that fail to list resources or prompts keep working without them.
+    async fn discover(&self, id: Uuid, init_result: &InitializeResult) {
+        let tools = match self.send_request(id, "tools/list", None, None).await {
+            Ok(result) => match serde_json::from_value::<McpToolsResponse>(result) {
+                Ok(tools_response) => tools_response.tools,
+                Err(e) => {
+                    self.update_state_error(id, format!("Failed to parse tools: {}", e))
+                        .await;
+                    return;
+                }
+            },
+            Err(e) => {
+                self.update_state_error(id, format!("tools/list failed: {}", e))
+                    .await;
+                return;
+            }
+        };
+
+        let capabilities = &init_result.capabilities;
+        let resources = match capabilities.resources {
+            Some(_) => self
+                .list_optional::<McpResourcesResponse>(id, "resources/list")
+                .await
+                .map(|r| r.resources)
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let prompts = match capabilities.prompts {
+            Some(_) => self
+                .list_optional::<McpPromptsResponse>(id, "prompts/list")
+                .await
+                .map(|r| r.prompts)
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        let discovered = Discovered {
+            tools,
+            resources,
+            prompts,
+            // Extract server version if available
+            version: init_result
+                .server_info
+                .as_ref()
+                .and_then(|s| s.version.clone()),
+        };
+
+        // Update config with discovered tools, resources and prompts
+        let _ = self.config_store.update(id, |c| discovered.apply(c)).await;
+
+        // Update runtime state
+        self.update_state_success(id, &discovered).await;
+    }
+
+    /// Send a listing request whose failure should not fail the connection.
+    async fn list_optional<T: serde::de::DeserializeOwned>(
+        &self,
+        id: Uuid,
+        method: &str,
+    ) -> Option<T> {
+        let listed = match self.send_request(id, method, None, None).await {
+            Ok(result) => serde_json::from_value(result).map_err(anyhow::Error::from),
+            Err(e) => Err(e),
+        };
+        match listed {
+            Ok(listed) => Some(listed),
+            Err(e) => {
+                tracing::warn!(mcp_id = %id, method = method, error = %e, "MCP listing failed");
+                None
+            }
+        }
+    }
+
     /// Refresh an MCP server - reconnect and discover tools.
     pub async fn refresh(&self, id: Uuid) -> anyhow::Result<McpServerState> {
         let state = self
@@ -821,10 +1140,32 @@ impl McpRegistry {
             return Ok(state);
         }
 
-        match &state.config.transport {
-            McpTransport::Http { endpoint, headers } => {
-                self.refresh_http(id, endpoint.clone(), headers.clone())
+        // Workspace-scoped stdio MCPs only run as instances inside workspaces;
+        // stop a host process left over from before a scope change
+        if Self::runs_in_workspace(&state.config) {
+            self.disconnect(id).await;
+            return self
+                .get(id)
+                .await
+                .ok_or_else(|| anyhow::anyhow!("MCP not found"));
+        }
+
+        // A manual refresh starts over after the supervisor gave up
+        self.supervision.lock().await.remove(&id);
+
+        let config = match self.resolve_config(&state.config).await {
+            Ok(config) => config,
+            Err(e) => {
+                self.update_state_error(id, e.to_string()).await;
+                return self
+                    .get(id)
                     .await
+                    .ok_or_else(|| anyhow::anyhow!("MCP not found"));
+            }
+        };
+        match &config.transport {
+            McpTransport::Http { .. } | McpTransport::Sse { .. } => {
+                self.refresh_remote(id, &config.transport).await
             }
             McpTransport::Stdio { command, args, env } => {
                 self.refresh_stdio(id, command.clone(), args.clone(), env.clone())
@@ -833,18 +1174,25 @@ impl McpRegistry {
         }
     }
 
-    /// Refresh an HTTP MCP server
-    async fn refresh_http(
+    /// Refresh an HTTP or SSE MCP server
+    async fn refresh_remote(
         &self,
         id: Uuid,
-        endpoint: String,
-        headers: HashMap<String, String>,
+        transport: &McpTransport,
     ) -> anyhow::Result<McpServerState> {
-        let endpoint = endpoint.trim_end_matches('/').to_string();
+        // End the previous session if any
+        self.disconnect(id).await;
 
         // Step 1: Initialize the MCP connection with JSON-RPC
-        let init_result = match self.initialize_mcp_http(&endpoint, &headers).await {
-            Ok(result) => result,
+        let sink = NotificationSink::new(id, self.notifications.clone());
+        let connected = match serde_json::to_value(Self::initialize_params()) {
+            Ok(params) => {
+                RemoteClient::connect(transport, self.http_client.clone(), params, sink).await
+            }
+            Err(e) => Err(e.into()),
+        };
+        let (client, init_result) = match connected {
+            Ok(connected) => connected,
             Err(e) => {
                 self.update_state_error(id, format!("Initialize failed: {}", e))
                     .await;
@@ -854,51 +1202,13 @@ impl McpRegistry {
                     .ok_or_else(|| anyhow::anyhow!("MCP not found"));
             }
         };
-
-        // Extract server version if available
-        let server_version = init_result
-            .server_info
-            .as_ref()
-            .and_then(|s| s.version.clone());
-
-        // Step 2: List tools using JSON-RPC
-        match self
-            .send_jsonrpc_http(&endpoint, "tools/list", None, &headers)
+        self.remote_clients
+            .write()
             .await
-        {
-            Ok(result) => {
-                match serde_json::from_value::<McpToolsResponse>(result) {
-                    Ok(tools_response) => {
-                        let tool_descriptors = tools_response.tools;
-                        let tool_names: Vec<String> =
-                            tool_descriptors.iter().map(|t| t.name.clone()).collect();
-
-                        // Update config with discovered tools
-                        let _ = self
-                            .config_store
-                            .update(id, |c| {
-                                c.tools = tool_names.clone();
-                                c.tool_descriptors = tool_descriptors.clone();
-                                c.version = server_version.clone();
-                                c.last_connected_at = Some(chrono::Utc::now());
-                            })
-                            .await;
-
-                        // Update runtime state
-                        self.update_state_success(id, tool_descriptors, server_version)
-                            .await;
-                    }
-                    Err(e) => {
-                        self.update_state_error(id, format!("Failed to parse tools: {}", e))
-                            .await;
-                    }
-                }
-            }
-            Err(e) => {
-                self.update_state_error(id, format!("tools/list failed: {}", e))
-                    .await;
-            }
-        }
+            .insert(id, Arc::new(client));
+
+        // Step 2: Discover tools, resources and prompts
+        self.discover(id, &init_result).await;
 
         self.get(id)
             .await
@@ -914,17 +1224,11 @@ impl McpRegistry {
         env: HashMap<String, String>,
     ) -> anyhow::Result<McpServerState> {
         // Kill existing process if any
-        {
-            let mut processes = self.stdio_processes.write().await;
-            if let Some(process) = processes.remove(&id) {
-                let mut proc = process.lock().await;
-                let _ = proc.child.kill().await;
-            }
-        }
+        self.disconnect(id).await;
 
         // Spawn new process
-        let process = match self.spawn_stdio_process(&command, &args, &env).await {
-            Ok(p) => Arc::new(Mutex::new(p)),
+        let process = match self.spawn_stdio_process(id, &command, &args, &env).await {
+            Ok(p) => Arc::new(SharedStdio::new(p)),
             Err(e) => {
                 self.update_state_error(id, format!("Failed to spawn process: {}", e))
                     .await;
@@ -940,6 +1244,9 @@ impl McpRegistry {
             let mut processes = self.stdio_processes.write().await;
             processes.insert(id, Arc::clone(&process));
         }
+        if let Some(state) = self.states.write().await.get_mut(&id) {
+            state.started_at = Some(chrono::Utc::now());
+        }
 
         // Step 1: Initialize the MCP connection
         let init_result = match self.initialize_mcp_stdio(&process).await {
@@ -960,153 +1267,1121 @@ impl McpRegistry {
             }
         };
 
-        // Extract server version if available
-        let server_version = init_result
-            .server_info
-            .as_ref()
-            .and_then(|s| s.version.clone());
-
-        // Step 2: List tools
-        match self.send_jsonrpc_stdio(&process, "tools/list", None).await {
-            Ok(result) => {
-                match serde_json::from_value::<McpToolsResponse>(result) {
-                    Ok(tools_response) => {
-                        let tool_descriptors = tools_response.tools;
-                        let tool_names: Vec<String> =
-                            tool_descriptors.iter().map(|t| t.name.clone()).collect();
-
-                        // Update config with discovered tools
-                        let _ = self
-                            .config_store
-                            .update(id, |c| {
-                                c.tools = tool_names.clone();
-                                c.tool_descriptors = tool_descriptors.clone();
-                                c.version = server_version.clone();
-                                c.last_connected_at = Some(chrono::Utc::now());
-                            })
-                            .await;
-
-                        // Update runtime state
-                        self.update_state_success(id, tool_descriptors, server_version)
-                            .await;
-                    }
-                    Err(e) => {
-                        self.update_state_error(id, format!("Failed to parse tools: {}", e))
-                            .await;
-                    }
-                }
-            }
-            Err(e) => {
-                self.update_state_error(id, format!("tools/list failed: {}", e))
-                    .await;
-            }
-        }
+        // Step 2: Discover tools, resources and prompts
+        self.discover(id, &init_result).await;
 
         self.get(id)
             .await
             .ok_or_else(|| anyhow::anyhow!("MCP not found"))
     }
 
-    /// Refresh all MCP servers concurrently.
-    pub async fn refresh_all(&self) {
-        let ids: Vec<Uuid> = self.states.read().await.keys().cloned().collect();
-
-        // Refresh all MCPs concurrently using join_all
-        let futures: Vec<_> = ids.iter().map(|id| self.refresh(*id)).collect();
-        futures::future::join_all(futures).await;
+    /// Start supervising stdio MCPs in the background; the same loop saves
+    /// changed tool call telemetry.
+    ///
+    /// The task stops once the registry is dropped.
+    pub fn start_supervisor(self: &Arc<Self>) {
+        let registry = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(supervisor::SUPERVISOR_TICK);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let Some(registry) = Weak::upgrade(&registry) else {
+                    break;
+                };
+                registry.supervise_once().await;
+                if let Err(e) = registry.telemetry.flush().await {
+                    tracing::warn!(error = %e, "Failed to save MCP telemetry");
+                }
+            }
+        });
     }
 
-    /// Call a tool on an MCP server.
-    pub async fn call_tool(
-        &self,
-        mcp_id: Uuid,
-        tool_name: &str,
-        arguments: serde_json::Value,
-    ) -> anyhow::Result<String> {
-        // Check if tool is disabled
-        if self.disabled_tools.read().await.contains(tool_name) {
-            anyhow::bail!("Tool {} is disabled", tool_name);
-        }
-
-        let state = self
-            .get(mcp_id)
+    /// Check every enabled stdio MCP once, restarting failed ones when due.
+    ///
+    /// Workspace instances are only checked for exited processes; they are
+    /// started again by their next call.
+    async fn supervise_once(&self) {
+        let candidates: Vec<(Uuid, McpServerState)> = self
+            .states
+            .read()
             .await
-            .ok_or_else(|| anyhow::anyhow!("MCP not found"))?;
-
-        if !state.config.enabled {
-            anyhow::bail!("MCP {} is disabled", state.config.name);
-        }
-
-        if state.status != McpStatus::Connected {
-            anyhow::bail!("MCP {} is not connected", state.config.name);
-        }
+            .iter()
+            .filter(|(_, s)| {
+                s.config.enabled
+                    && matches!(s.config.transport, McpTransport::Stdio { .. })
+                    && !Self::runs_in_workspace(&s.config)
+            })
+            .map(|(id, s)| (*id, s.clone()))
+            .collect();
+
+        for (id, state) in candidates {
+            let process = self.stdio_processes.read().await.get(&id).cloned();
+            let ping_due = {
+                let mut supervision = self.supervision.lock().await;
+                let entry = supervision.entry(id).or_default();
+                if entry.gave_up {
+                    continue;
+                }
+                entry.ping_due(tokio::time::Instant::now())
+            };
 
-        let params = serde_json::json!({
-            "name": tool_name,
-            "arguments": arguments
-        });
+            let health = match process {
+                Some(process) => self.check_process(&process, ping_due).await,
+                // Never started (or disconnected on purpose): nothing to restore
+                None if state.status != McpStatus::Error => continue,
+                None => Health::Failed(state.error.clone().unwrap_or_default()),
+            };
 
-        let result = match &state.config.transport {
-            McpTransport::Http { endpoint, headers } => {
-                let endpoint = endpoint.trim_end_matches('/');
-                self.send_jsonrpc_http(endpoint, "tools/call", Some(params), headers)
-                    .await
+            // Decide under the lock, restart after releasing it: spawning and
+            // initializing a process can take as long as the connect timeout
+            let now = tokio::time::Instant::now();
+            let mut newly_failed = None;
+            let restart = {
+                let mut supervision = self.supervision.lock().await;
+                let entry = supervision.entry(id).or_default();
+                match health {
+                    Health::Busy => {
+                        if ping_due {
+                            entry.last_ping = Some(now);
+                        }
+                        None
+                    }
+                    Health::Healthy if state.status == McpStatus::Error => {
+                        // Running, but initialization or discovery failed
+                        entry
+                            .failed(now)
+                            .then(|| entry.restarting().then_some(entry.failures))
+                    }
+                    Health::Healthy => {
+                        if ping_due {
+                            entry.last_ping = Some(now);
+                        }
+                        if state.status == McpStatus::Connected {
+                            let uptime = with_uptime(&state).uptime_secs.unwrap_or(0);
+                            entry.healthy(Duration::from_secs(uptime));
+                        }
+                        None
+                    }
+                    Health::Failed(reason) => {
+                        if entry.restart_at.is_none() {
+                            newly_failed = Some(reason);
+                        }
+                        entry
+                            .failed(now)
+                            .then(|| entry.restarting().then_some(entry.failures))
+                    }
+                }
+            };
+
+            if let Some(reason) = newly_failed {
+                tracing::warn!(mcp = %state.config.name, reason = %reason, "Stdio MCP failed");
+                if state.status != McpStatus::Error {
+                    self.update_state_error(id, reason).await;
+                }
             }
-            McpTransport::Stdio { .. } => {
-                let processes = self.stdio_processes.read().await;
-                let process = processes
-                    .get(&mcp_id)
-                    .ok_or_else(|| anyhow::anyhow!("No stdio process for MCP {}", mcp_id))?;
-                self.send_jsonrpc_stdio(process, "tools/call", Some(params))
-                    .await
+            if let Some(attempt) = restart {
+                self.restart(id, &state, attempt).await;
+            }
+        }
+
+        self.reap_workspace_instances().await;
+        self.refresh_expiring_oauth().await;
+    }
+
+    /// Check whether a stdio process is still running and answers `ping`.
+    async fn check_process(&self, process: &Arc<SharedStdio>, ping: bool) -> Health {
+        {
+            // A process in the middle of a request is pinged through it
+            let Ok(mut proc) = process.try_lock() else {
+                return process.check_busy(ping);
+            };
+            match proc.child.try_wait() {
+                Ok(None) => {}
+                Ok(Some(status)) => return Health::Failed(format!("Process exited: {}", status)),
+                Err(e) => return Health::Failed(format!("Process check failed: {}", e)),
+            }
+            if let Some(rss) = proc.child.id().and_then(supervisor::resident_memory) {
+                if rss > supervisor::MAX_RESIDENT_MEMORY {
+                    return Health::Failed(format!(
+                        "Memory use of {} MiB exceeds the limit of {} MiB",
+                        rss >> 20,
+                        supervisor::MAX_RESIDENT_MEMORY >> 20
+                    ));
+                }
+            }
+        }
+        if !ping {
+            return Health::Healthy;
+        }
+        let pinged = tokio::time::timeout(
+            supervisor::PING_TIMEOUT,
+            self.send_jsonrpc_stdio(process, "ping", None, None),
+        )
+        .await;
+        match pinged {
+            Ok(Ok(_)) => Health::Healthy,
+            Ok(Err(e)) => Health::Failed(format!("Ping failed: {}", e)),
+            Err(_) => Health::Failed("Ping timed out".to_string()),
+        }
+    }
+
+    /// Restart a failed stdio MCP as restart number `attempt`, or give up
+    /// on it when `attempt` is None (the crash-loop limit was reached).
+    async fn restart(&self, id: Uuid, state: &McpServerState, attempt: Option<u32>) {
+        if !matches!(state.config.transport, McpTransport::Stdio { .. }) {
+            return;
+        }
+        let Some(attempt) = attempt else {
+            tracing::error!(mcp = %state.config.name, "Stdio MCP keeps failing, giving up");
+            self.disconnect(id).await;
+            self.update_state_error(
+                id,
+                format!(
+                    "Crash loop: gave up after {} restarts; refresh to try again",
+                    supervisor::MAX_RESTARTS
+                ),
+            )
+            .await;
+            return;
+        };
+
+        tracing::info!(mcp = %state.config.name, attempt = attempt, "Restarting stdio MCP");
+        if let Some(state) = self.states.write().await.get_mut(&id) {
+            state.restarts += 1;
+        }
+        let config = match self.resolve_config(&state.config).await {
+            Ok(config) => config,
+            Err(e) => {
+                self.update_state_error(id, e.to_string()).await;
+                return;
             }
         };
+        if let McpTransport::Stdio { command, args, env } = config.transport {
+            let _ = self.refresh_stdio(id, command, args, env).await;
+        }
+    }
 
-        match result {
-            Ok(result) => {
-                let response: McpCallToolResponse = serde_json::from_value(result)?;
-
-                // Increment counters
-                {
-                    let mut states = self.states.write().await;
-                    if let Some(state) = states.get_mut(&mcp_id) {
-                        if response.is_error {
-                            state.tool_errors += 1;
-                        } else {
-                            state.tool_calls += 1;
-                        }
+    /// Refresh all MCP servers concurrently.
+    pub async fn refresh_all(&self) {
+        let ids: Vec<Uuid> = self.states.read().await.keys().cloned().collect();
+
+        // Refresh all MCPs concurrently using join_all
+        let futures: Vec<_> = ids.iter().map(|id| self.refresh(*id)).collect();
+        futures::future::join_all(futures).await;
+    }
+
+    /// Workspace-scoped stdio MCPs used by `workspace`, with the state of
+    /// their instance in it (disconnected when not started).
+    pub async fn workspace_instances(&self, workspace: &Workspace) -> Vec<McpWorkspaceInstance> {
+        let configs = crate::workspace::filter_mcp_configs_for_workspace(
+            self.list_configs().await,
+            &workspace.mcps,
+        );
+        let instances = self.workspace_instances.read().await;
+        configs
+            .into_iter()
+            .filter(Self::runs_in_workspace)
+            .map(|c| match instances.get(&(c.id, workspace.id)) {
+                Some(instance) => instance.state.clone(),
+                None => McpWorkspaceInstance {
+                    mcp_id: c.id,
+                    workspace_id: workspace.id,
+                    name: c.name,
+                    status: McpStatus::Disconnected,
+                    error: None,
+                    tools: Vec::new(),
+                    prompts: Vec::new(),
+                    started_at: None,
+                },
+            })
+            .collect()
+    }
+
+    /// Whether missions run `config` as an instance inside their workspace
+    /// rather than through the registry's own connection.
+    pub fn runs_in_workspace(config: &McpServerConfig) -> bool {
+        config.scope == McpScope::Workspace
+            && matches!(config.transport, McpTransport::Stdio { .. })
+    }
+
+    /// Start a workspace-scoped stdio MCP inside `workspace` and list its
+    /// tools, replacing a running instance.
+    ///
+    /// Failures after validation are recorded on the returned instance.
+    pub async fn start_in_workspace(
+        &self,
+        mcp_id: Uuid,
+        workspace: &Workspace,
+    ) -> anyhow::Result<McpWorkspaceInstance> {
+        let config = self
+            .get(mcp_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("MCP not found"))?
+            .config;
+        if config.scope != McpScope::Workspace {
+            anyhow::bail!("MCP {} is not workspace-scoped", config.name);
+        }
+        if !config.enabled {
+            anyhow::bail!("MCP {} is disabled", config.name);
+        }
+        let McpTransport::Stdio { command, args, .. } = &config.transport else {
+            anyhow::bail!("MCP {} does not use the stdio transport", config.name);
+        };
+
+        self.stop_in_workspace(mcp_id, workspace.id).await;
+
+        let stderr = Arc::new(StderrLog::default());
+        let mut state = McpWorkspaceInstance {
+            mcp_id,
+            workspace_id: workspace.id,
+            name: config.name.clone(),
+            status: McpStatus::Connected,
+            error: None,
+            tools: Vec::new(),
+            prompts: Vec::new(),
+            started_at: Some(chrono::Utc::now()),
+        };
+        // Secret references of the MCP and workspace env are resolved once
+        // per start; the instance keeps the resolved env until it restarts.
+        let spawned = async {
+            let resolved = self.resolve_config(&config).await?;
+            let McpTransport::Stdio { env, .. } = resolved.transport else {
+                unreachable!("resolving keeps the transport");
+            };
+            let workspace = crate::workspace::with_resolved_env(workspace, self.secrets()).await?;
+            let child = WorkspaceExec::new(workspace.clone())
+                .spawn_streaming(&workspace.path, command, args, env)
+                .await?;
+            Self::attach_stdio(child, Arc::clone(&stderr))
+        }
+        .await;
+        let process = match spawned {
+            Ok(process) => {
+                let process = Arc::new(SharedStdio::new(process));
+                match self.discover_in_workspace(&process).await {
+                    Ok((tools, prompts)) => {
+                        state.tools = tools;
+                        state.prompts = prompts;
+                        Some(process)
+                    }
+                    Err(e) => {
+                        let _ = process.lock().await.child.kill().await;
+                        state.error = Some(e);
+                        None
                     }
                 }
+            }
+            Err(e) => {
+                state.error = Some(format!("Failed to spawn process: {:#}", e));
+                state.started_at = None;
+                None
+            }
+        };
+        if let Some(error) = &state.error {
+            tracing::warn!(
+                mcp = %config.name,
+                workspace = %workspace.name,
+                error = %error,
+                "Workspace MCP failed to start"
+            );
+            state.status = McpStatus::Error;
+        }
 
-                if response.is_error {
-                    let error_text = response
-                        .content
-                        .iter()
-                        .filter_map(|c| c.text.as_deref())
-                        .collect::<Vec<_>>()
-                        .join("\n");
-                    anyhow::bail!("Tool error: {}", error_text);
-                }
+        self.workspace_instances.write().await.insert(
+            (mcp_id, workspace.id),
+            WorkspaceInstance {
+                process,
+                stderr,
+                state: state.clone(),
+            },
+        );
+        Ok(state)
+    }
 
-                // Combine text content
-                let output = response
-                    .content
-                    .iter()
-                    .filter_map(|c| c.text.as_deref())
-                    .collect::<Vec<_>>()
-                    .join("\n");
+    /// Initialize a workspace instance and list its tools and prompts.
+    ///
+    /// As in [`Self::discover`], failing to list prompts is not an error.
+    async fn discover_in_workspace(
+        &self,
+        process: &Arc<SharedStdio>,
+    ) -> Result<(Vec<McpToolDescriptor>, Vec<McpPromptDescriptor>), String> {
+        let init_result = self
+            .initialize_mcp_stdio(process)
+            .await
+            .map_err(|e| format!("Initialize failed: {}", e))?;
+        let result = self
+            .send_jsonrpc_stdio(process, "tools/list", None, None)
+            .await
+            .map_err(|e| format!("tools/list failed: {}", e))?;
+        let tools = serde_json::from_value::<McpToolsResponse>(result)
+            .map(|r| r.tools)
+            .map_err(|e| format!("Failed to parse tools: {}", e))?;
+        let prompts = match init_result.capabilities.prompts {
+            Some(_) => self
+                .send_jsonrpc_stdio(process, "prompts/list", None, None)
+                .await
+                .ok()
+                .and_then(|result| serde_json::from_value::<McpPromptsResponse>(result).ok())
+                .map(|r| r.prompts)
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+        Ok((tools, prompts))
+    }
+
+    /// Stop the instance of an MCP in a workspace; returns whether one existed.
+    pub async fn stop_in_workspace(&self, mcp_id: Uuid, workspace_id: Uuid) -> bool {
+        self.stop_instances(|id, ws| id == mcp_id && ws == workspace_id)
+            .await
+            > 0
+    }
+
+    /// Stop every MCP instance of a workspace (when it is deleted or its
+    /// container is rebuilt).
+    pub async fn stop_workspace(&self, workspace_id: Uuid) {
+        self.stop_instances(|_, ws| ws == workspace_id).await;
+    }
+
+    /// Stop the MCP instances of every workspace (server shutdown).
+    pub async fn stop_all_workspaces(&self) {
+        self.stop_instances(|_, _| true).await;
+    }
+
+    /// Tools of an MCP's instance in `workspace`, prefixed as in
+    /// [`Self::list_tools`] and paired with their unprefixed names; the
+    /// instance is started if it is not running.
+    pub(super) async fn workspace_tools(
+        &self,
+        mcp_id: Uuid,
+        workspace: &Workspace,
+    ) -> anyhow::Result<Vec<(McpTool, String)>> {
+        let (_, instance) = self.running_instance(mcp_id, workspace).await?;
+        let disabled = self.disabled_tools.read().await;
+        Ok(instance
+            .tools
+            .iter()
+            .map(|descriptor| prefixed_tool(mcp_id, &instance.name, descriptor, &disabled))
+            .collect())
+    }
+
+    /// Remove the workspace instances matching `(mcp_id, workspace_id)` and
+    /// kill their processes; returns how many were removed.
+    async fn stop_instances(&self, matches: impl Fn(Uuid, Uuid) -> bool) -> usize {
+        let stopped: Vec<WorkspaceInstance> = {
+            let mut instances = self.workspace_instances.write().await;
+            let keys: Vec<(Uuid, Uuid)> = instances
+                .keys()
+                .filter(|(id, ws)| matches(*id, *ws))
+                .copied()
+                .collect();
+            keys.iter()
+                .filter_map(|key| instances.remove(key))
+                .collect()
+        };
+        for instance in &stopped {
+            if let Some(process) = &instance.process {
+                let _ = process.lock().await.child.kill().await;
+            }
+        }
+        stopped.len()
+    }
+
+    /// Recent stderr lines of an MCP's instance in a workspace, oldest first.
+    pub async fn workspace_stderr_lines(&self, mcp_id: Uuid, workspace_id: Uuid) -> Vec<String> {
+        self.workspace_instances
+            .read()
+            .await
+            .get(&(mcp_id, workspace_id))
+            .map(|instance| instance.stderr.lines())
+            .unwrap_or_default()
+    }
 
-                Ok(output)
+    /// Mark workspace instances whose process exited as failed; they are
+    /// started again on their next tool call.
+    async fn reap_workspace_instances(&self) {
+        let running: Vec<_> = self
+            .workspace_instances
+            .read()
+            .await
+            .iter()
+            .filter_map(|(key, instance)| instance.process.clone().map(|p| (*key, p)))
+            .collect();
+        for (key, process) in running {
+            if let Health::Failed(reason) = self.check_process(&process, false).await {
+                let mut instances = self.workspace_instances.write().await;
+                if let Some(instance) = instances.get_mut(&key) {
+                    if instance
+                        .process
+                        .as_ref()
+                        .is_some_and(|p| Arc::ptr_eq(p, &process))
+                    {
+                        tracing::warn!(mcp = %instance.state.name, reason = %reason, "Workspace MCP failed");
+                        instance.process = None;
+                        instance.state.status = McpStatus::Error;
+                        instance.state.error = Some(reason);
+                    }
+                }
             }
+        }
+    }
+
+    /// Process and state of an MCP's instance in `workspace`, starting it
+    /// there if it is not running.
+    async fn running_instance(
+        &self,
+        mcp_id: Uuid,
+        workspace: &Workspace,
+    ) -> anyhow::Result<(Arc<SharedStdio>, McpWorkspaceInstance)> {
+        let running = self
+            .workspace_instances
+            .read()
+            .await
+            .get(&(mcp_id, workspace.id))
+            .and_then(|instance| Some((instance.process.clone()?, instance.state.clone())));
+        if let Some(running) = running {
+            return Ok(running);
+        }
+        let instance = self.start_in_workspace(mcp_id, workspace).await?;
+        if let Some(error) = instance.error {
+            anyhow::bail!("MCP {} failed to start: {}", instance.name, error);
+        }
+        self.workspace_instances
+            .read()
+            .await
+            .get(&(mcp_id, workspace.id))
+            .and_then(|running| Some((running.process.clone()?, running.state.clone())))
+            .ok_or_else(|| anyhow::anyhow!("MCP {} is not running", instance.name))
+    }
+
+    /// Call a tool of a workspace-scoped stdio MCP inside `workspace`,
+    /// starting its instance there if it is not running. The call is subject
+    /// to the MCP's limits and counted in the telemetry of `mission_id`.
+    pub async fn call_tool_in_workspace(
+        &self,
+        mcp_id: Uuid,
+        workspace: &Workspace,
+        tool_name: &str,
+        arguments: serde_json::Value,
+        mission_id: Option<Uuid>,
+    ) -> anyhow::Result<String> {
+        if self.disabled_tools.read().await.contains(tool_name) {
+            anyhow::bail!("Tool {} is disabled", tool_name);
+        }
+
+        let (process, _) = self.running_instance(mcp_id, workspace).await?;
+        let config = self
+            .get(mcp_id)
+            .await
+            .ok_or(McpRequestError::NotFound)?
+            .config;
+
+        // Held until the call finished
+        let _permit = match self
+            .limiter
+            .acquire(mcp_id, &config.name, tool_name, &config.limits)
+        {
+            Ok(permit) => permit,
             Err(e) => {
-                // Increment error counter
-                let mut states = self.states.write().await;
-                if let Some(state) = states.get_mut(&mcp_id) {
+                self.telemetry
+                    .record_rate_limited(mcp_id, tool_name, mission_id);
+                anyhow::bail!(e);
+            }
+        };
+
+        let params = serde_json::json!({
+            "name": tool_name,
+            "arguments": arguments
+        });
+        let started = std::time::Instant::now();
+        let result = match self
+            .send_jsonrpc_stdio(&process, "tools/call", Some(params), None)
+            .await
+        {
+            Ok(result) => serde_json::from_value::<McpCallToolResponse>(result)
+                .map_err(anyhow::Error::from)
+                .and_then(|response| Self::tool_output(&response)),
+            Err(e) => Err(anyhow::anyhow!("Tool call failed: {}", e)),
+        };
+        self.telemetry.record(
+            mcp_id,
+            tool_name,
+            mission_id,
+            started.elapsed(),
+            result.as_ref().err().map(|e| e.to_string()).as_deref(),
+        );
+        result
+    }
+
+    /// Send a request over the stdio process or remote session of an MCP.
+    async fn send_request(
+        &self,
+        mcp_id: Uuid,
+        method: &str,
+        params: Option<serde_json::Value>,
+        progress: Option<&McpProgressCallback>,
+    ) -> anyhow::Result<serde_json::Value> {
+        let client = self.remote_clients.read().await.get(&mcp_id).cloned();
+        if let Some(client) = client {
+            return client.request(method, params, progress).await;
+        }
+        let process = self
+            .stdio_processes
+            .read()
+            .await
+            .get(&mcp_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No connection for MCP {}", mcp_id))?;
+        self.send_jsonrpc_stdio(&process, method, params, progress)
+            .await
+    }
+
+    /// Get the state of an MCP, failing unless it is enabled and connected.
+    async fn ensure_connected(&self, mcp_id: Uuid) -> anyhow::Result<McpServerState> {
+        let state = self.get(mcp_id).await.ok_or(McpRequestError::NotFound)?;
+
+        if !state.config.enabled {
+            return Err(McpRequestError::Disabled(state.config.name).into());
+        }
+
+        if Self::runs_in_workspace(&state.config) {
+            return Err(McpRequestError::WorkspaceScoped(state.config.name).into());
+        }
+
+        if state.status != McpStatus::Connected {
+            return Err(McpRequestError::NotConnected(state.config.name).into());
+        }
+
+        Ok(state)
+    }
+
+    /// Call a tool on an MCP server.
+    pub async fn call_tool(
+        &self,
+        mcp_id: Uuid,
+        tool_name: &str,
+        arguments: serde_json::Value,
+    ) -> anyhow::Result<String> {
+        self.call_tool_with_progress(mcp_id, tool_name, arguments, None)
+            .await
+    }
+
+    /// Call a tool on an MCP server, passing progress notifications sent
+    /// while it runs to `progress`.
+    pub async fn call_tool_with_progress(
+        &self,
+        mcp_id: Uuid,
+        tool_name: &str,
+        arguments: serde_json::Value,
+        progress: Option<McpProgressCallback>,
+    ) -> anyhow::Result<String> {
+        self.call_tool_as(mcp_id, tool_name, arguments, progress, None)
+            .await
+    }
+
+    /// Call a tool on an MCP server on behalf of a mission, whose telemetry
+    /// it is counted in.
+    pub async fn call_tool_for_mission(
+        &self,
+        mission_id: Uuid,
+        mcp_id: Uuid,
+        tool_name: &str,
+        arguments: serde_json::Value,
+    ) -> anyhow::Result<String> {
+        self.call_tool_as(mcp_id, tool_name, arguments, None, Some(mission_id))
+            .await
+    }
+
+    async fn call_tool_as(
+        &self,
+        mcp_id: Uuid,
+        tool_name: &str,
+        arguments: serde_json::Value,
+        progress: Option<McpProgressCallback>,
+        mission_id: Option<Uuid>,
+    ) -> anyhow::Result<String> {
+        // Check if tool is disabled
+        if self.disabled_tools.read().await.contains(tool_name) {
+            anyhow::bail!("Tool {} is disabled", tool_name);
+        }
+
+        let state = self.ensure_connected(mcp_id).await?;
+
+        // Held until the call finished
+        let _permit =
+            match self
+                .limiter
+                .acquire(mcp_id, &state.config.name, tool_name, &state.config.limits)
+            {
+                Ok(permit) => permit,
+                Err(e) => {
+                    self.telemetry
+                        .record_rate_limited(mcp_id, tool_name, mission_id);
+                    anyhow::bail!(e);
+                }
+            };
+
+        let params = serde_json::json!({
+            "name": tool_name,
+            "arguments": arguments
+        });
+
+        let started = std::time::Instant::now();
+        let result = match self
+            .send_request(mcp_id, "tools/call", Some(params), progress.as_ref())
+            .await
+        {
+            Ok(result) => serde_json::from_value::<McpCallToolResponse>(result)
+                .map_err(anyhow::Error::from)
+                .and_then(|response| Self::tool_output(&response)),
+            Err(e) => Err(anyhow::anyhow!("Tool call failed: {}", e)),
+        };
+        self.telemetry.record(
+            mcp_id,
+            tool_name,
+            mission_id,
+            started.elapsed(),
+            result.as_ref().err().map(|e| e.to_string()).as_deref(),
+        );
+
+        // Increment counters
+        {
+            let mut states = self.states.write().await;
+            if let Some(state) = states.get_mut(&mcp_id) {
+                if result.is_ok() {
+                    state.tool_calls += 1;
+                } else {
                     state.tool_errors += 1;
                 }
-                anyhow::bail!("Tool call failed: {}", e);
             }
         }
+
+        result
+    }
+
+    /// Set the rate limits and concurrency caps of an MCP's tool calls.
+    pub async fn set_limits(&self, id: Uuid, limits: McpLimits) -> anyhow::Result<McpServerState> {
+        if self.get(id).await.is_none() {
+            return Err(McpRequestError::NotFound.into());
+        }
+        let config = self
+            .config_store
+            .update(id, |c| c.limits = limits.clone())
+            .await?;
+        if let Some(state) = self.states.write().await.get_mut(&id) {
+            state.config = config;
+        }
+        self.warn_unenforced_limits().await;
+        self.get(id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("MCP not found"))
+    }
+
+    /// Warn about call limits that harnesses bypass: without the gateway
+    /// they call MCPs directly, so only calls made by sandboxed.sh itself are
+    /// limited and recorded in the telemetry.
+    pub async fn warn_unenforced_limits(&self) {
+        if self.gateway().is_some() {
+            return;
+        }
+        for state in self.states.read().await.values() {
+            if !state.config.limits.is_empty() {
+                tracing::warn!(
+                    mcp = %state.config.name,
+                    "MCP call limits are not enforced on harness tool calls without the MCP gateway (SANDBOXED_SH_MCP_GATEWAY)"
+                );
+            }
+        }
+    }
+
+    /// Per-tool call statistics matching `query`.
+    pub fn tool_stats(&self, query: &McpStatsQuery) -> Vec<McpToolStats> {
+        self.telemetry.stats(query)
+    }
+
+    /// Use `redactor` to mask secrets in the errors kept in the telemetry.
+    pub fn set_redactor(&self, redactor: Arc<crate::secrets::SecretRedactor>) {
+        self.telemetry.set_redactor(redactor);
+    }
+
+    /// Use `secrets` for the OAuth credentials of remote MCPs.
+    pub fn set_secrets(&self, secrets: Arc<SecretsStore>) {
+        let _ = self.secrets.set(secrets);
+    }
+
+    /// The secrets store, once the server has set it.
+    pub fn secrets(&self) -> Option<&SecretsStore> {
+        self.secrets.get().map(|s| s.as_ref())
+    }
+
+    fn secrets_store(&self) -> anyhow::Result<&Arc<SecretsStore>> {
+        self.secrets
+            .get()
+            .ok_or_else(|| anyhow::anyhow!("Secrets store is not available"))
+    }
+
+    /// Start authorizing a remote MCP with OAuth; returns the URL the user
+    /// must open. Without a `client_id`, a client is registered dynamically.
+    pub async fn begin_oauth(
+        &self,
+        id: Uuid,
+        redirect_uri: Option<String>,
+        client_id: Option<String>,
+    ) -> anyhow::Result<String> {
+        let state = self
+            .get(id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("MCP not found"))?;
+        let endpoint = match &state.config.transport {
+            McpTransport::Http { endpoint, .. } | McpTransport::Sse { endpoint, .. } => {
+                endpoint.clone()
+            }
+            McpTransport::Stdio { .. } => {
+                anyhow::bail!("Only HTTP and SSE MCPs can be authorized with OAuth")
+            }
+        };
+        // Fail before the user authorizes if the tokens could not be stored
+        self.secrets_store()?;
+
+        let redirect_uri = redirect_uri.unwrap_or_else(|| oauth::DEFAULT_REDIRECT_URI.to_string());
+        let (url, pending) =
+            oauth::begin(&self.http_client, &endpoint, &redirect_uri, client_id).await?;
+        self.pending_oauth.lock().await.insert(id, pending);
+        Ok(url)
+    }
+
+    /// Complete a pending OAuth authorization with the code (or the pasted
+    /// redirect URL), store the tokens and reconnect the MCP.
+    pub async fn complete_oauth(&self, id: Uuid, input: &str) -> anyhow::Result<McpServerState> {
+        let pending = self.pending_oauth.lock().await.remove(&id).ok_or_else(|| {
+            anyhow::anyhow!("No pending OAuth authorization. Please start the OAuth flow again.")
+        })?;
+        if pending.created_at.elapsed() > oauth::PENDING_TIMEOUT {
+            anyhow::bail!("OAuth authorization expired. Please start again.");
+        }
+
+        let credentials = oauth::complete(&self.http_client, &pending, input).await?;
+        {
+            let slot = self.oauth_slot(id).await;
+            let mut cached = slot.lock().await;
+            self.store_oauth(id, &credentials).await?;
+            *cached = Some(credentials);
+        }
+
+        let status = McpOAuthStatus {
+            authorization_server: pending.server.issuer.clone(),
+            scope: pending.server.scope.clone(),
+            authorized_at: chrono::Utc::now(),
+        };
+        let config = self
+            .config_store
+            .update(id, |c| c.oauth = Some(status.clone()))
+            .await?;
+        if let Some(state) = self.states.write().await.get_mut(&id) {
+            state.config = config;
+        }
+        self.refresh(id).await
+    }
+
+    /// Remove the OAuth authorization of an MCP and disconnect it.
+    pub async fn revoke_oauth(&self, id: Uuid) -> anyhow::Result<McpServerState> {
+        self.disconnect(id).await;
+        self.forget_oauth(id).await;
+        let config = self.config_store.update(id, |c| c.oauth = None).await?;
+        if let Some(state) = self.states.write().await.get_mut(&id) {
+            state.config = config;
+            state.status = if state.config.enabled {
+                McpStatus::Disconnected
+            } else {
+                McpStatus::Disabled
+            };
+            state.error = None;
+        }
+        self.get(id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("MCP not found"))
+    }
+
+    /// Drop the pending authorization and credentials of an MCP.
+    async fn forget_oauth(&self, id: Uuid) {
+        self.pending_oauth.lock().await.remove(&id);
+        self.oauth_credentials.lock().await.remove(&id);
+        if let Some(secrets) = self.secrets.get() {
+            // Fails when the MCP was never authorized
+            let _ = secrets
+                .delete_secret(oauth::TOKENS_REGISTRY, &OAuthCredentials::secret_key(id))
+                .await;
+        }
+    }
+
+    /// Save the OAuth credentials of an MCP in the secrets store.
+    async fn store_oauth(&self, id: Uuid, credentials: &OAuthCredentials) -> anyhow::Result<()> {
+        let metadata = SecretMetadata {
+            secret_type: Some(SecretType::OAuthAccessToken),
+            expires_at: credentials.expires_at,
+            labels: HashMap::from([("mcp".to_string(), id.to_string())]),
+        };
+        self.secrets_store()?
+            .set_secret(
+                oauth::TOKENS_REGISTRY,
+                &OAuthCredentials::secret_key(id),
+                &serde_json::to_string(credentials)?,
+                Some(metadata),
+            )
+            .await
+    }
+
+    async fn oauth_slot(&self, id: Uuid) -> OAuthSlot {
+        Arc::clone(self.oauth_credentials.lock().await.entry(id).or_default())
+    }
+
+    /// Access token of an OAuth-authorized MCP, refreshed when it expires
+    /// soon.
+    async fn oauth_token(&self, id: Uuid) -> anyhow::Result<String> {
+        // Only this MCP's slot is held across the refresh
+        let slot = self.oauth_slot(id).await;
+        let mut cached = slot.lock().await;
+        let mut credentials = match cached.as_ref() {
+            Some(credentials) => credentials.clone(),
+            None => {
+                let stored = self
+                    .secrets_store()?
+                    .get_secret(oauth::TOKENS_REGISTRY, &OAuthCredentials::secret_key(id))
+                    .await?;
+                serde_json::from_str(&stored)?
+            }
+        };
+        if credentials.needs_refresh() {
+            credentials = oauth::refresh(&self.http_client, &credentials).await?;
+            self.store_oauth(id, &credentials).await?;
+        }
+        let token = credentials.access_token.clone();
+        *cached = Some(credentials);
+        Ok(token)
+    }
+
+    /// Send the bearer token of an OAuth-authorized MCP in its headers.
+    async fn apply_oauth(&self, config: &mut McpServerConfig) -> anyhow::Result<()> {
+        if config.oauth.is_none() {
+            return Ok(());
+        }
+        let token = self.oauth_token(config.id).await?;
+        if let McpTransport::Http { headers, .. } | McpTransport::Sse { headers, .. } =
+            &mut config.transport
+        {
+            headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+        }
+        Ok(())
+    }
+
+    /// Refresh the access tokens of connected OAuth MCPs that expire soon
+    /// and send the new token on their live sessions.
+    async fn refresh_expiring_oauth(&self) {
+        let slots: Vec<(Uuid, OAuthSlot)> = self
+            .oauth_credentials
+            .lock()
+            .await
+            .iter()
+            .map(|(id, slot)| (*id, Arc::clone(slot)))
+            .collect();
+        for (id, slot) in slots {
+            // Skip slots being refreshed right now
+            let expiring = slot
+                .try_lock()
+                .is_ok_and(|cached| cached.as_ref().is_some_and(|c| c.needs_refresh()));
+            if !expiring {
+                continue;
+            }
+            let client = self.remote_clients.read().await.get(&id).cloned();
+            let Some(client) = client else {
+                continue;
+            };
+            match self.oauth_token(id).await {
+                Ok(token) => client.set_header("Authorization", format!("Bearer {}", token)),
+                Err(e) => {
+                    tracing::warn!(mcp = %id, error = %e, "Failed to refresh OAuth token of MCP")
+                }
+            }
+        }
+    }
+
+    /// Connect the enabled OAuth MCPs that are not connected, e.g. once the
+    /// secrets store holding their tokens was unlocked.
+    pub async fn refresh_oauth(&self) {
+        let ids: Vec<Uuid> = self
+            .states
+            .read()
+            .await
+            .values()
+            .filter(|s| {
+                s.config.enabled && s.config.oauth.is_some() && s.status != McpStatus::Connected
+            })
+            .map(|s| s.config.id)
+            .collect();
+        let futures: Vec<_> = ids.iter().map(|id| self.refresh(*id)).collect();
+        futures::future::join_all(futures).await;
+    }
+
+    /// Text of a `tools/call` response, or the error it reports.
+    fn tool_output(response: &McpCallToolResponse) -> anyhow::Result<String> {
+        // Combine text content
+        let text = response
+            .content
+            .iter()
+            .filter_map(|c| c.text.as_deref())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if response.is_error {
+            anyhow::bail!("Tool error: {}", text);
+        }
+        Ok(text)
+    }
+
+    /// Read a resource from an MCP server.
+    pub async fn read_resource(
+        &self,
+        mcp_id: Uuid,
+        uri: &str,
+    ) -> anyhow::Result<McpReadResourceResponse> {
+        self.ensure_connected(mcp_id).await?;
+        let result = self
+            .send_request(
+                mcp_id,
+                "resources/read",
+                Some(serde_json::json!({ "uri": uri })),
+                None,
+            )
+            .await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Subscribe to changes of a resource.
+    ///
+    /// Updates arrive as `notifications/resources/updated` through
+    /// [`Self::subscribe_notifications`], so stdio servers are refused.
+    pub async fn subscribe_resource(&self, mcp_id: Uuid, uri: &str) -> anyhow::Result<()> {
+        let state = self.ensure_connected(mcp_id).await?;
+        if matches!(state.config.transport, McpTransport::Stdio { .. }) {
+            return Err(McpRequestError::UpdatesUnsupported(state.config.name).into());
+        }
+        self.send_request(
+            mcp_id,
+            "resources/subscribe",
+            Some(serde_json::json!({ "uri": uri })),
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Get a prompt from an MCP server, filled in with `arguments`.
+    pub async fn get_prompt(
+        &self,
+        mcp_id: Uuid,
+        name: &str,
+        arguments: HashMap<String, String>,
+    ) -> anyhow::Result<McpPromptResult> {
+        self.ensure_connected(mcp_id).await?;
+        let result = self
+            .send_request(
+                mcp_id,
+                "prompts/get",
+                Some(serde_json::json!({ "name": name, "arguments": arguments })),
+                None,
+            )
+            .await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Get a prompt as a mission in `workspace` sees it: workspace-scoped
+    /// stdio MCPs answer from their instance in the workspace.
+    pub async fn get_prompt_in_workspace(
+        &self,
+        mcp_id: Uuid,
+        workspace: &Workspace,
+        name: &str,
+        arguments: HashMap<String, String>,
+    ) -> anyhow::Result<McpPromptResult> {
+        let config = self
+            .get(mcp_id)
+            .await
+            .ok_or(McpRequestError::NotFound)?
+            .config;
+        if !Self::runs_in_workspace(&config) {
+            return self.get_prompt(mcp_id, name, arguments).await;
+        }
+        let (process, _) = self.running_instance(mcp_id, workspace).await?;
+        let result = self
+            .send_jsonrpc_stdio(
+                &process,
+                "prompts/get",
+                Some(serde_json::json!({ "name": name, "arguments": arguments })),
+                None,
+            )
+            .await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Find the prompt invoked by a slash command name of the form
+    /// `mcp__<server>__<prompt>`, where `<server>` is the sanitized MCP name.
+    ///
+    /// Only MCPs a mission in `workspace` can use under `policies` are
+    /// searched; a workspace-scoped stdio MCP is started in the workspace to
+    /// list its prompts.
+    pub async fn find_prompt_command(
+        &self,
+        command: &str,
+        workspace: &Workspace,
+        policies: &McpPolicies,
+    ) -> Option<(Uuid, McpPromptDescriptor)> {
+        let (prefix, prompt) = command.strip_prefix("mcp__")?.split_once("__")?;
+        // Only MCPs the mission's harness config exposes
+        let configs = policies.apply(
+            crate::workspace::filter_mcp_configs_for_workspace(
+                self.list_configs().await,
+                &workspace.mcps,
+            ),
+            self.gateway().is_some(),
+        );
+        for config in configs {
+            if sanitize_mcp_prefix(&config.name) != prefix {
+                continue;
+            }
+            let prompts = if Self::runs_in_workspace(&config) {
+                match self.running_instance(config.id, workspace).await {
+                    Ok((_, instance)) => instance.prompts,
+                    Err(e) => {
+                        tracing::warn!(mcp = %config.name, error = %e, "Workspace MCP unavailable for prompt");
+                        continue;
+                    }
+                }
+            } else {
+                match self.get(config.id).await {
+                    Some(state) if state.status == McpStatus::Connected => state.config.prompts,
+                    _ => continue,
+                }
+            };
+            if let Some(found) = prompts.into_iter().find(|p| p.name == prompt) {
+                return Some((config.id, found));
+            }
+        }
+        None
+    }
+
+    /// Serve the per-mission MCP gateway (see [`super::gateway`]); harness
+    /// configs rendered afterwards route global MCPs through it.
+    pub fn enable_gateway(&self, settings: GatewaySettings) {
+        let _ = self.gateway.set(settings);
+    }
+
+    /// Gateway settings, if the gateway is enabled.
+    pub fn gateway(&self) -> Option<&GatewaySettings> {
+        self.gateway.get()
+    }
+
+    /// Gateway tokens of running missions.
+    pub fn gateway_tokens(&self) -> &GatewayTokens {
+        &self.gateway_tokens
     }
 
     /// List all tools from all connected MCPs.
@@ -1114,29 +2389,28 @@ impl McpRegistry {
     /// Tool names are prefixed with the MCP server name to avoid conflicts
     /// with built-in tools (e.g., `filesystem_read_file` instead of `read_file`).
     pub async fn list_tools(&self) -> Vec<McpTool> {
+        self.prefixed_tools()
+            .await
+            .into_iter()
+            .map(|(tool, _)| tool)
+            .collect()
+    }
+
+    /// Tools of all connected MCPs, each paired with its unprefixed name.
+    pub(super) async fn prefixed_tools(&self) -> Vec<(McpTool, String)> {
         let states = self.states.read().await;
         let disabled = self.disabled_tools.read().await;
 
         let mut tools = Vec::new();
         for state in states.values() {
             if state.config.enabled && state.status == McpStatus::Connected {
-                // Derive prefix from MCP server name (sanitized for function names)
-                let prefix = sanitize_mcp_prefix(&state.config.name);
-
                 for descriptor in &state.config.tool_descriptors {
-                    // Prefix tool name with MCP server name to avoid conflicts
-                    let prefixed_name = format!("{}_{}", prefix, descriptor.name);
-                    let prefixed_description =
-                        format!("[{}] {}", state.config.name, descriptor.description);
-
-                    tools.push(McpTool {
-                        name: prefixed_name.clone(),
-                        description: prefixed_description,
-                        parameters_schema: descriptor.input_schema.clone(),
-                        mcp_id: state.config.id,
-                        enabled: !disabled.contains(&descriptor.name)
-                            && !disabled.contains(&prefixed_name),
-                    });
+                    tools.push(prefixed_tool(
+                        state.config.id,
+                        &state.config.name,
+                        descriptor,
+                        &disabled,
+                    ));
                 }
             }
         }
@@ -1205,3 +2479,300 @@ impl McpRegistry {
         }
     }
 }
+
+/// Tool of the MCP `mcp_name`, with its name prefixed by the MCP name to
+/// avoid conflicts with built-in tools, paired with its unprefixed name.
+fn prefixed_tool(
+    mcp_id: Uuid,
+    mcp_name: &str,
+    descriptor: &McpToolDescriptor,
+    disabled: &std::collections::HashSet<String>,
+) -> (McpTool, String) {
+    // Derive prefix from MCP server name (sanitized for function names)
+    let prefixed_name = format!("{}_{}", sanitize_mcp_prefix(mcp_name), descriptor.name);
+    let tool = McpTool {
+        name: prefixed_name.clone(),
+        description: format!("[{}] {}", mcp_name, descriptor.description),
+        parameters_schema: descriptor.input_schema.clone(),
+        mcp_id,
+        enabled: !disabled.contains(&descriptor.name) && !disabled.contains(&prefixed_name),
+    };
+    (tool, descriptor.name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Answers every request with a result that works for `initialize`,
+    /// `tools/list` and `ping`, and crashes on `tools/call`.
+    const FAKE_SERVER: &str = r#"
+while IFS= read -r line; do
+  case "$line" in *'"method":"tools/call"'*) echo "crashing" >&2; exit 1;; esac
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9][0-9]*\).*/\1/p')
+  [ -n "$id" ] && printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2025-03-26","capabilities":{},"tools":[]}}\n' "$id"
+done
+"#;
+
+    async fn add_stdio(registry: &McpRegistry, script: &str, scope: McpScope) -> Uuid {
+        registry
+            .add(AddMcpRequest {
+                name: "fake".to_string(),
+                transport: McpTransport::Stdio {
+                    command: "sh".to_string(),
+                    args: vec!["-c".to_string(), script.to_string()],
+                    env: HashMap::new(),
+                },
+                description: None,
+                scope: Some(scope),
+                default_enabled: None,
+            })
+            .await
+            .unwrap()
+            .config
+            .id
+    }
+
+    /// Run the supervisor until it attempts a restart, skipping the backoff.
+    async fn supervise_until_restart(registry: &McpRegistry, id: Uuid) {
+        registry.supervise_once().await;
+        if let Some(entry) = registry.supervision.lock().await.get_mut(&id) {
+            entry.restart_at = Some(tokio::time::Instant::now());
+        }
+        registry.supervise_once().await;
+    }
+
+    #[tokio::test]
+    async fn supervisor_restarts_crashed_process() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = McpRegistry::new(tmp.path()).await;
+        let id = add_stdio(&registry, FAKE_SERVER, McpScope::Global).await;
+
+        let state = registry.refresh(id).await.unwrap();
+        assert_eq!(state.status, McpStatus::Connected, "{:?}", state.error);
+        assert!(state.started_at.is_some());
+
+        assert!(registry.call_tool(id, "boom", json!({})).await.is_err());
+        // Closing stdout comes just before the exit is observable
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        registry.supervise_once().await;
+        let state = registry.get(id).await.unwrap();
+        assert_eq!(state.status, McpStatus::Error);
+        assert!(state.error.unwrap().contains("Process exited"));
+
+        supervise_until_restart(&registry, id).await;
+        let state = registry.get(id).await.unwrap();
+        assert_eq!(state.status, McpStatus::Connected, "{:?}", state.error);
+        assert_eq!(state.restarts, 1);
+        assert_eq!(registry.stderr_lines(id), vec!["crashing"]);
+
+        // The restarted process answers pings
+        registry.supervise_once().await;
+        assert_eq!(registry.get(id).await.unwrap().status, McpStatus::Connected);
+    }
+
+    #[tokio::test]
+    async fn supervisor_gives_up_on_crash_loop() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = McpRegistry::new(tmp.path()).await;
+        let id = add_stdio(&registry, "echo broken >&2; exit 1", McpScope::Global).await;
+
+        let state = registry.refresh(id).await.unwrap();
+        assert_eq!(state.status, McpStatus::Error);
+
+        for _ in 0..=supervisor::MAX_RESTARTS {
+            supervise_until_restart(&registry, id).await;
+        }
+        let state = registry.get(id).await.unwrap();
+        assert_eq!(state.restarts, supervisor::MAX_RESTARTS);
+        assert!(state.error.unwrap().starts_with("Crash loop"));
+        assert!(registry.stderr_lines(id).contains(&"broken".to_string()));
+
+        // No more restarts until a manual refresh
+        supervise_until_restart(&registry, id).await;
+        assert_eq!(
+            registry.get(id).await.unwrap().restarts,
+            supervisor::MAX_RESTARTS
+        );
+        registry.refresh(id).await.unwrap();
+        assert!(!registry.supervision.lock().await.contains_key(&id));
+    }
+
+    /// Run the supervisor once with a ping due.
+    async fn supervise_with_ping(registry: &McpRegistry, id: Uuid) {
+        if let Some(entry) = registry.supervision.lock().await.get_mut(&id) {
+            entry.last_ping = None;
+        }
+        registry.supervise_once().await;
+    }
+
+    #[tokio::test]
+    async fn supervisor_restarts_busy_process_that_ignores_pings() {
+        // Answers like FAKE_SERVER but stops reading on `tools/call`
+        let script = FAKE_SERVER.replace(r#"echo "crashing" >&2; exit 1;;"#, "exec sleep 600;;");
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = Arc::new(McpRegistry::new(tmp.path()).await);
+        let id = add_stdio(&registry, &script, McpScope::Global).await;
+        let state = registry.refresh(id).await.unwrap();
+        assert_eq!(state.status, McpStatus::Connected, "{:?}", state.error);
+
+        let call = tokio::spawn({
+            let registry = Arc::clone(&registry);
+            async move { registry.call_tool(id, "stuck", json!({})).await }
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // The ping goes out through the request in flight
+        supervise_with_ping(&registry, id).await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(registry.get(id).await.unwrap().status, McpStatus::Connected);
+        let process = registry.stdio_processes.read().await[&id].clone();
+        let sent = process.busy_ping.lock().unwrap().expect("ping sent");
+
+        // Unanswered for longer than the ping timeout
+        *process.busy_ping.lock().unwrap() = Some(sent - supervisor::PING_TIMEOUT);
+        registry.supervise_once().await;
+        let state = registry.get(id).await.unwrap();
+        assert_eq!(state.status, McpStatus::Error);
+        assert!(state.error.unwrap().contains("Ping timed out"));
+        let result = tokio::time::timeout(Duration::from_secs(5), call).await;
+        assert!(result.unwrap().unwrap().is_err());
+
+        supervise_until_restart(&registry, id).await;
+        let state = registry.get(id).await.unwrap();
+        assert_eq!(state.status, McpStatus::Connected, "{:?}", state.error);
+        assert_eq!(state.restarts, 1);
+    }
+
+    #[tokio::test]
+    async fn supervisor_keeps_slow_process_that_answers_pings() {
+        // Answers `tools/call` after a second, and pings meanwhile
+        let script = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9][0-9]*\).*/\1/p')
+  case "$line" in *'"method":"tools/call"'*)
+    (sleep 1; printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"done"}]}}\n' "$id") &
+    continue;;
+  esac
+  [ -n "$id" ] && printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2025-03-26","capabilities":{},"tools":[]}}\n' "$id"
+done
+"#;
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = Arc::new(McpRegistry::new(tmp.path()).await);
+        let id = add_stdio(&registry, script, McpScope::Global).await;
+        let state = registry.refresh(id).await.unwrap();
+        assert_eq!(state.status, McpStatus::Connected, "{:?}", state.error);
+
+        let call = tokio::spawn({
+            let registry = Arc::clone(&registry);
+            async move { registry.call_tool(id, "slow", json!({})).await }
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        supervise_with_ping(&registry, id).await;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let process = registry.stdio_processes.read().await[&id].clone();
+        assert!(process.busy_ping.lock().unwrap().is_none(), "ping answered");
+        registry.supervise_once().await;
+        assert_eq!(registry.get(id).await.unwrap().status, McpStatus::Connected);
+
+        assert_eq!(call.await.unwrap().unwrap(), "done");
+    }
+
+    #[tokio::test]
+    async fn stdio_mcps_refuse_resource_subscriptions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = McpRegistry::new(tmp.path()).await;
+        let id = add_stdio(&registry, FAKE_SERVER, McpScope::Global).await;
+
+        let err = registry
+            .subscribe_resource(id, "file:///a")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<McpRequestError>(),
+            Some(McpRequestError::NotConnected(_))
+        ));
+        registry.refresh(id).await.unwrap();
+        let err = registry
+            .subscribe_resource(id, "file:///a")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<McpRequestError>(),
+            Some(McpRequestError::UpdatesUnsupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn workspace_instances_run_per_workspace() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = McpRegistry::new(tmp.path()).await;
+        let id = add_stdio(&registry, FAKE_SERVER, McpScope::Workspace).await;
+        let mut workspace = Workspace::default_host(tmp.path().to_path_buf());
+        workspace.mcps = vec!["fake".to_string()];
+
+        let instances = registry.workspace_instances(&workspace).await;
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].status, McpStatus::Disconnected);
+
+        // A tool call starts the instance, which then crashes
+        assert!(registry
+            .call_tool_in_workspace(id, &workspace, "boom", json!({}), None)
+            .await
+            .is_err());
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        registry.supervise_once().await;
+        let instance = registry.workspace_instances(&workspace).await.remove(0);
+        assert_eq!(instance.status, McpStatus::Error);
+        assert!(instance.error.unwrap().contains("Process exited"));
+        assert_eq!(
+            registry.workspace_stderr_lines(id, workspace.id).await,
+            vec!["crashing"]
+        );
+        // The registry-level MCP was never started, and is not started on
+        // the host by a refresh or the supervisor either
+        registry.refresh(id).await.unwrap();
+        registry.supervise_once().await;
+        assert_eq!(
+            registry.get(id).await.unwrap().status,
+            McpStatus::Disconnected
+        );
+        let err = registry.call_tool(id, "boom", json!({})).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<McpRequestError>(),
+            Some(McpRequestError::WorkspaceScoped(_))
+        ));
+
+        let instance = registry.start_in_workspace(id, &workspace).await.unwrap();
+        assert_eq!(
+            instance.status,
+            McpStatus::Connected,
+            "{:?}",
+            instance.error
+        );
+        registry.stop_workspace(workspace.id).await;
+        let instance = registry.workspace_instances(&workspace).await.remove(0);
+        assert_eq!(instance.status, McpStatus::Disconnected);
+
+        // Only workspace-scoped MCPs run inside workspaces
+        registry.start_in_workspace(id, &workspace).await.unwrap();
+        registry
+            .update(
+                id,
+                UpdateMcpRequest {
+                    name: None,
+                    transport: None,
+                    description: None,
+                    enabled: None,
+                    scope: Some(McpScope::Global),
+                    default_enabled: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(registry.workspace_instances.read().await.is_empty());
+        assert!(registry.start_in_workspace(id, &workspace).await.is_err());
+    }
+}
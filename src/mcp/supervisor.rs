@@ -0,0 +1,210 @@
+//! Supervision of stdio MCP processes.
+//!
+//! The registry's supervisor loop checks each stdio MCP every
+//! [`SUPERVISOR_TICK`]: exited processes, servers that stop answering
+//! `ping` (sent through the request in flight while a process is busy),
+//! and (on Linux) processes whose resident memory grew past
+//! [`MAX_RESIDENT_MEMORY`] are restarted with exponential backoff, and an
+//! MCP that keeps failing right after restarts is left in the error state
+//! until it is refreshed manually. Each process's stderr is kept in a
+//! [`StderrLog`].
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::ChildStderr;
+use tokio::time::Instant;
+
+/// How often the supervisor checks stdio MCPs.
+pub(super) const SUPERVISOR_TICK: Duration = Duration::from_secs(5);
+/// How often a running process is pinged.
+pub(super) const PING_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a ping may take before the process counts as stuck.
+pub(super) const PING_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delay before the first restart; doubled for each consecutive failure.
+const RESTART_BASE_DELAY: Duration = Duration::from_secs(1);
+const RESTART_MAX_DELAY: Duration = Duration::from_secs(300);
+/// Consecutive restarts after which the supervisor gives up.
+pub(super) const MAX_RESTARTS: u32 = 5;
+/// How long a restarted process must stay healthy to reset the failure count.
+const STABLE_AFTER: Duration = Duration::from_secs(120);
+
+/// Resident memory above which a process counts as leaking and is restarted.
+pub(super) const MAX_RESIDENT_MEMORY: u64 = 2 << 30;
+
+/// Number of stderr lines kept per MCP.
+const STDERR_LINES: usize = 200;
+
+/// Ring buffer of the most recent stderr lines of a stdio MCP.
+///
+/// Shared across restarts so the output of a crashed process stays visible.
+#[derive(Debug, Default)]
+pub(super) struct StderrLog {
+    lines: StdMutex<VecDeque<String>>,
+}
+
+impl StderrLog {
+    pub(super) fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == STDERR_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    pub(super) fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Copy a process's stderr into `log` until the stream closes.
+pub(super) fn capture_stderr(stderr: ChildStderr, log: Arc<StderrLog>) {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            log.push(line);
+        }
+    });
+}
+
+/// Resident memory of a process in bytes, where `/proc` provides it.
+pub(super) fn resident_memory(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+/// Result of checking a stdio MCP.
+#[derive(Debug)]
+pub(super) enum Health {
+    /// Running and answered the last ping (or was not due for one)
+    Healthy,
+    /// In the middle of a request and answering pings; checked again on the
+    /// next tick
+    Busy,
+    /// Needs a restart, with the reason
+    Failed(String),
+}
+
+/// Restart bookkeeping of one stdio MCP.
+#[derive(Debug, Default)]
+pub(super) struct Supervision {
+    /// Consecutive restarts without a stable period in between
+    pub(super) failures: u32,
+    /// When the pending restart is due
+    pub(super) restart_at: Option<Instant>,
+    /// Set once the crash-loop limit is reached
+    pub(super) gave_up: bool,
+    pub(super) last_ping: Option<Instant>,
+}
+
+impl Supervision {
+    /// Delay before the next restart.
+    fn backoff(&self) -> Duration {
+        RESTART_BASE_DELAY
+            .saturating_mul(1 << self.failures.min(16))
+            .min(RESTART_MAX_DELAY)
+    }
+
+    /// Record a failure; returns whether a restart is due now.
+    ///
+    /// The first call schedules the restart, later calls report it once the
+    /// backoff has elapsed.
+    pub(super) fn failed(&mut self, now: Instant) -> bool {
+        match self.restart_at {
+            None => {
+                self.restart_at = Some(now + self.backoff());
+                false
+            }
+            Some(at) => at <= now,
+        }
+    }
+
+    /// Record a restart attempt; returns false when the crash-loop limit is
+    /// reached instead.
+    pub(super) fn restarting(&mut self) -> bool {
+        self.restart_at = None;
+        self.last_ping = None;
+        if self.failures >= MAX_RESTARTS {
+            self.gave_up = true;
+            return false;
+        }
+        self.failures += 1;
+        true
+    }
+
+    /// Record a healthy check of a process running for `uptime`.
+    pub(super) fn healthy(&mut self, uptime: Duration) {
+        self.restart_at = None;
+        if uptime >= STABLE_AFTER {
+            self.failures = 0;
+        }
+    }
+
+    /// Whether a ping is due.
+    pub(super) fn ping_due(&self, now: Instant) -> bool {
+        self.last_ping
+            .is_none_or(|last| now.duration_since(last) >= PING_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stderr_log_keeps_latest_lines() {
+        let log = StderrLog::default();
+        for i in 0..STDERR_LINES + 3 {
+            log.push(i.to_string());
+        }
+        let lines = log.lines();
+        assert_eq!(lines.len(), STDERR_LINES);
+        assert_eq!(lines[0], "3");
+    }
+
+    #[test]
+    fn restarts_back_off_and_give_up() {
+        let mut supervision = Supervision::default();
+        let start = Instant::now();
+
+        // First failure only schedules the restart
+        assert!(!supervision.failed(start));
+        assert!(supervision.failed(start + Duration::from_secs(1)));
+        assert!(supervision.restarting());
+
+        // The delay doubles with each consecutive failure
+        assert!(!supervision.failed(start));
+        assert!(!supervision.failed(start + Duration::from_secs(1)));
+        assert!(supervision.failed(start + Duration::from_secs(2)));
+
+        // A short healthy period does not reset the count
+        supervision.healthy(Duration::from_secs(1));
+        assert_eq!(supervision.failures, 1);
+        supervision.healthy(STABLE_AFTER);
+        assert_eq!(supervision.failures, 0);
+
+        for _ in 0..MAX_RESTARTS {
+            assert!(supervision.restarting());
+        }
+        assert!(!supervision.restarting());
+        assert!(supervision.gave_up);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reads_resident_memory() {
+        let rss = resident_memory(std::process::id()).unwrap();
+        assert!(rss > 0 && rss < MAX_RESIDENT_MEMORY);
+        assert_eq!(resident_memory(u32::MAX), None);
+    }
+}
@@ -0,0 +1,475 @@
+//! Per-tool telemetry of MCP tool calls.
+//!
+//! Every call made through [`super::McpRegistry::call_tool`] is counted per
+//! MCP, tool and mission, with a latency histogram and the last error. The
+//! counters are kept in `.sandboxed-sh/mcp/telemetry.json`, written by the
+//! supervisor loop when they changed, so they survive restarts. Error texts
+//! are redacted before they are kept, and the counters of old missions are
+//! folded into the totals outside missions so the file stays bounded.
+//! Calls harnesses make directly (without the MCP gateway) are not seen.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex, OnceLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::secrets::SecretRedactor;
+
+/// Days after its last call that a mission's counters are folded into the
+/// totals outside missions.
+const MISSION_RETENTION_DAYS: i64 = 30;
+
+/// Most per-mission entries kept; the least recently called ones beyond it
+/// are folded into the totals outside missions.
+const MAX_MISSION_ENTRIES: usize = 5_000;
+
+/// Upper bounds (in milliseconds) of the latency histogram buckets; slower
+/// calls go into a final overflow bucket.
+const LATENCY_BUCKETS_MS: [u64; 14] = [
+    5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000, 300_000,
+];
+
+/// Call latencies in fixed buckets, so that histograms can be merged across
+/// missions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LatencyHistogram {
+    /// Call counts per bucket of [`LATENCY_BUCKETS_MS`], plus the overflow
+    buckets: Vec<u64>,
+    max_ms: u64,
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, ms: u64) {
+        self.buckets.resize(LATENCY_BUCKETS_MS.len() + 1, 0);
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    fn merge(&mut self, other: &LatencyHistogram) {
+        self.buckets.resize(LATENCY_BUCKETS_MS.len() + 1, 0);
+        for (count, other) in self.buckets.iter_mut().zip(&other.buckets) {
+            *count += other;
+        }
+        self.max_ms = self.max_ms.max(other.max_ms);
+    }
+
+    /// Upper bound of the bucket holding the `p`-th percentile (capped at
+    /// the slowest call).
+    fn percentile(&self, p: f64) -> Option<u64> {
+        let total: u64 = self.buckets.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((total as f64) * p).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let bound = LATENCY_BUCKETS_MS.get(i).copied().unwrap_or(self.max_ms);
+                return Some(bound.min(self.max_ms));
+            }
+        }
+        Some(self.max_ms)
+    }
+}
+
+/// Counters of one tool of one MCP within one mission (or outside missions).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ToolTelemetry {
+    calls: u64,
+    errors: u64,
+    /// Calls rejected by a rate limit or concurrency cap (not sent)
+    rate_limited: u64,
+    latency: LatencyHistogram,
+    last_error: Option<String>,
+    last_error_at: Option<DateTime<Utc>>,
+    last_called_at: Option<DateTime<Utc>>,
+}
+
+impl ToolTelemetry {
+    fn merge(&mut self, other: &ToolTelemetry) {
+        self.calls += other.calls;
+        self.errors += other.errors;
+        self.rate_limited += other.rate_limited;
+        self.latency.merge(&other.latency);
+        if other.last_error_at > self.last_error_at {
+            self.last_error = other.last_error.clone();
+            self.last_error_at = other.last_error_at;
+        }
+        self.last_called_at = self.last_called_at.max(other.last_called_at);
+    }
+}
+
+/// Key of the telemetry counters: MCP, tool and mission.
+type TelemetryKey = (Uuid, String, Option<Uuid>);
+
+/// One entry of the telemetry file.
+#[derive(Debug, Serialize, Deserialize)]
+struct TelemetryEntry {
+    mcp_id: Uuid,
+    tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mission_id: Option<Uuid>,
+    #[serde(flatten)]
+    telemetry: ToolTelemetry,
+}
+
+/// Filter and grouping of [`McpToolStats`].
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct McpStatsQuery {
+    /// Only calls of this MCP
+    pub mcp_id: Option<Uuid>,
+    /// Only calls made within this mission
+    pub mission_id: Option<Uuid>,
+    /// Report each mission separately instead of summing them up
+    #[serde(default)]
+    pub by_mission: bool,
+}
+
+/// Statistics of one MCP tool.
+#[derive(Debug, Clone, Serialize)]
+pub struct McpToolStats {
+    pub mcp_id: Uuid,
+    pub tool: String,
+    /// Set when grouped by mission; None for calls outside missions and
+    /// those of missions that aged out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mission_id: Option<Uuid>,
+    pub calls: u64,
+    pub errors: u64,
+    /// Share of calls that failed (0.0 - 1.0)
+    pub error_rate: f64,
+    pub rate_limited: u64,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub max_ms: u64,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub last_called_at: Option<DateTime<Utc>>,
+}
+
+/// Persistent store of the tool call telemetry.
+pub(super) struct TelemetryStore {
+    path: PathBuf,
+    entries: StdMutex<HashMap<TelemetryKey, ToolTelemetry>>,
+    /// Set when the entries changed since they were last written
+    dirty: AtomicBool,
+    /// Masks secrets in the recorded errors
+    redactor: OnceLock<Arc<SecretRedactor>>,
+}
+
+impl TelemetryStore {
+    /// Load the telemetry of the working directory, if any.
+    pub(super) async fn load(working_dir: &Path) -> Self {
+        let path = working_dir
+            .join(".sandboxed-sh")
+            .join("mcp")
+            .join("telemetry.json");
+        let entries: Vec<TelemetryEntry> = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    // Keep the unreadable file for inspection rather than
+                    // overwriting it on the next flush
+                    let corrupt = path.with_extension("json.corrupt");
+                    tracing::warn!(
+                        path = %path.display(),
+                        moved_to = %corrupt.display(),
+                        error = %e,
+                        "MCP telemetry is unreadable; starting afresh"
+                    );
+                    if let Err(e) = tokio::fs::rename(&path, &corrupt).await {
+                        tracing::warn!(error = %e, "Failed to move aside MCP telemetry");
+                    }
+                    Vec::new()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to read MCP telemetry");
+                Vec::new()
+            }
+        };
+        Self {
+            path,
+            entries: StdMutex::new(
+                entries
+                    .into_iter()
+                    .map(|e| ((e.mcp_id, e.tool, e.mission_id), e.telemetry))
+                    .collect(),
+            ),
+            dirty: AtomicBool::new(false),
+            redactor: OnceLock::new(),
+        }
+    }
+
+    /// Use `redactor` to mask secrets in the recorded errors.
+    pub(super) fn set_redactor(&self, redactor: Arc<SecretRedactor>) {
+        let _ = self.redactor.set(redactor);
+    }
+
+    fn redact(&self, text: &str) -> String {
+        match self.redactor.get() {
+            Some(redactor) => redactor.redact(text).into_owned(),
+            None => text.to_string(),
+        }
+    }
+
+    fn update(
+        &self,
+        mcp_id: Uuid,
+        tool: &str,
+        mission_id: Option<Uuid>,
+        f: impl FnOnce(&mut ToolTelemetry),
+    ) {
+        let mut entries = self.entries.lock().unwrap();
+        f(entries
+            .entry((mcp_id, tool.to_string(), mission_id))
+            .or_default());
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Record a finished call and its error, if it failed.
+    pub(super) fn record(
+        &self,
+        mcp_id: Uuid,
+        tool: &str,
+        mission_id: Option<Uuid>,
+        elapsed: Duration,
+        error: Option<&str>,
+    ) {
+        let error = error.map(|e| self.redact(e));
+        self.update(mcp_id, tool, mission_id, |t| {
+            let now = Utc::now();
+            t.calls += 1;
+            t.latency.record(elapsed.as_millis() as u64);
+            t.last_called_at = Some(now);
+            if let Some(error) = error {
+                t.errors += 1;
+                t.last_error = Some(error);
+                t.last_error_at = Some(now);
+            }
+        });
+    }
+
+    /// Record a call rejected by a limit.
+    pub(super) fn record_rate_limited(&self, mcp_id: Uuid, tool: &str, mission_id: Option<Uuid>) {
+        self.update(mcp_id, tool, mission_id, |t| t.rate_limited += 1);
+    }
+
+    /// Drop the telemetry of a removed MCP.
+    pub(super) fn remove_mcp(&self, mcp_id: Uuid) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|(id, _, _), _| *id != mcp_id);
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Statistics matching `query`, sorted by MCP, tool and mission.
+    pub(super) fn stats(&self, query: &McpStatsQuery) -> Vec<McpToolStats> {
+        let mut grouped: HashMap<TelemetryKey, ToolTelemetry> = HashMap::new();
+        for ((mcp_id, tool, mission_id), telemetry) in self.entries.lock().unwrap().iter() {
+            if query.mcp_id.is_some_and(|id| id != *mcp_id)
+                || query.mission_id.is_some_and(|id| Some(id) != *mission_id)
+            {
+                continue;
+            }
+            let mission_id = if query.by_mission { *mission_id } else { None };
+            grouped
+                .entry((*mcp_id, tool.clone(), mission_id))
+                .or_default()
+                .merge(telemetry);
+        }
+
+        let mut stats: Vec<McpToolStats> = grouped
+            .into_iter()
+            .map(|((mcp_id, tool, mission_id), t)| McpToolStats {
+                mcp_id,
+                tool,
+                mission_id,
+                calls: t.calls,
+                errors: t.errors,
+                error_rate: if t.calls == 0 {
+                    0.0
+                } else {
+                    t.errors as f64 / t.calls as f64
+                },
+                rate_limited: t.rate_limited,
+                p50_ms: t.latency.percentile(0.5),
+                p95_ms: t.latency.percentile(0.95),
+                max_ms: t.latency.max_ms,
+                // Errors recorded before the redactor was set
+                last_error: t.last_error.map(|e| self.redact(&e)),
+                last_error_at: t.last_error_at,
+                last_called_at: t.last_called_at,
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            (a.mcp_id, &a.tool, a.mission_id).cmp(&(b.mcp_id, &b.tool, b.mission_id))
+        });
+        stats
+    }
+
+    /// Write the telemetry to disk if it changed.
+    pub(super) async fn flush(&self) -> anyhow::Result<()> {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let entries: Vec<TelemetryEntry> = {
+            let mut entries = self.entries.lock().unwrap();
+            fold_old_missions(&mut entries, Utc::now());
+            entries
+                .iter()
+                .map(|((mcp_id, tool, mission_id), telemetry)| TelemetryEntry {
+                    mcp_id: *mcp_id,
+                    tool: tool.clone(),
+                    mission_id: *mission_id,
+                    telemetry: telemetry.clone(),
+                })
+                .collect()
+        };
+        let written = async {
+            if let Some(parent) = self.path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            // Write to a temp file first so a crash cannot truncate it
+            let tmp = self.path.with_extension("json.tmp");
+            tokio::fs::write(&tmp, serde_json::to_string(&entries)?).await?;
+            tokio::fs::rename(&tmp, &self.path).await?;
+            anyhow::Ok(())
+        }
+        .await;
+        if written.is_err() {
+            // Try again on the next flush
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        written
+    }
+}
+
+/// Fold the counters of missions last called more than
+/// [`MISSION_RETENTION_DAYS`] ago, and of the least recently called missions
+/// beyond [`MAX_MISSION_ENTRIES`], into the totals outside missions.
+fn fold_old_missions(entries: &mut HashMap<TelemetryKey, ToolTelemetry>, now: DateTime<Utc>) {
+    let cutoff = now - chrono::Duration::days(MISSION_RETENTION_DAYS);
+    let mut missions: Vec<(Option<DateTime<Utc>>, TelemetryKey)> = entries
+        .iter()
+        .filter(|((_, _, mission_id), _)| mission_id.is_some())
+        .map(|(key, t)| (t.last_called_at, key.clone()))
+        .collect();
+    // Most recently called first; never called (only rate limited) last
+    missions.sort_by_key(|(last_called_at, _)| std::cmp::Reverse(*last_called_at));
+    for (i, (last_called_at, key)) in missions.into_iter().enumerate() {
+        if i < MAX_MISSION_ENTRIES && last_called_at.is_some_and(|at| at >= cutoff) {
+            continue;
+        }
+        if let Some(telemetry) = entries.remove(&key) {
+            let (mcp_id, tool, _) = key;
+            entries
+                .entry((mcp_id, tool, None))
+                .or_default()
+                .merge(&telemetry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_percentiles() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.percentile(0.5), None);
+        for ms in [3, 4, 40, 45, 48, 90, 200, 400, 700, 4_000] {
+            histogram.record(ms);
+        }
+        assert_eq!(histogram.percentile(0.5), Some(50));
+        assert_eq!(histogram.percentile(0.95), Some(4_000));
+        histogram.record(900_000);
+        assert_eq!(histogram.max_ms, 900_000);
+        assert_eq!(histogram.percentile(1.0), Some(900_000));
+    }
+
+    #[tokio::test]
+    async fn stats_group_by_mission_and_persist() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = TelemetryStore::load(tmp.path()).await;
+        let (mcp, mission) = (Uuid::new_v4(), Uuid::new_v4());
+        store.record(
+            mcp,
+            "lookup",
+            Some(mission),
+            Duration::from_millis(20),
+            None,
+        );
+        store.record(mcp, "lookup", None, Duration::from_millis(80), Some("boom"));
+        store.record_rate_limited(mcp, "lookup", Some(mission));
+        store.flush().await.unwrap();
+
+        let store = TelemetryStore::load(tmp.path()).await;
+        let total = store.stats(&McpStatsQuery::default());
+        assert_eq!(total.len(), 1);
+        assert_eq!((total[0].calls, total[0].errors), (2, 1));
+        assert_eq!(total[0].error_rate, 0.5);
+        assert_eq!(total[0].rate_limited, 1);
+        assert_eq!(total[0].last_error.as_deref(), Some("boom"));
+        assert_eq!(total[0].p95_ms, Some(80));
+
+        let by_mission = store.stats(&McpStatsQuery {
+            by_mission: true,
+            ..Default::default()
+        });
+        assert_eq!(by_mission.len(), 2);
+        let in_mission = store.stats(&McpStatsQuery {
+            mission_id: Some(mission),
+            ..Default::default()
+        });
+        assert_eq!((in_mission[0].calls, in_mission[0].errors), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn unreadable_telemetry_is_moved_aside() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join(".sandboxed-sh").join("mcp");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("telemetry.json"), "[{\"mcp_id\":").unwrap();
+
+        let store = TelemetryStore::load(tmp.path()).await;
+        assert!(store.stats(&McpStatsQuery::default()).is_empty());
+        assert!(dir.join("telemetry.json.corrupt").exists());
+        assert!(!dir.join("telemetry.json").exists());
+    }
+
+    #[test]
+    fn old_missions_fold_into_totals() {
+        let now = Utc::now();
+        let mcp = Uuid::new_v4();
+        let entry = |calls, days_ago| ToolTelemetry {
+            calls,
+            last_called_at: Some(now - chrono::Duration::days(days_ago)),
+            ..Default::default()
+        };
+        let (recent, old) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut entries = HashMap::from([
+            ((mcp, "lookup".to_string(), None), entry(1, 0)),
+            ((mcp, "lookup".to_string(), Some(recent)), entry(2, 1)),
+            ((mcp, "lookup".to_string(), Some(old)), entry(4, 60)),
+        ]);
+
+        fold_old_missions(&mut entries, now);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[&(mcp, "lookup".to_string(), None)].calls, 5);
+        assert_eq!(entries[&(mcp, "lookup".to_string(), Some(recent))].calls, 2);
+    }
+}
@@ -3,16 +3,27 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::limits::McpLimits;
+use super::oauth::McpOAuthStatus;
+
 /// Transport type for MCP server communication.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum McpTransport {
-    /// HTTP JSON-RPC transport (server must be running and listening)
+    /// Streamable HTTP transport (server must be running and listening).
+    ///
+    /// Also works with servers that answer every POST with plain JSON.
     Http {
         endpoint: String,
         #[serde(default)]
         headers: std::collections::HashMap<String, String>,
     },
+    /// Legacy HTTP+SSE transport (GET event stream plus a POST endpoint)
+    Sse {
+        endpoint: String,
+        #[serde(default)]
+        headers: std::collections::HashMap<String, String>,
+    },
     /// Stdio transport (spawn process, communicate via stdin/stdout)
     Stdio {
         command: String,
@@ -46,6 +57,23 @@ pub enum McpStatus {
     Disabled,
 }
 
+/// Why a request could not be sent to an MCP server.
+#[derive(Debug, thiserror::Error)]
+pub enum McpRequestError {
+    #[error("MCP not found")]
+    NotFound,
+    #[error("MCP {0} is disabled")]
+    Disabled(String),
+    #[error("MCP {0} is not connected")]
+    NotConnected(String),
+    /// Workspace-scoped stdio MCPs only run inside workspaces
+    #[error("MCP {0} runs inside workspaces, not on the host")]
+    WorkspaceScoped(String),
+    /// Stdio servers have no channel for notifications outside a request
+    #[error("MCP {0} cannot send resource updates over the stdio transport")]
+    UpdatesUnsupported(String),
+}
+
 /// Scope for MCP servers (global or workspace-scoped).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -165,6 +193,30 @@ pub struct ServerInfo {
     pub version: Option<String>,
 }
 
+/// Progress reported by an MCP server while a request is running
+/// (`notifications/progress`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpProgress {
+    pub progress: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Callback receiving progress of a tool call.
+pub type McpProgressCallback = std::sync::Arc<dyn Fn(McpProgress) + Send + Sync>;
+
+/// A notification sent by an MCP server, e.g. `notifications/tools/list_changed`.
+#[derive(Debug, Clone, Serialize)]
+pub struct McpNotification {
+    /// Which MCP server sent it
+    pub mcp_id: Uuid,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<serde_json::Value>,
+}
+
 /// Configuration for a single MCP server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpServerConfig {
@@ -172,7 +224,7 @@ pub struct McpServerConfig {
     pub id: Uuid,
     /// Human-readable name (e.g., "GitHub", "Browser Extension")
     pub name: String,
-    /// Transport configuration (HTTP or stdio)
+    /// Transport configuration (HTTP, SSE or stdio)
     pub transport: McpTransport,
     /// Scope for this MCP (global or workspace-scoped)
     #[serde(default)]
@@ -194,6 +246,23 @@ pub struct McpServerConfig {
     /// Tool descriptors with full metadata (name, description, schema)
     #[serde(default)]
     pub tool_descriptors: Vec<McpToolDescriptor>,
+    /// Resources exposed by this MCP (populated after connection)
+    #[serde(default)]
+    pub resources: Vec<McpResourceDescriptor>,
+    /// Prompts exposed by this MCP (populated after connection)
+    #[serde(default)]
+    pub prompts: Vec<McpPromptDescriptor>,
+    /// Tools a mission's tool policy denies; only set on configs rendered
+    /// into a harness config
+    #[serde(skip)]
+    pub denied_tools: Vec<String>,
+    /// Rate limits and concurrency caps of tool calls
+    #[serde(default, skip_serializing_if = "McpLimits::is_empty")]
+    pub limits: McpLimits,
+    /// OAuth authorization of a remote MCP; its tokens live in the secrets
+    /// store
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth: Option<McpOAuthStatus>,
     /// When this MCP was added
     pub created_at: chrono::DateTime<chrono::Utc>,
     /// Last time we successfully connected
@@ -217,6 +286,11 @@ impl McpServerConfig {
             version: None,
             tools: Vec::new(),
             tool_descriptors: Vec::new(),
+            resources: Vec::new(),
+            prompts: Vec::new(),
+            denied_tools: Vec::new(),
+            limits: McpLimits::default(),
+            oauth: None,
             created_at: chrono::Utc::now(),
             last_connected_at: None,
         }
@@ -240,6 +314,11 @@ impl McpServerConfig {
             version: None,
             tools: Vec::new(),
             tool_descriptors: Vec::new(),
+            resources: Vec::new(),
+            prompts: Vec::new(),
+            denied_tools: Vec::new(),
+            limits: McpLimits::default(),
+            oauth: None,
             created_at: chrono::Utc::now(),
             last_connected_at: None,
         }
@@ -260,6 +339,14 @@ pub struct McpServerState {
     pub tool_calls: u64,
     /// Number of failed tool calls
     pub tool_errors: u64,
+    /// When the current stdio process was started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Seconds since the current stdio process was started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime_secs: Option<u64>,
+    /// Number of times the supervisor restarted the stdio process
+    pub restarts: u32,
 }
 
 impl McpServerState {
@@ -275,10 +362,33 @@ impl McpServerState {
             error: None,
             tool_calls: 0,
             tool_errors: 0,
+            started_at: None,
+            uptime_secs: None,
+            restarts: 0,
         }
     }
 }
 
+/// A workspace-scoped stdio MCP running inside one workspace (not persisted).
+#[derive(Debug, Clone, Serialize)]
+pub struct McpWorkspaceInstance {
+    pub mcp_id: Uuid,
+    pub workspace_id: Uuid,
+    pub name: String,
+    /// Connected once initialized and its tools listed
+    pub status: McpStatus,
+    /// Error message if status is Error
+    pub error: Option<String>,
+    /// Tools discovered in this workspace
+    pub tools: Vec<McpToolDescriptor>,
+    /// Prompts discovered in this workspace
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prompts: Vec<McpPromptDescriptor>,
+    /// When the process was started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
 /// A tool exposed by an MCP server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpTool {
@@ -336,6 +446,102 @@ pub struct McpToolDescriptor {
     pub input_schema: serde_json::Value,
 }
 
+/// MCP resource list response from server (`resources/list`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct McpResourcesResponse {
+    pub resources: Vec<McpResourceDescriptor>,
+}
+
+/// Resource descriptor from MCP server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResourceDescriptor {
+    pub uri: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// Response from reading an MCP resource (`resources/read`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpReadResourceResponse {
+    pub contents: Vec<McpResourceContents>,
+}
+
+/// Contents of a resource: `text` for text resources, base64 `blob` otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResourceContents {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+/// MCP prompt list response from server (`prompts/list`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct McpPromptsResponse {
+    pub prompts: Vec<McpPromptDescriptor>,
+}
+
+/// Prompt descriptor from MCP server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpPromptDescriptor {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<McpPromptArgument>,
+}
+
+/// An argument accepted by an MCP prompt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpPromptArgument {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Response from getting an MCP prompt (`prompts/get`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpPromptResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub messages: Vec<McpPromptMessage>,
+}
+
+impl McpPromptResult {
+    /// Text of all messages, one per paragraph. Embedded resources
+    /// contribute their text; images and other binary content are skipped.
+    pub fn text(&self) -> String {
+        self.messages
+            .iter()
+            .filter_map(|m| match m.content.get("type").and_then(|t| t.as_str()) {
+                Some("text") => m.content.get("text").and_then(|t| t.as_str()),
+                Some("resource") => m.content.pointer("/resource/text").and_then(|t| t.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// A message of an MCP prompt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpPromptMessage {
+    /// `user` or `assistant`
+    pub role: String,
+    /// Text, image or embedded resource content, kept as sent
+    pub content: serde_json::Value,
+}
+
 /// Request to call an MCP tool.
 #[derive(Debug, Clone, Serialize)]
 pub struct McpCallToolRequest {
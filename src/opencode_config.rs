@@ -265,7 +265,7 @@ fn resolve_command_path(cmd: &str) -> String {
 
 fn opencode_entry_from_mcp(config: &crate::mcp::McpServerConfig) -> Value {
     match &config.transport {
-        McpTransport::Http { endpoint, headers } => {
+        McpTransport::Http { endpoint, headers } | McpTransport::Sse { endpoint, headers } => {
             let mut entry = serde_json::Map::new();
             entry.insert("type".to_string(), json!("http"));
             entry.insert("endpoint".to_string(), json!(endpoint));
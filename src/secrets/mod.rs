@@ -7,6 +7,7 @@
 //! ```text
 //! .sandboxed-sh/secrets/
 //! ├── config.json           # Key configuration (git-tracked)
+//! ├── audit.jsonl           # Resolutions of secret references (no values)
 //! ├── keys/
 //! │   └── default.key       # Key marker file (git-tracked)
 //! └── registries/
@@ -39,11 +40,27 @@
 //! // Export to workspace
 //! store.export_to_workspace(&workspace_path, "mcp-tokens", None).await?;
 //! ```
+//!
+//! ## Secret references
+//!
+//! Workspace env vars, MCP `env`/`headers` and template env vars can
+//! reference secrets as `${secret:registry/key}`, resolved at mission launch
+//! by [`resolve_secret_refs`].
+//!
+//! ## Redaction
+//!
+//! Mission events are masked by [`SecretRedactor`] before they are stored or
+//! streamed, so known secret values and credentials never reach the event
+//! log.
 
 mod crypto;
+mod redact;
+mod refs;
 mod store;
 pub mod types;
 
 pub use crypto::{CryptoError, SecretsCrypto};
+pub use redact::{SecretRedactor, REDACTED};
+pub use refs::{resolve_env_refs, resolve_secret_refs};
 pub use store::SecretsStore;
 pub use types::*;
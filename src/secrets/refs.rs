@@ -0,0 +1,131 @@
+//! `${secret:registry/key}` references to secrets store entries.
+//!
+//! Workspace env vars, MCP `env`/`headers` and template env vars may hold
+//! references instead of plaintext, e.g. `${secret:api-keys/github}`. They
+//! are stored as written and only resolved when a mission, service or
+//! workspace MCP starts (see [`crate::workspace::with_resolved_env`]) and by
+//! the harness config writers, so resolved values are never persisted. Each
+//! resolution is recorded in the audit log of the store.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use super::SecretsStore;
+
+/// `${secret:registry/key}`; the key may itself contain `/`.
+fn reference_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\$\{secret:([^/}]+)/([^}]+)\}").expect("Invalid regex"))
+}
+
+/// Whether `value` contains secret references.
+fn has_secret_refs(value: &str) -> bool {
+    reference_regex().is_match(value)
+}
+
+/// Replace the secret references in `value`, resolved for `context`.
+///
+/// Fails when a reference cannot be resolved, e.g. because the store is
+/// locked; values without references are returned as is.
+pub async fn resolve_secret_refs(
+    store: Option<&SecretsStore>,
+    value: &str,
+    context: &str,
+) -> anyhow::Result<String> {
+    if !has_secret_refs(value) {
+        return Ok(value.to_string());
+    }
+    let store = store.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Cannot resolve secret references for {}: the secrets store is not available",
+            context
+        )
+    })?;
+
+    let mut resolved = String::with_capacity(value.len());
+    let mut last = 0;
+    for captures in reference_regex().captures_iter(value) {
+        let whole = captures.get(0).expect("match");
+        resolved.push_str(&value[last..whole.start()]);
+        resolved.push_str(
+            &store
+                .resolve_reference(&captures[1], &captures[2], context)
+                .await?,
+        );
+        last = whole.end();
+    }
+    resolved.push_str(&value[last..]);
+    Ok(resolved)
+}
+
+/// Resolve the secret references in the values of `env`.
+pub async fn resolve_env_refs(
+    store: Option<&SecretsStore>,
+    env: &HashMap<String, String>,
+    context: &str,
+) -> anyhow::Result<HashMap<String, String>> {
+    let mut resolved = HashMap::with_capacity(env.len());
+    for (name, value) in env {
+        let value = resolve_secret_refs(store, value, &format!("{} {}", context, name)).await?;
+        resolved.insert(name.clone(), value);
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn resolves_and_audits_references() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = SecretsStore::new(tmp.path()).await.unwrap();
+        store.initialize("default").await.unwrap();
+
+        // Plain values need no store
+        assert_eq!(
+            resolve_secret_refs(None, "plain $HOME", "env")
+                .await
+                .unwrap(),
+            "plain $HOME"
+        );
+
+        let err = resolve_secret_refs(Some(&store), "${secret:api-keys/github}", "env TOKEN")
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with(
+                "Cannot resolve ${secret:api-keys/github} for env TOKEN: Secrets are locked"
+            ),
+            "{}",
+            err
+        );
+
+        store.unlock("pass").await.unwrap();
+        store
+            .set_secret("api-keys", "github/token", "ghp_123", None)
+            .await
+            .unwrap();
+        let env = HashMap::from([(
+            "AUTH".to_string(),
+            "Bearer ${secret:api-keys/github/token}".to_string(),
+        )]);
+        let resolved = resolve_env_refs(Some(&store), &env, "workspace dev")
+            .await
+            .unwrap();
+        assert_eq!(resolved["AUTH"], "Bearer ghp_123");
+
+        let audit = store.audit_log(0, 10).await.unwrap();
+        assert_eq!(audit.len(), 2);
+        assert!(audit[0].success);
+        assert_eq!(audit[0].key, "github/token");
+        assert_eq!(audit[0].context, "workspace dev AUTH");
+        assert!(!audit[1].success);
+        let log =
+            std::fs::read_to_string(tmp.path().join(".sandboxed-sh/secrets/audit.jsonl")).unwrap();
+        assert!(!log.contains("ghp_123"));
+    }
+}
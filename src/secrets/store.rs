@@ -14,6 +14,15 @@ use tokio::sync::RwLock;
 use super::crypto::SecretsCrypto;
 use super::types::*;
 
+/// Audit log of secret resolutions, one JSON entry per line.
+const AUDIT_LOG: &str = "audit.jsonl";
+/// Where the audit log is moved once it grows past [`AUDIT_LOG_MAX_BYTES`],
+/// replacing the previous one.
+const AUDIT_LOG_ROTATED: &str = "audit.jsonl.1";
+const AUDIT_LOG_MAX_BYTES: u64 = 4 * 1024 * 1024;
+/// Bytes read at a time when paging backwards through the audit log.
+const AUDIT_READ_CHUNK: u64 = 64 * 1024;
+
 /// Store for managing encrypted secrets.
 pub struct SecretsStore {
     /// Base directory (.sandboxed-sh/secrets)
@@ -371,6 +380,86 @@ impl SecretsStore {
         Ok(())
     }
 
+    /// Resolve a `${secret:registry/key}` reference for `context`, recording
+    /// the resolution (without the value) in the audit log.
+    pub async fn resolve_reference(
+        &self,
+        registry_name: &str,
+        key: &str,
+        context: &str,
+    ) -> Result<String> {
+        let result = self.get_secret(registry_name, key).await.map_err(|e| {
+            anyhow::anyhow!(
+                "Cannot resolve ${{secret:{}/{}}} for {}: {}",
+                registry_name,
+                key,
+                context,
+                e
+            )
+        });
+
+        let entry = SecretAuditEntry {
+            timestamp: chrono::Utc::now(),
+            registry: registry_name.to_string(),
+            key: key.to_string(),
+            context: context.to_string(),
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+        tracing::info!(
+            registry = %registry_name,
+            key = %key,
+            context = %context,
+            success = entry.success,
+            "Resolved secret reference"
+        );
+        if let Err(e) = self.append_audit(&entry).await {
+            tracing::warn!(error = %e, "Failed to record secret resolution");
+        }
+
+        result
+    }
+
+    async fn append_audit(&self, entry: &SecretAuditEntry) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        fs::create_dir_all(&self.base_dir).await?;
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let path = self.base_dir.join(AUDIT_LOG);
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        if file.metadata().await?.len() > AUDIT_LOG_MAX_BYTES {
+            fs::rename(&path, self.base_dir.join(AUDIT_LOG_ROTATED)).await?;
+        }
+        Ok(())
+    }
+
+    /// Secret resolutions newest first, skipping the `offset` most recent.
+    ///
+    /// Covers the current and the previously rotated audit log; only the
+    /// requested page is read.
+    pub async fn audit_log(&self, offset: usize, limit: usize) -> Result<Vec<SecretAuditEntry>> {
+        let mut page = AuditPage {
+            skip: offset,
+            limit,
+            entries: Vec::new(),
+        };
+        for name in [AUDIT_LOG, AUDIT_LOG_ROTATED] {
+            if page.is_full() {
+                break;
+            }
+            page.read_backwards(&self.base_dir.join(name))
+                .await
+                .context("Failed to read secrets audit log")?;
+        }
+        Ok(page.entries)
+    }
+
     /// Delete a registry and all its secrets.
     pub async fn delete_registry(&self, registry_name: &str) -> Result<()> {
         let mut registries = self.registries.write().await;
@@ -476,11 +565,140 @@ impl SecretsStore {
     }
 }
 
+/// A page of audit entries collected newest first.
+struct AuditPage {
+    skip: usize,
+    limit: usize,
+    entries: Vec<SecretAuditEntry>,
+}
+
+impl AuditPage {
+    fn is_full(&self) -> bool {
+        self.entries.len() >= self.limit
+    }
+
+    /// Add the entries of `line`; unreadable lines (e.g. a torn write) are ignored.
+    fn push_line(&mut self, line: &[u8]) {
+        let Ok(entry) = serde_json::from_slice::<SecretAuditEntry>(line) else {
+            return;
+        };
+        if self.skip > 0 {
+            self.skip -= 1;
+        } else {
+            self.entries.push(entry);
+        }
+    }
+
+    /// Collect entries from the end of the log at `path` until the page is full.
+    async fn read_backwards(&mut self, path: &Path) -> std::io::Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        let mut file = match fs::File::open(path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let mut end = file.metadata().await?.len();
+        // Start of a line whose beginning lies in a chunk not read yet
+        let mut partial: Vec<u8> = Vec::new();
+        while end > 0 && !self.is_full() {
+            let start = end.saturating_sub(AUDIT_READ_CHUNK);
+            let mut chunk = vec![0; (end - start) as usize];
+            file.seek(std::io::SeekFrom::Start(start)).await?;
+            file.read_exact(&mut chunk).await?;
+            chunk.extend_from_slice(&partial);
+
+            // Unless at the start of the file, the first line may be incomplete
+            let complete_from = if start == 0 {
+                0
+            } else {
+                match chunk.iter().position(|&b| b == b'\n') {
+                    Some(newline) => newline + 1,
+                    None => {
+                        partial = chunk;
+                        end = start;
+                        continue;
+                    }
+                }
+            };
+            for line in chunk[complete_from..].rsplit(|&b| b == b'\n') {
+                if self.is_full() {
+                    break;
+                }
+                if !line.is_empty() {
+                    self.push_line(line);
+                }
+            }
+            chunk.truncate(complete_from);
+            partial = chunk;
+            end = start;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    fn audit_entry(key: String) -> SecretAuditEntry {
+        SecretAuditEntry {
+            timestamp: chrono::Utc::now(),
+            registry: "api-keys".to_string(),
+            key,
+            context: "workspace dev env TOKEN".to_string(),
+            success: true,
+            error: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn audit_log_rotates_and_pages_backwards() {
+        let temp = tempdir().unwrap();
+        let store = SecretsStore::new(temp.path()).await.unwrap();
+
+        // Enough entries to span several read chunks
+        let mut log = String::new();
+        for i in 0..2000 {
+            log.push_str(&serde_json::to_string(&audit_entry(format!("key-{}", i))).unwrap());
+            log.push('\n');
+        }
+        fs::create_dir_all(&store.base_dir).await.unwrap();
+        fs::write(store.base_dir.join(AUDIT_LOG), &log)
+            .await
+            .unwrap();
+
+        let page = store.audit_log(0, 3).await.unwrap();
+        let keys: Vec<_> = page.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, vec!["key-1999", "key-1998", "key-1997"]);
+        let page = store.audit_log(1500, 2).await.unwrap();
+        let keys: Vec<_> = page.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, vec!["key-499", "key-498"]);
+        assert_eq!(store.audit_log(0, 5000).await.unwrap().len(), 2000);
+
+        // Past the size cap the log is rotated; both files are paged through
+        let padding = " ".repeat(AUDIT_LOG_MAX_BYTES as usize);
+        fs::write(
+            store.base_dir.join(AUDIT_LOG),
+            format!("{}{}\n", log, padding),
+        )
+        .await
+        .unwrap();
+        store
+            .append_audit(&audit_entry("rotated".to_string()))
+            .await
+            .unwrap();
+        assert!(!store.base_dir.join(AUDIT_LOG).exists());
+        store
+            .append_audit(&audit_entry("latest".to_string()))
+            .await
+            .unwrap();
+        let page = store.audit_log(0, 3).await.unwrap();
+        let keys: Vec<_> = page.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, vec!["latest", "rotated", "key-1999"]);
+    }
+
     #[tokio::test]
     async fn test_secrets_store_lifecycle() {
         let temp = tempdir().unwrap();
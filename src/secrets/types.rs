@@ -228,3 +228,16 @@ pub struct InitializeRequest {
 fn default_key_id() -> String {
     "default".to_string()
 }
+
+/// A resolution of a `${secret:registry/key}` reference (never the value).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretAuditEntry {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub registry: String,
+    pub key: String,
+    /// What the secret was resolved for (e.g. a workspace env var)
+    pub context: String,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
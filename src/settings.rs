@@ -5,6 +5,9 @@
 
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+
+use crate::library::{LibraryLayerConfig, SecretScanMode};
+use crate::skills_registry::RegistrySource;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -14,6 +17,18 @@ pub struct Settings {
     /// Git remote URL for the configuration library.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub library_remote: Option<String>,
+    /// Overlay library layers stacked on top of the primary library, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub library_layers: Vec<LibraryLayerConfig>,
+    /// Refuse library commits and pushes while validation reports errors.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub validate_library_on_commit: bool,
+    /// How plaintext secrets are handled on library commits and pushes.
+    #[serde(default)]
+    pub secret_scan: SecretScanMode,
+    /// Where registry skills are searched and fetched from.
+    #[serde(default)]
+    pub skills_registry: RegistrySource,
 }
 
 /// In-memory store for global settings with disk persistence.
@@ -28,6 +43,8 @@ impl SettingsStore {
     ///
     /// If no settings file exists, uses environment variables as defaults:
     /// - `LIBRARY_REMOTE` - Git remote URL for the configuration library
+    /// - `SKILLS_REGISTRY_MIRROR` / `SKILLS_REGISTRY_INDEX` - Skill registry mirror
+    ///   directory or index URL
     pub async fn new(working_dir: &PathBuf) -> Self {
         let storage_path = working_dir.join(".sandboxed-sh/settings.json");
 
@@ -66,6 +83,20 @@ impl SettingsStore {
             library_remote: std::env::var("LIBRARY_REMOTE").ok().or_else(|| {
                 Some("https://github.com/Th0rgal/sandboxed-library-template.git".to_string())
             }),
+            library_layers: Vec::new(),
+            validate_library_on_commit: false,
+            secret_scan: SecretScanMode::default(),
+            skills_registry: Self::skills_registry_from_env(),
+        }
+    }
+
+    fn skills_registry_from_env() -> RegistrySource {
+        if let Ok(path) = std::env::var("SKILLS_REGISTRY_MIRROR") {
+            RegistrySource::Mirror { path: path.into() }
+        } else if let Ok(url) = std::env::var("SKILLS_REGISTRY_INDEX") {
+            RegistrySource::Index { url }
+        } else {
+            RegistrySource::SkillsSh
         }
     }
 
@@ -123,6 +154,43 @@ impl SettingsStore {
         }
     }
 
+    /// Get the overlay library layers.
+    pub async fn get_library_layers(&self) -> Vec<LibraryLayerConfig> {
+        self.settings.read().await.library_layers.clone()
+    }
+
+    /// Update the overlay library layers.
+    ///
+    /// Returns whether the value changed.
+    pub async fn set_library_layers(
+        &self,
+        layers: Vec<LibraryLayerConfig>,
+    ) -> Result<bool, std::io::Error> {
+        let mut settings = self.settings.write().await;
+        if settings.library_layers == layers {
+            return Ok(false);
+        }
+        settings.library_layers = layers;
+        drop(settings); // Release lock before saving
+        self.save_to_disk().await?;
+        Ok(true)
+    }
+
+    /// Whether library commits and pushes are gated on validation.
+    pub async fn validate_library_on_commit(&self) -> bool {
+        self.settings.read().await.validate_library_on_commit
+    }
+
+    /// How plaintext secrets are handled on library commits and pushes.
+    pub async fn secret_scan_mode(&self) -> SecretScanMode {
+        self.settings.read().await.secret_scan
+    }
+
+    /// Where registry skills are searched and fetched from.
+    pub async fn skills_registry(&self) -> RegistrySource {
+        self.settings.read().await.skills_registry.clone()
+    }
+
     /// Update multiple settings at once.
     pub async fn update(&self, new_settings: Settings) -> Result<(), std::io::Error> {
         let mut settings = self.settings.write().await;
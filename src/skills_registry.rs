@@ -1,14 +1,168 @@
-//! Skills.sh registry integration.
+//! Skills registry integration.
 //!
-//! This module provides integration with the skills.sh registry using the
-//! `bunx skills` CLI. It handles searching, installing, and updating skills
-//! from the community registry.
+//! Where skills are searched and fetched from is a [`RegistrySource`]:
+//! - `skills_sh` (default): search goes through the `bunx skills` CLI and
+//!   repositories are fetched from GitHub
+//! - `index`: a JSON [`RegistryIndex`] served over HTTP, cached on disk so
+//!   search keeps working offline
+//! - `mirror`: a local directory of bare repository mirrors plus an
+//!   `index.json`, built with `library::skills_mirror::build_mirror`
+//!
+//! Listing, installing and updating skills fetch the repositories directly
+//! with git (see `library::skills_lock`), so bun is only needed for skills.sh
+//! search.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
 use tokio::process::Command;
 
+/// Name of the index file in a mirror directory and in the index cache.
+pub const INDEX_FILE: &str = "index.json";
+
+/// How long a downloaded index is used before it is fetched again.
+const INDEX_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Where registry skills are searched and fetched from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RegistrySource {
+    /// skills.sh search and upstream repositories.
+    #[default]
+    SkillsSh,
+    /// A skill index served over HTTP; repositories are fetched upstream.
+    Index { url: String },
+    /// A local directory of mirrored repositories with an `index.json`.
+    Mirror { path: PathBuf },
+}
+
+impl RegistrySource {
+    /// Git URL to fetch `identifier` from.
+    ///
+    /// Mirrors serve the repositories they contain and fall back to upstream
+    /// for the rest.
+    pub fn repo_url(&self, identifier: &str) -> String {
+        if let RegistrySource::Mirror { path } = self {
+            if let Some(repo) = mirror_repo_path(path, identifier) {
+                if repo.exists() {
+                    return repo.to_string_lossy().to_string();
+                }
+            }
+        }
+        repo_url(identifier)
+    }
+}
+
+/// Skill index of a registry source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryIndex {
+    #[serde(default = "index_version")]
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<String>,
+    #[serde(default)]
+    pub skills: Vec<RegistryIndexEntry>,
+}
+
+fn index_version() -> u32 {
+    1
+}
+
+impl Default for RegistryIndex {
+    fn default() -> Self {
+        Self {
+            version: index_version(),
+            generated_at: None,
+            skills: Vec::new(),
+        }
+    }
+}
+
+/// A skill in a [`RegistryIndex`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegistryIndexEntry {
+    /// Repository identifier (e.g., "vercel-labs/agent-skills")
+    pub identifier: String,
+    /// Skill name within the repo
+    pub name: String,
+    /// Skill directory in the repository (empty for a root-level skill)
+    #[serde(default)]
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Commit the entry was indexed at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+impl RegistryIndex {
+    /// Read an index file.
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read skill index {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid skill index {}", path.display()))
+    }
+
+    /// Write the index file, creating parent directories.
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, serde_json::to_string_pretty(self)?).await?;
+        Ok(())
+    }
+
+    /// Skills matching `query`, best matches first.
+    ///
+    /// Exact name matches rank above name prefixes, then names containing the
+    /// query, then identifier and description matches.
+    pub fn search(&self, query: &str) -> Vec<RegistrySkillListing> {
+        let query = query.trim().to_lowercase();
+        let mut ranked: Vec<(u8, &RegistryIndexEntry)> = self
+            .skills
+            .iter()
+            .filter_map(|entry| {
+                let name = entry.name.to_lowercase();
+                let rank = if query.is_empty() || name == query {
+                    0
+                } else if name.starts_with(&query) {
+                    1
+                } else if name.contains(&query) {
+                    2
+                } else if entry.identifier.to_lowercase().contains(&query) {
+                    3
+                } else if entry
+                    .description
+                    .as_deref()
+                    .is_some_and(|d| d.to_lowercase().contains(&query))
+                {
+                    4
+                } else {
+                    return None;
+                };
+                Some((rank, entry))
+            })
+            .collect();
+        ranked.sort_by(|(ra, a), (rb, b)| {
+            ra.cmp(rb)
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.identifier.cmp(&b.identifier))
+        });
+        ranked
+            .into_iter()
+            .map(|(_, entry)| RegistrySkillListing {
+                identifier: entry.identifier.clone(),
+                name: entry.name.clone(),
+                description: entry.description.clone(),
+            })
+            .collect()
+    }
+}
+
 /// A skill listing from the registry search results.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegistrySkillListing {
@@ -21,15 +175,6 @@ pub struct RegistrySkillListing {
     pub description: Option<String>,
 }
 
-/// Result of installing skills from the registry.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct InstallResult {
-    /// Skills that were successfully installed
-    pub installed: Vec<String>,
-    /// Any errors encountered
-    pub errors: Vec<String>,
-}
-
 /// Check if bun is available in the system.
 pub async fn check_bun_available() -> bool {
     Command::new("bun")
@@ -114,153 +259,191 @@ pub async fn search_skills(query: &str) -> Result<Vec<RegistrySkillListing>> {
     Ok(skills)
 }
 
-/// List available skills in a repository without installing.
+/// Search skills in `source`.
 ///
-/// Uses `bunx skills add <identifier> --list` to see what skills are available.
-pub async fn list_repo_skills(identifier: &str) -> Result<Vec<String>> {
-    let output = Command::new("bunx")
-        .args(["skills", "add", identifier, "--list"])
-        .env("NO_COLOR", "1")
-        .output()
-        .await
-        .context("Failed to run bunx skills add --list")?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut skills = Vec::new();
-
-    // Parse skill names from the list output
-    for line in stdout.lines() {
-        let line = line.trim();
-        // Look for lines that appear to be skill names
-        if !line.is_empty()
-            && !line.starts_with("Available")
-            && !line.starts_with("─")
-            && !line.contains("http")
-        {
-            // Clean up the skill name
-            let name = line
-                .trim_start_matches("- ")
-                .trim_start_matches("• ")
-                .trim();
-            if !name.is_empty() && !name.contains(' ') {
-                skills.push(name.to_string());
+/// Index sources are cached under `cache_dir`; a stale cache is refreshed and
+/// used as-is when the index cannot be downloaded.
+pub async fn search(
+    source: &RegistrySource,
+    cache_dir: &Path,
+    query: &str,
+) -> Result<Vec<RegistrySkillListing>> {
+    let index = match source {
+        RegistrySource::SkillsSh => return search_skills(query).await,
+        RegistrySource::Index { url } => cached_index(url, cache_dir, false).await?,
+        RegistrySource::Mirror { path } => {
+            let index_path = path.join(INDEX_FILE);
+            if !index_path.exists() {
+                anyhow::bail!(
+                    "Skill mirror {} has no {}, build it first",
+                    path.display(),
+                    INDEX_FILE
+                );
             }
+            RegistryIndex::load(&index_path).await?
         }
-    }
-
-    Ok(skills)
+    };
+    Ok(index.search(query))
 }
 
-/// Install a skill from the registry to a target directory.
+/// Index downloaded from `url`, cached at `cache_dir/index.json`.
 ///
-/// Uses `bunx skills add <identifier>` to install the skill.
-/// The skill files will be placed in the library's skill directory.
-pub async fn install_skill(
-    identifier: &str,
-    skill_names: Option<&[&str]>,
-    target_dir: &Path,
-) -> Result<InstallResult> {
-    // Build the command
-    let mut cmd = Command::new("bunx");
-    cmd.args(["skills", "add", identifier, "-y"]);
-
-    // Add specific skill names if provided
-    if let Some(names) = skill_names {
-        for name in names {
-            cmd.args(["--skill", name]);
+/// `refresh` downloads the index even when the cache is fresh.
+pub async fn cached_index(url: &str, cache_dir: &Path, refresh: bool) -> Result<RegistryIndex> {
+    let cache_path = cache_dir.join(INDEX_FILE);
+    let fresh = tokio::fs::metadata(&cache_path)
+        .await
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < INDEX_CACHE_TTL);
+    if fresh && !refresh {
+        if let Ok(index) = RegistryIndex::load(&cache_path).await {
+            return Ok(index);
         }
     }
 
-    // Set the working directory
-    cmd.current_dir(target_dir);
-    cmd.env("NO_COLOR", "1");
-
-    let output = cmd
-        .output()
-        .await
-        .context("Failed to run bunx skills add")?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
-
-    // Parse installed skills from output
-    let mut installed = Vec::new();
-    let mut errors = Vec::new();
-
-    if output.status.success() {
-        // Parse successful installations from stdout
-        for line in stdout.lines() {
-            let line = line.trim();
-            if line.contains("installed") || line.contains("Added") || line.contains("✓") {
-                // Extract skill name from the line
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                for part in parts {
-                    if !part.contains("installed")
-                        && !part.contains("Added")
-                        && !part.starts_with("✓")
-                        && !part.is_empty()
-                    {
-                        installed.push(part.to_string());
-                        break;
-                    }
-                }
+    match download_index(url).await {
+        Ok(index) => {
+            if let Err(e) = index.save(&cache_path).await {
+                tracing::warn!(error = %e, "Failed to cache skill index");
             }
+            Ok(index)
         }
-    } else {
-        errors.push(format!("Installation failed: {}", stderr));
+        Err(e) if cache_path.exists() => {
+            tracing::warn!(url = %url, error = %e, "Skill index unavailable, using cached copy");
+            RegistryIndex::load(&cache_path).await
+        }
+        Err(e) => Err(e),
     }
-
-    Ok(InstallResult { installed, errors })
 }
 
-/// Check for available updates to installed skills.
-///
-/// Uses `bunx skills check` to see if updates are available.
-pub async fn check_updates(working_dir: &Path) -> Result<Vec<String>> {
-    let output = Command::new("bunx")
-        .args(["skills", "check"])
-        .current_dir(working_dir)
-        .env("NO_COLOR", "1")
-        .output()
+async fn download_index(url: &str) -> Result<RegistryIndex> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let response = client
+        .get(url)
+        .send()
         .await
-        .context("Failed to run bunx skills check")?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut updates = Vec::new();
+        .with_context(|| format!("Failed to download skill index from {}", url))?
+        .error_for_status()?;
+    response
+        .json()
+        .await
+        .with_context(|| format!("Invalid skill index at {}", url))
+}
 
-    for line in stdout.lines() {
-        let line = line.trim();
-        if line.contains("update available") || line.contains("→") {
-            updates.push(line.to_string());
-        }
+/// Path of the bare mirror of `identifier` below a mirror directory.
+///
+/// `owner/repo` identifiers live at `github.com/owner/repo.git`, URLs at
+/// `<host>/<path>.git`. Plain local paths are never mirrored.
+pub fn mirror_repo_path(mirror: &Path, identifier: &str) -> Option<PathBuf> {
+    if identifier.starts_with('/') || identifier.starts_with('.') {
+        return None;
     }
-
-    Ok(updates)
+    let key = if let Some((_, rest)) = identifier.split_once("://") {
+        // Drop credentials and port
+        let rest = rest.rsplit_once('@').map_or(rest, |(_, r)| r);
+        let (host, path) = rest.split_once('/')?;
+        let host = host.split(':').next().unwrap_or(host);
+        // `file:///srv/repo` has no host
+        format!(
+            "{}/{}",
+            if host.is_empty() { "localhost" } else { host },
+            path
+        )
+    } else if let Some(rest) = identifier.strip_prefix("git@") {
+        rest.replacen(':', "/", 1)
+    } else {
+        format!("github.com/{}", identifier)
+    };
+    let key = key.trim_end_matches('/').trim_end_matches(".git");
+    let parts: Vec<&str> = key.split('/').collect();
+    if parts.len() < 2
+        || parts
+            .iter()
+            .any(|p| p.is_empty() || *p == "." || *p == ".." || p.contains('\\'))
+    {
+        return None;
+    }
+    Some(mirror.join(format!("{}.git", key)))
 }
 
-/// Update all installed skills to their latest versions.
+/// Git URL of a registry identifier.
 ///
-/// Uses `bunx skills update` to update all skills.
-pub async fn update_all(working_dir: &Path) -> Result<Vec<String>> {
-    let output = Command::new("bunx")
-        .args(["skills", "update"])
-        .current_dir(working_dir)
-        .env("NO_COLOR", "1")
-        .output()
-        .await
-        .context("Failed to run bunx skills update")?;
+/// `owner/repo` identifiers point to GitHub; URLs and local paths are used as-is.
+pub fn repo_url(identifier: &str) -> String {
+    if identifier.contains("://")
+        || identifier.starts_with("git@")
+        || identifier.starts_with('/')
+        || identifier.starts_with('.')
+    {
+        identifier.to_string()
+    } else {
+        format!(
+            "https://github.com/{}.git",
+            identifier.trim_end_matches(".git")
+        )
+    }
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut updated = Vec::new();
+/// Check that an identifier is `owner/repo` or a remote git URL.
+///
+/// Identifiers come from API requests and from `skills.lock` files of synced
+/// libraries, so they must never be taken for a git option or a local path.
+pub fn validate_identifier(identifier: &str) -> Result<()> {
+    let invalid = || anyhow::anyhow!("Invalid registry identifier: {}", identifier);
+    if identifier.is_empty()
+        || identifier.starts_with('-')
+        || identifier
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control())
+    {
+        return Err(invalid());
+    }
 
-    for line in stdout.lines() {
-        let line = line.trim();
-        if line.contains("updated") || line.contains("✓") {
-            updated.push(line.to_string());
+    if let Some((scheme, _)) = identifier.split_once("://") {
+        let url = url::Url::parse(identifier).map_err(|_| invalid())?;
+        if !matches!(scheme, "https" | "http" | "ssh" | "git")
+            || url.host_str().is_none_or(|host| host.starts_with('-'))
+        {
+            return Err(invalid());
         }
+        return Ok(());
+    }
+    if let Some(rest) = identifier.strip_prefix("git@") {
+        return match rest.split_once(':') {
+            Some((host, path))
+                if !host.is_empty()
+                    && !path.is_empty()
+                    && !host.starts_with('-')
+                    && !path.starts_with('-') =>
+            {
+                Ok(())
+            }
+            _ => Err(invalid()),
+        };
+    }
+
+    let segment = |s: &str| {
+        !s.is_empty()
+            && !s.starts_with(['-', '.'])
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    match identifier.split_once('/') {
+        Some((owner, repo)) if segment(owner) && segment(repo) => Ok(()),
+        _ => Err(invalid()),
     }
+}
 
-    Ok(updated)
+/// List available skills in a repository without installing.
+///
+/// Fetches the repository tree with git and returns the directories that
+/// contain a `SKILL.md`.
+pub async fn list_repo_skills(source: &RegistrySource, identifier: &str) -> Result<Vec<String>> {
+    crate::library::skills_lock::list_repo_skills(source, identifier).await
 }
 
 #[cfg(test)]
@@ -273,4 +456,99 @@ mod tests {
         let available = check_bun_available().await;
         println!("Bun available: {}", available);
     }
+
+    #[test]
+    fn test_repo_url() {
+        assert_eq!(
+            repo_url("vercel-labs/agent-skills"),
+            "https://github.com/vercel-labs/agent-skills.git"
+        );
+        assert_eq!(
+            repo_url("git@github.com:acme/skills.git"),
+            "git@github.com:acme/skills.git"
+        );
+        assert_eq!(repo_url("/srv/skills"), "/srv/skills");
+    }
+
+    #[test]
+    fn test_validate_identifier() {
+        for valid in [
+            "vercel-labs/agent-skills",
+            "acme/skills.git",
+            "https://git.example.com/team/skills.git",
+            "ssh://git@git.example.com/team/skills",
+            "git@github.com:acme/skills.git",
+        ] {
+            assert!(validate_identifier(valid).is_ok(), "{}", valid);
+        }
+        for invalid in [
+            "--upload-pack=touch /tmp/x",
+            "ext::sh -c touch% /tmp/x",
+            "file:///etc/skills",
+            "/srv/skills",
+            "../skills",
+            "acme/../../etc",
+            "acme",
+            "https://-oProxyCommand=x/repo",
+        ] {
+            assert!(validate_identifier(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_mirror_repo_path() {
+        let mirror = Path::new("/mirror");
+        assert_eq!(
+            mirror_repo_path(mirror, "acme/skills"),
+            Some(PathBuf::from("/mirror/github.com/acme/skills.git"))
+        );
+        assert_eq!(
+            mirror_repo_path(mirror, "https://token@git.example.com:8443/team/skills.git"),
+            Some(PathBuf::from("/mirror/git.example.com/team/skills.git"))
+        );
+        assert_eq!(
+            mirror_repo_path(mirror, "git@github.com:acme/skills.git"),
+            Some(PathBuf::from("/mirror/github.com/acme/skills.git"))
+        );
+        assert_eq!(mirror_repo_path(mirror, "/srv/skills"), None);
+        assert_eq!(mirror_repo_path(mirror, "acme/../../etc"), None);
+
+        // Repositories missing from the mirror are fetched upstream
+        let source = RegistrySource::Mirror {
+            path: mirror.to_path_buf(),
+        };
+        assert_eq!(
+            source.repo_url("acme/skills"),
+            "https://github.com/acme/skills.git"
+        );
+    }
+
+    #[test]
+    fn test_index_search_ranking() {
+        let entry = |identifier: &str, name: &str, description: &str| RegistryIndexEntry {
+            identifier: identifier.to_string(),
+            name: name.to_string(),
+            path: format!("skills/{}", name),
+            description: Some(description.to_string()),
+            commit: None,
+        };
+        let index = RegistryIndex {
+            skills: vec![
+                entry("acme/docs", "docx", "Word documents"),
+                entry("acme/docs", "pdf-forms", "Fill PDF forms"),
+                entry("acme/docs", "pdf", "Read and write PDF files"),
+                entry("acme/misc", "extract", "Extract tables from a pdf"),
+                entry("acme/pdf-tools", "merge", "Merge files"),
+                entry("acme/misc", "compress-pdf", "Compress files"),
+            ],
+            ..Default::default()
+        };
+        let names: Vec<String> = index.search("PDF").into_iter().map(|s| s.name).collect();
+        assert_eq!(
+            names,
+            vec!["pdf", "pdf-forms", "compress-pdf", "merge", "extract"]
+        );
+        assert!(index.search("spreadsheet").is_empty());
+        assert_eq!(index.search("").len(), 6);
+    }
 }
@@ -13,6 +13,7 @@ use serde_json::{json, Value};
 use walkdir::WalkDir;
 
 use super::{resolve_path, Tool};
+use crate::glob::glob_match;
 
 /// List contents of a directory.
 pub struct ListDirectory;
@@ -186,37 +187,27 @@ impl Tool for SearchFiles {
     }
 }
 
-/// Simple glob pattern matching.
-fn glob_match(pattern: &str, text: &str) -> bool {
-    let parts: Vec<&str> = pattern.split('*').collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if parts.len() == 1 {
-        // No wildcards
-        return pattern == text;
-    }
-
-    let mut pos = 0;
-    for (i, part) in parts.iter().enumerate() {
-        if part.is_empty() {
-            continue;
-        }
-
-        match text[pos..].find(part) {
-            Some(idx) => {
-                // First part must be at start if pattern doesn't start with *
-                if i == 0 && idx != 0 {
-                    return false;
-                }
-                pos += idx + part.len();
-            }
-            None => return false,
+    #[tokio::test]
+    async fn search_files_anchors_glob_at_both_ends() {
+        let tmp = tempfile::tempdir().unwrap();
+        for name in ["a", "aa", "aba", "ab"] {
+            std::fs::write(tmp.path().join(name), "").unwrap();
         }
-    }
 
-    // Last part must be at end if pattern doesn't end with *
-    if !pattern.ends_with('*') && !parts.last().unwrap().is_empty() {
-        return text.ends_with(parts.last().unwrap());
+        let out = SearchFiles
+            .execute(json!({"pattern": "a*a"}), tmp.path())
+            .await
+            .unwrap();
+        let mut names: Vec<_> = out
+            .lines()
+            .map(|line| line.rsplit('/').next().unwrap())
+            .collect();
+        names.sort();
+        // `a` is not matched: the leading and trailing `a` cannot overlap
+        assert_eq!(names, vec!["aa", "aba"]);
     }
-
-    true
 }
@@ -15,6 +15,7 @@ use tokio::io::AsyncWriteExt;
 use walkdir::WalkDir;
 
 use super::{resolve_path_simple as resolve_path, Tool};
+use crate::glob::glob_match;
 
 fn default_index_dir(working_dir: &Path) -> PathBuf {
     working_dir.join(".sandboxed_sh").join("index")
@@ -303,32 +304,23 @@ impl Tool for SearchFileIndex {
     }
 }
 
-/// Simple glob pattern matching (supports '*' only).
-fn glob_match(pattern: &str, text: &str) -> bool {
-    let parts: Vec<&str> = pattern.split('*').collect();
-    if parts.len() == 1 {
-        return pattern == text;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn search_index_anchors_glob_at_both_ends() {
+        let tmp = tempfile::tempdir().unwrap();
+        let index = tmp.path().join("files.txt");
+        std::fs::write(&index, "/src/a\n/src/aa\n/src/aba\n/src/ab\n").unwrap();
+
+        let out = SearchFileIndex
+            .execute(
+                json!({"query": "*/a*a", "index_path": index.to_string_lossy()}),
+                tmp.path(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(out, "/src/aa\n/src/aba");
     }
-
-    let mut pos = 0usize;
-    for (i, part) in parts.iter().enumerate() {
-        if part.is_empty() {
-            continue;
-        }
-        match text[pos..].find(part) {
-            Some(idx) => {
-                if i == 0 && idx != 0 {
-                    return false;
-                }
-                pos += idx + part.len();
-            }
-            None => return false,
-        }
-    }
-
-    if !pattern.ends_with('*') && !parts.last().unwrap().is_empty() {
-        return text.ends_with(parts.last().unwrap());
-    }
-
-    true
 }
@@ -23,10 +23,13 @@ use uuid::Uuid;
 
 use crate::ai_providers::{AIProvider, ProviderType};
 use crate::config::Config;
+use crate::harness_config::{HarnessConfigFiles, HarnessSecrets};
 use crate::library::env_crypto::strip_encrypted_tags;
 use crate::library::LibraryStore;
-use crate::mcp::{McpRegistry, McpScope, McpServerConfig, McpTransport};
+use crate::mcp::{McpPolicies, McpRegistry, McpScope, McpServerConfig, McpTransport};
 use crate::nspawn::{self, NspawnDistro};
+use crate::secrets::SecretsStore;
+use crate::workspace_services::{ServiceDefinition, ServiceSupervisor};
 
 // ─────────────────────────────────────────────────────────────────────────────
 // Workspace Types
@@ -182,6 +185,9 @@ pub struct Workspace {
     /// Defaults to "default" if not specified.
     #[serde(default)]
     pub config_profile: Option<String>,
+    /// Background services supervised while the workspace is ready.
+    #[serde(default)]
+    pub services: Vec<ServiceDefinition>,
 }
 
 impl Workspace {
@@ -207,6 +213,7 @@ impl Workspace {
             tailscale_mode: None,
             mcps: Vec::new(),
             config_profile: None,
+            services: Vec::new(),
         }
     }
 
@@ -232,6 +239,7 @@ impl Workspace {
             shared_network: None,
             tailscale_mode: None,
             mcps: Vec::new(),
+            services: Vec::new(),
         }
     }
 }
@@ -245,19 +253,22 @@ pub struct WorkspaceStore {
     workspaces: RwLock<HashMap<Uuid, Workspace>>,
     storage_path: PathBuf,
     working_dir: PathBuf,
+    /// Supervisor for workspace background services
+    services: Arc<ServiceSupervisor>,
 }
 
 impl WorkspaceStore {
     /// Create a new workspace store, loading existing data from disk.
     ///
     /// This also scans for orphaned container directories and restores them.
-    pub async fn new(working_dir: PathBuf) -> Self {
+    pub async fn new(working_dir: PathBuf, secrets: Option<Arc<SecretsStore>>) -> Self {
         let storage_path = working_dir.join(".sandboxed-sh/workspaces.json");
 
         let store = Self {
             workspaces: RwLock::new(HashMap::new()),
             storage_path,
             working_dir: working_dir.clone(),
+            services: Arc::new(ServiceSupervisor::new(&working_dir, secrets)),
         };
 
         // Load existing workspaces from disk
@@ -423,6 +434,7 @@ impl WorkspaceStore {
                     tailscale_mode: None,
                     mcps: Vec::new(),
                     config_profile: None,
+                    services: Vec::new(),
                 };
 
                 orphaned.push(workspace);
@@ -458,35 +470,61 @@ impl WorkspaceStore {
         let id = workspace.id;
         {
             let mut guard = self.workspaces.write().await;
-            guard.insert(id, workspace);
+            guard.insert(id, workspace.clone());
         }
 
         if let Err(e) = self.save_to_disk().await {
             tracing::error!("Failed to save workspaces to disk: {}", e);
         }
 
+        self.services.start_workspace(&workspace).await;
+
         id
     }
 
     /// Update a workspace.
+    ///
+    /// Starts the workspace's autostart services when it transitions to ready.
     pub async fn update(&self, workspace: Workspace) -> bool {
-        let updated = {
+        let previous_status = {
             let mut guard = self.workspaces.write().await;
-            if guard.contains_key(&workspace.id) {
-                guard.insert(workspace.id, workspace);
-                true
-            } else {
-                false
+            match guard.get_mut(&workspace.id) {
+                Some(existing) => {
+                    let previous = existing.status;
+                    *existing = workspace.clone();
+                    previous
+                }
+                None => return false,
             }
         };
 
-        if updated {
-            if let Err(e) = self.save_to_disk().await {
-                tracing::error!("Failed to save workspaces to disk: {}", e);
-            }
+        if let Err(e) = self.save_to_disk().await {
+            tracing::error!("Failed to save workspaces to disk: {}", e);
         }
 
-        updated
+        let was_ready = previous_status == WorkspaceStatus::Ready;
+        let is_ready = workspace.status == WorkspaceStatus::Ready;
+        if !was_ready && is_ready {
+            self.services.start_workspace(&workspace).await;
+        } else if was_ready && !is_ready {
+            // Services run in the workspace being rebuilt (or failed); they
+            // start again once it is ready
+            self.services.stop_workspace(workspace.id).await;
+        }
+
+        true
+    }
+
+    /// Supervisor for workspace background services.
+    pub fn services(&self) -> &Arc<ServiceSupervisor> {
+        &self.services
+    }
+
+    /// Start autostart services of every ready workspace (server startup).
+    pub async fn start_ready_services(&self) {
+        for workspace in self.list().await {
+            self.services.start_workspace(&workspace).await;
+        }
     }
 
     /// Delete a workspace (cannot delete the default host workspace).
@@ -501,6 +539,7 @@ impl WorkspaceStore {
         };
 
         if existed {
+            self.services.stop_workspace(id).await;
             if let Err(e) = self.save_to_disk().await {
                 tracing::error!("Failed to save workspaces to disk: {}", e);
             }
@@ -607,7 +646,7 @@ fn opencode_entry_from_mcp(
     }
 
     match &config.transport {
-        McpTransport::Http { endpoint, headers } => {
+        McpTransport::Http { endpoint, headers } | McpTransport::Sse { endpoint, headers } => {
             let mut entry = serde_json::Map::new();
             entry.insert("type".to_string(), json!("http"));
             entry.insert("endpoint".to_string(), json!(endpoint));
@@ -822,6 +861,15 @@ fn claude_entry_from_mcp(
             }
             serde_json::Value::Object(entry)
         }
+        McpTransport::Sse { endpoint, headers } => {
+            let mut entry = serde_json::Map::new();
+            entry.insert("type".to_string(), json!("sse"));
+            entry.insert("url".to_string(), json!(endpoint));
+            if !headers.is_empty() {
+                entry.insert("headers".to_string(), json!(headers));
+            }
+            serde_json::Value::Object(entry)
+        }
         McpTransport::Stdio { .. } => {
             let opencode_entry = opencode_entry_from_mcp(
                 config,
@@ -883,6 +931,53 @@ async fn write_opencode_config(
     shared_network: Option<bool>,
     custom_providers: Option<&[AIProvider]>,
 ) -> anyhow::Result<()> {
+    let mut files = HarnessConfigFiles::default();
+    let target = HarnessConfigTarget {
+        dir: workspace_dir,
+        workspace_root,
+        workspace_type,
+        workspace_env,
+        shared_network,
+    };
+    render_opencode_config(
+        &mut files,
+        target,
+        mcp_configs,
+        skill_allowlist,
+        command_contents,
+        custom_providers,
+    )
+    .await?;
+    files.write_to(workspace_dir).await
+}
+
+/// The workspace a harness config is rendered for.
+#[derive(Clone, Copy)]
+struct HarnessConfigTarget<'a> {
+    /// Directory the config files are written to
+    dir: &'a Path,
+    workspace_root: &'a Path,
+    workspace_type: WorkspaceType,
+    workspace_env: &'a HashMap<String, String>,
+    shared_network: Option<bool>,
+}
+
+/// Render `opencode.json` (and commands as OpenCode skills) into `files`.
+async fn render_opencode_config(
+    files: &mut HarnessConfigFiles,
+    target: HarnessConfigTarget<'_>,
+    mcp_configs: Vec<McpServerConfig>,
+    skill_allowlist: Option<&[String]>,
+    command_contents: Option<&[CommandContent]>,
+    custom_providers: Option<&[AIProvider]>,
+) -> anyhow::Result<()> {
+    let HarnessConfigTarget {
+        dir: workspace_dir,
+        workspace_root,
+        workspace_type,
+        workspace_env,
+        shared_network,
+    } = target;
     fn strip_jsonc_comments(input: &str) -> String {
         let mut out = String::with_capacity(input.len());
         let mut chars = input.chars().peekable();
@@ -951,9 +1046,14 @@ async fn write_opencode_config(
         true
     });
 
+    // OpenCode names MCP tools `<server>_<tool>`
+    let mut denied_tools = Vec::new();
     for config in filtered_configs {
         let base = sanitize_key(&config.name);
         let key = unique_key(&base, &mut used);
+        for tool in &config.denied_tools {
+            denied_tools.push(format!("{}_{}", key, tool));
+        }
         mcp_map.insert(
             key,
             opencode_entry_from_mcp(
@@ -1039,6 +1139,9 @@ async fn write_opencode_config(
             tools.insert("browser_*".to_string(), json!(false));
         }
     }
+    for tool in denied_tools {
+        tools.insert(tool, json!(false));
+    }
     let mut base_config = serde_json::json!({});
     let base_dir = resolve_opencode_config_dir();
     let base_path = base_dir.join("opencode.json");
@@ -1177,46 +1280,39 @@ async fn write_opencode_config(
     let config_payload = serde_json::to_string_pretty(&config_value)?;
 
     // Write to workspace root
-    let config_path = workspace_dir.join("opencode.json");
-    tokio::fs::write(&config_path, &config_payload).await?;
+    files.insert("opencode.json", config_payload.clone());
 
     // Also write to .opencode/ for OpenCode config discovery
-    let opencode_dir = workspace_dir.join(".opencode");
-    tokio::fs::create_dir_all(&opencode_dir).await?;
-    let opencode_config_path = opencode_dir.join("opencode.json");
-    tokio::fs::write(opencode_config_path, config_payload).await?;
+    files.insert(".opencode/opencode.json", config_payload);
 
     // Write commands as skills for OpenCode (since OpenCode doesn't have a separate command system)
     if let Some(commands) = command_contents {
-        write_commands_as_opencode_skills(workspace_dir, commands).await?;
+        render_commands_as_opencode_skills(files, commands);
     }
 
     Ok(())
 }
 
-/// Write Claude Code configuration to the workspace.
+/// Render Claude Code configuration into `files`.
 /// Generates `.claude/settings.local.json` and `CLAUDE.md` files.
-async fn write_claudecode_config(
-    workspace_dir: &Path,
+fn render_claudecode_config(
+    files: &mut HarnessConfigFiles,
+    target: HarnessConfigTarget<'_>,
     mcp_configs: Vec<McpServerConfig>,
-    workspace_root: &Path,
-    workspace_type: WorkspaceType,
-    workspace_env: &HashMap<String, String>,
     skill_contents: Option<&[SkillContent]>,
     command_contents: Option<&[CommandContent]>,
-    shared_network: Option<bool>,
 ) -> anyhow::Result<()> {
-    // Create .claude directory
-    let claude_dir = workspace_dir.join(".claude");
-    tokio::fs::create_dir_all(&claude_dir).await?;
-
+    let HarnessConfigTarget {
+        dir: workspace_dir,
+        workspace_root,
+        workspace_type,
+        workspace_env,
+        shared_network,
+    } = target;
     let workspace_env_file = if !workspace_env.is_empty() {
-        let sandboxed_dir = workspace_dir.join(".sandboxed-sh");
-        tokio::fs::create_dir_all(&sandboxed_dir).await?;
-        let env_path = sandboxed_dir.join("workspace_env.json");
-        let payload = serde_json::to_string_pretty(workspace_env)?;
-        tokio::fs::write(&env_path, payload).await?;
-        Some(".sandboxed-sh/workspace_env.json".to_string())
+        let env_path = ".sandboxed-sh/workspace_env.json";
+        files.insert(env_path, serde_json::to_string_pretty(workspace_env)?);
+        Some(env_path.to_string())
     } else {
         None
     };
@@ -1227,9 +1323,13 @@ async fn write_claudecode_config(
 
     let filtered_configs = mcp_configs.into_iter().filter(|c| c.enabled);
 
+    let mut denied_tools = Vec::new();
     for config in filtered_configs {
         let base = sanitize_key(&config.name);
         let key = unique_key(&base, &mut used);
+        for tool in &config.denied_tools {
+            denied_tools.push(format!("mcp__{}__{}", key, tool));
+        }
         mcp_servers.insert(
             key,
             claude_entry_from_mcp(
@@ -1260,24 +1360,35 @@ async fn write_claudecode_config(
         WorkspaceType::Container => vec!["Bash", "Edit", "Write", "Read", "mcp__*"],
         WorkspaceType::Host => vec!["Bash", "Edit", "Write", "Read", "mcp__*"],
     };
-    let settings = json!({
-        "mcpServers": mcp_servers,
-        "permissions": {
-            "allow": permissions
-        }
-    });
-    let settings_path = claude_dir.join("settings.local.json");
-    let settings_content = serde_json::to_string_pretty(&settings)?;
-    tokio::fs::write(&settings_path, settings_content).await?;
+    // Deny rules take precedence over the `mcp__*` allow rule
+    let settings = if denied_tools.is_empty() {
+        json!({
+            "mcpServers": mcp_servers,
+            "permissions": {
+                "allow": permissions
+            }
+        })
+    } else {
+        json!({
+            "mcpServers": mcp_servers,
+            "permissions": {
+                "allow": permissions,
+                "deny": denied_tools
+            }
+        })
+    };
+    files.insert(
+        ".claude/settings.local.json",
+        serde_json::to_string_pretty(&settings)?,
+    );
 
     // Write skills to .claude/skills/ using Claude Code's native format
     // This allows Claude to discover and list skills properly
     if let Some(skills) = skill_contents {
-        write_claudecode_skills_to_workspace(workspace_dir, skills).await?;
+        render_claudecode_skills(files, skills);
 
         // Generate minimal CLAUDE.md with workspace context only
         // Skills are now in .claude/skills/ and Claude will discover them automatically
-        let claude_md_path = workspace_dir.join("CLAUDE.md");
         let mut claude_md = String::new();
         claude_md.push_str("# sandboxed.sh Workspace\n\n");
 
@@ -1302,35 +1413,35 @@ async fn write_claudecode_config(
             }
         }
 
-        tokio::fs::write(&claude_md_path, claude_md).await?;
+        files.insert("CLAUDE.md", claude_md);
     }
 
     // Write commands to .claude/commands/ using Claude Code's native custom slash command format
     if let Some(commands) = command_contents {
-        write_claudecode_commands_to_workspace(workspace_dir, commands).await?;
+        render_claudecode_commands(files, commands);
     }
 
     Ok(())
 }
 
-/// Write Amp configuration to the workspace.
+/// Render Amp configuration into `files`.
 /// Generates `AGENTS.md`, `.agents/skills/`, and optionally `settings.json`.
-async fn write_amp_config(
-    workspace_dir: &Path,
+fn render_amp_config(
+    files: &mut HarnessConfigFiles,
+    target: HarnessConfigTarget<'_>,
     mcp_configs: Vec<McpServerConfig>,
-    workspace_root: &Path,
-    workspace_type: WorkspaceType,
-    workspace_env: &HashMap<String, String>,
     skill_contents: Option<&[SkillContent]>,
-    _shared_network: Option<bool>,
 ) -> anyhow::Result<()> {
-    // Create .agents directory for skills
-    let agents_dir = workspace_dir.join(".agents");
-    tokio::fs::create_dir_all(&agents_dir).await?;
-
+    let HarnessConfigTarget {
+        dir: workspace_dir,
+        workspace_root,
+        workspace_type,
+        workspace_env,
+        ..
+    } = target;
     // Write skills to .agents/skills/ using Amp's native format
     if let Some(skills) = skill_contents {
-        write_amp_skills_to_workspace(workspace_dir, skills).await?;
+        render_amp_skills(files, skills);
     }
 
     // Build MCP servers config for Amp (amp.mcpServers format)
@@ -1339,9 +1450,15 @@ async fn write_amp_config(
 
     let filtered_configs = mcp_configs.into_iter().filter(|c| c.enabled);
 
+    // Amp applies the first matching rule, so rejections go first
+    let mut permissions: Vec<serde_json::Value> = Vec::new();
     for config in filtered_configs {
         let base = sanitize_key(&config.name);
         let key = unique_key(&base, &mut used);
+        for tool in &config.denied_tools {
+            permissions
+                .push(json!({ "tool": format!("mcp__{}__{}", key, tool), "action": "reject" }));
+        }
         mcp_servers.insert(
             key,
             amp_entry_from_mcp(
@@ -1356,26 +1473,24 @@ async fn write_amp_config(
 
     // Write settings.json if we have MCP servers or need permissions
     if !mcp_servers.is_empty() {
+        permissions.extend([
+            // Allow all bash commands in managed workspaces
+            json!({ "tool": "Bash", "action": "allow" }),
+            // Allow all file operations
+            json!({ "tool": "Read", "action": "allow" }),
+            json!({ "tool": "Write", "action": "allow" }),
+            json!({ "tool": "Edit", "action": "allow" }),
+            // Allow all MCP tools
+            json!({ "tool": "mcp__*", "action": "allow" }),
+        ]);
         let settings = json!({
             "amp.mcpServers": mcp_servers,
-            "amp.permissions": [
-                // Allow all bash commands in managed workspaces
-                { "tool": "Bash", "action": "allow" },
-                // Allow all file operations
-                { "tool": "Read", "action": "allow" },
-                { "tool": "Write", "action": "allow" },
-                { "tool": "Edit", "action": "allow" },
-                // Allow all MCP tools
-                { "tool": "mcp__*", "action": "allow" }
-            ]
+            "amp.permissions": permissions
         });
-        let settings_path = workspace_dir.join("settings.json");
-        let settings_content = serde_json::to_string_pretty(&settings)?;
-        tokio::fs::write(&settings_path, settings_content).await?;
+        files.insert("settings.json", serde_json::to_string_pretty(&settings)?);
     }
 
     // Write AGENTS.md with workspace context
-    let agents_md_path = workspace_dir.join("AGENTS.md");
     let mut agents_md = String::new();
     agents_md.push_str("# sandboxed.sh Workspace\n\n");
 
@@ -1414,7 +1529,7 @@ async fn write_amp_config(
         }
     }
 
-    tokio::fs::write(&agents_md_path, agents_md).await?;
+    files.insert("AGENTS.md", agents_md);
 
     Ok(())
 }
@@ -1432,7 +1547,7 @@ fn amp_entry_from_mcp(
     let mut entry = serde_json::Map::new();
 
     match &config.transport {
-        McpTransport::Http { endpoint, headers } => {
+        McpTransport::Http { endpoint, headers } | McpTransport::Sse { endpoint, headers } => {
             // HTTP/SSE-based MCP server
             entry.insert("url".to_string(), json!(endpoint));
             if !headers.is_empty() {
@@ -1460,54 +1575,39 @@ pub async fn write_amp_skills_to_workspace(
     workspace_dir: &Path,
     skills: &[SkillContent],
 ) -> anyhow::Result<()> {
-    let skills_dir = workspace_dir.join(".agents").join("skills");
+    let mut files = HarnessConfigFiles::default();
+    render_amp_skills(&mut files, skills);
+    files.write_to(workspace_dir).await?;
 
-    // Clean up old skills directory to remove stale skills
-    if skills_dir.exists() {
-        let _ = tokio::fs::remove_dir_all(&skills_dir).await;
-    }
+    tracing::info!(
+        count = skills.len(),
+        workspace = %workspace_dir.display(),
+        "Wrote Amp skills to workspace"
+    );
 
-    if skills.is_empty() {
-        return Ok(());
-    }
+    Ok(())
+}
 
-    tokio::fs::create_dir_all(&skills_dir).await?;
+/// Render skills into `.agents/skills/`, replacing whatever is there.
+pub(crate) fn render_amp_skills(files: &mut HarnessConfigFiles, skills: &[SkillContent]) {
+    files.manage_dir(".agents/skills");
 
     for skill in skills {
-        let skill_dir = skills_dir.join(&skill.name);
-        tokio::fs::create_dir_all(&skill_dir).await?;
+        let skill_dir = format!(".agents/skills/{}", skill.name);
 
         // Ensure skill content has required frontmatter fields for Amp
         let content_with_frontmatter =
             ensure_amp_skill_frontmatter(&skill.content, &skill.name, skill.description.as_deref());
+        files.insert(format!("{}/SKILL.md", skill_dir), content_with_frontmatter);
 
-        // Write SKILL.md
-        let skill_md_path = skill_dir.join("SKILL.md");
-        tokio::fs::write(&skill_md_path, &content_with_frontmatter).await?;
-
-        // Write additional files (preserving subdirectory structure)
+        // Additional files (preserving subdirectory structure)
         for (relative_path, file_content) in &skill.files {
-            let file_path = skill_dir.join(relative_path);
-            if let Some(parent) = file_path.parent() {
-                tokio::fs::create_dir_all(parent).await?;
-            }
-            tokio::fs::write(&file_path, file_content).await?;
+            files.insert(
+                format!("{}/{}", skill_dir, relative_path),
+                file_content.clone(),
+            );
         }
-
-        tracing::debug!(
-            skill = %skill.name,
-            workspace = %workspace_dir.display(),
-            "Wrote Amp skill to workspace"
-        );
     }
-
-    tracing::info!(
-        count = skills.len(),
-        workspace = %workspace_dir.display(),
-        "Wrote Amp skills to workspace"
-    );
-
-    Ok(())
 }
 
 /// Ensure the skill content has required YAML frontmatter fields for Amp.
@@ -1564,58 +1664,81 @@ pub async fn write_backend_config(
     shared_network: Option<bool>,
     custom_providers: Option<&[AIProvider]>,
 ) -> anyhow::Result<()> {
+    render_backend_config(
+        workspace_dir,
+        backend_id,
+        mcp_configs,
+        workspace_root,
+        workspace_type,
+        workspace_env,
+        skill_allowlist,
+        skill_contents,
+        command_contents,
+        shared_network,
+        custom_providers,
+    )
+    .await?
+    .write_to(workspace_dir)
+    .await
+}
+
+/// Render the backend-specific configuration without touching disk.
+/// Returns exactly the files [`write_backend_config`] would write.
+#[allow(clippy::too_many_arguments)]
+pub async fn render_backend_config(
+    workspace_dir: &Path,
+    backend_id: &str,
+    mcp_configs: Vec<McpServerConfig>,
+    workspace_root: &Path,
+    workspace_type: WorkspaceType,
+    workspace_env: &HashMap<String, String>,
+    skill_allowlist: Option<&[String]>,
+    skill_contents: Option<&[SkillContent]>,
+    command_contents: Option<&[CommandContent]>,
+    shared_network: Option<bool>,
+    custom_providers: Option<&[AIProvider]>,
+) -> anyhow::Result<HarnessConfigFiles> {
+    let mut files = HarnessConfigFiles::default();
+    let target = HarnessConfigTarget {
+        dir: workspace_dir,
+        workspace_root,
+        workspace_type,
+        workspace_env,
+        shared_network,
+    };
     match backend_id {
         "opencode" => {
-            write_opencode_config(
-                workspace_dir,
+            render_opencode_config(
+                &mut files,
+                target,
                 mcp_configs,
-                workspace_root,
-                workspace_type,
-                workspace_env,
                 skill_allowlist,
                 command_contents,
-                shared_network,
                 custom_providers,
             )
-            .await
+            .await?;
         }
         "claudecode" => {
             // Keep OpenCode config in sync for compatibility with existing execution pipeline.
-            write_opencode_config(
-                workspace_dir,
+            render_opencode_config(
+                &mut files,
+                target,
                 mcp_configs.clone(),
-                workspace_root,
-                workspace_type,
-                workspace_env,
                 skill_allowlist,
                 command_contents,
-                shared_network,
                 custom_providers,
             )
             .await?;
-            write_claudecode_config(
-                workspace_dir,
+            render_claudecode_config(
+                &mut files,
+                target,
                 mcp_configs,
-                workspace_root,
-                workspace_type,
-                workspace_env,
                 skill_contents,
                 command_contents,
-                shared_network,
-            )
-            .await
+            )?;
         }
         "amp" => {
-            write_amp_config(
-                workspace_dir,
-                mcp_configs,
-                workspace_root,
-                workspace_type,
-                workspace_env,
-                skill_contents,
-                shared_network,
-            )
-            .await
+            render_amp_config(&mut files, target, mcp_configs, skill_contents)?;
         }
         _ => {
             // Unknown backend - write OpenCode config as fallback
@@ -1623,20 +1746,18 @@ pub async fn write_backend_config(
                 backend = backend_id,
                 "Unknown backend, falling back to OpenCode config"
             );
-            write_opencode_config(
-                workspace_dir,
+            render_opencode_config(
+                &mut files,
+                target,
                 mcp_configs,
-                workspace_root,
-                workspace_type,
-                workspace_env,
                 skill_allowlist,
                 command_contents,
-                shared_network,
                 custom_providers,
             )
-            .await
+            .await?;
         }
     }
+    Ok(files)
 }
 
 /// Skill content to be written to the workspace.
@@ -1666,7 +1787,7 @@ pub struct CommandContent {
 
 /// Ensure the skill content has a `name` field in the YAML frontmatter.
 /// OpenCode requires `name` field for skill discovery.
-fn ensure_skill_name_in_frontmatter(content: &str, skill_name: &str) -> String {
+pub(crate) fn ensure_skill_name_in_frontmatter(content: &str, skill_name: &str) -> String {
     // Check if the content starts with YAML frontmatter
     if !content.starts_with("---") {
         // No frontmatter, add it with name field
@@ -1717,49 +1838,41 @@ pub async fn write_skills_to_workspace(
         return Ok(());
     }
 
-    let skills_dir = workspace_dir.join(".opencode").join("skill");
-    tokio::fs::create_dir_all(&skills_dir).await?;
+    let mut files = HarnessConfigFiles::default();
+    render_opencode_skills(&mut files, skills);
+    files.write_to(workspace_dir).await?;
+
+    tracing::info!(
+        count = skills.len(),
+        workspace = %workspace_dir.display(),
+        "Wrote skills to workspace"
+    );
+
+    Ok(())
+}
 
+/// Render skills as `.opencode/skill/<name>/` directories.
+fn render_opencode_skills(files: &mut HarnessConfigFiles, skills: &[SkillContent]) {
     for skill in skills {
-        let skill_dir = skills_dir.join(&skill.name);
-        tokio::fs::create_dir_all(&skill_dir).await?;
+        let skill_dir = format!(".opencode/skill/{}", skill.name);
 
         // Ensure skill content has required `name` field in frontmatter
         let content_with_name = ensure_skill_name_in_frontmatter(&skill.content, &skill.name);
 
         // Strip <encrypted> tags - deployed skills should have bare plaintext values
-        let content_for_workspace = strip_encrypted_tags(&content_with_name);
-
-        // Write SKILL.md
-        let skill_md_path = skill_dir.join("SKILL.md");
-        tokio::fs::write(&skill_md_path, &content_for_workspace).await?;
+        files.insert(
+            format!("{}/SKILL.md", skill_dir),
+            strip_encrypted_tags(&content_with_name),
+        );
 
-        // Write additional files (preserving subdirectory structure)
+        // Additional files (preserving subdirectory structure, e.g. "references/guide.md")
         for (relative_path, file_content) in &skill.files {
-            let file_path = skill_dir.join(relative_path);
-            // Create parent directories if needed (e.g., "references/guide.md")
-            if let Some(parent) = file_path.parent() {
-                tokio::fs::create_dir_all(parent).await?;
-            }
-            // Also strip encrypted tags from additional files
-            let file_content_stripped = strip_encrypted_tags(file_content);
-            tokio::fs::write(&file_path, file_content_stripped).await?;
+            files.insert(
+                format!("{}/{}", skill_dir, relative_path),
+                strip_encrypted_tags(file_content),
+            );
         }
-
-        tracing::debug!(
-            skill = %skill.name,
-            workspace = %workspace_dir.display(),
-            "Wrote skill to workspace"
-        );
     }
-
-    tracing::info!(
-        count = skills.len(),
-        workspace = %workspace_dir.display(),
-        "Wrote skills to workspace"
-    );
-
-    Ok(())
 }
 
 /// Write skill files to the workspace's `.claude/skills/` directory.
@@ -1772,34 +1885,36 @@ pub async fn write_claudecode_skills_to_workspace(
     workspace_dir: &Path,
     skills: &[SkillContent],
 ) -> anyhow::Result<()> {
-    let skills_dir = workspace_dir.join(".claude").join("skills");
+    let mut files = HarnessConfigFiles::default();
+    render_claudecode_skills(&mut files, skills);
+    files.write_to(workspace_dir).await?;
 
-    tracing::debug!(
+    tracing::info!(
+        count = skills.len(),
         workspace = %workspace_dir.display(),
-        skills_dir = %skills_dir.display(),
+        "Wrote Claude Code skills to workspace"
+    );
+
+    Ok(())
+}
+
+/// Render skills into `.claude/skills/`, replacing whatever is there.
+pub(crate) fn render_claudecode_skills(files: &mut HarnessConfigFiles, skills: &[SkillContent]) {
+    tracing::debug!(
         skill_count = skills.len(),
         skill_names = ?skills.iter().map(|s| &s.name).collect::<Vec<_>>(),
-        "Writing Claude Code skills to workspace"
+        "Rendering Claude Code skills"
     );
 
-    // Clean up old skills directory to remove stale skills
-    if skills_dir.exists() {
-        let _ = tokio::fs::remove_dir_all(&skills_dir).await;
-    }
+    files.manage_dir(".claude/skills");
 
     if skills.is_empty() {
-        tracing::warn!(
-            workspace = %workspace_dir.display(),
-            "No skills to write for Claude Code"
-        );
-        return Ok(());
+        tracing::warn!("No skills to write for Claude Code");
+        return;
     }
 
-    tokio::fs::create_dir_all(&skills_dir).await?;
-
     for skill in skills {
-        let skill_dir = skills_dir.join(&skill.name);
-        tokio::fs::create_dir_all(&skill_dir).await?;
+        let skill_dir = format!(".claude/skills/{}", skill.name);
 
         // Ensure skill content has required frontmatter fields for Claude Code
         let content_with_frontmatter = ensure_claudecode_skill_frontmatter(
@@ -1809,38 +1924,19 @@ pub async fn write_claudecode_skills_to_workspace(
         );
 
         // Strip <encrypted> tags - deployed skills should have bare plaintext values
-        let content_for_workspace = strip_encrypted_tags(&content_with_frontmatter);
-
-        // Write SKILL.md
-        let skill_md_path = skill_dir.join("SKILL.md");
-        tokio::fs::write(&skill_md_path, &content_for_workspace).await?;
+        files.insert(
+            format!("{}/SKILL.md", skill_dir),
+            strip_encrypted_tags(&content_with_frontmatter),
+        );
 
-        // Write additional files (preserving subdirectory structure)
+        // Additional files (preserving subdirectory structure, e.g. "references/guide.md")
         for (relative_path, file_content) in &skill.files {
-            let file_path = skill_dir.join(relative_path);
-            // Create parent directories if needed (e.g., "references/guide.md")
-            if let Some(parent) = file_path.parent() {
-                tokio::fs::create_dir_all(parent).await?;
-            }
-            // Also strip encrypted tags from additional files
-            let file_content_stripped = strip_encrypted_tags(file_content);
-            tokio::fs::write(&file_path, file_content_stripped).await?;
+            files.insert(
+                format!("{}/{}", skill_dir, relative_path),
+                strip_encrypted_tags(file_content),
+            );
         }
-
-        tracing::debug!(
-            skill = %skill.name,
-            workspace = %workspace_dir.display(),
-            "Wrote Claude Code skill to workspace"
-        );
     }
-
-    tracing::info!(
-        count = skills.len(),
-        workspace = %workspace_dir.display(),
-        "Wrote Claude Code skills to workspace"
-    );
-
-    Ok(())
 }
 
 /// Write command files to the workspace's `.claude/commands/` directory.
@@ -1849,50 +1945,33 @@ pub async fn write_claudecode_commands_to_workspace(
     workspace_dir: &Path,
     commands: &[CommandContent],
 ) -> anyhow::Result<()> {
-    let commands_dir = workspace_dir.join(".claude").join("commands");
+    let mut files = HarnessConfigFiles::default();
+    render_claudecode_commands(&mut files, commands);
+    files.write_to(workspace_dir).await?;
 
-    tracing::debug!(
+    tracing::info!(
+        count = commands.len(),
         workspace = %workspace_dir.display(),
-        commands_dir = %commands_dir.display(),
-        command_count = commands.len(),
-        command_names = ?commands.iter().map(|c| &c.name).collect::<Vec<_>>(),
-        "Writing Claude Code commands to workspace"
+        "Wrote Claude Code commands to workspace"
     );
 
-    // Clean up old commands directory to remove stale commands
-    if commands_dir.exists() {
-        let _ = tokio::fs::remove_dir_all(&commands_dir).await;
-    }
-
-    if commands.is_empty() {
-        tracing::debug!(
-            workspace = %workspace_dir.display(),
-            "No commands to write for Claude Code"
-        );
-        return Ok(());
-    }
+    Ok(())
+}
 
-    tokio::fs::create_dir_all(&commands_dir).await?;
+/// Render commands into `.claude/commands/`, replacing whatever is there.
+pub(crate) fn render_claudecode_commands(
+    files: &mut HarnessConfigFiles,
+    commands: &[CommandContent],
+) {
+    files.manage_dir(".claude/commands");
 
     for command in commands {
         // Claude Code commands are just markdown files, not directories
-        let command_path = commands_dir.join(format!("{}.md", command.name));
-        tokio::fs::write(&command_path, &command.content).await?;
-
-        tracing::debug!(
-            command = %command.name,
-            workspace = %workspace_dir.display(),
-            "Wrote Claude Code command to workspace"
+        files.insert(
+            format!(".claude/commands/{}.md", command.name),
+            command.content.clone(),
         );
     }
-
-    tracing::info!(
-        count = commands.len(),
-        workspace = %workspace_dir.display(),
-        "Wrote Claude Code commands to workspace"
-    );
-
-    Ok(())
 }
 
 /// Write commands as skills to the workspace's `.opencode/skill/` directory.
@@ -1905,25 +1984,9 @@ pub async fn write_commands_as_opencode_skills(
         return Ok(());
     }
 
-    let skills_dir = workspace_dir.join(".opencode").join("skill");
-    tokio::fs::create_dir_all(&skills_dir).await?;
-
-    for command in commands {
-        let skill_dir = skills_dir.join(&command.name);
-        tokio::fs::create_dir_all(&skill_dir).await?;
-
-        // Convert command to skill format with proper frontmatter
-        let skill_content = convert_command_to_skill_content(&command.content, &command.name);
-
-        let skill_md_path = skill_dir.join("SKILL.md");
-        tokio::fs::write(&skill_md_path, &skill_content).await?;
-
-        tracing::debug!(
-            command = %command.name,
-            workspace = %workspace_dir.display(),
-            "Wrote command as OpenCode skill"
-        );
-    }
+    let mut files = HarnessConfigFiles::default();
+    render_commands_as_opencode_skills(&mut files, commands);
+    files.write_to(workspace_dir).await?;
 
     tracing::info!(
         count = commands.len(),
@@ -1934,6 +1997,17 @@ pub async fn write_commands_as_opencode_skills(
     Ok(())
 }
 
+/// Render commands as `.opencode/skill/<name>/SKILL.md` files.
+fn render_commands_as_opencode_skills(files: &mut HarnessConfigFiles, commands: &[CommandContent]) {
+    for command in commands {
+        // Convert command to skill format with proper frontmatter
+        files.insert(
+            format!(".opencode/skill/{}/SKILL.md", command.name),
+            convert_command_to_skill_content(&command.content, &command.name),
+        );
+    }
+}
+
 /// Convert command content to skill format by ensuring proper frontmatter.
 fn convert_command_to_skill_content(content: &str, name: &str) -> String {
     // Check if the content starts with YAML frontmatter
@@ -1964,8 +2038,109 @@ fn convert_command_to_skill_content(content: &str, name: &str) -> String {
     content.to_string()
 }
 
+/// Check the frontmatter of an imported command.
+///
+/// Command frontmatter is passed through unchanged (Claude Code reads it
+/// natively and OpenCode gets the command as a skill), so it only has to
+/// parse; content without frontmatter is fine.
+pub(crate) fn normalize_command_frontmatter(content: &str) -> anyhow::Result<String> {
+    if content.starts_with("---") && crate::library::parse_frontmatter(content).0.is_none() {
+        anyhow::bail!("Invalid frontmatter");
+    }
+    Ok(content.to_string())
+}
+
+/// Convert the frontmatter of an imported agent to the library format.
+///
+/// Claude Code lists the tools an agent may use (`tools: Read, Grep` or a
+/// YAML list); the library uses OpenCode's mapping of tool name to enabled
+/// flag, so the list becomes `"*": false` plus an entry per tool
+/// (`mcp__github__search` → `github_search`). Fails on frontmatter that
+/// does not parse and on tools that cannot be converted.
+pub(crate) fn normalize_agent_frontmatter(content: &str) -> anyhow::Result<String> {
+    if !content.starts_with("---") {
+        return Ok(content.to_string());
+    }
+    let (Some(frontmatter), _) = crate::library::parse_frontmatter(content) else {
+        anyhow::bail!("Invalid frontmatter");
+    };
+    let allowed: Vec<String> = match frontmatter.get("tools") {
+        None | Some(serde_yaml::Value::Null) => return Ok(content.to_string()),
+        Some(serde_yaml::Value::Mapping(mapping)) => {
+            if mapping.iter().all(|(k, v)| k.is_string() && v.is_bool()) {
+                return Ok(content.to_string());
+            }
+            anyhow::bail!("`tools` must map tool names to true or false");
+        }
+        Some(serde_yaml::Value::String(list)) => list.split(',').map(str::to_string).collect(),
+        Some(serde_yaml::Value::Sequence(list)) => list
+            .iter()
+            .map(|tool| {
+                tool.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| anyhow::anyhow!("`tools` entries must be tool names"))
+            })
+            .collect::<anyhow::Result<_>>()?,
+        Some(_) => anyhow::bail!("`tools` must be a list of tool names or a mapping"),
+    };
+
+    let mut tools_block = String::from("tools:\n  \"*\": false\n");
+    for tool in allowed.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        tools_block.push_str(&format!("  \"{}\": true\n", opencode_tool_name(tool)?));
+    }
+
+    // Replace the `tools:` entry (and the indented list lines below it)
+    let normalized = content.replace("\r\n", "\n");
+    let end = normalized[3..]
+        .find("\n---")
+        .map(|i| i + 3)
+        .ok_or_else(|| anyhow::anyhow!("Invalid frontmatter"))?;
+    let mut lines = Vec::new();
+    let mut in_tools = false;
+    for line in normalized[3..end].lines() {
+        if in_tools && (line.starts_with(' ') || line.starts_with('\t') || line.starts_with('-')) {
+            continue;
+        }
+        in_tools = line.starts_with("tools:") || line.starts_with("tools :");
+        if in_tools {
+            continue;
+        }
+        // Claude descriptions often contain unquoted colons; the nested
+        // tools mapping is only readable if the whole block is valid YAML.
+        match line.strip_prefix("description:").map(str::trim) {
+            Some(desc) if !desc.starts_with('"') && !desc.starts_with('\'') => {
+                lines.push(format!("description: {}", format_yaml_description(desc)));
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+    let yaml = format!("{}\n{}", lines.join("\n"), tools_block);
+    if serde_yaml::from_str::<serde_yaml::Value>(&yaml).is_err() {
+        anyhow::bail!("Frontmatter is not valid YAML after converting `tools`");
+    }
+    Ok(format!("---{}{}", yaml, &normalized[end + 1..]))
+}
+
+/// OpenCode name of a Claude Code tool.
+fn opencode_tool_name(tool: &str) -> anyhow::Result<String> {
+    if let Some(mcp) = tool.strip_prefix("mcp__") {
+        return Ok(match mcp.split_once("__") {
+            Some((server, name)) => format!("{}_{}", server, name),
+            None => format!("{}_*", mcp),
+        });
+    }
+    if tool == "*"
+        || tool
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Ok(tool.to_ascii_lowercase());
+    }
+    anyhow::bail!("Cannot convert tool `{}` to an OpenCode tool name", tool)
+}
+
 /// Format a YAML description value, quoting if it contains special chars.
-fn format_yaml_description(desc: &str) -> String {
+pub(crate) fn format_yaml_description(desc: &str) -> String {
     let clean = desc.replace('\n', " ");
     // Quote if it contains colons, brackets, or other YAML special characters
     if clean.contains(':')
@@ -1995,7 +2170,7 @@ fn format_yaml_description(desc: &str) -> String {
 /// Ensure the skill content has proper YAML frontmatter for Claude Code.
 /// Claude Code requires `name` and benefits from `description` for auto-discovery.
 /// Also fixes invalid YAML descriptions that contain colons without quotes.
-fn ensure_claudecode_skill_frontmatter(
+pub(crate) fn ensure_claudecode_skill_frontmatter(
     content: &str,
     skill_name: &str,
     description: Option<&str>,
@@ -2277,19 +2452,9 @@ pub async fn write_agents_to_workspace(
         return Ok(());
     }
 
-    let agents_dir = workspace_dir.join(".opencode").join("agent");
-    tokio::fs::create_dir_all(&agents_dir).await?;
-
-    for agent in agents {
-        let agent_path = agents_dir.join(format!("{}.md", agent.name));
-        tokio::fs::write(&agent_path, &agent.content).await?;
-
-        tracing::debug!(
-            agent = %agent.name,
-            workspace = %workspace_dir.display(),
-            "Wrote agent to workspace"
-        );
-    }
+    let mut files = HarnessConfigFiles::default();
+    render_agents(&mut files, agents);
+    files.write_to(workspace_dir).await?;
 
     tracing::info!(
         count = agents.len(),
@@ -2300,26 +2465,26 @@ pub async fn write_agents_to_workspace(
     Ok(())
 }
 
-/// Sync library agents to a specific directory's `.opencode/agent/` folder.
-pub async fn sync_agents_to_dir(
-    target_dir: &Path,
-    agent_names: &[String],
-    context_name: &str,
-    library: &LibraryStore,
-) -> anyhow::Result<()> {
-    if agent_names.is_empty() {
-        tracing::debug!(
-            context = %context_name,
-            "No agents to sync"
+/// Render agents as `.opencode/agent/<name>.md` files.
+fn render_agents(files: &mut HarnessConfigFiles, agents: &[AgentContent]) {
+    for agent in agents {
+        files.insert(
+            format!(".opencode/agent/{}.md", agent.name),
+            agent.content.clone(),
         );
-        return Ok(());
     }
+}
 
-    let mut agents_to_write = Vec::new();
+async fn collect_agent_contents(
+    agent_names: &[String],
+    context_name: &str,
+    library: &LibraryStore,
+) -> Vec<AgentContent> {
+    let mut agents = Vec::new();
     for agent_name in agent_names {
         match library.get_library_agent(agent_name).await {
             Ok(agent) => {
-                agents_to_write.push(AgentContent {
+                agents.push(AgentContent {
                     name: agent.name,
                     content: agent.content,
                 });
@@ -2334,7 +2499,25 @@ pub async fn sync_agents_to_dir(
             }
         }
     }
+    agents
+}
 
+/// Sync library agents to a specific directory's `.opencode/agent/` folder.
+pub async fn sync_agents_to_dir(
+    target_dir: &Path,
+    agent_names: &[String],
+    context_name: &str,
+    library: &LibraryStore,
+) -> anyhow::Result<()> {
+    if agent_names.is_empty() {
+        tracing::debug!(
+            context = %context_name,
+            "No agents to sync"
+        );
+        return Ok(());
+    }
+
+    let agents_to_write = collect_agent_contents(agent_names, context_name, library).await;
     write_agents_to_workspace(target_dir, &agents_to_write).await?;
 
     tracing::info!(
@@ -2359,7 +2542,7 @@ async fn prepare_workspace_dir(path: &Path) -> anyhow::Result<PathBuf> {
 /// - Non-empty `workspace_mcps` → include only MCPs whose name is in the list
 ///
 /// In both cases, globally disabled MCPs are excluded.
-fn filter_mcp_configs_for_workspace(
+pub fn filter_mcp_configs_for_workspace(
     configs: Vec<McpServerConfig>,
     workspace_mcps: &[String],
 ) -> Vec<McpServerConfig> {
@@ -2420,7 +2603,13 @@ pub async fn prepare_mission_workspace_in(
 ) -> anyhow::Result<PathBuf> {
     let dir = mission_workspace_dir_for_root(&workspace.path, mission_id);
     prepare_workspace_dir(&dir).await?;
-    let mcp_configs = filter_mcp_configs_for_workspace(mcp.list_configs().await, &workspace.mcps);
+    let mcp_configs = mcp
+        .resolve_configs(filter_mcp_configs_for_workspace(
+            mcp.list_configs().await,
+            &workspace.mcps,
+        ))
+        .await;
+    let workspace_env = resolved_workspace_env(workspace, mcp.secrets()).await?;
     let skill_allowlist = if workspace.skills.is_empty() {
         None
     } else {
@@ -2431,7 +2620,7 @@ pub async fn prepare_mission_workspace_in(
         mcp_configs,
         &workspace.path,
         workspace.workspace_type,
-        &workspace.env_vars,
+        &workspace_env,
         skill_allowlist,
         None, // No command_contents for simple workspace preparation
         workspace.shared_network,
@@ -2441,6 +2630,34 @@ pub async fn prepare_mission_workspace_in(
     Ok(dir)
 }
 
+/// `workspace` with the secret references in its env vars resolved.
+///
+/// Missions, services and workspace MCPs resolve once when they start and
+/// run every command with the result, so the secrets store is not read (and
+/// audited) again for each command.
+pub async fn with_resolved_env(
+    workspace: &Workspace,
+    secrets: Option<&SecretsStore>,
+) -> anyhow::Result<Workspace> {
+    let mut resolved = workspace.clone();
+    resolved.env_vars = resolved_workspace_env(workspace, secrets).await?;
+    Ok(resolved)
+}
+
+/// Workspace env vars with their secret references resolved, for a
+/// launching mission or a workspace build step.
+async fn resolved_workspace_env(
+    workspace: &Workspace,
+    secrets: Option<&SecretsStore>,
+) -> anyhow::Result<HashMap<String, String>> {
+    crate::secrets::resolve_env_refs(
+        secrets,
+        &workspace.env_vars,
+        &format!("workspace {} env", workspace.name),
+    )
+    .await
+}
+
 /// Prepare a workspace directory for a mission with skill and tool syncing.
 /// This version syncs skills and tools from the workspace to the mission directory.
 pub async fn prepare_mission_workspace_with_skills(
@@ -2450,7 +2667,14 @@ pub async fn prepare_mission_workspace_with_skills(
     mission_id: Uuid,
 ) -> anyhow::Result<PathBuf> {
     prepare_mission_workspace_with_skills_backend(
-        workspace, mcp, library, mission_id, "opencode", None, None,
+        workspace,
+        mcp,
+        library,
+        mission_id,
+        &McpPolicies::default(),
+        "opencode",
+        None,
+        None,
     )
     .await
 }
@@ -2489,19 +2713,35 @@ fn read_custom_providers_from_file(workspace_root: &Path) -> Vec<AIProvider> {
     Vec::new()
 }
 
-/// Prepare a workspace directory for a mission with skill and tool syncing for a specific backend.
-pub async fn prepare_mission_workspace_with_skills_backend(
+/// oh-my-opencode settings file, relative to the mission directory.
+const OH_MY_OPENCODE_CONFIG: &str = ".opencode/oh-my-opencode.json";
+
+/// Render the harness config written into `target_dir` for a mission in
+/// `workspace` running `backend_id` with `config_profile`.
+///
+/// This is what [`prepare_mission_workspace_with_skills_backend`] writes, so
+/// it can also be previewed or checked for drift without touching disk.
+/// With the MCP gateway enabled, the global MCPs of `mission_id` are routed
+/// through its gateway. Tools denied by `tool_policy` are marked as denied.
+///
+/// With [`HarnessSecrets::Resolve`], secret references in workspace env vars
+/// and in the configs of the MCPs the workspace uses are resolved, so
+/// rendering fails while the secrets store is locked. Previews pass
+/// [`HarnessSecrets::Placeholders`] and read no secrets.
+#[allow(clippy::too_many_arguments)]
+pub async fn render_workspace_harness_config(
     workspace: &Workspace,
     mcp: &McpRegistry,
     library: Option<&LibraryStore>,
-    mission_id: Uuid,
+    mission_id: Option<Uuid>,
+    tool_policy: &McpPolicies,
+    target_dir: &Path,
     backend_id: &str,
     custom_providers: Option<&[AIProvider]>,
     config_profile: Option<&str>,
-) -> anyhow::Result<PathBuf> {
-    let dir = mission_workspace_dir_for_root(&workspace.path, mission_id);
-    prepare_workspace_dir(&dir).await?;
-
+    context: &str,
+    secrets: HarnessSecrets,
+) -> anyhow::Result<HarnessConfigFiles> {
     // Get custom providers: use provided list or read from file
     let providers_from_file;
     let effective_custom_providers = if let Some(providers) = custom_providers {
@@ -2515,6 +2755,27 @@ pub async fn prepare_mission_workspace_with_skills_backend(
         }
     };
     let mcp_configs = filter_mcp_configs_for_workspace(mcp.list_configs().await, &workspace.mcps);
+    let (mcp_configs, workspace_env) = match secrets {
+        HarnessSecrets::Resolve => (
+            mcp.resolve_configs(mcp_configs).await,
+            resolved_workspace_env(workspace, mcp.secrets()).await?,
+        ),
+        HarnessSecrets::Placeholders => (
+            McpRegistry::unresolved_configs(mcp_configs),
+            workspace.env_vars.clone(),
+        ),
+    };
+    let gateway_enforced = mission_id.is_some() && mcp.gateway().is_some();
+    let mut mcp_configs = tool_policy.apply(mcp_configs, gateway_enforced);
+    if let (Some(mission_id), Some(gateway)) = (mission_id, mcp.gateway()) {
+        mcp_configs = crate::mcp::gateway::gateway_configs(
+            mcp_configs,
+            gateway,
+            mcp.gateway_tokens(),
+            mission_id,
+            secrets,
+        )?;
+    }
     let skill_allowlist = if workspace.skills.is_empty() {
         None
     } else {
@@ -2522,15 +2783,14 @@ pub async fn prepare_mission_workspace_with_skills_backend(
     };
     let mut skill_contents: Option<Vec<SkillContent>> = None;
     let mut command_contents: Option<Vec<CommandContent>> = None;
+    let mut agent_contents: Vec<AgentContent> = Vec::new();
 
     if let Some(lib) = library {
-        let context = format!("mission-{}", mission_id);
-
         // Collect commands from library (for all backends)
-        let commands = collect_command_contents(&context, lib).await;
+        let commands = collect_command_contents(context, lib).await;
         if !commands.is_empty() {
             tracing::info!(
-                mission_id = %mission_id,
+                context = %context,
                 backend_id = %backend_id,
                 workspace = %workspace.name,
                 command_count = commands.len(),
@@ -2542,60 +2802,75 @@ pub async fn prepare_mission_workspace_with_skills_backend(
             command_contents = Some(commands);
         }
 
-        // Collect skills (only for claudecode and amp, which use skill contents directly)
-        if backend_id == "claudecode" || backend_id == "amp" {
-            let skill_names = match resolve_workspace_skill_names(workspace, lib).await {
-                Ok(names) => {
-                    tracing::debug!(
-                        mission_id = %mission_id,
-                        backend_id = %backend_id,
-                        workspace = %workspace.name,
-                        skill_count = names.len(),
-                        skills = ?names,
-                        "Resolved skill names for mission"
-                    );
-                    names
-                }
-                Err(e) => {
-                    tracing::warn!(
-                        mission_id = %mission_id,
-                        backend_id = %backend_id,
-                        workspace = %workspace.name,
-                        error = %e,
-                        "Failed to resolve skill names for mission, using empty list"
-                    );
-                    Vec::new()
-                }
-            };
-            let skills = collect_skill_contents(&skill_names, &context, lib).await;
-            tracing::info!(
-                mission_id = %mission_id,
-                backend_id = %backend_id,
-                workspace = %workspace.name,
-                skill_count = skills.len(),
-                skill_names = ?skill_names,
-                "Collected {} skills for {} backend",
-                skills.len(),
-                backend_id
-            );
-            skill_contents = Some(skills);
+        // Collect skills (rendered natively for claudecode and amp, and into
+        // `.opencode/skill/` for every backend)
+        let skill_names = match resolve_workspace_skill_names(workspace, lib).await {
+            Ok(names) => {
+                tracing::debug!(
+                    context = %context,
+                    backend_id = %backend_id,
+                    workspace = %workspace.name,
+                    skill_count = names.len(),
+                    skills = ?names,
+                    "Resolved skill names for harness config"
+                );
+                names
+            }
+            Err(e) => {
+                tracing::warn!(
+                    context = %context,
+                    backend_id = %backend_id,
+                    workspace = %workspace.name,
+                    error = %e,
+                    "Failed to resolve skill names for harness config, using empty list"
+                );
+                Vec::new()
+            }
+        };
+        let skills = collect_skill_contents(&skill_names, context, lib).await;
+        tracing::info!(
+            context = %context,
+            backend_id = %backend_id,
+            workspace = %workspace.name,
+            skill_count = skills.len(),
+            skill_names = ?skill_names,
+            "Collected {} skills for {} backend",
+            skills.len(),
+            backend_id
+        );
+        skill_contents = Some(skills);
+
+        // Library agents (used by mission agent selection)
+        match lib.list_library_agents().await {
+            Ok(agents) => {
+                let agent_names: Vec<String> = agents.into_iter().map(|a| a.name).collect();
+                agent_contents = collect_agent_contents(&agent_names, context, lib).await;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    context = %context,
+                    workspace = %workspace.name,
+                    error = %e,
+                    "Failed to list library agents"
+                );
+            }
         }
     } else {
         tracing::warn!(
-            mission_id = %mission_id,
+            context = %context,
             backend_id = %backend_id,
             workspace = %workspace.name,
-            "Library not available, cannot sync skills/commands to mission workspace"
+            "Library not available, cannot render skills/commands into harness config"
         );
     }
 
-    write_backend_config(
-        &dir,
+    let mut files = render_backend_config(
+        target_dir,
         backend_id,
         mcp_configs,
         &workspace.path,
         workspace.workspace_type,
-        &workspace.env_vars,
+        &workspace_env,
         skill_allowlist,
         skill_contents.as_deref(),
         command_contents.as_deref(),
@@ -2604,12 +2879,19 @@ pub async fn prepare_mission_workspace_with_skills_backend(
     )
     .await?;
 
-    // Sync oh-my-opencode settings into the mission directory when using OpenCode.
+    // Skills and agents are also synced into `.opencode/` for every backend.
+    if let Some(skills) = skill_contents.as_deref().filter(|s| !s.is_empty()) {
+        files.manage_dir(".opencode/skill");
+        render_opencode_skills(&mut files, skills);
+    }
+    render_agents(&mut files, &agent_contents);
+
+    // oh-my-opencode settings from the config profile when using OpenCode.
     if backend_id == "opencode" {
         if let Some(lib) = library {
             let profile = config_profile.unwrap_or("default");
             tracing::info!(
-                mission = %mission_id,
+                context = %context,
                 workspace = %workspace.name,
                 profile = %profile,
                 "Loading oh-my-opencode settings from profile"
@@ -2617,65 +2899,28 @@ pub async fn prepare_mission_workspace_with_skills_backend(
             match lib.get_opencode_settings_for_profile(profile).await {
                 Ok(settings) => {
                     if !settings.as_object().map(|o| o.is_empty()).unwrap_or(true) {
-                        let opencode_dir = dir.join(".opencode");
-                        if let Err(e) = tokio::fs::create_dir_all(&opencode_dir).await {
-                            tracing::warn!(
-                                mission = %mission_id,
-                                workspace = %workspace.name,
-                                error = %e,
-                                "Failed to create .opencode directory for OpenCode settings"
-                            );
-                        } else {
-                            let dest_path = opencode_dir.join("oh-my-opencode.json");
-                            match serde_json::to_string_pretty(&settings) {
-                                Ok(content) => {
-                                    // Patch agent models for Claude Code OAuth compatibility
-                                    let patched_content =
-                                        patch_opencode_agent_models_for_oauth(&content);
-
-                                    let jsonc_path = opencode_dir.join("oh-my-opencode.jsonc");
-                                    if jsonc_path.exists() {
-                                        if let Err(e) = tokio::fs::remove_file(&jsonc_path).await {
-                                            tracing::warn!(
-                                                mission = %mission_id,
-                                                workspace = %workspace.name,
-                                                error = %e,
-                                                "Failed to remove oh-my-opencode.jsonc"
-                                            );
-                                        }
-                                    }
-                                    if let Err(e) =
-                                        tokio::fs::write(&dest_path, &patched_content).await
-                                    {
-                                        tracing::warn!(
-                                            mission = %mission_id,
-                                            workspace = %workspace.name,
-                                            error = %e,
-                                            "Failed to write oh-my-opencode settings"
-                                        );
-                                    } else {
-                                        tracing::info!(
-                                            mission = %mission_id,
-                                            path = %dest_path.display(),
-                                            "Synced oh-my-opencode settings to mission directory"
-                                        );
-                                    }
-                                }
-                                Err(e) => {
-                                    tracing::warn!(
-                                        mission = %mission_id,
-                                        workspace = %workspace.name,
-                                        error = %e,
-                                        "Failed to serialize oh-my-opencode settings"
-                                    );
-                                }
+                        match serde_json::to_string_pretty(&settings) {
+                            Ok(content) => {
+                                // Patch agent models for Claude Code OAuth compatibility
+                                files.insert(
+                                    OH_MY_OPENCODE_CONFIG,
+                                    patch_opencode_agent_models_for_oauth(&content),
+                                );
+                            }
+                            Err(e) => {
+                                tracing::warn!(
+                                    context = %context,
+                                    workspace = %workspace.name,
+                                    error = %e,
+                                    "Failed to serialize oh-my-opencode settings"
+                                );
                             }
                         }
                     }
                 }
                 Err(e) => {
                     tracing::warn!(
-                        mission = %mission_id,
+                        context = %context,
                         workspace = %workspace.name,
                         error = %e,
                         "Failed to load oh-my-opencode settings from library"
@@ -2685,57 +2930,78 @@ pub async fn prepare_mission_workspace_with_skills_backend(
         }
     }
 
-    // Sync skills and tools from workspace to mission directory
-    if let Some(lib) = library {
-        let context = format!("mission-{}", mission_id);
+    Ok(files)
+}
 
-        // Sync skills
-        let skill_names = match resolve_workspace_skill_names(workspace, lib).await {
-            Ok(names) => names,
-            Err(e) => {
-                tracing::warn!(
-                    mission = %mission_id,
-                    workspace = %workspace.name,
-                    error = %e,
-                    "Failed to resolve skills from library"
-                );
-                Vec::new()
-            }
-        };
-        if !skill_names.is_empty() {
-            if let Err(e) = sync_skills_to_dir(&dir, &skill_names, &context, lib).await {
-                tracing::warn!(
-                    mission = %mission_id,
-                    workspace = %workspace.name,
-                    error = %e,
-                    "Failed to sync skills to mission directory"
-                );
-            }
-        }
+/// Lock serializing the harness config writes to one mission directory.
+///
+/// Missions are prepared on launch and re-rendered in the background after
+/// library changes; without it, two writes could interleave in the same
+/// directory.
+fn mission_dir_lock(dir: &Path) -> Arc<tokio::sync::Mutex<()>> {
+    static LOCKS: std::sync::OnceLock<
+        std::sync::Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>,
+    > = std::sync::OnceLock::new();
+    let mut locks = LOCKS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    // Drop the locks no one holds or waits for
+    locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+    Arc::clone(locks.entry(dir.to_path_buf()).or_default())
+}
 
-        // Sync library agents (used by mission agent selection)
-        let agent_names = match lib.list_library_agents().await {
-            Ok(agents) => agents.into_iter().map(|agent| agent.name).collect(),
-            Err(e) => {
-                tracing::warn!(
-                    mission = %mission_id,
-                    workspace = %workspace.name,
-                    error = %e,
-                    "Failed to list library agents"
-                );
-                Vec::new()
-            }
-        };
-        if !agent_names.is_empty() {
-            if let Err(e) = sync_agents_to_dir(&dir, &agent_names, &context, lib).await {
+/// Prepare a workspace directory for a mission with skill and tool syncing for a specific backend.
+#[allow(clippy::too_many_arguments)]
+pub async fn prepare_mission_workspace_with_skills_backend(
+    workspace: &Workspace,
+    mcp: &McpRegistry,
+    library: Option<&LibraryStore>,
+    mission_id: Uuid,
+    tool_policy: &McpPolicies,
+    backend_id: &str,
+    custom_providers: Option<&[AIProvider]>,
+    config_profile: Option<&str>,
+) -> anyhow::Result<PathBuf> {
+    let dir = mission_workspace_dir_for_root(&workspace.path, mission_id);
+    let lock = mission_dir_lock(&dir);
+    let _guard = lock.lock().await;
+    prepare_workspace_dir(&dir).await?;
+
+    let context = format!("mission-{}", mission_id);
+    let files = render_workspace_harness_config(
+        workspace,
+        mcp,
+        library,
+        Some(mission_id),
+        tool_policy,
+        &dir,
+        backend_id,
+        custom_providers,
+        config_profile,
+        &context,
+        HarnessSecrets::Resolve,
+    )
+    .await?;
+    files.write_to(&dir).await?;
+
+    // A leftover .jsonc would shadow the oh-my-opencode settings we just wrote.
+    if files.files.contains_key(OH_MY_OPENCODE_CONFIG) {
+        let jsonc_path = dir.join(".opencode").join("oh-my-opencode.jsonc");
+        if jsonc_path.exists() {
+            if let Err(e) = tokio::fs::remove_file(&jsonc_path).await {
                 tracing::warn!(
                     mission = %mission_id,
                     workspace = %workspace.name,
                     error = %e,
-                    "Failed to sync agents to mission directory"
+                    "Failed to remove oh-my-opencode.jsonc"
                 );
             }
         }
+        tracing::info!(
+            mission = %mission_id,
+            "Synced oh-my-opencode settings to mission directory"
+        );
     }
 
     Ok(dir)
@@ -2749,7 +3015,7 @@ pub async fn prepare_task_workspace(
 ) -> anyhow::Result<PathBuf> {
     let dir = task_workspace_dir_for_root(&config.working_dir, task_id);
     prepare_workspace_dir(&dir).await?;
-    let mcp_configs = mcp.list_configs().await;
+    let mcp_configs = mcp.resolve_configs(mcp.list_configs().await).await;
     let workspace_env = HashMap::new();
     write_opencode_config(
         &dir,
@@ -3092,6 +3358,7 @@ pub async fn build_container_workspace(
     force_rebuild: bool,
     working_dir: &Path,
     library: Option<&LibraryStore>,
+    secrets: Option<&SecretsStore>,
 ) -> anyhow::Result<()> {
     if workspace.workspace_type != WorkspaceType::Container {
         return Err(anyhow::anyhow!("Workspace is not a container type"));
@@ -3205,7 +3472,7 @@ pub async fn build_container_workspace(
             if has_init_scripts || has_custom_script {
                 append_to_init_log(&workspace.path, "[sandboxed] Running init script...\n");
             }
-            if let Err(e) = run_workspace_init_script(workspace, library).await {
+            if let Err(e) = run_workspace_init_script(workspace, library, secrets).await {
                 append_to_init_log(
                     &workspace.path,
                     &format!("[sandboxed] Init script failed: {}\n", e),
@@ -3216,7 +3483,7 @@ pub async fn build_container_workspace(
                 return Err(e);
             }
             append_to_init_log(&workspace.path, "[sandboxed] Installing harnesses...\n");
-            if let Err(e) = bootstrap_workspace_harnesses(workspace).await {
+            if let Err(e) = bootstrap_workspace_harnesses(workspace, secrets).await {
                 tracing::warn!(
                     workspace = %workspace.name,
                     error = %e,
@@ -3329,7 +3596,10 @@ fn env_var_bool(name: &str, default: bool) -> bool {
     }
 }
 
-async fn bootstrap_workspace_harnesses(workspace: &Workspace) -> anyhow::Result<()> {
+async fn bootstrap_workspace_harnesses(
+    workspace: &Workspace,
+    secrets: Option<&SecretsStore>,
+) -> anyhow::Result<()> {
     if workspace.workspace_type != WorkspaceType::Container || !use_nspawn_for_workspace(workspace)
     {
         return Ok(());
@@ -3415,7 +3685,7 @@ echo "[sandboxed] Harness bootstrap done"
     };
 
     let mut config = nspawn::NspawnConfig::default();
-    config.env = workspace.env_vars.clone();
+    config.env = resolved_workspace_env(workspace, secrets).await?;
 
     let command = vec![shell.to_string(), "/sandboxed-bootstrap.sh".to_string()];
     let output = nspawn::execute_in_container(&workspace.path, &command, &config).await?;
@@ -3447,6 +3717,7 @@ echo "[sandboxed] Harness bootstrap done"
 async fn run_workspace_init_script(
     workspace: &Workspace,
     library: Option<&LibraryStore>,
+    secrets: Option<&SecretsStore>,
 ) -> anyhow::Result<()> {
     let has_fragments = !workspace.init_scripts.is_empty();
     let custom_script = workspace
@@ -3537,7 +3808,7 @@ async fn run_workspace_init_script(
     };
 
     let mut config = nspawn::NspawnConfig::default();
-    config.env = workspace.env_vars.clone();
+    config.env = resolved_workspace_env(workspace, secrets).await?;
 
     let command = vec![shell.to_string(), "/sandboxed-init.sh".to_string()];
     let output = nspawn::execute_in_container(&workspace.path, &command, &config).await?;
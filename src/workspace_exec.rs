@@ -56,6 +56,11 @@ impl WorkspaceExec {
         }
     }
 
+    /// Merge the workspace env vars with `extra_env`.
+    ///
+    /// Values are used as given: secret references in the workspace env are
+    /// resolved once by the caller (see [`crate::workspace::with_resolved_env`]),
+    /// not for every command.
     fn build_env(&self, extra_env: HashMap<String, String>) -> HashMap<String, String> {
         let mut merged = self.workspace.env_vars.clone();
         merged.extend(extra_env);
@@ -250,7 +255,9 @@ impl WorkspaceExec {
             .filter(|s| !s.trim().is_empty())
     }
 
-    async fn running_container_leader(&self) -> Option<String> {
+    /// PID of the running container's leader process, if the container is up.
+    /// Its `/proc/<pid>/net` view reflects the container's network namespace.
+    pub(crate) async fn running_container_leader(&self) -> Option<String> {
         let name = self.machine_name()?;
         let machinectl = if Path::new("/usr/bin/machinectl").exists() {
             "/usr/bin/machinectl"
@@ -596,4 +603,37 @@ impl WorkspaceExec {
         let child = cmd.spawn().context("Failed to spawn workspace command")?;
         Ok(child)
     }
+
+    /// Spawn a long-running background service.
+    ///
+    /// Unlike `spawn_streaming`, stdin is closed and the process becomes the
+    /// leader of a new process group so the supervisor can signal the whole
+    /// tree (shell, nsenter/nspawn and the program) when stopping it.
+    pub async fn spawn_service(
+        &self,
+        cwd: &Path,
+        program: &str,
+        args: &[String],
+        env: HashMap<String, String>,
+    ) -> anyhow::Result<Child> {
+        let env = self.build_env(env);
+        let mut cmd = self
+            .build_command(
+                cwd,
+                program,
+                args,
+                env,
+                Stdio::null(),
+                Stdio::piped(),
+                Stdio::piped(),
+            )
+            .await
+            .context("Failed to build workspace command")?;
+        #[cfg(unix)]
+        cmd.process_group(0);
+        cmd.kill_on_drop(true);
+
+        let child = cmd.spawn().context("Failed to spawn workspace service")?;
+        Ok(child)
+    }
 }
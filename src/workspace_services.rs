@@ -0,0 +1,960 @@
+//! Managed background services for workspaces.
+//!
+//! Workspace templates can declare long-running helpers (databases, dev
+//! servers, the desktop X session) instead of having agents start them ad hoc
+//! through `run_command`. The [`ServiceSupervisor`] owned by `WorkspaceStore`:
+//! - Starts `autostart` services once the workspace is ready
+//! - Restarts crashed or unhealthy services according to their restart policy,
+//!   with exponential backoff
+//! - Captures stdout/stderr into an in-memory tail and a log file under
+//!   `.sandboxed-sh/services/<workspace-id>/<name>.log`, rotated to
+//!   `<name>.log.1` once it reaches [`LOG_FILE_MAX_BYTES`]
+//!
+//! Services run through [`WorkspaceExec`], so container services live inside
+//! the container like any other workspace process.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+use tokio::process::Child;
+use tokio::sync::{watch, RwLock};
+use uuid::Uuid;
+
+use crate::secrets::SecretsStore;
+use crate::workspace::{Workspace, WorkspaceStatus};
+use crate::workspace_exec::WorkspaceExec;
+
+/// Number of log lines kept in memory per service.
+const LOG_TAIL_CAPACITY: usize = 1000;
+
+/// Size at which a service log file is rotated; one rotated file is kept.
+pub const LOG_FILE_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Chunk size for reading the tail of a log file backwards.
+const LOG_READ_CHUNK: u64 = 64 * 1024;
+
+/// First restart delay after a crash.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Upper bound for the restart delay.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A service that stays up this long resets its restart backoff.
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+/// Grace period between SIGTERM and SIGKILL when stopping a service.
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_health_interval() -> u64 {
+    30
+}
+
+fn default_health_timeout() -> u64 {
+    10
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Service Definitions
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// When a service should be restarted after its process exits.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartPolicy {
+    /// Never restart automatically
+    Never,
+    /// Restart on non-zero exit or failed health checks
+    #[default]
+    OnFailure,
+    /// Always restart, even after a clean exit
+    Always,
+}
+
+/// Health check run periodically inside the workspace.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServiceHealthCheck {
+    /// Shell command; exit code 0 means healthy (e.g. `pg_isready`)
+    pub command: String,
+    /// Seconds between checks (default: 30)
+    #[serde(default = "default_health_interval")]
+    pub interval_secs: u64,
+    /// Seconds before a check counts as failed (default: 10)
+    #[serde(default = "default_health_timeout")]
+    pub timeout_secs: u64,
+    /// Consecutive failures before the service is restarted (default: 3)
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+}
+
+/// A background service declared on a workspace or workspace template.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServiceDefinition {
+    /// Unique name within the workspace (e.g. "postgres", "vite")
+    pub name: String,
+    /// Shell command to run (executed with `/bin/sh -lc`)
+    pub command: String,
+    /// Extra environment variables (merged over the workspace env)
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Working directory, relative to the workspace root (default: root);
+    /// may not contain `..`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Restart policy (default: on_failure)
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    /// Optional health check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<ServiceHealthCheck>,
+    /// Start automatically when the workspace becomes ready (default: true)
+    #[serde(default = "default_true")]
+    pub autostart: bool,
+}
+
+/// Validate a list of service definitions (names and commands).
+pub fn validate_services(services: &[ServiceDefinition]) -> Result<(), String> {
+    let mut seen = std::collections::HashSet::new();
+    for service in services {
+        let name = service.name.trim();
+        if name.is_empty() {
+            return Err("Service name cannot be empty".to_string());
+        }
+        if name.contains("..") || name.contains('/') || name.contains('\\') {
+            return Err(format!(
+                "Service name '{}' contains invalid characters",
+                name
+            ));
+        }
+        if service.command.trim().is_empty() {
+            return Err(format!("Service '{}' has an empty command", name));
+        }
+        if let Some(cwd) = service.cwd.as_deref() {
+            if Path::new(cwd)
+                .components()
+                .any(|c| matches!(c, Component::ParentDir))
+            {
+                return Err(format!(
+                    "Service '{}' has a working directory outside the workspace: {}",
+                    name, cwd
+                ));
+            }
+        }
+        if !seen.insert(name) {
+            return Err(format!("Duplicate service name '{}'", name));
+        }
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Service Status
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Lifecycle state of a managed service.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceState {
+    /// Not running (never started or stopped on request)
+    Stopped,
+    /// Process is being spawned
+    Starting,
+    /// Process is running
+    Running,
+    /// Waiting before the next restart attempt
+    Backoff,
+    /// Exited cleanly and will not be restarted
+    Exited,
+    /// Exited with an error and will not be restarted
+    Failed,
+}
+
+/// Result of the most recent health check.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceHealth {
+    Unknown,
+    Healthy,
+    Unhealthy,
+}
+
+/// Status snapshot of a service, as returned by the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceStatus {
+    pub name: String,
+    pub workspace_id: Uuid,
+    pub state: ServiceState,
+    pub health: ServiceHealth,
+    /// PID of the supervised process (host PID namespace)
+    pub pid: Option<u32>,
+    /// Number of automatic restarts since the service was last started
+    pub restarts: u32,
+    pub started_at: Option<DateTime<Utc>>,
+    pub last_exit_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub definition: ServiceDefinition,
+}
+
+impl ServiceStatus {
+    fn stopped(workspace_id: Uuid, definition: ServiceDefinition) -> Self {
+        Self {
+            name: definition.name.clone(),
+            workspace_id,
+            state: ServiceState::Stopped,
+            health: ServiceHealth::Unknown,
+            pid: None,
+            restarts: 0,
+            started_at: None,
+            last_exit_code: None,
+            last_error: None,
+            definition,
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Supervisor
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A service instance tracked by the supervisor.
+struct ManagedService {
+    status: RwLock<ServiceStatus>,
+    logs: Mutex<VecDeque<String>>,
+    stop_tx: watch::Sender<bool>,
+}
+
+impl ManagedService {
+    fn push_log(&self, line: String) {
+        if let Ok(mut logs) = self.logs.lock() {
+            if logs.len() >= LOG_TAIL_CAPACITY {
+                logs.pop_front();
+            }
+            logs.push_back(line);
+        }
+    }
+
+    async fn is_active(&self) -> bool {
+        matches!(
+            self.status.read().await.state,
+            ServiceState::Starting | ServiceState::Running | ServiceState::Backoff
+        )
+    }
+}
+
+/// Why a supervised process stopped.
+enum RunOutcome {
+    /// The process exited on its own
+    Exited(Option<i32>),
+    /// Health checks failed `failure_threshold` times in a row
+    Unhealthy,
+    /// A stop was requested
+    StopRequested,
+}
+
+/// Supervises background services across all workspaces.
+pub struct ServiceSupervisor {
+    log_root: PathBuf,
+    services: RwLock<HashMap<Uuid, HashMap<String, Arc<ManagedService>>>>,
+    /// Resolves the secret references in service env vars
+    secrets: Option<Arc<SecretsStore>>,
+}
+
+impl ServiceSupervisor {
+    pub fn new(working_dir: &Path, secrets: Option<Arc<SecretsStore>>) -> Self {
+        Self {
+            log_root: working_dir.join(".sandboxed-sh").join("services"),
+            services: RwLock::new(HashMap::new()),
+            secrets,
+        }
+    }
+
+    fn log_path(&self, workspace_id: Uuid, name: &str) -> PathBuf {
+        self.log_root
+            .join(workspace_id.to_string())
+            .join(format!("{}.log", name))
+    }
+
+    async fn managed(&self, workspace_id: Uuid, name: &str) -> Option<Arc<ManagedService>> {
+        self.services
+            .read()
+            .await
+            .get(&workspace_id)
+            .and_then(|m| m.get(name))
+            .cloned()
+    }
+
+    /// Start every `autostart` service of a ready workspace that isn't running.
+    pub async fn start_workspace(&self, workspace: &Workspace) {
+        if workspace.status != WorkspaceStatus::Ready {
+            return;
+        }
+        for definition in workspace.services.iter().filter(|s| s.autostart) {
+            if let Err(e) = self.start(workspace, &definition.name).await {
+                tracing::warn!(
+                    workspace = %workspace.name,
+                    service = %definition.name,
+                    error = %e,
+                    "Failed to start workspace service"
+                );
+            }
+        }
+    }
+
+    /// Start a service (no-op if it is already running).
+    pub async fn start(&self, workspace: &Workspace, name: &str) -> anyhow::Result<ServiceStatus> {
+        let definition = workspace
+            .services
+            .iter()
+            .find(|s| s.name == name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Service not found: {}", name))?;
+        if workspace.status != WorkspaceStatus::Ready {
+            anyhow::bail!(
+                "Workspace {} is not ready (status: {:?})",
+                workspace.name,
+                workspace.status
+            );
+        }
+
+        let mut guard = self.services.write().await;
+        let by_name = guard.entry(workspace.id).or_default();
+        if let Some(existing) = by_name.get(name) {
+            if existing.is_active().await {
+                return Ok(existing.status.read().await.clone());
+            }
+        }
+
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let mut status = ServiceStatus::stopped(workspace.id, definition);
+        status.state = ServiceState::Starting;
+        let service = Arc::new(ManagedService {
+            status: RwLock::new(status.clone()),
+            logs: Mutex::new(VecDeque::new()),
+            stop_tx,
+        });
+        by_name.insert(name.to_string(), Arc::clone(&service));
+        drop(guard);
+
+        let log_path = self.log_path(workspace.id, name);
+        tokio::spawn(run_service(
+            service,
+            workspace.clone(),
+            self.secrets.clone(),
+            log_path,
+            stop_rx,
+        ));
+        tracing::info!(workspace = %workspace.name, service = %name, "Started workspace service");
+        Ok(status)
+    }
+
+    /// Stop a service and wait for its process to exit.
+    pub async fn stop(&self, workspace_id: Uuid, name: &str) -> anyhow::Result<()> {
+        let service = self
+            .managed(workspace_id, name)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Service is not running: {}", name))?;
+        let _ = service.stop_tx.send(true);
+
+        // Wait for the runner to observe the stop and terminate the process.
+        let deadline = Instant::now() + STOP_GRACE_PERIOD + Duration::from_secs(5);
+        while service.is_active().await && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        Ok(())
+    }
+
+    /// Stop every service of a workspace (e.g. before deletion).
+    pub async fn stop_workspace(&self, workspace_id: Uuid) {
+        let names: Vec<String> = self
+            .services
+            .read()
+            .await
+            .get(&workspace_id)
+            .map(|m| m.keys().cloned().collect())
+            .unwrap_or_default();
+        for name in names {
+            let _ = self.stop(workspace_id, &name).await;
+        }
+        self.services.write().await.remove(&workspace_id);
+    }
+
+    /// Stop the services of a workspace that are no longer declared in
+    /// `services`, so they do not keep running unmanaged.
+    pub async fn stop_removed(&self, workspace_id: Uuid, services: &[ServiceDefinition]) {
+        let removed: Vec<String> = self
+            .services
+            .read()
+            .await
+            .get(&workspace_id)
+            .map(|m| {
+                m.keys()
+                    .filter(|name| !services.iter().any(|s| &s.name == *name))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        for name in removed {
+            if let Err(e) = self.stop(workspace_id, &name).await {
+                tracing::debug!(service = %name, error = %e, "Removed service was not running");
+            }
+            if let Some(managed) = self.services.write().await.get_mut(&workspace_id) {
+                managed.remove(&name);
+            }
+            tracing::info!(workspace_id = %workspace_id, service = %name, "Stopped removed workspace service");
+        }
+    }
+
+    /// Stop every supervised service (server shutdown).
+    pub async fn stop_all(&self) {
+        let workspace_ids: Vec<Uuid> = self.services.read().await.keys().copied().collect();
+        for id in workspace_ids {
+            self.stop_workspace(id).await;
+        }
+    }
+
+    /// Status of every service declared on the workspace.
+    pub async fn list(&self, workspace: &Workspace) -> Vec<ServiceStatus> {
+        let mut statuses = Vec::with_capacity(workspace.services.len());
+        for definition in &workspace.services {
+            let status = match self.managed(workspace.id, &definition.name).await {
+                Some(service) => service.status.read().await.clone(),
+                None => ServiceStatus::stopped(workspace.id, definition.clone()),
+            };
+            statuses.push(status);
+        }
+        statuses
+    }
+
+    /// Last `lines` log lines of a service.
+    ///
+    /// Uses the in-memory tail when the service has run since startup and
+    /// falls back to the log file otherwise.
+    pub async fn logs(
+        &self,
+        workspace_id: Uuid,
+        name: &str,
+        lines: usize,
+    ) -> anyhow::Result<Vec<String>> {
+        if let Some(service) = self.managed(workspace_id, name).await {
+            let logs = service
+                .logs
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Log buffer poisoned"))?;
+            let skip = logs.len().saturating_sub(lines);
+            return Ok(logs.iter().skip(skip).cloned().collect());
+        }
+
+        let path = self.log_path(workspace_id, name);
+        match read_log_tail(&path, lines).await {
+            Ok(tail) => Ok(tail),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Last `lines` lines of a log file, read backwards from the end.
+async fn read_log_tail(path: &Path, lines: usize) -> std::io::Result<Vec<String>> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut pos = file.metadata().await?.len();
+    let mut buf: Vec<u8> = Vec::new();
+    let mut newlines = 0;
+    // One newline more than `lines` guarantees the first kept line is whole.
+    while pos > 0 && newlines <= lines {
+        let chunk = LOG_READ_CHUNK.min(pos);
+        pos -= chunk;
+        file.seek(std::io::SeekFrom::Start(pos)).await?;
+        let mut read = vec![0u8; chunk as usize];
+        file.read_exact(&mut read).await?;
+        newlines += read.iter().filter(|&&b| b == b'\n').count();
+        read.extend_from_slice(&buf);
+        buf = read;
+    }
+    let text = String::from_utf8_lossy(&buf);
+    let all: Vec<&str> = text.lines().collect();
+    let skip = all.len().saturating_sub(lines);
+    Ok(all[skip..].iter().map(|l| l.to_string()).collect())
+}
+
+/// Service log file, rotated to `<name>.log.1` at [`LOG_FILE_MAX_BYTES`].
+struct LogFile {
+    path: PathBuf,
+    file: tokio::fs::File,
+    len: u64,
+}
+
+impl LogFile {
+    async fn open(path: &Path) -> std::io::Result<Self> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        let len = file.metadata().await?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            len,
+        })
+    }
+
+    async fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.len >= LOG_FILE_MAX_BYTES {
+            self.rotate().await?;
+        }
+        self.file.write_all(line.as_bytes()).await?;
+        self.file.write_all(b"\n").await?;
+        self.len += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    async fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush().await?;
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        tokio::fs::rename(&self.path, rotated).await?;
+        *self = Self::open(&self.path).await?;
+        Ok(())
+    }
+}
+
+/// Working directory of a service; `cwd` is relative to the workspace root
+/// and may not leave it.
+fn service_cwd(workspace_root: &Path, cwd: Option<&str>) -> anyhow::Result<PathBuf> {
+    let Some(dir) = cwd.map(str::trim).filter(|d| !d.is_empty()) else {
+        return Ok(workspace_root.to_path_buf());
+    };
+    let relative = Path::new(dir.trim_start_matches('/'));
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        anyhow::bail!("Working directory is outside the workspace: {}", dir);
+    }
+    Ok(workspace_root.join(relative))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Service Runner
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Supervision loop for a single service: spawn, watch, restart.
+async fn run_service(
+    service: Arc<ManagedService>,
+    workspace: Workspace,
+    secrets: Option<Arc<SecretsStore>>,
+    log_path: PathBuf,
+    mut stop_rx: watch::Receiver<bool>,
+) {
+    let definition = service.status.read().await.definition.clone();
+    // The service env is merged over the workspace env and resolved with it,
+    // once; restarts reuse the same env.
+    let mut service_workspace = workspace.clone();
+    service_workspace.env_vars.extend(definition.env.clone());
+    let setup = async {
+        let resolved = crate::workspace::with_resolved_env(&service_workspace, secrets.as_deref())
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to resolve env: {}", e))?;
+        let cwd = service_cwd(&workspace.path, definition.cwd.as_deref())?;
+        anyhow::Ok((WorkspaceExec::new(resolved), cwd))
+    };
+    let (exec, cwd) = match setup.await {
+        Ok(setup) => setup,
+        Err(e) => {
+            let mut status = service.status.write().await;
+            status.state = ServiceState::Failed;
+            status.last_error = Some(e.to_string());
+            drop(status);
+            service.push_log(format!("[supervisor] {}", e));
+            return;
+        }
+    };
+    if let Some(parent) = log_path.parent() {
+        let _ = tokio::fs::create_dir_all(parent).await;
+    }
+    let args = vec!["-lc".to_string(), definition.command.clone()];
+    let mut backoff = INITIAL_BACKOFF;
+
+    loop {
+        if *stop_rx.borrow() {
+            break;
+        }
+        {
+            let mut status = service.status.write().await;
+            status.state = ServiceState::Starting;
+            status.health = ServiceHealth::Unknown;
+        }
+
+        let spawned = exec
+            .spawn_service(&cwd, "/bin/sh", &args, HashMap::new())
+            .await;
+        let started = Instant::now();
+        let outcome = match spawned {
+            Ok(mut child) => {
+                {
+                    let mut status = service.status.write().await;
+                    status.state = ServiceState::Running;
+                    status.pid = child.id();
+                    status.started_at = Some(Utc::now());
+                    status.last_error = None;
+                }
+                let log_file = LogFile::open(&log_path)
+                    .await
+                    .ok()
+                    .map(|f| Arc::new(tokio::sync::Mutex::new(f)));
+                let mut pumps = Vec::new();
+                if let Some(stdout) = child.stdout.take() {
+                    pumps.push(tokio::spawn(pump_logs(
+                        stdout,
+                        Arc::clone(&service),
+                        log_file.clone(),
+                    )));
+                }
+                if let Some(stderr) = child.stderr.take() {
+                    pumps.push(tokio::spawn(pump_logs(
+                        stderr,
+                        Arc::clone(&service),
+                        log_file.clone(),
+                    )));
+                }
+                let outcome =
+                    supervise_child(&service, &exec, &cwd, &definition, &mut child, &mut stop_rx)
+                        .await;
+                // Let the pumps drain remaining output before reporting the exit.
+                let _ =
+                    tokio::time::timeout(Duration::from_secs(2), futures::future::join_all(pumps))
+                        .await;
+                outcome
+            }
+            Err(e) => {
+                service.status.write().await.last_error = Some(e.to_string());
+                service.push_log(format!("[supervisor] failed to spawn: {}", e));
+                RunOutcome::Exited(None)
+            }
+        };
+
+        let restart = {
+            let mut status = service.status.write().await;
+            status.pid = None;
+            match outcome {
+                RunOutcome::StopRequested => {
+                    status.state = ServiceState::Stopped;
+                    false
+                }
+                RunOutcome::Unhealthy => {
+                    status.last_error = Some("Health check failed".to_string());
+                    definition.restart != RestartPolicy::Never
+                }
+                RunOutcome::Exited(code) => {
+                    status.last_exit_code = code;
+                    let clean = code == Some(0);
+                    if !clean && status.last_error.is_none() {
+                        status.last_error = Some(match code {
+                            Some(c) => format!("Exited with code {}", c),
+                            None => "Terminated by signal".to_string(),
+                        });
+                    }
+                    match definition.restart {
+                        RestartPolicy::Always => true,
+                        RestartPolicy::OnFailure => !clean,
+                        RestartPolicy::Never => false,
+                    }
+                }
+            }
+        };
+
+        if !restart {
+            let mut status = service.status.write().await;
+            if status.state != ServiceState::Stopped {
+                status.state = if status.last_exit_code == Some(0) {
+                    ServiceState::Exited
+                } else {
+                    ServiceState::Failed
+                };
+            }
+            break;
+        }
+
+        if started.elapsed() >= STABLE_RUN {
+            backoff = INITIAL_BACKOFF;
+        }
+        {
+            let mut status = service.status.write().await;
+            status.state = ServiceState::Backoff;
+            status.restarts += 1;
+        }
+        service.push_log(format!(
+            "[supervisor] restarting in {}s",
+            backoff.as_secs().max(1)
+        ));
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = stop_rx.changed() => {}
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+
+    if *stop_rx.borrow() {
+        service.status.write().await.state = ServiceState::Stopped;
+    }
+    tracing::info!(
+        workspace = %workspace.name,
+        service = %definition.name,
+        "Workspace service supervisor exited"
+    );
+}
+
+/// Wait for the child to exit, a stop request, or repeated health check failures.
+async fn supervise_child(
+    service: &ManagedService,
+    exec: &WorkspaceExec,
+    cwd: &Path,
+    definition: &ServiceDefinition,
+    child: &mut Child,
+    stop_rx: &mut watch::Receiver<bool>,
+) -> RunOutcome {
+    let health = async {
+        let Some(check) = definition.health_check.as_ref() else {
+            return std::future::pending::<()>().await;
+        };
+        let interval = Duration::from_secs(check.interval_secs.max(1));
+        let mut failures = 0u32;
+        loop {
+            tokio::time::sleep(interval).await;
+            let args = vec!["-lc".to_string(), check.command.clone()];
+            let result = tokio::time::timeout(
+                Duration::from_secs(check.timeout_secs.max(1)),
+                exec.output(cwd, "/bin/sh", &args, HashMap::new()),
+            )
+            .await;
+            let healthy = matches!(result, Ok(Ok(ref out)) if out.status.success());
+            service.status.write().await.health = if healthy {
+                ServiceHealth::Healthy
+            } else {
+                ServiceHealth::Unhealthy
+            };
+            failures = if healthy { 0 } else { failures + 1 };
+            if failures >= check.failure_threshold.max(1) {
+                return;
+            }
+        }
+    };
+
+    let outcome = tokio::select! {
+        status = child.wait() => {
+            return RunOutcome::Exited(status.ok().and_then(|s| s.code()));
+        }
+        _ = stop_rx.changed() => RunOutcome::StopRequested,
+        _ = health => {
+            service.push_log("[supervisor] health check failed, restarting".to_string());
+            RunOutcome::Unhealthy
+        }
+    };
+    terminate_child(child).await;
+    outcome
+}
+
+/// Send SIGTERM to the service's process group, then SIGKILL after a grace period.
+async fn terminate_child(child: &mut Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // Services are spawned as process group leaders, so this reaches
+        // the whole process tree (shell, nsenter/nspawn and the program).
+        unsafe {
+            libc::kill(-(pid as i32), libc::SIGTERM);
+        }
+        if tokio::time::timeout(STOP_GRACE_PERIOD, child.wait())
+            .await
+            .is_ok()
+        {
+            return;
+        }
+        unsafe {
+            libc::kill(-(pid as i32), libc::SIGKILL);
+        }
+    }
+    let _ = child.kill().await;
+}
+
+/// Copy process output into the in-memory tail and the log file.
+async fn pump_logs<R: AsyncRead + Unpin>(
+    reader: R,
+    service: Arc<ManagedService>,
+    log_file: Option<Arc<tokio::sync::Mutex<LogFile>>>,
+) {
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let Some(file) = log_file.as_ref() {
+            let _ = file.lock().await.write_line(&line).await;
+        }
+        service.push_log(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(name: &str, command: &str) -> ServiceDefinition {
+        ServiceDefinition {
+            name: name.to_string(),
+            command: command.to_string(),
+            env: HashMap::new(),
+            cwd: None,
+            restart: RestartPolicy::Never,
+            health_check: None,
+            autostart: true,
+        }
+    }
+
+    #[test]
+    fn test_service_definition_defaults() {
+        let def: ServiceDefinition =
+            serde_json::from_str(r#"{"name": "db", "command": "postgres"}"#).unwrap();
+        assert_eq!(def.restart, RestartPolicy::OnFailure);
+        assert!(def.autostart);
+        assert!(def.health_check.is_none());
+
+        let check: ServiceHealthCheck =
+            serde_json::from_str(r#"{"command": "pg_isready"}"#).unwrap();
+        assert_eq!(check.interval_secs, 30);
+        assert_eq!(check.failure_threshold, 3);
+    }
+
+    #[test]
+    fn test_validate_services() {
+        assert!(validate_services(&[definition("db", "postgres")]).is_ok());
+        assert!(validate_services(&[definition("", "postgres")]).is_err());
+        assert!(validate_services(&[definition("../db", "postgres")]).is_err());
+        assert!(validate_services(&[definition("db", "  ")]).is_err());
+        assert!(
+            validate_services(&[definition("db", "postgres"), definition("db", "redis")]).is_err()
+        );
+        let mut escaping = definition("db", "postgres");
+        escaping.cwd = Some("data/../../etc".to_string());
+        assert!(validate_services(&[escaping]).is_err());
+    }
+
+    #[test]
+    fn test_service_cwd_stays_in_workspace() {
+        let root = Path::new("/workspaces/w");
+        assert_eq!(service_cwd(root, None).unwrap(), root);
+        assert_eq!(service_cwd(root, Some(" ")).unwrap(), root);
+        assert_eq!(
+            service_cwd(root, Some("/app/./web")).unwrap(),
+            root.join("app/./web")
+        );
+        assert!(service_cwd(root, Some("../other")).is_err());
+        assert!(service_cwd(root, Some("app/../../other")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_log_file_rotates_and_tail_reads_from_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("web.log");
+        let mut log = LogFile::open(&path).await.unwrap();
+        log.len = LOG_FILE_MAX_BYTES;
+        log.write_line("after rotation").await.unwrap();
+        assert!(dir.path().join("web.log.1").exists());
+        assert_eq!(
+            tokio::fs::read_to_string(&path).await.unwrap(),
+            "after rotation\n"
+        );
+
+        // Spans several read chunks
+        let content: String = (0..20_000).map(|i| format!("line {}\n", i)).collect();
+        tokio::fs::write(&path, &content).await.unwrap();
+        let tail = read_log_tail(&path, 3).await.unwrap();
+        assert_eq!(tail, vec!["line 19997", "line 19998", "line 19999"]);
+        let all = read_log_tail(&path, 100_000).await.unwrap();
+        assert_eq!(all.len(), 20_000);
+        assert_eq!(all[0], "line 0");
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_captures_logs_and_exit() {
+        let dir = tempfile::tempdir().unwrap();
+        let supervisor = ServiceSupervisor::new(dir.path(), None);
+        let mut workspace = Workspace::default_host(dir.path().to_path_buf());
+        workspace.services = vec![definition("hello", "echo hello; exit 3")];
+
+        supervisor.start(&workspace, "hello").await.unwrap();
+        for _ in 0..50 {
+            let status = &supervisor.list(&workspace).await[0];
+            if status.state == ServiceState::Failed {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let status = &supervisor.list(&workspace).await[0];
+        assert_eq!(status.state, ServiceState::Failed);
+        assert_eq!(status.last_exit_code, Some(3));
+        let logs = supervisor.logs(workspace.id, "hello", 10).await.unwrap();
+        assert_eq!(logs, vec!["hello".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_stop_removed_services() {
+        let dir = tempfile::tempdir().unwrap();
+        let supervisor = ServiceSupervisor::new(dir.path(), None);
+        let mut workspace = Workspace::default_host(dir.path().to_path_buf());
+        workspace.services = vec![
+            definition("keep", "sleep 30"),
+            definition("drop", "sleep 30"),
+        ];
+        supervisor.start(&workspace, "keep").await.unwrap();
+        supervisor.start(&workspace, "drop").await.unwrap();
+
+        workspace.services.retain(|s| s.name == "keep");
+        supervisor
+            .stop_removed(workspace.id, &workspace.services)
+            .await;
+
+        assert!(supervisor.managed(workspace.id, "drop").await.is_none());
+        let keep = supervisor.managed(workspace.id, "keep").await.unwrap();
+        assert!(keep.is_active().await);
+        supervisor.stop_workspace(workspace.id).await;
+    }
+
+    #[tokio::test]
+    async fn test_services_stop_when_workspace_leaves_ready() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = crate::workspace::WorkspaceStore::new(dir.path().to_path_buf(), None).await;
+        let mut workspace = store
+            .get(crate::workspace::DEFAULT_WORKSPACE_ID)
+            .await
+            .unwrap();
+        assert_eq!(workspace.status, WorkspaceStatus::Ready);
+        workspace.services = vec![definition("web", "sleep 30")];
+        store.update(workspace.clone()).await;
+        store.services().start(&workspace, "web").await.unwrap();
+
+        workspace.status = WorkspaceStatus::Building;
+        store.update(workspace.clone()).await;
+        assert!(store
+            .services()
+            .managed(workspace.id, "web")
+            .await
+            .is_none());
+
+        // Autostart services come back once the workspace is ready again
+        workspace.status = WorkspaceStatus::Ready;
+        store.update(workspace.clone()).await;
+        let web = store.services().managed(workspace.id, "web").await.unwrap();
+        assert!(web.is_active().await);
+        store.services().stop_workspace(workspace.id).await;
+    }
+}